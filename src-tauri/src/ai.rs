@@ -0,0 +1,169 @@
+//! Optional transforms of text entries (summarize, translate, rewrite) through a user-configured
+//! OpenAI-compatible chat completions endpoint. Nothing is sent unless `ai_enabled` is set, and
+//! sensitive entries are never sent. The API key is encrypted for the current Windows user with
+//! DPAPI before it is written to config.ini.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Prefix of DPAPI-encrypted keys in config.ini.
+const PROTECTED_PREFIX: &str = "dpapi:";
+
+#[derive(Clone, Copy)]
+pub enum TransformKind {
+    Summarize,
+    Translate,
+    Rewrite,
+}
+
+impl TransformKind {
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "summarize" => Some(Self::Summarize),
+            "translate" => Some(Self::Translate),
+            "rewrite" => Some(Self::Rewrite),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Summarize => "summarize",
+            Self::Translate => "translate",
+            Self::Rewrite => "rewrite",
+        }
+    }
+
+    fn instructions(self, target_lang: &str) -> String {
+        let task = match self {
+            Self::Summarize => format!("Summarize the text concisely in {}.", target_lang),
+            Self::Translate => format!("Translate the text into {}.", target_lang),
+            Self::Rewrite => format!("Rewrite the text in {} so it is clear and well written, keeping its meaning.", target_lang),
+        };
+        format!("{} Reply with the resulting text only, without commentary.", task)
+    }
+}
+
+/// Sends `text` with the instructions for `kind` and returns the model's reply.
+pub fn transform(
+    endpoint: &str,
+    model: &str,
+    api_key: &str,
+    kind: TransformKind,
+    target_lang: &str,
+    text: &str,
+) -> Result<String, String> {
+    let url = completions_url(endpoint);
+    let body = serde_json::json!({
+        "model": model,
+        "messages": [
+            { "role": "system", "content": kind.instructions(target_lang) },
+            { "role": "user", "content": text },
+        ],
+    });
+    let mut request = ureq::post(&url)
+        .timeout(REQUEST_TIMEOUT)
+        .set("Content-Type", "application/json");
+    if !api_key.is_empty() {
+        request = request.set("Authorization", &format!("Bearer {}", api_key));
+    }
+    let response = match request.send_string(&body.to_string()) {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, response)) => {
+            let detail = response.into_string().unwrap_or_default();
+            return Err(format!("The AI endpoint returned {}: {}", code, api_error_message(&detail)));
+        }
+        Err(e) => return Err(format!("Cannot reach the AI endpoint: {}", e)),
+    };
+    let reply: serde_json::Value = serde_json::from_str(&response.into_string().map_err(|e| e.to_string())?)
+        .map_err(|e| format!("Unexpected response from the AI endpoint: {}", e))?;
+    reply["choices"][0]["message"]["content"]
+        .as_str()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| "The AI endpoint returned no text".to_string())
+}
+
+/// Accepts either the API base ("https://api.openai.com/v1") or the full completions URL.
+fn completions_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim().trim_end_matches('/');
+    if endpoint.ends_with("/chat/completions") {
+        endpoint.to_string()
+    } else {
+        format!("{}/chat/completions", endpoint)
+    }
+}
+
+/// OpenAI-style `{"error": {"message": ...}}` bodies, or the raw body.
+fn api_error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v["error"]["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| body.chars().take(200).collect())
+}
+
+/// Encrypts the key for config.ini; an empty key stays empty.
+pub fn protect_key(key: &str) -> Result<String, String> {
+    if key.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format!("{}{}", PROTECTED_PREFIX, STANDARD.encode(dpapi::protect(key.as_bytes())?)))
+}
+
+pub fn unprotect_key(stored: &str) -> Result<String, String> {
+    if stored.is_empty() {
+        return Ok(String::new());
+    }
+    let encoded = stored
+        .strip_prefix(PROTECTED_PREFIX)
+        .ok_or("The stored AI API key is not encrypted; set it again")?;
+    let data = STANDARD.decode(encoded).map_err(|e| e.to_string())?;
+    String::from_utf8(dpapi::unprotect(&data)?).map_err(|e| e.to_string())
+}
+
+#[cfg(windows)]
+mod dpapi {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{LocalFree, HLOCAL};
+    use windows::Win32::Security::Cryptography::{
+        CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+
+    unsafe fn take_blob(blob: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+        let data = std::slice::from_raw_parts(blob.pbData, blob.cbData as usize).to_vec();
+        let _ = LocalFree(Some(HLOCAL(blob.pbData as *mut _)));
+        data
+    }
+
+    pub fn protect(data: &[u8]) -> Result<Vec<u8>, String> {
+        let input = CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_ptr() as *mut u8 };
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptProtectData(&input, PCWSTR::null(), None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+                .map_err(|e| format!("Cannot encrypt the API key: {}", e))?;
+            Ok(take_blob(output))
+        }
+    }
+
+    pub fn unprotect(data: &[u8]) -> Result<Vec<u8>, String> {
+        let input = CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_ptr() as *mut u8 };
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptUnprotectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+                .map_err(|e| format!("Cannot decrypt the API key (was it set by another user?): {}", e))?;
+            Ok(take_blob(output))
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod dpapi {
+    pub fn protect(_data: &[u8]) -> Result<Vec<u8>, String> {
+        Err("Storing an AI API key is only supported on Windows".into())
+    }
+
+    pub fn unprotect(_data: &[u8]) -> Result<Vec<u8>, String> {
+        Err("Storing an AI API key is only supported on Windows".into())
+    }
+}
@@ -0,0 +1,153 @@
+//! Archive databases. Old entries can be moved into per-year databases in the data directory's
+//! `archive/` folder; those and any attached from elsewhere are searched read-only without being
+//! merged into the live one.
+
+use crate::database::Database;
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ArchiveInfo {
+    pub name: String,
+    pub path: String,
+    pub entry_count: i64,
+    pub size_bytes: u64,
+    /// Attached from outside the archive folder.
+    pub attached: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ArchivedEntry {
+    pub archive: String,
+    pub id: i64,
+    pub app_name: Option<String>,
+    pub content_type: String,
+    pub text_content: Option<String>,
+    pub image_path: Option<String>,
+    pub created_at: String,
+    pub source_url: Option<String>,
+    pub is_sensitive: bool,
+    /// Shown for sensitive entries in place of the text, which is left out.
+    pub masked_preview: Option<String>,
+}
+
+/// Applies an `archive_policy` such as "90d": entries older than that move to the archive and
+/// their images to `archive/images`. Returns the number of entries moved.
+pub fn apply_policy(db: &Database, policy: &str) -> Result<usize, String> {
+    let Some(days) = policy.strip_suffix('d').and_then(|d| d.parse::<i64>().ok()).filter(|d| *d > 0) else {
+        return Ok(0);
+    };
+    let (moved, images) = db.archive_entries_older_than(days).map_err(|e| e.to_string())?;
+    if !images.is_empty() {
+        let images_dir = db.images_dir();
+        let archived_images = db.archive_dir().join("images");
+        std::fs::create_dir_all(&archived_images).map_err(|e| e.to_string())?;
+        // Images still shared with live entries are copied rather than moved
+        let released = db.release_images(images.clone()).map_err(|e| e.to_string())?;
+        for f in images {
+            let from = images_dir.join(&f);
+            let to = archived_images.join(&f);
+            if !released.contains(&f) {
+                let _ = std::fs::copy(&from, &to);
+            } else if std::fs::rename(&from, &to).is_err() && std::fs::copy(&from, &to).is_ok() {
+                // rename fails across volumes
+                let _ = std::fs::remove_file(&from);
+            }
+        }
+    }
+    Ok(moved)
+}
+
+/// Archive databases in `archive_dir` followed by the attached ones (`;`-separated paths).
+pub fn archive_paths(archive_dir: &Path, attached: &str) -> Vec<(PathBuf, bool)> {
+    let mut paths: Vec<(PathBuf, bool)> = std::fs::read_dir(archive_dir)
+        .map(|dir| {
+            dir.flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("db")))
+                .map(|p| (p, false))
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    for p in attached.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+        let p = PathBuf::from(p);
+        if !paths.iter().any(|(existing, _)| *existing == p) {
+            paths.push((p, true));
+        }
+    }
+    paths
+}
+
+fn open_read_only(path: &Path) -> rusqlite::Result<Connection> {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+}
+
+fn archive_name(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// Opens `path` and checks that it looks like a CutBoard database.
+pub fn archive_info(path: &Path, attached: bool) -> Result<ArchiveInfo, String> {
+    let conn = open_read_only(path).map_err(|e| e.to_string())?;
+    let entry_count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM clipboard_entries", [], |row| row.get(0))
+        .map_err(|_| format!("{} is not a CutBoard database", path.display()))?;
+    Ok(ArchiveInfo {
+        name: archive_name(path),
+        path: path.to_string_lossy().to_string(),
+        entry_count,
+        size_bytes: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        attached,
+    })
+}
+
+/// Text search across archives, newest first. Archives that fail to open are skipped.
+pub fn search(paths: &[(PathBuf, bool)], query: &str, limit: i64) -> Vec<ArchivedEntry> {
+    let _t = crate::metrics::timer("db.search_archives");
+    let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    let mut results = Vec::new();
+    for (path, _) in paths {
+        if let Ok(mut found) = search_one(path, &pattern, limit) {
+            results.append(&mut found);
+        }
+    }
+    results.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    results.truncate(limit.max(0) as usize);
+    results
+}
+
+fn search_one(path: &Path, pattern: &str, limit: i64) -> rusqlite::Result<Vec<ArchivedEntry>> {
+    let conn = open_read_only(path)?;
+    // Archives can predate the is_sensitive column
+    let has_sensitive = conn
+        .prepare("SELECT 1 FROM pragma_table_info('clipboard_entries') WHERE name = 'is_sensitive'")?
+        .exists([])?;
+    let sensitive_col = if has_sensitive { "COALESCE(e.is_sensitive,0)" } else { "0" };
+    // Sensitive text is not matched, or a search could confirm what a secret contains
+    let sql = format!(
+        "SELECT e.id, a.name, e.content_type, e.text_content, e.image_path, e.created_at, e.source_url, {0}
+         FROM clipboard_entries e LEFT JOIN apps a ON a.id = e.app_id
+         WHERE ({0} = 0 AND e.text_content LIKE ?1 ESCAPE '\\') OR e.source_url LIKE ?1 ESCAPE '\\'
+         ORDER BY e.created_at DESC LIMIT ?2",
+        sensitive_col
+    );
+    let archive = archive_name(path);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![pattern, limit], |row| {
+        Ok(ArchivedEntry {
+            archive: archive.clone(),
+            id: row.get(0)?,
+            app_name: row.get(1)?,
+            content_type: row.get(2)?,
+            text_content: row.get(3)?,
+            image_path: row.get(4)?,
+            created_at: row.get(5)?,
+            source_url: row.get(6)?,
+            is_sensitive: row.get::<_, i64>(7)? != 0,
+            masked_preview: None,
+        })
+    })?;
+    rows.collect()
+}
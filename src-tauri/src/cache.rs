@@ -0,0 +1,311 @@
+//! Least-recently-used cache shared by the image, icon, favicon and language caches.
+//!
+//! Entries live in a slab threaded by a doubly linked list in recency order, with a hash map from
+//! key to slot, so lookups, touches, inserts and evictions are all O(1). A cache can be bounded by
+//! entry count, by total weight (usually bytes) or both, and entries can expire after a TTL.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    weight: usize,
+    inserted: Instant,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+pub struct LruCache<K, V> {
+    map: HashMap<K, usize>,
+    slots: Vec<Option<Node<K, V>>>,
+    free: Vec<usize>,
+    /// Most recently used.
+    head: Option<usize>,
+    /// Least recently used; evicted first.
+    tail: Option<usize>,
+    max_entries: Option<usize>,
+    max_weight: Option<usize>,
+    weigh: fn(&V) -> usize,
+    ttl: Option<Duration>,
+    weight: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    /// An unbounded cache; add limits with `max_entries`, `max_weight` and `ttl`.
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            head: None,
+            tail: None,
+            max_entries: None,
+            max_weight: None,
+            weigh: |_| 0,
+            ttl: None,
+            weight: 0,
+        }
+    }
+
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = Some(max);
+        self
+    }
+
+    /// Bounds the sum of `weigh(value)` over all entries.
+    pub fn max_weight(mut self, max: usize, weigh: fn(&V) -> usize) -> Self {
+        self.max_weight = Some(max);
+        self.weigh = weigh;
+        self
+    }
+
+    /// Entries older than `ttl` are treated as absent.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    pub fn weight_budget(&self) -> Option<usize> {
+        self.max_weight
+    }
+
+    pub fn set_max_weight(&mut self, max: usize) {
+        self.max_weight = Some(max);
+        self.evict();
+    }
+
+    /// Returns the value and marks it most recently used.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = *self.map.get(key)?;
+        if self.is_expired(idx) {
+            self.remove_slot(idx);
+            return None;
+        }
+        self.unlink(idx);
+        self.push_front(idx);
+        self.slots[idx].as_ref().map(|n| &n.value)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some(&idx) = self.map.get(&key) {
+            self.remove_slot(idx);
+        }
+        let weight = (self.weigh)(&value);
+        if self.max_weight.is_some_and(|max| weight > max) {
+            return;
+        }
+        let node = Node { key: key.clone(), value, weight, inserted: Instant::now(), prev: None, next: None };
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.slots[idx] = Some(node);
+                idx
+            }
+            None => {
+                self.slots.push(Some(node));
+                self.slots.len() - 1
+            }
+        };
+        self.map.insert(key, idx);
+        self.weight += weight;
+        self.push_front(idx);
+        self.evict();
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = *self.map.get(key)?;
+        self.remove_slot(idx)
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.slots.clear();
+        self.free.clear();
+        self.head = None;
+        self.tail = None;
+        self.weight = 0;
+    }
+
+    fn is_expired(&self, idx: usize) -> bool {
+        match (self.ttl, &self.slots[idx]) {
+            (Some(ttl), Some(node)) => node.inserted.elapsed() > ttl,
+            _ => false,
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.max_entries.is_some_and(|max| self.map.len() > max)
+            || self.max_weight.is_some_and(|max| self.weight > max)
+        {
+            let Some(tail) = self.tail else { break };
+            self.remove_slot(tail);
+        }
+    }
+
+    fn remove_slot(&mut self, idx: usize) -> Option<V> {
+        self.unlink(idx);
+        let node = self.slots[idx].take()?;
+        self.free.push(idx);
+        self.map.remove(&node.key);
+        self.weight -= node.weight;
+        Some(node.value)
+    }
+
+    fn unlink(&mut self, idx: usize) {
+        let Some(node) = self.slots[idx].as_mut() else { return };
+        let (prev, next) = (node.prev.take(), node.next.take());
+        match prev {
+            Some(p) => {
+                if let Some(n) = self.slots[p].as_mut() {
+                    n.next = next;
+                }
+            }
+            None if self.head == Some(idx) => self.head = next,
+            None => {}
+        }
+        match next {
+            Some(n) => {
+                if let Some(node) = self.slots[n].as_mut() {
+                    node.prev = prev;
+                }
+            }
+            None if self.tail == Some(idx) => self.tail = prev,
+            None => {}
+        }
+    }
+
+    fn push_front(&mut self, idx: usize) {
+        let old_head = self.head;
+        if let Some(node) = self.slots[idx].as_mut() {
+            node.prev = None;
+            node.next = old_head;
+        }
+        if let Some(h) = old_head {
+            if let Some(node) = self.slots[h].as_mut() {
+                node.prev = Some(idx);
+            }
+        }
+        self.head = Some(idx);
+        if self.tail.is_none() {
+            self.tail = Some(idx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keys from most to least recently used.
+    fn order(cache: &LruCache<&'static str, u32>) -> Vec<&'static str> {
+        let mut keys = Vec::new();
+        let mut at = cache.head;
+        while let Some(idx) = at {
+            let node = cache.slots[idx].as_ref().unwrap();
+            keys.push(node.key);
+            at = node.next;
+        }
+        keys
+    }
+
+    #[test]
+    fn evicts_least_recently_used_first() {
+        let mut cache = LruCache::new().max_entries(3);
+        for (i, key) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            cache.insert(key, i as u32);
+        }
+        assert_eq!(order(&cache), ["d", "c", "b"]);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn get_and_insert_move_a_key_to_the_front() {
+        let mut cache = LruCache::new().max_entries(3);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+
+        assert_eq!(cache.get("a"), Some(&1));
+        assert_eq!(order(&cache), ["a", "c", "b"]);
+
+        cache.insert("b", 20);
+        assert_eq!(order(&cache), ["b", "a", "c"]);
+        assert_eq!(cache.get("b"), Some(&20));
+        assert_eq!(cache.len(), 3);
+
+        // "c" is now the oldest
+        cache.insert("d", 4);
+        assert_eq!(order(&cache), ["d", "b", "a"]);
+    }
+
+    #[test]
+    fn capacity_one_keeps_the_newest() {
+        let mut cache = LruCache::new().max_entries(1);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(order(&cache), ["b"]);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(&2));
+        assert_eq!(cache.head, cache.tail);
+    }
+
+    #[test]
+    fn freed_slots_are_reused() {
+        let mut cache = LruCache::new().max_entries(2);
+        for (i, key) in ["a", "b", "c", "d", "e", "f"].into_iter().enumerate() {
+            cache.insert(key, i as u32);
+        }
+        assert_eq!(cache.slots.len(), 3);
+        assert_eq!(cache.remove("f"), Some(5));
+        cache.insert("g", 6);
+        assert_eq!(cache.slots.len(), 3);
+        assert_eq!(order(&cache), ["g", "e"]);
+    }
+
+    #[test]
+    fn weight_budget_evicts_until_it_fits() {
+        let mut cache = LruCache::new().max_weight(10, |v: &u32| *v as usize);
+        cache.insert("a", 4);
+        cache.insert("b", 4);
+        cache.insert("c", 4);
+        assert_eq!(order(&cache), ["c", "b"]);
+        assert_eq!(cache.weight(), 8);
+
+        // Larger than the whole budget: not cached at all
+        cache.insert("d", 11);
+        assert_eq!(cache.get("d"), None);
+        assert_eq!(order(&cache), ["c", "b"]);
+
+        cache.set_max_weight(4);
+        assert_eq!(order(&cache), ["c"]);
+        assert_eq!(cache.weight(), 4);
+    }
+
+    #[test]
+    fn expired_entries_are_dropped_on_access() {
+        let mut cache = LruCache::new().ttl(Duration::from_millis(1));
+        cache.insert("a", 1);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.len(), 0);
+    }
+}
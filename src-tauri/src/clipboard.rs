@@ -1,9 +1,10 @@
+use crate::error::CutboardError;
 use crate::{window_tracker, ConfigPath, DbState};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter, Manager};
 
-fn compute_content_hash(data: &[u8]) -> String {
+pub(crate) fn compute_content_hash(data: &[u8]) -> String {
     // Stable FNV-1a hash (deterministic across Rust versions, unlike DefaultHasher)
     let mut hash: u64 = 0xcbf29ce484222325;
     for &byte in data {
@@ -14,7 +15,38 @@ fn compute_content_hash(data: &[u8]) -> String {
 }
 
 static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
-pub static IGNORE_NEXT: AtomicBool = AtomicBool::new(false);
+
+/// Hashes of content we put on the clipboard ourselves. Each clipboard update whose hash matches
+/// consumes one entry, so unrelated copies arriving at the same moment are still recorded.
+static EXPECTED_WRITES: Mutex<Vec<(String, Instant)>> = Mutex::new(Vec::new());
+const EXPECTED_WRITE_TTL: Duration = Duration::from_secs(5);
+
+fn expect_write(hash: &str) {
+    let mut expected = EXPECTED_WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    expected.retain(|(_, at)| now.duration_since(*at) < EXPECTED_WRITE_TTL);
+    expected.push((hash.to_string(), now));
+}
+
+fn forget_write(hash: &str) {
+    let mut expected = EXPECTED_WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(pos) = expected.iter().rposition(|(h, _)| h == hash) {
+        expected.remove(pos);
+    }
+}
+
+fn take_expected_write(hash: &str) -> bool {
+    let mut expected = EXPECTED_WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    expected.retain(|(_, at)| now.duration_since(*at) < EXPECTED_WRITE_TTL);
+    match expected.iter().position(|(h, _)| h == hash) {
+        Some(pos) => {
+            expected.remove(pos);
+            true
+        }
+        None => false,
+    }
+}
 
 struct NotificationCache {
     language: String,
@@ -23,6 +55,7 @@ struct NotificationCache {
     text_label: String,
     image_label: String,
     body_tpl: String,
+    actions: crate::toast::ToastLabels,
 }
 
 static NOTIFICATION_CACHE: std::sync::Mutex<Option<NotificationCache>> =
@@ -34,19 +67,30 @@ pub fn invalidate_notification_cache() {
     }
 }
 
-fn send_copy_notification(app: &AppHandle, content_type: &str) {
+fn send_copy_notification(app: &AppHandle, app_id: i64, entry_id: i64, content_type: &str) {
     let config_path = match app.try_state::<ConfigPath>() {
         Some(cp) => cp,
         None => return,
     };
     let cfg = crate::config::AppConfig::load(&config_path.0);
-    if !cfg.show_copy_toast {
+    if !cfg.show_copy_toast || crate::dnd::is_active(&cfg) {
+        return;
+    }
+    let app_types = app
+        .try_state::<DbState>()
+        .and_then(|state| state.0.lock().ok()?.get_app_settings(app_id).ok())
+        .and_then(|s| s.notify);
+    let types = app_types.as_deref().unwrap_or(&cfg.notify_types);
+    if !types.split(',').any(|t| t.trim() == content_type) {
+        return;
+    }
+    if in_quiet_hours(&cfg.quiet_hours, chrono::Local::now().time()) {
         return;
     }
 
     let _ = app.emit("copy-toast", content_type);
 
-    let (title, body) = {
+    let (title, body, labels) = {
         let mut guard = NOTIFICATION_CACHE.lock().unwrap_or_else(|e| e.into_inner());
         let needs_refresh = match &*guard {
             Some(c) => c.language != cfg.language || c.show_toast != cfg.show_copy_toast,
@@ -61,21 +105,52 @@ fn send_copy_notification(app: &AppHandle, content_type: &str) {
                 text_label: lang_map.get("tabs.text").cloned().unwrap_or_else(|| "Text".into()),
                 image_label: lang_map.get("tabs.image").cloned().unwrap_or_else(|| "Image".into()),
                 body_tpl: lang_map.get("toast.recorded").cloned().unwrap_or_else(|| "Recorded: {type}".into()),
+                actions: crate::toast::ToastLabels {
+                    favorite: lang_map.get("toast.favorite").cloned().unwrap_or_else(|| "Favorite".into()),
+                    delete: lang_map.get("toast.delete").cloned().unwrap_or_else(|| "Delete".into()),
+                    paste_plain: lang_map.get("toast.paste_plain").cloned().unwrap_or_else(|| "Paste plain".into()),
+                },
             });
         }
         let c = guard.as_ref().unwrap();
         let type_label = if content_type == "image" { &c.image_label } else { &c.text_label };
-        (c.title.clone(), c.body_tpl.replace("{type}", type_label))
+        (c.title.clone(), c.body_tpl.replace("{type}", type_label), c.actions.clone())
     };
 
-    #[cfg(windows)]
-    show_balloon_notification(&title, &body);
+    let app = app.clone();
+    let content_type = content_type.to_string();
+    std::thread::spawn(move || {
+        if !crate::toast::show(&app, entry_id, &content_type, &title, &body, &labels) {
+            #[cfg(windows)]
+            show_balloon_notification(&title, &body);
+        }
+    });
 }
 
+/// Whether `time` falls in one of the comma separated "HH:MM-HH:MM" ranges. A range may wrap past
+/// midnight, e.g. "22:00-07:00"; ranges that do not parse are ignored.
+pub(crate) fn in_quiet_hours(spec: &str, time: chrono::NaiveTime) -> bool {
+    let parse = |s: &str| chrono::NaiveTime::parse_from_str(s.trim(), "%H:%M").ok();
+    spec.split(',')
+        .filter_map(|range| {
+            let (start, end) = range.split_once('-')?;
+            Some((parse(start)?, parse(end)?))
+        })
+        .any(|(start, end)| if start <= end { start <= time && time < end } else { time >= start || time < end })
+}
+
+/// Fallback for systems without toasts. Balloons bypass Focus Assist, so the user's
+/// notification state is checked by hand.
 #[cfg(windows)]
 fn show_balloon_notification(title: &str, body: &str) {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use windows::Win32::UI::Shell::{SHQueryUserNotificationState, QUNS_ACCEPTS_NOTIFICATIONS};
+
     static BALLOON_ACTIVE: AtomicBool = AtomicBool::new(false);
 
+    if unsafe { SHQueryUserNotificationState() }.is_ok_and(|state| state != QUNS_ACCEPTS_NOTIFICATIONS) {
+        return;
+    }
     if BALLOON_ACTIVE.swap(true, Ordering::SeqCst) {
         return;
     }
@@ -186,6 +261,159 @@ static PENDING_APP_INFO: std::sync::LazyLock<
     std::sync::Mutex<Option<window_tracker::AppWindowInfo>>,
 > = std::sync::LazyLock::new(|| std::sync::Mutex::new(None));
 
+// Clipboard owner process captured alongside PENDING_APP_INFO
+static PENDING_OWNER_APP: std::sync::Mutex<Option<window_tracker::AppWindowInfo>> =
+    std::sync::Mutex::new(None);
+
+/// Owners that broker the clipboard for other programs; entries keep the foreground attribution.
+const GENERIC_OWNERS: &[&str] = &[
+    "svchost.exe",
+    "conhost.exe",
+    "dllhost.exe",
+    "textinputhost.exe",
+    "applicationframehost.exe",
+    "rdpclip.exe",
+];
+
+fn is_generic_owner(exe_path: &str) -> bool {
+    let file_name = std::path::Path::new(exe_path)
+        .file_name()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    GENERIC_OWNERS.contains(&file_name.as_str())
+}
+
+/// Recent captures used to spot other clipboard managers / RDP re-setting content we already have.
+static RECENT_CAPTURES: std::sync::Mutex<std::collections::VecDeque<(String, Instant)>> =
+    std::sync::Mutex::new(std::collections::VecDeque::new());
+const ECHO_WINDOW: Duration = Duration::from_secs(10);
+const ECHO_MAX_REPEATS: usize = 2;
+
+/// Returns true when this update looks like an echo rather than a fresh copy: the same content was
+/// captured moments ago and is now being set by a background process, or it keeps being re-set.
+fn is_clipboard_echo(hash: &str, owner_exe: Option<&str>, foreground_exe: &str) -> bool {
+    let mut recent = RECENT_CAPTURES.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    while recent.front().is_some_and(|(_, at)| now.duration_since(*at) >= ECHO_WINDOW) {
+        recent.pop_front();
+    }
+    let repeats = recent.iter().filter(|(h, _)| h == hash).count();
+    recent.push_back((hash.to_string(), now));
+
+    let from_background = owner_exe.is_some_and(|o| !o.eq_ignore_ascii_case(foreground_exe));
+    (repeats > 0 && from_background) || repeats >= ECHO_MAX_REPEATS
+}
+
+fn evaluate_rules(app: &AppHandle, ctx: &crate::rules::CaptureContext) -> crate::rules::RuleOutcome {
+    let rules = match app.state::<DbState>().0.lock() {
+        Ok(db) => db.get_rules().unwrap_or_default(),
+        Err(_) => return Default::default(),
+    };
+    crate::rules::evaluate(&rules, ctx)
+}
+
+/// Tags, favorites and schedules expiry for a stored entry as its rules say, and favorites it
+/// when its app is set to auto-favorite. Failures are ignored so a broken rule never loses the
+/// capture itself.
+fn apply_rule_outcome(db: &crate::database::Database, entry_id: i64, app_id: i64, outcome: &crate::rules::RuleOutcome) {
+    if !outcome.tags.is_empty() {
+        let _ = db.add_entry_tags(entry_id, &outcome.tags);
+    }
+    let auto_favorite = db.get_app_settings(app_id).is_ok_and(|s| s.auto_favorite);
+    let _ = db.apply_rule_flags(entry_id, outcome.favorite || auto_favorite, outcome.retention_days);
+}
+
+/// Writes through a temporary file and renames it into place, so an exit mid-write never leaves
+/// a truncated image under the real name.
+pub(crate) fn write_image_file(path: &std::path::Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+/// Whether a new image file still fits above the free space threshold. Emits `low-disk-space`
+/// with the free bytes when it does not, so the UI can say why images stopped being recorded.
+#[cfg(windows)]
+fn has_room_for_image(app: &AppHandle, images_dir: &std::path::Path, min_free_mb: u64) -> bool {
+    match crate::disk::low_space(images_dir, min_free_mb) {
+        Some(free) => {
+            let _ = app.emit("low-disk-space", free);
+            false
+        }
+        None => true,
+    }
+}
+
+fn is_ignored_owner(owner_exe: &str, ignore_list: &str) -> bool {
+    let path = std::path::Path::new(owner_exe);
+    let file_name = path.file_name().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+    ignore_list
+        .split([',', ';'])
+        .map(|s| s.trim().to_lowercase())
+        .any(|s| !s.is_empty() && (s == file_name || s == stem))
+}
+
+/// Set while the workstation is locked, so nothing typed or pasted at the lock screen is recorded.
+static SESSION_LOCKED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+/// Set while the listener window is receiving clipboard updates.
+static LISTENER_ACTIVE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+/// The listener window, so `stop_monitor` can reach it; 0 until it is created.
+static LISTENER_HWND: std::sync::atomic::AtomicIsize = std::sync::atomic::AtomicIsize::new(0);
+
+/// Whether clipboard changes are being received, and whether capture is paused for a locked
+/// session or secure desktop.
+pub fn listener_status() -> (bool, bool) {
+    (LISTENER_ACTIVE.load(std::sync::atomic::Ordering::SeqCst), capture_paused())
+}
+
+fn capture_paused() -> bool {
+    SESSION_LOCKED.load(std::sync::atomic::Ordering::SeqCst) || window_tracker::is_secure_desktop_active()
+}
+
+/// Query parameters that only carry click tracking and never change the page.
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key.starts_with("utm_") || matches!(key.as_str(), "fbclid" | "gclid" | "dclid" | "msclkid" | "mc_eid")
+}
+
+/// Drops the fragment and tracking parameters and lowercases the host, so the same page copied
+/// from different links groups and dedups as one source.
+fn normalize_source_url(url: &str) -> String {
+    let url = url.split('#').next().unwrap_or(url);
+    let Some(scheme_end) = url.find("://") else {
+        return url.to_string();
+    };
+    let rest = &url[scheme_end + 3..];
+    let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let (authority, tail) = rest.split_at(authority_end);
+    // Only the host is case-insensitive, not any user info before it
+    let authority = match authority.rfind('@') {
+        Some(at) => format!("{}{}", &authority[..=at], authority[at + 1..].to_lowercase()),
+        None => authority.to_lowercase(),
+    };
+    let (path, query) = tail.split_once('?').unwrap_or((tail, ""));
+    let query: Vec<&str> = query
+        .split('&')
+        .filter(|p| !p.is_empty() && !is_tracking_param(p.split('=').next().unwrap_or(p)))
+        .collect();
+
+    let mut normalized = format!("{}://{}{}", url[..scheme_end].to_lowercase(), authority, path);
+    if !query.is_empty() {
+        normalized.push('?');
+        normalized.push_str(&query.join("&"));
+    }
+    normalized
+}
+
+/// Images are stored under their content hash, so identical images captured from different apps
+/// share one file (see `Database::release_images`).
+pub(crate) fn image_filename(hash: &str) -> String {
+    format!("{}.png", hash)
+}
+
 const CF_TEXT: u32 = 1;
 const CF_UNICODETEXT: u32 = 13;
 const CF_DIB: u32 = 8;
@@ -202,14 +430,71 @@ pub fn start_monitor(app: AppHandle) {
     });
 }
 
+/// Stops listening at exit. A copy still waiting out the debounce delay is recorded first, and
+/// this waits briefly for a capture in progress so no image is left half-written.
+pub fn stop_monitor() {
+    #[cfg(windows)]
+    {
+        use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+        use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+
+        let hwnd = LISTENER_HWND.load(std::sync::atomic::Ordering::SeqCst);
+        if hwnd == 0 {
+            return;
+        }
+        unsafe {
+            let _ = PostMessageW(Some(HWND(hwnd as *mut _)), WM_STOP_LISTENER, WPARAM(0), LPARAM(0));
+        }
+        let deadline = Instant::now() + Duration::from_secs(3);
+        while LISTENER_ACTIVE.load(std::sync::atomic::Ordering::SeqCst) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+/// WM_APP + 1, posted by `stop_monitor`.
+#[cfg(windows)]
+const WM_STOP_LISTENER: u32 = 0x8001;
+/// Tags the WM_COPYDATA that carries file paths from a second instance started by "Send to".
+#[cfg(windows)]
+const COPYDATA_IMPORT_FILES: usize = 0x4342_4649;
+
+/// Hands file paths to the running instance's listener window, NUL-separated in UTF-16. Returns
+/// whether an instance took them. Windows drops the message when CutBoard runs elevated and the
+/// sender does not, so a lower-integrity process cannot make it read files on its behalf.
+#[cfg(windows)]
+pub fn forward_files(paths: &[String]) -> bool {
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::Foundation::{LPARAM, WPARAM};
+    use windows::Win32::System::DataExchange::COPYDATASTRUCT;
+    use windows::Win32::UI::WindowsAndMessaging::{FindWindowExW, SendMessageW, HWND_MESSAGE, WM_COPYDATA};
+
+    let mut payload: Vec<u16> = paths.join("\0").encode_utf16().collect();
+    let data = COPYDATASTRUCT {
+        dwData: COPYDATA_IMPORT_FILES,
+        cbData: (payload.len() * 2) as u32,
+        lpData: payload.as_mut_ptr().cast(),
+    };
+    unsafe {
+        let Ok(hwnd) = FindWindowExW(Some(HWND_MESSAGE), None, w!("CutBoardClipboardListener"), PCWSTR::null()) else {
+            return false;
+        };
+        SendMessageW(hwnd, WM_COPYDATA, Some(WPARAM(0)), Some(LPARAM(&data as *const _ as isize))).0 != 0
+    }
+}
+
 #[cfg(windows)]
 fn run_windows_monitor() {
     use windows::core::PCWSTR;
     use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
-    use windows::Win32::System::DataExchange::AddClipboardFormatListener;
+    use windows::Win32::System::DataExchange::{AddClipboardFormatListener, RemoveClipboardFormatListener, COPYDATASTRUCT};
+    use windows::Win32::System::RemoteDesktop::{WTSRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION};
     use windows::Win32::UI::WindowsAndMessaging::*;
 
     const WM_CLIPBOARDUPDATE: u32 = 0x031D;
+    const WM_WTSSESSION_CHANGE: u32 = 0x02B1;
+    const WTS_SESSION_LOCK: usize = 0x7;
+    const WTS_SESSION_UNLOCK: usize = 0x8;
     const DEBOUNCE_TIMER_ID: usize = 1;
     const DEBOUNCE_MS: u32 = 300;
 
@@ -220,18 +505,68 @@ fn run_windows_monitor() {
         lparam: LPARAM,
     ) -> LRESULT {
         match msg {
+            WM_WTSSESSION_CHANGE => {
+                match wparam.0 {
+                    WTS_SESSION_LOCK => {
+                        SESSION_LOCKED.store(true, std::sync::atomic::Ordering::SeqCst);
+                        // Drop a capture still waiting on the debounce timer
+                        let _ = KillTimer(Some(hwnd), DEBOUNCE_TIMER_ID);
+                    }
+                    WTS_SESSION_UNLOCK => SESSION_LOCKED.store(false, std::sync::atomic::Ordering::SeqCst),
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_COPYDATA => {
+                let data = &*(lparam.0 as *const COPYDATASTRUCT);
+                if data.dwData != COPYDATA_IMPORT_FILES || data.lpData.is_null() {
+                    return LRESULT(0);
+                }
+                let units = std::slice::from_raw_parts(data.lpData as *const u16, data.cbData as usize / 2);
+                let paths: Vec<String> = String::from_utf16_lossy(units)
+                    .split('\0')
+                    .filter(|p| !p.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                // The sending instance waits on this message, so the import runs elsewhere
+                if let Some(app) = APP_HANDLE.get() {
+                    let app = app.clone();
+                    std::thread::spawn(move || {
+                        let _ = crate::commands::import_files(app, paths);
+                    });
+                }
+                LRESULT(1)
+            }
+            WM_STOP_LISTENER => {
+                // Killing the timer succeeds only when a capture was still waiting on it
+                if KillTimer(Some(hwnd), DEBOUNCE_TIMER_ID).is_ok()
+                    && !capture_paused()
+                    && std::panic::catch_unwind(on_clipboard_change).is_err()
+                {
+                    eprintln!("on_clipboard_change panicked, recovered");
+                }
+                let _ = RemoveClipboardFormatListener(hwnd);
+                PostQuitMessage(0);
+                LRESULT(0)
+            }
+            WM_CLIPBOARDUPDATE if capture_paused() => LRESULT(0),
             WM_CLIPBOARDUPDATE => {
                 // Capture foreground app NOW, before the debounce delay
-                if let Some(info) = window_tracker::get_foreground_app() {
-                    if let Ok(mut pending) = PENDING_APP_INFO.lock() {
-                        *pending = Some(info);
-                    }
+                // Cleared when nothing has focus, so an earlier update's app is not reused
+                if let Ok(mut pending) = PENDING_APP_INFO.lock() {
+                    *pending = window_tracker::get_foreground_app();
+                }
+                if let Ok(mut pending) = PENDING_OWNER_APP.lock() {
+                    *pending = window_tracker::get_clipboard_owner_app();
                 }
                 let _ = SetTimer(Some(hwnd), DEBOUNCE_TIMER_ID, DEBOUNCE_MS, None);
                 LRESULT(0)
             }
             WM_TIMER if wparam.0 == DEBOUNCE_TIMER_ID => {
                 let _ = KillTimer(Some(hwnd), DEBOUNCE_TIMER_ID);
+                if capture_paused() {
+                    return LRESULT(0);
+                }
                 if std::panic::catch_unwind(on_clipboard_change).is_err() {
                     eprintln!("on_clipboard_change panicked, recovered");
                 }
@@ -277,41 +612,107 @@ fn run_windows_monitor() {
             return;
         }
 
-        let _ = AddClipboardFormatListener(hwnd);
+        if let Err(e) = AddClipboardFormatListener(hwnd) {
+            eprintln!("AddClipboardFormatListener failed: {}", e);
+        } else {
+            LISTENER_ACTIVE.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+        LISTENER_HWND.store(hwnd.0 as isize, std::sync::atomic::Ordering::SeqCst);
+        let _ = WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION);
 
         let mut msg = MSG::default();
         while GetMessageW(&mut msg, None, 0, 0).as_bool() {
             let _ = TranslateMessage(&msg);
             DispatchMessageW(&msg);
         }
+        LISTENER_HWND.store(0, std::sync::atomic::Ordering::SeqCst);
+        LISTENER_ACTIVE.store(false, std::sync::atomic::Ordering::SeqCst);
     }
 }
 
 fn on_clipboard_change() {
-    if IGNORE_NEXT.swap(false, Ordering::SeqCst) {
-        return;
-    }
+    capture_clipboard(false);
+}
+
+/// Records what is on the clipboard now, without the duplicate and echo checks or the owner
+/// ignore list. Returns whether anything was recorded.
+pub fn capture_now() -> bool {
+    capture_clipboard(true)
+}
 
+/// Stores the clipboard content as an entry. `force` is set for captures the user asked for,
+/// which skip the checks that drop repeated or ignored copies. Returns whether an entry was
+/// recorded.
+fn capture_clipboard(force: bool) -> bool {
     let app = match APP_HANDLE.get() {
         Some(a) => a,
-        None => return,
+        None => return false,
     };
 
-    // Use the app info captured at WM_CLIPBOARDUPDATE time
-    let app_info = match PENDING_APP_INFO.lock().ok().and_then(|mut p| p.take()) {
-        Some(info) => info,
-        None => match window_tracker::get_foreground_app() {
-            Some(info) => info,
-            None => return,
-        },
+    let (app_info, owner_app) = if force {
+        // The user is in CutBoard when asking for this, so the clipboard owner is the only hint
+        let owner = window_tracker::get_clipboard_owner_app().filter(|o| !o.is_self);
+        (owner.unwrap_or_else(window_tracker::system_app), None)
+    } else {
+        // Use the app info captured at WM_CLIPBOARDUPDATE time. With no focused window at all
+        // the clip is still kept; the clipboard owner below may name the real source.
+        let app_info = PENDING_APP_INFO
+            .lock()
+            .ok()
+            .and_then(|mut p| p.take())
+            .or_else(window_tracker::get_foreground_app)
+            .unwrap_or_else(window_tracker::system_app);
+        (app_info, PENDING_OWNER_APP.lock().ok().and_then(|mut p| p.take()))
     };
 
     if app_info.is_self {
-        return;
+        return false;
     }
 
+    let owner_exe = owner_app.as_ref().map(|o| o.exe_path.clone());
+    let config = app
+        .try_state::<ConfigPath>()
+        .map(|cp| crate::config::AppConfig::load(&cp.0));
+    if let (Some(owner), Some(cfg)) = (owner_exe.as_deref(), config.as_ref()) {
+        if !force && is_ignored_owner(owner, &cfg.ignore_clipboard_owners) {
+            return false;
+        }
+    }
+    // Reading the clipboard through storing the entry and notifying the UI
+    let _timer = crate::metrics::timer("capture");
+
     #[cfg(windows)]
     {
+        // The foreground window is only a guess at who copied; prefer the process that actually
+        // owns the clipboard unless it is a generic broker
+        let source_exe = app_info.exe_path.clone();
+        let remote = window_tracker::detect_remote_session(&app_info);
+        let context_tags = match (&config, app_info.window_title.as_deref()) {
+            (Some(c), Some(title)) if c.context_tagging => crate::context::tags(title),
+            _ => Vec::new(),
+        };
+        let mut app_info = match owner_app {
+            Some(owner) if !owner.is_self && !is_generic_owner(&owner.exe_path) => owner,
+            _ => app_info,
+        };
+        // Optionally give each remote machine / VM its own sidebar entry instead of lumping
+        // everything under the client executable
+        if let Some(ref r) = remote {
+            if config.as_ref().is_some_and(|c| c.group_remote_sessions) {
+                let host_stem = std::path::Path::new(&source_exe)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                app_info.name = format!("{} ({})", r.session, r.host_label);
+                app_info.exe_path = format!("remote://{}/{}", host_stem, r.session);
+            }
+        }
+        let origin = crate::database::EntryOrigin {
+            source_exe: Some(&source_exe),
+            owner_exe: owner_exe.as_deref(),
+            remote_session: remote.as_ref().map(|r| r.session.as_str()),
+        };
+
         let mut content = read_clipboard_content();
 
         // Only keep source_url if it's a real HTTP/HTTPS URL
@@ -373,50 +774,108 @@ fn on_clipboard_change() {
             }
         }
 
+        // Types the user chose not to keep are dropped, including an image attached to text
+        if let Some(cfg) = config.as_ref() {
+            if !cfg.capture_images {
+                content.image = None;
+            }
+            if !cfg.capture_text {
+                content.text = None;
+                content.html = None;
+            }
+        }
+
+        if config.as_ref().is_some_and(|c| c.normalize_source_urls) {
+            content.source_url = content.source_url.as_deref().map(normalize_source_url);
+        }
+        let link_window = config.as_ref().map_or(0, |c| c.auto_link_window_secs);
+        let store_plain_text = config.as_ref().is_some_and(|c| c.store_plain_text_only);
+        let min_free_mb = config.as_ref().map_or(0, |c| c.min_free_space_mb);
+        let screenshot_tools = config.as_ref().map_or("", |c| c.screenshot_tools.as_str());
+        let is_screenshot =
+            crate::screenshot::is_screenshot_tool(owner_exe.as_deref().into_iter().chain([source_exe.as_str()]), screenshot_tools);
+
         if let Some(ref t) = content.text {
             if !t.trim().is_empty() {
                 let hash = compute_content_hash(t.as_bytes());
+                if take_expected_write(&hash) && !force {
+                    return false;
+                }
                 {
                     let mut last = LAST_CONTENT_HASH.lock().unwrap_or_else(|e| e.into_inner());
-                    if *last == hash {
-                        return;
+                    if *last == hash && !force {
+                        return false;
                     }
                     *last = hash.clone();
                 }
+                if !force && is_clipboard_echo(&hash, owner_exe.as_deref(), &source_exe) {
+                    return false;
+                }
 
-                let current_lang = {
-                    match app.try_state::<ConfigPath>() {
-                        Some(cp) => crate::config::AppConfig::load(&cp.0).language,
-                        None => "en".to_string(),
-                    }
+                let outcome = evaluate_rules(
+                    app,
+                    &crate::rules::CaptureContext {
+                        app_name: &app_info.name,
+                        exe_path: &app_info.exe_path,
+                        content_type: "text",
+                        text: Some(t),
+                        html: content.html.as_deref(),
+                        source_url: content.source_url.as_deref(),
+                    },
+                );
+                let transformed = outcome.text.clone();
+                let (t, hash, html) = match transformed.as_deref() {
+                    // The HTML flavor no longer matches the rewritten text, so it is dropped
+                    Some(new_text) => (new_text, compute_content_hash(new_text.as_bytes()), None),
+                    None => (t.as_str(), hash, content.html.as_deref()),
+                };
+                if outcome.apply_to_clipboard && transformed.is_some() {
+                    let _ = write_text_to_clipboard(t);
+                }
+
+                let (current_lang, regions) = config
+                    .as_ref()
+                    .map(|c| (c.language.as_str(), c.sensitive_regions.as_str()))
+                    .unwrap_or(("en", "auto"));
+                let sensitive_kind = crate::sensitive::sensitive_kind(t, current_lang, regions);
+                let path_info = if sensitive_kind.is_some() { None } else { crate::filepath::detect(t) };
+                let structured = if sensitive_kind.is_some() || path_info.is_some() { None } else { crate::structured::detect(t) };
+                // JSON arrays and the like would otherwise pass for comma separated tables
+                let table = if sensitive_kind.is_some() || path_info.is_some() || structured.is_some() {
+                    None
+                } else {
+                    crate::table::detect(t, html)
                 };
-                let is_sensitive = crate::sensitive::detect_sensitive(t, &current_lang);
 
                 let db_state = app.state::<DbState>();
                 let db = match db_state.0.lock() {
                     Ok(db) => db,
                     Err(e) => e.into_inner(),
                 };
-                let app_id = match db.get_or_create_app(
+                let (app_id, app_created) = match db.get_or_create_app(
                     &app_info.name,
                     &app_info.exe_path,
                     app_info.icon_base64.as_deref(),
+                    app_info.icon_stamp.as_deref(),
                 ) {
-                    Ok(id) => id,
-                    Err(_) => return,
+                    Ok(v) => v,
+                    Err(_) => return false,
+                };
+                // Formatting is still offered to the rules above, only not stored
+                let html = if store_plain_text || db.get_app_settings(app_id).is_ok_and(|s| s.store_plain_text) {
+                    None
+                } else {
+                    html
                 };
 
                 // If image data is also present, save the image file alongside the text entry
                 let attached_image = if let Some(ref png_data) = content.image {
-                    let img_hash = compute_content_hash(png_data);
-                    let filename = format!(
-                        "{}_{}.png",
-                        chrono::Local::now().format("%Y%m%d_%H%M%S_%3f"),
-                        &img_hash[..8]
-                    );
-                    let images_dir = db.images_dir();
-                    let image_file = images_dir.join(&filename);
-                    if std::fs::write(&image_file, png_data).is_ok() {
+                    let filename = image_filename(&compute_content_hash(png_data));
+                    let image_file = db.images_dir().join(&filename);
+                    if image_file.exists()
+                        || (has_room_for_image(app, &db.images_dir(), min_free_mb)
+                            && write_image_file(&image_file, png_data).is_ok())
+                    {
                         Some(filename)
                     } else {
                         None
@@ -425,84 +884,156 @@ fn on_clipboard_change() {
                     None
                 };
 
-                if db
-                    .upsert_text_entry_with_html(
+                if let Ok(entry_id) = crate::database::retry_busy(|| {
+                    db.upsert_text_entry_with_html(
                         app_id,
-                        t,
-                        &hash,
+                        &crate::database::TextCapture {
+                            text: t,
+                            hash: &hash,
+                            html,
+                            image_path: attached_image.as_deref(),
+                            sensitive_kind,
+                        },
                         content.source_url.as_deref(),
-                        content.html.as_deref(),
-                        is_sensitive,
-                        attached_image.as_deref(),
+                        &origin,
                     )
-                    .is_ok()
-                {
+                }) {
+                    apply_rule_outcome(&db, entry_id, app_id, &outcome);
+                    let _ = db.auto_link_entry(entry_id, app_id, link_window);
+                    if !context_tags.is_empty() {
+                        let _ = db.add_entry_tags(entry_id, &context_tags);
+                    }
+                    if let Some(info) = &path_info {
+                        let _ = db.set_path_info(entry_id, info);
+                    }
+                    if let Some(rows) = &table {
+                        let _ = db.set_entry_table(entry_id, rows);
+                    }
+                    if let Some(kind) = structured {
+                        let _ = db.set_detected_kind(entry_id, kind);
+                    }
+                    crate::event_stream::entry_created(&db, entry_id);
+                    let new_app = if app_created { db.get_app_by_id(app_id).ok() } else { None };
                     drop(db);
-                    if is_sensitive {
+                    if let Some(info) = new_app {
+                        let _ = app.emit("app-added", info);
+                    }
+                    if sensitive_kind.is_some() && !config.as_ref().is_some_and(crate::dnd::is_active) {
                         let _ = app.emit("sensitive-detected", "");
                     }
                     let _ = app.emit("clipboard-changed", "text");
-                    send_copy_notification(app, "text");
+                    send_copy_notification(app, app_id, entry_id, "text");
+                    if let Some(cfg) = config.as_ref() {
+                        use crate::sound::CaptureSound;
+                        let sound = if sensitive_kind.is_some() { CaptureSound::Sensitive } else { CaptureSound::Text };
+                        crate::sound::play(cfg, sound);
+                    }
+                    return true;
                 }
-                return;
+                return false;
             }
         }
 
-        if let Some(png_data) = content.image {
+        if let Some(mut png_data) = content.image {
+            let keep_original = is_screenshot && config.as_ref().is_none_or(|c| c.screenshot_keep_original);
+            if !keep_original {
+                let max_side = config.as_ref().map_or(0, |c| c.max_image_side);
+                if let Some(scaled) = crate::screenshot::downscale_png(&png_data, max_side) {
+                    png_data = scaled;
+                }
+            }
             let hash = compute_content_hash(&png_data);
+            if take_expected_write(&hash) && !force {
+                return false;
+            }
             {
                 let mut last = LAST_CONTENT_HASH.lock().unwrap_or_else(|e| e.into_inner());
-                if *last == hash {
-                    return;
+                if *last == hash && !force {
+                    return false;
                 }
                 *last = hash.clone();
             }
+            if !force && is_clipboard_echo(&hash, owner_exe.as_deref(), &source_exe) {
+                return false;
+            }
+            let outcome = evaluate_rules(
+                app,
+                &crate::rules::CaptureContext {
+                    app_name: &app_info.name,
+                    exe_path: &app_info.exe_path,
+                    content_type: "image",
+                    text: None,
+                    html: None,
+                    source_url: content.source_url.as_deref(),
+                },
+            );
 
             let db_state = app.state::<DbState>();
             let db = match db_state.0.lock() {
                 Ok(db) => db,
                 Err(e) => e.into_inner(),
             };
-            let app_id = match db.get_or_create_app(
+            let (app_id, app_created) = match db.get_or_create_app(
                 &app_info.name,
                 &app_info.exe_path,
                 app_info.icon_base64.as_deref(),
+                app_info.icon_stamp.as_deref(),
             ) {
-                Ok(id) => id,
-                Err(_) => return,
+                Ok(v) => v,
+                Err(_) => return false,
             };
-            let filename = format!(
-                "{}_{}.png",
-                chrono::Local::now().format("%Y%m%d_%H%M%S_%3f"),
-                &hash[..8]
-            );
+            let filename = image_filename(&hash);
+            let image_path = db.images_dir().join(&filename);
             let images_dir = db.images_dir();
-            let image_path = images_dir.join(&filename);
             drop(db);
+            if !image_path.exists() && !has_room_for_image(app, &images_dir, min_free_mb) {
+                return false;
+            }
 
-            if std::fs::write(&image_path, &png_data).is_ok() {
+            if image_path.exists() || write_image_file(&image_path, &png_data).is_ok() {
                 let db = match db_state.0.lock() {
                     Ok(db) => db,
                     Err(e) => e.into_inner(),
                 };
-                match db.upsert_image_entry(app_id, &filename, &hash, content.source_url.as_deref())
-                {
-                    Ok((_id, was_duplicate)) => {
-                        drop(db);
-                        if was_duplicate {
+                match crate::database::retry_busy(|| {
+                    db.upsert_image_entry(app_id, &filename, &hash, content.source_url.as_deref(), &origin)
+                }) {
+                    Ok((id, was_duplicate)) => {
+                        if is_screenshot {
+                            let _ = db.mark_screenshot(id);
+                        }
+                        apply_rule_outcome(&db, id, app_id, &outcome);
+                        let _ = db.auto_link_entry(id, app_id, link_window);
+                        if !context_tags.is_empty() {
+                            let _ = db.add_entry_tags(id, &context_tags);
+                        }
+                        // An older entry may still point at a file stored under a legacy name
+                        if was_duplicate && db.release_images(vec![filename.clone()]).is_ok_and(|r| !r.is_empty()) {
                             std::fs::remove_file(&image_path).ok();
                         }
+                        crate::event_stream::entry_created(&db, id);
+                        let new_app = if app_created { db.get_app_by_id(app_id).ok() } else { None };
+                        drop(db);
+                        if let Some(info) = new_app {
+                            let _ = app.emit("app-added", info);
+                        }
                         let _ = app.emit("clipboard-changed", "image");
-                        send_copy_notification(app, "image");
+                        send_copy_notification(app, app_id, id, "image");
+                        if let Some(cfg) = config.as_ref() {
+                            crate::sound::play(cfg, crate::sound::CaptureSound::Image);
+                        }
+                        return true;
                     }
                     Err(_) => {
-                        drop(db);
-                        std::fs::remove_file(&image_path).ok();
+                        if db.release_images(vec![filename.clone()]).is_ok_and(|r| !r.is_empty()) {
+                            std::fs::remove_file(&image_path).ok();
+                        }
                     }
                 }
             }
         }
     }
+    false
 }
 
 #[cfg(windows)]
@@ -527,6 +1058,32 @@ unsafe fn open_clipboard_with_retry(max_retries: u32) -> bool {
     false
 }
 
+/// Opens the clipboard for writing with the same retries as reads. Other apps often hold it for
+/// a moment right after their own copy; if it stays open the error names the holder.
+#[cfg(windows)]
+unsafe fn open_clipboard_for_write() -> Result<(), CutboardError> {
+    if open_clipboard_with_retry(5) {
+        Ok(())
+    } else {
+        Err(CutboardError::clipboard_busy(window_tracker::get_clipboard_holder_app().map(|a| a.name)))
+    }
+}
+
+/// Run after CloseClipboard: the sequence number must have moved past the value read right after
+/// EmptyClipboard, and `format` must still be on offer.
+#[cfg(windows)]
+unsafe fn verify_clipboard_write(seq_after_empty: u32, format: u32) -> Result<(), CutboardError> {
+    use windows::Win32::System::DataExchange::{GetClipboardSequenceNumber, IsClipboardFormatAvailable};
+
+    if GetClipboardSequenceNumber() == seq_after_empty {
+        return Err("Failed to write to clipboard: the data was not accepted".into());
+    }
+    if IsClipboardFormatAvailable(format).is_err() {
+        return Err("Failed to write to clipboard: another application replaced the data".into());
+    }
+    Ok(())
+}
+
 #[cfg(windows)]
 fn read_clipboard_content() -> ClipboardContent {
     use windows::core::PCWSTR;
@@ -717,6 +1274,7 @@ unsafe fn try_read_clipboard_image() -> Option<Vec<u8>> {
 
 #[cfg(windows)]
 fn dib_to_png(dib: &[u8]) -> Option<Vec<u8>> {
+    let _t = crate::metrics::timer("image.encode");
     if dib.len() < 40 {
         return None;
     }
@@ -865,50 +1423,185 @@ fn dib_to_png(dib: &[u8]) -> Option<Vec<u8>> {
 }
 
 #[cfg(windows)]
-pub fn write_text_to_clipboard(text: &str) -> bool {
+pub fn write_text_to_clipboard(text: &str) -> Result<(), CutboardError> {
+    write_rich_text_to_clipboard(text, None)
+}
+
+/// Wraps an HTML fragment in the CF_HTML header, whose offsets count bytes from the start.
+#[cfg(windows)]
+fn build_cf_html(fragment: &str) -> Vec<u8> {
+    const HEADER_LEN: usize = 105;
+    let prefix = "<html><body>\r\n<!--StartFragment-->";
+    let suffix = "<!--EndFragment-->\r\n</body></html>";
+    let start_fragment = HEADER_LEN + prefix.len();
+    let end_fragment = start_fragment + fragment.len();
+    let end_html = end_fragment + suffix.len();
+    let header = format!(
+        "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\n",
+        HEADER_LEN, end_html, start_fragment, end_fragment
+    );
+    debug_assert_eq!(header.len(), HEADER_LEN);
+    let mut data = format!("{}{}{}{}", header, prefix, fragment, suffix).into_bytes();
+    data.push(0);
+    data
+}
+
+/// Writes plain text, plus the HTML flavor when `html` is given so rich editors keep formatting.
+#[cfg(windows)]
+pub fn write_rich_text_to_clipboard(text: &str, html: Option<&str>) -> Result<(), CutboardError> {
     use windows::Win32::Foundation::HANDLE;
     use windows::Win32::System::DataExchange::*;
     use windows::Win32::System::Memory::*;
 
     unsafe {
-        if OpenClipboard(None).is_err() {
-            return false;
-        }
+        open_clipboard_for_write()?;
 
         let _ = EmptyClipboard();
+        let seq = GetClipboardSequenceNumber();
+
+        let hash = compute_content_hash(text.as_bytes());
+        expect_write(&hash);
 
         let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
         let size = wide.len() * 2;
 
-        let success = match GlobalAlloc(GLOBAL_ALLOC_FLAGS(0x0002), size) {
+        let written: Result<(), String> = match GlobalAlloc(GLOBAL_ALLOC_FLAGS(0x0002), size) {
             Ok(hmem) => {
                 let ptr = GlobalLock(hmem) as *mut u16;
                 if ptr.is_null() {
-                    false
+                    Err("GlobalLock failed".into())
                 } else {
                     std::ptr::copy_nonoverlapping(wide.as_ptr(), ptr, wide.len());
                     let _ = GlobalUnlock(hmem);
-                    SetClipboardData(CF_UNICODETEXT, Some(HANDLE(hmem.0))).is_ok()
+                    SetClipboardData(CF_UNICODETEXT, Some(HANDLE(hmem.0)))
+                        .map(|_| ())
+                        .map_err(|e| format!("SetClipboardData failed: {}", e))
                 }
             }
-            Err(_) => false,
+            Err(e) => Err(format!("GlobalAlloc failed: {}", e)),
         };
 
+        if written.is_ok() {
+            if let Some(html) = html {
+                let format_name: Vec<u16> = "HTML Format\0".encode_utf16().collect();
+                let cf_html = RegisterClipboardFormatW(windows::core::PCWSTR(format_name.as_ptr()));
+                let data = build_cf_html(html);
+                if cf_html != 0 {
+                    if let Ok(hmem) = GlobalAlloc(GLOBAL_ALLOC_FLAGS(0x0002), data.len()) {
+                        let ptr = GlobalLock(hmem) as *mut u8;
+                        if !ptr.is_null() {
+                            std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+                            let _ = GlobalUnlock(hmem);
+                            let _ = SetClipboardData(cf_html, Some(HANDLE(hmem.0)));
+                        }
+                    }
+                }
+            }
+        }
+
         let _ = CloseClipboard();
-        success
+        let result = match written {
+            Ok(()) => verify_clipboard_write(seq, CF_UNICODETEXT),
+            Err(e) => Err(format!("Failed to write to clipboard: {}", e).into()),
+        };
+        if result.is_err() {
+            forget_write(&hash);
+        }
+        result
     }
 }
 
+/// The clipboard's contents in every format held in global memory, to be put back with
+/// `restore_clipboard` after a temporary write.
 #[cfg(windows)]
-pub fn write_image_to_clipboard(png_path: &std::path::Path) -> bool {
+pub struct ClipboardSnapshot {
+    formats: Vec<(u32, Vec<u8>)>,
+    /// Of the Unicode text, so putting it back is not recorded as a new capture.
+    text_hash: Option<String>,
+}
+
+/// `None` when the clipboard cannot be opened. Bitmaps, metafiles and palettes are GDI handles
+/// rather than memory and are left out; Windows recreates CF_BITMAP from CF_DIB.
+#[cfg(windows)]
+pub fn snapshot_clipboard() -> Option<ClipboardSnapshot> {
+    use windows::Win32::Foundation::HGLOBAL;
+    use windows::Win32::System::DataExchange::{CloseClipboard, EnumClipboardFormats, GetClipboardData};
+    use windows::Win32::System::Memory::{GlobalLock, GlobalSize, GlobalUnlock};
+
+    // CF_BITMAP, CF_METAFILEPICT, CF_PALETTE, CF_ENHMETAFILE, the CF_DSP* variants, and the
+    // CF_OWNERDISPLAY and CF_GDIOBJFIRST..CF_GDIOBJLAST ranges
+    let is_handle = |format: u32| matches!(format, 2 | 3 | 9 | 14 | 0x80..=0x8E | 0x300..=0x3FF);
+
+    let mut snapshot = ClipboardSnapshot { formats: Vec::new(), text_hash: None };
+    unsafe {
+        if !open_clipboard_with_retry(5) {
+            return None;
+        }
+        let mut format = EnumClipboardFormats(0);
+        while format != 0 {
+            if !is_handle(format) {
+                if let Ok(handle) = GetClipboardData(format) {
+                    let hmem = HGLOBAL(handle.0);
+                    let size = GlobalSize(hmem);
+                    let ptr = GlobalLock(hmem) as *const u8;
+                    if size > 0 && !ptr.is_null() {
+                        snapshot.formats.push((format, std::slice::from_raw_parts(ptr, size).to_vec()));
+                    }
+                    if !ptr.is_null() {
+                        let _ = GlobalUnlock(hmem);
+                    }
+                }
+            }
+            format = EnumClipboardFormats(format);
+        }
+        let _ = CloseClipboard();
+    }
+
+    snapshot.text_hash = snapshot.formats.iter().find(|(format, _)| *format == 13).map(|(_, data)| {
+        let units: Vec<u16> = data.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).take_while(|&u| u != 0).collect();
+        compute_content_hash(String::from_utf16_lossy(&units).as_bytes())
+    });
+    Some(snapshot)
+}
+
+/// Puts a snapshot back, replacing whatever is on the clipboard now.
+#[cfg(windows)]
+pub fn restore_clipboard(snapshot: &ClipboardSnapshot) -> Result<(), CutboardError> {
     use windows::Win32::Foundation::HANDLE;
     use windows::Win32::System::DataExchange::*;
     use windows::Win32::System::Memory::*;
 
-    let img = match image::open(png_path) {
-        Ok(img) => img.to_rgba8(),
-        Err(_) => return false,
-    };
+    unsafe {
+        open_clipboard_for_write()?;
+        let _ = EmptyClipboard();
+        if let Some(hash) = &snapshot.text_hash {
+            expect_write(hash);
+        }
+        for (format, data) in &snapshot.formats {
+            if let Ok(hmem) = GlobalAlloc(GLOBAL_ALLOC_FLAGS(0x0002), data.len()) {
+                let ptr = GlobalLock(hmem) as *mut u8;
+                if !ptr.is_null() {
+                    std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+                    let _ = GlobalUnlock(hmem);
+                    let _ = SetClipboardData(*format, Some(HANDLE(hmem.0)));
+                }
+            }
+        }
+        let _ = CloseClipboard();
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn write_image_to_clipboard(png_path: &std::path::Path) -> Result<(), CutboardError> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::DataExchange::*;
+    use windows::Win32::System::Memory::*;
+
+    let png_data = std::fs::read(png_path)
+        .map_err(|e| CutboardError::path_invalid(format!("{}: {}", png_path.display(), e)))?;
+    let img = image::load_from_memory(&png_data).map_err(|e| e.to_string())?.to_rgba8();
 
     let width = img.width() as i32;
     let height = img.height() as i32;
@@ -938,36 +1631,220 @@ pub fn write_image_to_clipboard(png_path: &std::path::Path) -> bool {
     }
 
     unsafe {
-        if OpenClipboard(None).is_err() {
-            return false;
-        }
+        open_clipboard_for_write()?;
         let _ = EmptyClipboard();
+        let seq = GetClipboardSequenceNumber();
+
+        // The monitor reads the registered PNG format first, so offering the original bytes
+        // makes the update hash to exactly what we expect (and keeps alpha for apps that read it)
+        let hash = compute_content_hash(&png_data);
+        expect_write(&hash);
 
-        let success = match GlobalAlloc(GLOBAL_ALLOC_FLAGS(0x0002), total_size) {
+        let written: Result<(), String> = match GlobalAlloc(GLOBAL_ALLOC_FLAGS(0x0002), total_size) {
             Ok(hmem) => {
                 let ptr = GlobalLock(hmem) as *mut u8;
                 if ptr.is_null() {
-                    false
+                    Err("GlobalLock failed".into())
                 } else {
                     std::ptr::copy_nonoverlapping(dib.as_ptr(), ptr, total_size);
                     let _ = GlobalUnlock(hmem);
-                    SetClipboardData(CF_DIB, Some(HANDLE(hmem.0))).is_ok()
+                    SetClipboardData(CF_DIB, Some(HANDLE(hmem.0)))
+                        .map(|_| ())
+                        .map_err(|e| format!("SetClipboardData failed: {}", e))
                 }
             }
-            Err(_) => false,
+            Err(e) => Err(format!("GlobalAlloc failed: {}", e)),
         };
 
+        if written.is_ok() {
+            let fmt_name: Vec<u16> = "PNG\0".encode_utf16().collect();
+            let cf_png = RegisterClipboardFormatW(PCWSTR(fmt_name.as_ptr()));
+            if cf_png != 0 {
+                if let Ok(hmem) = GlobalAlloc(GLOBAL_ALLOC_FLAGS(0x0002), png_data.len()) {
+                    let ptr = GlobalLock(hmem) as *mut u8;
+                    if !ptr.is_null() {
+                        std::ptr::copy_nonoverlapping(png_data.as_ptr(), ptr, png_data.len());
+                        let _ = GlobalUnlock(hmem);
+                        let _ = SetClipboardData(cf_png, Some(HANDLE(hmem.0)));
+                    }
+                }
+            }
+        }
+
+        let _ = CloseClipboard();
+        let result = match written {
+            Ok(()) => verify_clipboard_write(seq, CF_DIB),
+            Err(e) => Err(format!("Failed to write to clipboard: {}", e).into()),
+        };
+        if result.is_err() {
+            forget_write(&hash);
+        }
+        result
+    }
+}
+
+/// Empties the OS clipboard. History is left untouched.
+#[cfg(windows)]
+pub fn clear_clipboard() -> Result<(), CutboardError> {
+    use windows::Win32::System::DataExchange::*;
+
+    unsafe {
+        open_clipboard_for_write()?;
+        let result = EmptyClipboard();
+        let _ = CloseClipboard();
+        result.map_err(|e| e.to_string().into())
+    }
+}
+
+/// What is on the clipboard right now, read without recording it.
+#[derive(serde::Serialize)]
+pub struct CurrentClipboard {
+    /// Formats on offer, e.g. "CF_UNICODETEXT" or "HTML Format".
+    pub formats: Vec<String>,
+    /// "text" or "image", as the content would be captured; None when neither is present.
+    pub content_type: Option<&'static str>,
+    /// Start of the text, masked when it looks sensitive.
+    pub text_preview: Option<String>,
+    pub text_chars: Option<usize>,
+    pub image_width: Option<u32>,
+    pub image_height: Option<u32>,
+    pub source_url: Option<String>,
+    pub is_sensitive: bool,
+    /// Matches `content_hash` of a history entry with the same content.
+    pub content_hash: Option<String>,
+    /// The newest history entry with this content, if any.
+    pub entry_id: Option<i64>,
+}
+
+const CURRENT_PREVIEW_CHARS: usize = 300;
+
+/// Width and height from a PNG's IHDR chunk.
+#[cfg(windows)]
+fn png_dimensions(png: &[u8]) -> Option<(u32, u32)> {
+    if png.len() < 24 || &png[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(png[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+#[cfg(windows)]
+fn clipboard_format_name(format: u32) -> String {
+    use windows::Win32::System::DataExchange::GetClipboardFormatNameW;
+
+    let standard = match format {
+        1 => "CF_TEXT",
+        2 => "CF_BITMAP",
+        3 => "CF_METAFILEPICT",
+        7 => "CF_OEMTEXT",
+        8 => "CF_DIB",
+        13 => "CF_UNICODETEXT",
+        14 => "CF_ENHMETAFILE",
+        15 => "CF_HDROP",
+        16 => "CF_LOCALE",
+        17 => "CF_DIBV5",
+        _ => "",
+    };
+    if !standard.is_empty() {
+        return standard.to_string();
+    }
+    let mut name = [0u16; 256];
+    let len = unsafe { GetClipboardFormatNameW(format, &mut name) };
+    if len > 0 {
+        String::from_utf16_lossy(&name[..len as usize])
+    } else {
+        format!("#{}", format)
+    }
+}
+
+#[cfg(windows)]
+fn clipboard_formats() -> Vec<String> {
+    use windows::Win32::System::DataExchange::{CloseClipboard, EnumClipboardFormats};
+
+    let mut formats = Vec::new();
+    unsafe {
+        if !open_clipboard_with_retry(5) {
+            return formats;
+        }
+        let mut format = EnumClipboardFormats(0);
+        while format != 0 {
+            formats.push(clipboard_format_name(format));
+            format = EnumClipboardFormats(format);
+        }
         let _ = CloseClipboard();
-        success
     }
+    formats
+}
+
+/// Reads the clipboard for display. `language` and `regions` pick the sensitive patterns, as
+/// for captures.
+#[cfg(windows)]
+pub fn current_clipboard(language: &str, regions: &str) -> Result<CurrentClipboard, CutboardError> {
+    let formats = clipboard_formats();
+    let content = read_clipboard_content();
+    let text = content.text.filter(|t| !t.trim().is_empty());
+    let is_sensitive = text
+        .as_deref()
+        .is_some_and(|t| crate::sensitive::sensitive_kind(t, language, regions).is_some());
+    let text_preview = text.as_deref().map(|t| {
+        if is_sensitive {
+            crate::sensitive::masked_preview(t, language, regions)
+        } else {
+            t.chars().take(CURRENT_PREVIEW_CHARS).collect()
+        }
+    });
+    let (image_width, image_height) = content.image.as_deref().and_then(png_dimensions).unzip();
+    let (content_type, content_hash) = match (&text, &content.image) {
+        (Some(t), _) => (Some("text"), Some(compute_content_hash(t.as_bytes()))),
+        (None, Some(png)) => (Some("image"), Some(compute_content_hash(png))),
+        (None, None) => (None, None),
+    };
+    Ok(CurrentClipboard {
+        formats,
+        content_type,
+        text_chars: text.as_deref().map(|t| t.chars().count()),
+        text_preview,
+        image_width,
+        image_height,
+        source_url: content.source_url,
+        is_sensitive,
+        content_hash,
+        entry_id: None,
+    })
 }
 
 #[cfg(not(windows))]
-pub fn write_text_to_clipboard(_text: &str) -> bool {
-    false
+pub fn current_clipboard(_language: &str, _regions: &str) -> Result<CurrentClipboard, CutboardError> {
+    Err(CutboardError::unsupported("Clipboard access is only supported on Windows"))
+}
+
+#[cfg(windows)]
+pub fn read_clipboard_text() -> Option<String> {
+    read_clipboard_content().text
 }
 
 #[cfg(not(windows))]
-pub fn write_image_to_clipboard(_path: &std::path::Path) -> bool {
-    false
+pub fn read_clipboard_text() -> Option<String> {
+    None
+}
+
+#[cfg(not(windows))]
+pub fn write_text_to_clipboard(_text: &str) -> Result<(), CutboardError> {
+    Err(CutboardError::unsupported("Clipboard access is only supported on Windows"))
+}
+
+#[cfg(not(windows))]
+pub fn write_rich_text_to_clipboard(_text: &str, _html: Option<&str>) -> Result<(), CutboardError> {
+    Err(CutboardError::unsupported("Clipboard access is only supported on Windows"))
+}
+
+#[cfg(not(windows))]
+pub fn write_image_to_clipboard(_path: &std::path::Path) -> Result<(), CutboardError> {
+    Err(CutboardError::unsupported("Clipboard access is only supported on Windows"))
+}
+
+#[cfg(not(windows))]
+pub fn clear_clipboard() -> Result<(), CutboardError> {
+    Err(CutboardError::unsupported("Clipboard access is only supported on Windows"))
 }
@@ -0,0 +1,107 @@
+//! Clean-ups for code copied from web pages and terminals, used by the code transforms. Each one
+//! leaves text it does not recognize unchanged.
+
+use fancy_regex::Regex;
+use std::sync::LazyLock;
+
+/// `$ `, `>>> `, `PS C:\dir> ` and `C:\dir> `. A bare `>`, `%` or `#` is left alone, since it
+/// is as likely a quote or a comment.
+static PROMPT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s*)(?:\$|>>>|PS [^>\n]*>|[A-Za-z]:\\[^>\n]*>)(?: |$)").unwrap());
+/// Python's continuation prompt, only stripped alongside `>>>` lines.
+static CONTINUATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\s*)\.\.\.(?: |$)").unwrap());
+static LINE_NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*(\d+)(?:[.:|)]|(?=\s)|$)[ \t]?").unwrap());
+
+/// Removes the leading whitespace every non-blank line shares, and the whitespace of blank lines.
+pub fn dedent(text: &str) -> String {
+    let mut common: Option<&str> = None;
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        common = Some(match common {
+            None => indent,
+            Some(c) => {
+                let shared: usize = c
+                    .chars()
+                    .zip(indent.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a.len_utf8())
+                    .sum();
+                &c[..shared]
+            }
+        });
+    }
+    let common = common.unwrap_or("");
+    map_lines(text, |line| {
+        if line.trim().is_empty() {
+            String::new()
+        } else {
+            line[common.len()..].to_string()
+        }
+    })
+}
+
+/// Turns typographic quotes and primes back into ASCII quotes.
+pub fn straighten_quotes(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => '\'',
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => '"',
+            _ => c,
+        })
+        .collect()
+}
+
+/// Strips listing line numbers, but only when every non-blank line has one and they increase,
+/// so a column of figures is not mistaken for a listing.
+pub fn strip_line_numbers(text: &str) -> String {
+    let mut last = None;
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let Ok(Some(caps)) = LINE_NUMBER.captures(line) else {
+            return text.to_string();
+        };
+        let n: u64 = caps[1].parse().unwrap_or(0);
+        if last.is_some_and(|prev| n <= prev) {
+            return text.to_string();
+        }
+        last = Some(n);
+    }
+    if last.is_none() {
+        return text.to_string();
+    }
+    map_lines(text, |line| LINE_NUMBER.replace(line, "").into_owned())
+}
+
+/// Removes shell and REPL prompts, keeping the indentation before them.
+pub fn strip_prompts(text: &str) -> String {
+    let python = text.lines().any(|l| l.trim_start().starts_with(">>>"));
+    map_lines(text, |line| {
+        let stripped = PROMPT.replace(line, "$1");
+        if python && stripped == line {
+            CONTINUATION.replace(line, "$1").into_owned()
+        } else {
+            stripped.into_owned()
+        }
+    })
+}
+
+/// Every clean-up, prompts and line numbers first so the indentation they hid is shared.
+pub fn clean(text: &str) -> String {
+    dedent(&straighten_quotes(&strip_line_numbers(&strip_prompts(text))))
+}
+
+/// Applies `f` to each line, keeping the line endings and whether the text ended with one.
+fn map_lines(text: &str, f: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let (body, ending) = match line.strip_suffix("\r\n") {
+            Some(body) => (body, "\r\n"),
+            None => match line.strip_suffix('\n') {
+                Some(body) => (body, "\n"),
+                None => (line, ""),
+            },
+        };
+        out.push_str(&f(body));
+        out.push_str(ending);
+    }
+    out
+}
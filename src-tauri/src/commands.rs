@@ -1,57 +1,58 @@
-use crate::clipboard::{self, IGNORE_NEXT};
+use crate::cache::LruCache;
+use crate::clipboard;
 use crate::config::AppConfig;
-use crate::database::{AppInfo, ClipboardEntry, SourceInfo};
+use crate::database::{AppInfo, ClipboardEntry, CollectionInfo, DeleteSummary, EntryEvent, EntryOrigin, EntryStub, RuleInfo, SourceInfo, TemplateInfo, TextCapture, MANUAL_APP_EXE};
+use crate::error::CutboardError;
 use crate::{ConfigPath, DbState};
 use base64::{engine::general_purpose::STANDARD, Engine};
-use serde::Serialize;
-use std::collections::VecDeque;
-use std::io::Write;
+use serde::{Deserialize, Serialize};
 use std::sync::atomic::Ordering;
 use tauri::{Emitter, Manager};
 
-const IMAGE_CACHE_MAX: usize = 50;
+/// Budget until `apply_cache_budgets` runs with the configured `image_cache_mb`.
+const DEFAULT_IMAGE_CACHE_BYTES: usize = 64 * 1024 * 1024;
 
-struct ImageLruCache {
-    order: VecDeque<String>,
-    map: std::collections::HashMap<String, String>,
+/// Base64 data URLs by image file name, bounded by total size since a single screenshot can be
+/// tens of megabytes once encoded.
+static IMAGE_B64_CACHE: std::sync::LazyLock<std::sync::Mutex<LruCache<String, String>>> =
+    std::sync::LazyLock::new(|| std::sync::Mutex::new(LruCache::new().max_weight(DEFAULT_IMAGE_CACHE_BYTES, String::len)));
+
+/// Sizes the image and icon caches from `image_cache_mb` and `icon_cache_mb`.
+pub fn apply_cache_budgets(config: &AppConfig) {
+    const MB: usize = 1024 * 1024;
+    IMAGE_B64_CACHE.lock().unwrap_or_else(|e| e.into_inner()).set_max_weight(config.image_cache_mb as usize * MB);
+    crate::window_tracker::set_icon_cache_budget(config.icon_cache_mb as usize * MB);
 }
 
-impl ImageLruCache {
-    fn new() -> Self {
-        Self { order: VecDeque::new(), map: std::collections::HashMap::new() }
-    }
-    fn get(&mut self, key: &str) -> Option<&String> {
-        if self.map.contains_key(key) {
-            self.order.retain(|k| k != key);
-            self.order.push_back(key.to_string());
-            self.map.get(key)
-        } else {
-            None
-        }
-    }
-    fn insert(&mut self, key: String, value: String) {
-        if self.map.len() >= IMAGE_CACHE_MAX {
-            if let Some(oldest) = self.order.pop_front() {
-                self.map.remove(&oldest);
-            }
-        }
-        self.order.push_back(key.clone());
-        self.map.insert(key, value);
+/// Whether revealing sensitive entries and unlocking require a Windows Hello confirmation.
+fn windows_hello_required(app: &tauri::AppHandle) -> bool {
+    let config_path = app.state::<ConfigPath>();
+    AppConfig::load(&config_path.0).use_windows_hello && crate::hello::is_available()
+}
+
+/// Sensitive text only leaves the backend through `reveal_sensitive_entry`; listings carry a
+/// masked preview in its place.
+fn redact_sensitive(app: &tauri::AppHandle, mut entries: Vec<ClipboardEntry>) -> Vec<ClipboardEntry> {
+    if !entries.iter().any(|e| e.is_sensitive) {
+        return entries;
     }
-    fn remove(&mut self, key: &str) {
-        self.map.remove(key);
-        self.order.retain(|k| k != key);
+    let config = AppConfig::load(&app.state::<ConfigPath>().0);
+    for entry in entries.iter_mut().filter(|e| e.is_sensitive) {
+        entry.masked_preview = entry
+            .text_content
+            .take()
+            .map(|text| crate::sensitive::masked_preview(&text, &config.language, &config.sensitive_regions));
+        entry.html_content = None;
     }
+    entries
 }
 
-static IMAGE_B64_CACHE: std::sync::LazyLock<std::sync::Mutex<ImageLruCache>> =
-    std::sync::LazyLock::new(|| std::sync::Mutex::new(ImageLruCache::new()));
-
+/// Hidden apps are left out unless `include_hidden` is set.
 #[tauri::command]
-pub fn get_apps(app: tauri::AppHandle) -> Result<Vec<AppInfo>, String> {
+pub fn get_apps(app: tauri::AppHandle, include_hidden: Option<bool>) -> Result<Vec<AppInfo>, CutboardError> {
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    db.get_apps().map_err(|e| e.to_string())
+    db.get_apps(include_hidden.unwrap_or(false)).map_err(Into::into)
 }
 
 #[tauri::command]
@@ -63,7 +64,8 @@ pub fn get_entries(
     source_domain: Option<String>,
     page: Option<i64>,
     page_size: Option<i64>,
-) -> Result<Vec<ClipboardEntry>, String> {
+) -> Result<Vec<ClipboardEntry>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
     db.get_entries(
@@ -74,14 +76,63 @@ pub fn get_entries(
         page.unwrap_or(1),
         page_size.unwrap_or(20),
     )
-    .map_err(|e| e.to_string())
+    .map(|entries| redact_sensitive(&app, entries))
+    .map_err(Into::into)
+}
+
+/// Ids of every entry matching the `get_entries` filters, so long lists can be virtualized and
+/// their rows loaded with `get_entries_by_ids` as they scroll into view.
+#[tauri::command]
+pub fn get_entry_ids(
+    app: tauri::AppHandle,
+    app_id: i64,
+    content_type: String,
+    search: Option<String>,
+    source_domain: Option<String>,
+) -> Result<Vec<EntryStub>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_entry_ids(app_id, &content_type, search.as_deref().unwrap_or(""), source_domain.as_deref().unwrap_or(""))
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_entries_by_ids(app: tauri::AppHandle, ids: Vec<i64>) -> Result<Vec<ClipboardEntry>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_entries_by_ids(&ids).map(|entries| redact_sensitive(&app, entries)).map_err(Into::into)
+}
+
+/// Records an action in the audit log when it is enabled. Failures never block the action.
+fn audit(app: &tauri::AppHandle, db: &crate::database::Database, action: &str, entry_id: Option<i64>, detail: Option<&str>) {
+    let config_path = app.state::<ConfigPath>();
+    if AppConfig::load(&config_path.0).audit_log_enabled {
+        let _ = db.log_audit(action, entry_id, detail);
+    }
+}
+
+#[tauri::command]
+pub fn get_audit_log(app: tauri::AppHandle, page: Option<i64>, page_size: Option<i64>) -> Result<Vec<crate::database::AuditRecord>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let page_size = page_size.unwrap_or(100);
+    db.get_audit_log(page_size, (page.unwrap_or(1) - 1).max(0) * page_size)
+        .map_err(Into::into)
 }
 
 #[tauri::command]
-pub fn delete_entry(app: tauri::AppHandle, id: i64) -> Result<(), String> {
+pub fn delete_entry(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    if let Some(image_filename) = db.delete_entry(id).map_err(|e| e.to_string())? {
+    if db.is_entry_locked(id)? {
+        return Err(CutboardError::invalid_input("Entry is locked; unlock it before deleting"));
+    }
+    audit(&app, &db, "entry_deleted", Some(id), None);
+    if let Some(image_filename) = db.delete_entry(id)? {
         let image_path = db.images_dir().join(&image_filename);
         std::fs::remove_file(image_path).ok();
         if let Ok(mut cache) = IMAGE_B64_CACHE.lock() { cache.remove(&image_filename); }
@@ -89,332 +140,2342 @@ pub fn delete_entry(app: tauri::AppHandle, id: i64) -> Result<(), String> {
     Ok(())
 }
 
+/// With `dry_run`, only reports what would be deleted.
 #[tauri::command]
-pub fn delete_entries_by_domain(app: tauri::AppHandle, app_id: i64, domain: String) -> Result<(), String> {
+pub fn delete_entries_by_domain(
+    app: tauri::AppHandle,
+    app_id: i64,
+    domain: String,
+    dry_run: Option<bool>,
+) -> Result<DeleteSummary, CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    let image_paths = db.delete_entries_by_domain(app_id, &domain).map_err(|e| e.to_string())?;
+    let summary = db.domain_delete_summary(app_id, &domain)?;
+    if dry_run.unwrap_or(false) {
+        return Ok(summary);
+    }
+    let image_paths = db.delete_entries_by_domain(app_id, &domain)?;
+    audit(&app, &db, "entries_deleted", None, Some(&format!("app {} domain {}: {} entries", app_id, domain, summary.entries)));
     let images_dir = db.images_dir();
     for filename in image_paths {
         std::fs::remove_file(images_dir.join(&filename)).ok();
     }
     let _ = app.emit("clipboard-changed", ());
-    Ok(())
+    Ok(DeleteSummary { dry_run: false, ..summary })
 }
 
+/// With `dry_run`, only reports what would be deleted.
 #[tauri::command]
-pub fn clear_app_entries(app: tauri::AppHandle, app_id: i64) -> Result<(), String> {
+pub fn clear_app_entries(app: tauri::AppHandle, app_id: i64, dry_run: Option<bool>) -> Result<DeleteSummary, CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    let image_paths = db.clear_app_entries(app_id).map_err(|e| e.to_string())?;
+    let summary = db.clear_app_summary(app_id)?;
+    if dry_run.unwrap_or(false) {
+        return Ok(summary);
+    }
+    let image_paths = db.clear_app_entries(app_id)?;
+    audit(&app, &db, "app_cleared", None, Some(&format!("app {}: {} entries", app_id, summary.entries)));
     let images_dir = db.images_dir();
     for filename in image_paths {
         std::fs::remove_file(images_dir.join(&filename)).ok();
     }
-    Ok(())
+    Ok(DeleteSummary { dry_run: false, ..summary })
 }
 
+/// With `dry_run`, only reports what would be deleted.
 #[tauri::command]
-pub fn clear_database(app: tauri::AppHandle) -> Result<(), String> {
+pub fn clear_database(app: tauri::AppHandle, dry_run: Option<bool>) -> Result<DeleteSummary, CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    let image_paths = db.clear_all_entries().map_err(|e| e.to_string())?;
+    let summary = db.clear_all_summary()?;
+    if dry_run.unwrap_or(false) {
+        return Ok(summary);
+    }
+    let image_paths = db.clear_all_entries()?;
+    audit(&app, &db, "database_cleared", None, Some(&format!("{} entries", summary.entries)));
     let images_dir = db.images_dir();
     for filename in image_paths {
         std::fs::remove_file(images_dir.join(&filename)).ok();
     }
-    if let Ok(mut cache) = IMAGE_B64_CACHE.lock() { *cache = ImageLruCache::new(); }
+    if let Ok(mut cache) = IMAGE_B64_CACHE.lock() { cache.clear(); }
     let _ = app.emit("clipboard-changed", ());
-    Ok(())
+    Ok(DeleteSummary { dry_run: false, ..summary })
 }
 
+/// `format` picks how text goes back: "plain" drops the HTML, "markdown" copies the HTML converted
+/// to Markdown (or the text as is when there is none), and "html" renders Markdown text as rich
+/// text.
+/// By default the stored HTML is kept unless the app is set to plain text.
 #[tauri::command]
-pub fn copy_entry_to_clipboard(app: tauri::AppHandle, id: i64) -> Result<(), String> {
+pub fn copy_entry_to_clipboard(app: tauri::AppHandle, id: i64, format: Option<String>) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    let entry = db.get_entry_by_id(id).map_err(|e| e.to_string())?;
-
-    IGNORE_NEXT.store(true, Ordering::SeqCst);
+    let entry = db.get_entry_by_id(id)?;
 
     match entry.content_type.as_str() {
         "text" => {
-            let text = entry.text_content.as_ref().ok_or("Text content is empty")?;
-            if !clipboard::write_text_to_clipboard(text) {
-                IGNORE_NEXT.store(false, Ordering::SeqCst);
-                return Err("Failed to write to clipboard".into());
+            let text = entry.text_content.as_ref().ok_or_else(|| CutboardError::not_found("Text content is empty"))?;
+            let stored_html = entry.html_content.as_deref();
+            match format.as_deref() {
+                None | Some("") => {
+                    let plain_text = db.get_app_settings(entry.app_id)?.plain_text;
+                    clipboard::write_rich_text_to_clipboard(text, stored_html.filter(|_| !plain_text))?;
+                }
+                Some("plain") => clipboard::write_text_to_clipboard(text)?,
+                Some("markdown") => {
+                    let markdown = stored_html.map(crate::markdown::from_html);
+                    clipboard::write_text_to_clipboard(markdown.as_deref().unwrap_or(text))?
+                }
+                Some("html") => clipboard::write_rich_text_to_clipboard(text, Some(&crate::markdown::to_html(text)))?,
+                Some(other) => return Err(CutboardError::invalid_input(format!("Unknown copy format: {}", other))),
             }
         }
         "image" => {
-            let filename = entry.image_path.as_ref().ok_or("Image path is empty")?;
+            let filename = entry.image_path.as_ref().ok_or_else(|| CutboardError::not_found("Image path is empty"))?;
             let path = db.images_dir().join(filename);
-            if !clipboard::write_image_to_clipboard(&path) {
-                IGNORE_NEXT.store(false, Ordering::SeqCst);
-                return Err("Failed to write image to clipboard".into());
-            }
+            clipboard::write_image_to_clipboard(&path)?;
         }
-        _ => {
-            IGNORE_NEXT.store(false, Ordering::SeqCst);
-            return Err("Unknown content type".into());
+        _ => return Err(CutboardError::invalid_input("Unknown content type")),
+    }
+    let _ = db.record_entry_event(id, "reused", None);
+    audit(&app, &db, "entry_copied", Some(id), None);
+    Ok(())
+}
+
+/// Prints the entry's text or image on the default printer. The job is named after the entry's
+/// title, or its capture time when it has none.
+#[tauri::command(async)]
+pub fn print_entry(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let (entry, images_dir) = {
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        (db.get_entry_by_id(id)?, db.images_dir())
+    };
+    let doc_name = format!("CutBoard - {}", entry.title.as_deref().unwrap_or(&entry.created_at));
+    // The database stays unlocked while the spooler works
+    match entry.content_type.as_str() {
+        "text" => {
+            let text = entry.text_content.as_deref().ok_or_else(|| CutboardError::not_found("Text is empty"))?;
+            crate::print::print_text(&doc_name, text)?;
+        }
+        "image" => {
+            let filename = entry.image_path.as_ref().ok_or_else(|| CutboardError::not_found("Image path is empty"))?;
+            crate::print::print_image(&doc_name, &images_dir.join(filename))?;
         }
+        _ => return Err(CutboardError::invalid_input("Unknown content type")),
     }
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    audit(&app, &db, "entry_printed", Some(id), None);
+    Ok(())
+}
+
+/// Copies an image entry as a `data:image/png;base64,…` URI, for pasting into HTML or CSS.
+#[tauri::command(async)]
+pub fn copy_image_as_data_uri(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let (_, png) = read_entry_image(&app, id)?;
+    clipboard::write_text_to_clipboard(&format!("data:image/png;base64,{}", STANDARD.encode(&png)))?;
+    record_image_copy(&app, id, "data_uri")
+}
+
+/// Copies an image entry as a Markdown image, for docs and issues. The image is embedded as a data
+/// URI unless `link` is set, which points at the stored file instead. The entry's title becomes
+/// the alt text.
+#[tauri::command(async)]
+pub fn copy_image_as_markdown(app: tauri::AppHandle, id: i64, link: Option<bool>) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let markdown = if link.unwrap_or(false) {
+        let (entry, images_dir) = {
+            let state = app.state::<DbState>();
+            let db = state.0.lock().map_err(|e| e.to_string())?;
+            (db.get_entry_by_id(id)?, db.images_dir())
+        };
+        let filename = entry
+            .image_path
+            .as_ref()
+            .filter(|_| entry.content_type == "image")
+            .ok_or_else(|| CutboardError::invalid_input("Not an image entry"))?;
+        let path = images_dir.join(filename);
+        // Forward slashes and escaped spaces keep the URL intact in Markdown
+        let path = path.to_string_lossy().replace('\\', "/").replace(' ', "%20");
+        format!("![{}](file:///{})", markdown_alt(&entry), path.trim_start_matches('/'))
+    } else {
+        let (entry, png) = read_entry_image(&app, id)?;
+        format!("![{}](data:image/png;base64,{})", markdown_alt(&entry), STANDARD.encode(&png))
+    };
+    clipboard::write_text_to_clipboard(&markdown)?;
+    record_image_copy(&app, id, "markdown")
+}
+
+fn markdown_alt(entry: &ClipboardEntry) -> String {
+    entry.title.as_deref().unwrap_or("").replace(['[', ']'], "")
+}
+
+fn record_image_copy(app: &tauri::AppHandle, id: i64, format: &str) -> Result<(), CutboardError> {
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let _ = db.record_entry_event(id, "reused", None);
+    audit(app, &db, "entry_copied", Some(id), Some(format));
     Ok(())
 }
 
+/// Rotates, crops or blacks out parts of an image entry and saves the result as a new entry
+/// derived from it, leaving the original as it was. Returns the new entry's id.
+#[tauri::command(async)]
+pub fn edit_image(app: tauri::AppHandle, id: i64, ops: Vec<crate::image_edit::ImageOp>) -> Result<i64, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let (source, png) = read_entry_image(&app, id)?;
+    // Decoding and encoding happen without holding the database
+    let edited = crate::image_edit::apply(&png, &ops)?;
+    save_derived_image(&app, &source, &edited, "edited")
+}
+
+/// Finds text in an image entry, e.g. a screenshot, and saves a copy with every sensitive word
+/// blacked out. Returns the new entry's id, or `None` when nothing sensitive was found.
+#[tauri::command(async)]
+pub fn auto_redact_image(app: tauri::AppHandle, id: i64) -> Result<Option<i64>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let (source, png) = read_entry_image(&app, id)?;
+    let config = AppConfig::load(&app.state::<ConfigPath>().0);
+    match crate::image_edit::auto_redact(&png, &config.language, &config.sensitive_regions)? {
+        Some(redacted) => save_derived_image(&app, &source, &redacted, "redacted").map(Some),
+        None => Ok(None),
+    }
+}
+
+fn read_entry_image(app: &tauri::AppHandle, id: i64) -> Result<(ClipboardEntry, Vec<u8>), CutboardError> {
+    let state = app.state::<DbState>();
+    let (source, images_dir) = {
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        (db.get_entry_by_id(id)?, db.images_dir())
+    };
+    let filename = source
+        .image_path
+        .as_ref()
+        .filter(|_| source.content_type == "image")
+        .ok_or_else(|| CutboardError::invalid_input("Not an image entry"))?;
+    let png = std::fs::read(images_dir.join(filename))?;
+    Ok((source, png))
+}
+
+/// Stores an edited image as a new entry derived from `source` and returns its id.
+fn save_derived_image(app: &tauri::AppHandle, source: &ClipboardEntry, png: &[u8], kind: &str) -> Result<i64, CutboardError> {
+    let state = app.state::<DbState>();
+    let images_dir = state.0.lock().map_err(|e| e.to_string())?.images_dir();
+    let hash = clipboard::compute_content_hash(png);
+    let filename = clipboard::image_filename(&hash);
+    let path = images_dir.join(&filename);
+    if !path.exists() {
+        clipboard::write_image_file(&path, png)?;
+    }
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let new_id = db.insert_derived_image_entry(source, &filename, &hash, kind)?;
+    audit(app, &db, &format!("entry_image_{}", kind), Some(source.id), Some(&new_id.to_string()));
+    crate::event_stream::entry_created(&db, new_id);
+    drop(db);
+    let _ = app.emit("clipboard-changed", "image");
+    Ok(new_id)
+}
+
+/// The cells of an entry captured as a table, or of its text when it parses as one now.
 #[tauri::command]
-pub fn get_image_base64(app: tauri::AppHandle, image_path: String) -> Result<String, String> {
-    if image_path.contains("..") || image_path.contains('/') || image_path.contains('\\') {
-        return Err("Invalid image path".into());
+pub fn get_entry_table(app: tauri::AppHandle, id: i64) -> Result<Option<Vec<Vec<String>>>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    entry_table(&db, id)
+}
+
+fn entry_table(db: &crate::database::Database, id: i64) -> Result<Option<Vec<Vec<String>>>, CutboardError> {
+    if let Some(rows) = db.get_entry_table(id)? {
+        return Ok(Some(rows));
+    }
+    let entry = db.get_entry_by_id(id)?;
+    if entry.is_sensitive {
+        return Ok(None);
+    }
+    Ok(entry.text_content.as_deref().and_then(|text| crate::table::detect(text, entry.html_content.as_deref())))
+}
+
+/// The entry's JSON, XML or YAML text, `style` "pretty" (the default) or "minify". Entries
+/// captured before detection are recognized on the fly.
+#[tauri::command(async)]
+pub fn format_entry(app: tauri::AppHandle, id: i64, style: Option<String>) -> Result<String, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let pretty = match style.as_deref().unwrap_or("pretty") {
+        "pretty" => true,
+        "minify" => false,
+        other => return Err(CutboardError::invalid_input(format!("Unknown format style: {}", other))),
+    };
+    let entry = {
+        let state = app.state::<DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        db.get_entry_by_id(id)?
+    };
+    if entry.is_sensitive {
+        return Err(CutboardError::invalid_input("Sensitive entries are not formatted"));
     }
+    let text = entry
+        .text_content
+        .filter(|_| entry.content_type == "text")
+        .ok_or_else(|| CutboardError::invalid_input("Only text entries can be formatted"))?;
+    let kind = match entry.detected_kind.as_deref() {
+        Some(kind @ ("json" | "xml" | "yaml")) => kind,
+        _ => crate::structured::detect(&text).ok_or_else(|| CutboardError::invalid_input("Entry is not JSON, XML or YAML"))?,
+    };
+    crate::structured::format(&text, kind, pretty)
+}
 
-    {
-        let mut cache = IMAGE_B64_CACHE.lock().unwrap_or_else(|e| e.into_inner());
-        if let Some(cached) = cache.get(&image_path) {
-            return Ok(cached.clone());
+/// Copies a table entry as "csv", "markdown" or "html". HTML goes on the clipboard as formatted
+/// content with tab separated text alongside, so it pastes as a table into documents and
+/// spreadsheets alike.
+#[tauri::command]
+pub fn copy_entry_as_table(app: tauri::AppHandle, id: i64, format: String) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let rows = entry_table(&db, id)?.ok_or_else(|| CutboardError::invalid_input("Entry is not a table"))?;
+    match format.as_str() {
+        "csv" => clipboard::write_text_to_clipboard(&crate::table::to_csv(&rows))?,
+        "markdown" => clipboard::write_text_to_clipboard(&crate::table::to_markdown(&rows))?,
+        "html" => {
+            let tsv = rows.iter().map(|row| row.join("\t")).collect::<Vec<_>>().join("\n");
+            clipboard::write_rich_text_to_clipboard(&tsv, Some(&crate::table::to_html(&rows)))?
         }
+        other => return Err(CutboardError::invalid_input(format!("Unknown table format: {}", other))),
     }
+    let _ = db.record_entry_event(id, "reused", None);
+    audit(&app, &db, "entry_copied", Some(id), Some(&format!("table_{}", format)));
+    Ok(())
+}
 
+/// Joins the text entries in the given order and copies the result as plain text. `style` is
+/// "bullets", "numbered", "csv" (one clip per row) or "json" (an array of strings). Images are
+/// skipped.
+#[tauri::command]
+pub fn copy_entries_as_list(app: tauri::AppHandle, ids: Vec<i64>, style: String) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    let images_dir = db.images_dir();
-    let full_path = images_dir.join(&image_path);
-    let canonical = full_path.canonicalize().map_err(|e| e.to_string())?;
-    let canonical_base = images_dir.canonicalize().map_err(|e| e.to_string())?;
-    if !canonical.starts_with(&canonical_base) {
-        return Err("Path traversal denied".into());
+    let mut items = Vec::with_capacity(ids.len());
+    for &id in &ids {
+        if let Some(text) = db.get_entry_by_id(id)?.text_content {
+            items.push((id, text));
+        }
     }
-    let data = std::fs::read(&canonical).map_err(|e| e.to_string())?;
-    let result = format!("data:image/png;base64,{}", STANDARD.encode(&data));
-
-    {
-        let mut cache = IMAGE_B64_CACHE.lock().unwrap_or_else(|e| e.into_inner());
-        cache.insert(image_path, result.clone());
+    if items.is_empty() {
+        return Err(CutboardError::invalid_input("None of the selected entries has text"));
     }
+    let texts: Vec<&str> = items.iter().map(|(_, t)| t.as_str()).collect();
+    let list = format_list(&texts, &style)?;
+    clipboard::write_text_to_clipboard(&list)?;
+    for (id, _) in &items {
+        let _ = db.record_entry_event(*id, "reused", None);
+    }
+    audit(&app, &db, "entries_copied", None, Some(&format!("{} as {}", items.len(), style)));
+    Ok(())
+}
 
-    Ok(result)
+fn format_list(items: &[&str], style: &str) -> Result<String, CutboardError> {
+    // Continuation lines line up under the first line's text
+    let list_item = |marker: String, text: &str| {
+        let indent = " ".repeat(marker.chars().count());
+        let mut lines = text.trim_end().lines();
+        let mut out = format!("{}{}", marker, lines.next().unwrap_or(""));
+        for line in lines {
+            out.push('\n');
+            if !line.is_empty() {
+                out.push_str(&indent);
+                out.push_str(line);
+            }
+        }
+        out
+    };
+    let csv_field = |text: &str| {
+        if text.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.to_string()
+        }
+    };
+    let lines: Vec<String> = match style {
+        "bullets" => items.iter().map(|t| list_item("- ".into(), t)).collect(),
+        "numbered" => items.iter().enumerate().map(|(i, t)| list_item(format!("{}. ", i + 1), t)).collect(),
+        "csv" => items.iter().map(|t| csv_field(t.trim_end())).collect(),
+        "json" => return serde_json::to_string_pretty(items).map_err(|e| e.to_string().into()),
+        other => return Err(CutboardError::invalid_input(format!("Unknown list style: {}", other))),
+    };
+    Ok(lines.join("\n"))
 }
 
+/// Adds text and image files to the history, as sent from Explorer.
 #[tauri::command]
-pub fn get_images_base64_batch(
+pub fn import_files(app: tauri::AppHandle, paths: Vec<String>) -> Result<crate::import::ImportSummary, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let config = AppConfig::load(&app.state::<ConfigPath>().0);
+    finish_import(&app, paths.len(), |db| crate::import::import_files(db, &config, &paths))
+}
+
+/// Adds text and image files dropped on the window, which sends their contents.
+#[tauri::command]
+pub fn import_dropped_files(
     app: tauri::AppHandle,
-    image_paths: Vec<String>,
-) -> Result<std::collections::HashMap<String, String>, String> {
+    files: Vec<crate::import::DroppedFile>,
+) -> Result<crate::import::ImportSummary, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let config = AppConfig::load(&app.state::<ConfigPath>().0);
+    finish_import(&app, files.len(), |db| crate::import::import_dropped(db, &config, &files))
+}
+
+fn finish_import(
+    app: &tauri::AppHandle,
+    total: usize,
+    import: impl FnOnce(&crate::database::Database) -> rusqlite::Result<(crate::import::ImportSummary, Option<AppInfo>)>,
+) -> Result<crate::import::ImportSummary, CutboardError> {
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    let images_dir = db.images_dir();
-    let canonical_base = images_dir.canonicalize().map_err(|e| e.to_string())?;
+    let (summary, new_app) = import(&db)?;
+    if !summary.entry_ids.is_empty() {
+        let detail = format!("{} of {}", summary.entry_ids.len(), total);
+        audit(app, &db, "files_imported", None, Some(&detail));
+    }
+    drop(db);
 
-    let mut result = std::collections::HashMap::new();
-    let mut cache = IMAGE_B64_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(info) = new_app {
+        let _ = app.emit("app-added", info);
+    }
+    if !summary.entry_ids.is_empty() {
+        let _ = app.emit("clipboard-changed", "imported");
+    }
+    Ok(summary)
+}
 
-    for path in &image_paths {
-        if path.contains("..") || path.contains('/') || path.contains('\\') {
-            continue;
+#[tauri::command]
+pub fn get_app_settings(app: tauri::AppHandle, app_id: i64) -> Result<crate::database::AppSettings, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_app_settings(app_id).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn set_app_settings(app: tauri::AppHandle, app_id: i64, settings: crate::database::AppSettings) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.set_app_settings(app_id, &settings).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_entry_insights(app: tauri::AppHandle, id: i64) -> Result<Vec<crate::insights::Insight>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let entry = db.get_entry_by_id(id)?;
+    if entry.is_sensitive {
+        return Ok(Vec::new());
+    }
+    Ok(entry.text_content.as_deref().map(crate::insights::for_text).unwrap_or_default())
+}
+
+/// Sizes and counts for the detail pane. Text counts are `None` for image entries and the image
+/// fields `None` for text ones.
+#[derive(Serialize, Default)]
+pub struct EntryStats {
+    pub chars: Option<usize>,
+    pub words: Option<usize>,
+    pub lines: Option<usize>,
+    /// UTF-8 size of the text, or the image file's size.
+    pub bytes: u64,
+    pub image_width: Option<u32>,
+    pub image_height: Option<u32>,
+}
+
+/// Each CJK character counts as a word, since those scripts do not separate words with spaces.
+fn count_words(text: &str) -> usize {
+    // Kana, CJK ideographs and their extensions
+    let is_cjk = |c: char| {
+        matches!(
+            c,
+            '\u{3040}'..='\u{30FF}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}'
+                | '\u{F900}'..='\u{FAFF}' | '\u{20000}'..='\u{2FA1F}'
+        )
+    };
+    let mut words = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk(c) {
+            words += 1;
+            in_word = false;
+        } else if c.is_whitespace() {
+            in_word = false;
+        } else if !in_word {
+            words += 1;
+            in_word = true;
         }
-        if let Some(cached) = cache.get(path) {
-            result.insert(path.clone(), cached.clone());
+    }
+    words
+}
+
+/// Checks the hotkey, clipboard listener, database, images folder and language files, so the UI
+/// can show what to fix instead of a feature failing silently.
+#[tauri::command]
+pub fn run_self_test(app: tauri::AppHandle) -> Result<crate::selftest::SelfTestReport, CutboardError> {
+    let config = AppConfig::load(&app.state::<ConfigPath>().0);
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    Ok(crate::selftest::run(&db, &config))
+}
+
+#[tauri::command]
+pub fn get_entry_stats(app: tauri::AppHandle, id: i64) -> Result<EntryStats, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let entry = db.get_entry_by_id(id)?;
+    let images_dir = db.images_dir();
+    drop(db);
+
+    if entry.content_type == "image" {
+        let mut stats = EntryStats::default();
+        if let Some(file) = &entry.image_path {
+            let path = images_dir.join(file);
+            stats.bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            if let Ok((w, h)) = image::image_dimensions(&path) {
+                stats.image_width = Some(w);
+                stats.image_height = Some(h);
+            }
+        }
+        return Ok(stats);
+    }
+    let text = entry.text_content.unwrap_or_default();
+    Ok(EntryStats {
+        chars: Some(text.chars().count()),
+        words: Some(count_words(&text)),
+        lines: Some(text.lines().count()),
+        bytes: text.len() as u64,
+        ..Default::default()
+    })
+}
+
+const MAX_FIND_MATCHES: usize = 1000;
+
+/// One occurrence in an entry's text. Offsets are in UTF-16 code units, as the viewer's strings
+/// index them.
+#[derive(Serialize)]
+pub struct TextMatch {
+    pub start: usize,
+    pub end: usize,
+    /// 1-based.
+    pub line: usize,
+}
+
+#[derive(Serialize)]
+pub struct FindResult {
+    pub matches: Vec<TextMatch>,
+    /// All occurrences, including those past the returned ones.
+    pub total: usize,
+}
+
+/// Finds `query` in the text in a single pass. Case folding is per character, so every folded
+/// char lines up with the original one.
+fn find_matches(text: &str, query: &str, case_sensitive: bool) -> FindResult {
+    let fold = |c: char| if case_sensitive { c } else { c.to_lowercase().next().unwrap_or(c) };
+    let haystack: String = text.chars().map(fold).collect();
+    let needle: String = query.chars().map(fold).collect();
+    let needle_units: usize = query.chars().map(char::len_utf16).sum();
+
+    let mut result = FindResult { matches: Vec::new(), total: 0 };
+    let mut chars = haystack.char_indices().peekable();
+    let (mut units, mut line) = (0, 1);
+    for (byte_start, _) in haystack.match_indices(needle.as_str()) {
+        result.total += 1;
+        if result.matches.len() == MAX_FIND_MATCHES {
             continue;
         }
-        let full_path = images_dir.join(path);
-        if let Ok(canonical) = full_path.canonicalize() {
-            if canonical.starts_with(&canonical_base) {
-                if let Ok(data) = std::fs::read(&canonical) {
-                    let b64 = format!("data:image/png;base64,{}", STANDARD.encode(&data));
-                    cache.insert(path.clone(), b64.clone());
-                    result.insert(path.clone(), b64);
-                }
+        while let Some(&(i, c)) = chars.peek() {
+            if i >= byte_start {
+                break;
             }
+            units += c.len_utf16();
+            if c == '\n' {
+                line += 1;
+            }
+            chars.next();
         }
+        result.matches.push(TextMatch { start: units, end: units + needle_units, line });
     }
-    Ok(result)
+    result
+}
+
+/// Occurrences of `query` in a text entry, so the viewer can jump between them in large pastes.
+#[tauri::command]
+pub fn find_in_entry(
+    app: tauri::AppHandle,
+    id: i64,
+    query: String,
+    case_sensitive: Option<bool>,
+) -> Result<FindResult, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    if query.is_empty() {
+        return Err(CutboardError::invalid_input("Query is empty"));
+    }
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let entry = db.get_entry_by_id(id)?;
+    drop(db);
+    let text = match entry.text_content {
+        Some(text) if !entry.is_sensitive => text,
+        _ => return Ok(FindResult { matches: Vec::new(), total: 0 }),
+    };
+    Ok(find_matches(&text, &query, case_sensitive.unwrap_or(false)))
+}
+
+/// Writes derived text (transformed content, template output) to the clipboard without it
+/// being captured as a new history entry.
+#[tauri::command]
+pub fn copy_text_without_recording(text: String) -> Result<(), CutboardError> {
+    clipboard::write_text_to_clipboard(&text)
+}
+
+/// The live clipboard for a "currently on the clipboard" row, read without recording it.
+#[tauri::command]
+pub fn get_current_clipboard(app: tauri::AppHandle) -> Result<clipboard::CurrentClipboard, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let config = AppConfig::load(&app.state::<ConfigPath>().0);
+    let mut current = clipboard::current_clipboard(&config.language, &config.sensitive_regions)?;
+    if let (Some(content_type), Some(hash)) = (current.content_type, current.content_hash.as_deref()) {
+        let state = app.state::<DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        current.entry_id = db.find_entry_by_hash(content_type, hash)?;
+    }
+    Ok(current)
+}
+
+/// Records the clipboard even when it repeats the last capture or comes from an ignored owner,
+/// e.g. after capture was paused. Returns false when there was nothing to record.
+#[tauri::command(async)]
+pub fn capture_now() -> Result<bool, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    Ok(clipboard::capture_now())
+}
+
+/// Scrubs the OS clipboard (e.g. after pasting a password) without touching history.
+#[tauri::command]
+pub fn clear_system_clipboard() -> Result<(), CutboardError> {
+    clipboard::clear_clipboard()
+}
+
+#[tauri::command]
+pub fn add_manual_entry(app: tauri::AppHandle, text: String, tags: Option<Vec<String>>) -> Result<i64, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    if text.trim().is_empty() {
+        return Err(CutboardError::invalid_input("Text content is empty"));
+    }
+    let config = {
+        let config_path = app.state::<ConfigPath>();
+        AppConfig::load(&config_path.0)
+    };
+    let hash = clipboard::compute_content_hash(text.as_bytes());
+    let sensitive_kind = crate::sensitive::sensitive_kind(&text, &config.language, &config.sensitive_regions);
+
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let (app_id, app_created) = db.get_or_create_app("CutBoard", MANUAL_APP_EXE, None, None)?;
+    let id = db.upsert_text_entry_with_html(
+        app_id,
+        &TextCapture { text: &text, hash: &hash, sensitive_kind, ..Default::default() },
+        None,
+        &EntryOrigin::default(),
+    )?;
+    if let Some(tags) = tags {
+        db.add_entry_tags(id, &tags)?;
+    }
+    crate::event_stream::entry_created(&db, id);
+    let new_app = if app_created { db.get_app_by_id(app_id).ok() } else { None };
+    drop(db);
+
+    if let Some(info) = new_app {
+        let _ = app.emit("app-added", info);
+    }
+    let _ = app.emit("clipboard-changed", "text");
+    Ok(id)
+}
+
+/// One entry with its related entries in `links`.
+#[tauri::command]
+pub fn get_entry_by_id(app: tauri::AppHandle, id: i64) -> Result<ClipboardEntry, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let entry = db.get_entry_by_id(id)?;
+    drop(db);
+    Ok(redact_sensitive(&app, vec![entry]).remove(0))
+}
+
+#[tauri::command]
+pub fn link_entries(app: tauri::AppHandle, a: i64, b: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    if a == b {
+        return Err(CutboardError::invalid_input("An entry cannot be linked to itself"));
+    }
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_entry_by_id(a)?;
+    db.get_entry_by_id(b)?;
+    db.link_entries(a, b).map_err(Into::into)
+}
+
+/// Returns whether the entries were linked.
+#[tauri::command]
+pub fn unlink_entries(app: tauri::AppHandle, a: i64, b: i64) -> Result<bool, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.unlink_entries(a, b).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_entry_tags(app: tauri::AppHandle, id: i64) -> Result<Vec<String>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_entry_tags(id).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_entry_events(app: tauri::AppHandle, id: i64) -> Result<Vec<EntryEvent>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_entry_events(id).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn set_entry_tags(app: tauri::AppHandle, id: i64, tags: Vec<String>) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.set_entry_tags(id, &tags).map_err(Into::into)
+}
+
+/// `archived` reads from the archive's image folder, for entries returned by `search_archives`.
+#[tauri::command]
+pub fn get_image_base64(app: tauri::AppHandle, image_path: String, archived: Option<bool>) -> Result<String, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    if image_path.contains("..") || image_path.contains('/') || image_path.contains('\\') {
+        return Err(CutboardError::path_invalid("Invalid image path"));
+    }
+    let archived = archived.unwrap_or(false);
+    let cache_key = if archived { format!("archive/{}", image_path) } else { image_path.clone() };
+
+    {
+        let mut cache = IMAGE_B64_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = cache.get(&cache_key) {
+            crate::metrics::increment("image_cache.hit");
+            return Ok(cached.clone());
+        }
+    }
+    crate::metrics::increment("image_cache.miss");
+
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let images_dir = if archived { db.archive_dir().join("images") } else { db.images_dir() };
+    let full_path = images_dir.join(&image_path);
+    let canonical = full_path.canonicalize()?;
+    let canonical_base = images_dir.canonicalize()?;
+    if !canonical.starts_with(&canonical_base) {
+        return Err(CutboardError::path_invalid("Path traversal denied"));
+    }
+    let data = std::fs::read(&canonical)?;
+    let result = format!("data:image/png;base64,{}", STANDARD.encode(&data));
+
+    {
+        let mut cache = IMAGE_B64_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        cache.insert(cache_key, result.clone());
+    }
+
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn get_images_base64_batch(
+    app: tauri::AppHandle,
+    image_paths: Vec<String>,
+) -> Result<std::collections::HashMap<String, String>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let images_dir = db.images_dir();
+    let canonical_base = images_dir.canonicalize()?;
+
+    let mut result = std::collections::HashMap::new();
+    let mut cache = IMAGE_B64_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+
+    for path in &image_paths {
+        if path.contains("..") || path.contains('/') || path.contains('\\') {
+            continue;
+        }
+        if let Some(cached) = cache.get(path) {
+            crate::metrics::increment("image_cache.hit");
+            result.insert(path.clone(), cached.clone());
+            continue;
+        }
+        crate::metrics::increment("image_cache.miss");
+        let full_path = images_dir.join(path);
+        if let Ok(canonical) = full_path.canonicalize() {
+            if canonical.starts_with(&canonical_base) {
+                if let Ok(data) = std::fs::read(&canonical) {
+                    let b64 = format!("data:image/png;base64,{}", STANDARD.encode(&data));
+                    cache.insert(path.clone(), b64.clone());
+                    result.insert(path.clone(), b64);
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[derive(Serialize)]
+pub struct EntryCounts {
+    pub text_count: i64,
+    pub image_count: i64,
+}
+
+#[tauri::command]
+pub fn get_entry_counts(
+    app: tauri::AppHandle,
+    app_id: i64,
+    source_domain: Option<String>,
+) -> Result<EntryCounts, CutboardError> {
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let (text_count, image_count) = db.get_entry_counts(app_id, source_domain.as_deref().unwrap_or(""))?;
+    Ok(EntryCounts { text_count, image_count })
+}
+
+#[derive(Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    pub budget_bytes: usize,
+}
+
+#[derive(Serialize)]
+pub struct StorageStats {
+    pub db_size: u64,
+    pub images_size: u64,
+    pub images_count: u64,
+    /// Free bytes on the data drive, when known.
+    pub free_space: Option<u64>,
+    /// Free space is below `min_free_space_mb`, so new images are not being saved.
+    pub low_disk_space: bool,
+    pub image_cache: CacheStats,
+    pub icon_cache: CacheStats,
+}
+
+#[tauri::command]
+pub fn get_storage_stats(app: tauri::AppHandle) -> Result<StorageStats, CutboardError> {
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+
+    let db_path = db.db_path();
+    let db_size = std::fs::metadata(&db_path).map(|m| m.len()).unwrap_or(0);
+
+    let images_dir = db.images_dir();
+    let mut images_size: u64 = 0;
+    let mut images_count: u64 = 0;
+    if images_dir.exists() {
+        if let Ok(entries) = std::fs::read_dir(&images_dir) {
+            for entry in entries.flatten() {
+                if let Ok(meta) = entry.metadata() {
+                    if meta.is_file() {
+                        images_size += meta.len();
+                        images_count += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let image_cache = {
+        let cache = IMAGE_B64_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        CacheStats { entries: cache.len(), bytes: cache.weight(), budget_bytes: cache.weight_budget().unwrap_or(0) }
+    };
+    let (entries, bytes, budget_bytes) = crate::window_tracker::icon_cache_stats();
+    let icon_cache = CacheStats { entries, bytes, budget_bytes };
+
+    let min_free_mb = AppConfig::load(&app.state::<ConfigPath>().0).min_free_space_mb;
+    let free_space = crate::disk::free_space(&images_dir);
+    let low_disk_space = crate::disk::low_space(&images_dir, min_free_mb).is_some();
+
+    Ok(StorageStats {
+        db_size,
+        images_size,
+        images_count,
+        free_space,
+        low_disk_space,
+        image_cache,
+        icon_cache,
+    })
+}
+
+/// Counters and timings gathered since startup; see `metrics`.
+#[tauri::command]
+pub fn get_perf_metrics() -> crate::metrics::PerfMetrics {
+    crate::metrics::snapshot()
+}
+
+/// First run means onboarding was never finished and nothing has been captured yet, so existing
+/// installs upgrading to a build with onboarding are not sent through the tour.
+#[tauri::command]
+pub fn is_first_run(app: tauri::AppHandle) -> Result<bool, CutboardError> {
+    let config_path = app.state::<ConfigPath>();
+    if AppConfig::load(&config_path.0).onboarding_done {
+        return Ok(false);
+    }
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    Ok(db.count_entries()? == 0)
+}
+
+#[tauri::command]
+pub fn set_onboarding_done(app: tauri::AppHandle) -> Result<(), CutboardError> {
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    if !config.onboarding_done {
+        config.onboarding_done = true;
+        config.save(&config_path.0);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn generate_sample_data(app: tauri::AppHandle) -> Result<usize, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let count = crate::onboarding::generate_sample_data(&db)?;
+    drop(db);
+    let _ = app.emit("clipboard-changed", "text");
+    Ok(count)
+}
+
+#[tauri::command]
+pub fn detect_conflicting_clipboard_managers() -> Vec<String> {
+    crate::onboarding::detect_conflicting_managers()
+}
+
+fn archive_paths(app: &tauri::AppHandle) -> Result<Vec<(std::path::PathBuf, bool)>, String> {
+    let archive_dir = {
+        let state = app.state::<DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        db.archive_dir()
+    };
+    let config_path = app.state::<ConfigPath>();
+    let config = AppConfig::load(&config_path.0);
+    Ok(crate::archive::archive_paths(&archive_dir, &config.attached_archives))
+}
+
+#[tauri::command]
+pub fn get_archives(app: tauri::AppHandle) -> Result<Vec<crate::archive::ArchiveInfo>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    Ok(archive_paths(&app)?
+        .iter()
+        .filter_map(|(path, attached)| crate::archive::archive_info(path, *attached).ok())
+        .collect())
+}
+
+/// Makes an archive database outside the archive folder searchable. It is only ever read.
+#[tauri::command]
+pub fn attach_archive(app: tauri::AppHandle, path: String) -> Result<crate::archive::ArchiveInfo, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let info = crate::archive::archive_info(std::path::Path::new(&path), true)?;
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    if !config.attached_archives.split(';').any(|p| p.trim() == path) {
+        if !config.attached_archives.is_empty() {
+            config.attached_archives.push(';');
+        }
+        config.attached_archives.push_str(&path);
+        config.save(&config_path.0);
+    }
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn detach_archive(app: tauri::AppHandle, path: String) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    config.attached_archives = config
+        .attached_archives
+        .split(';')
+        .map(str::trim)
+        .filter(|p| !p.is_empty() && *p != path)
+        .collect::<Vec<_>>()
+        .join(";");
+    config.save(&config_path.0);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn search_archives(app: tauri::AppHandle, query: String, limit: Option<i64>) -> Result<Vec<crate::archive::ArchivedEntry>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut results = crate::archive::search(&archive_paths(&app)?, query.trim(), limit.unwrap_or(100).clamp(1, 1000));
+    // Masked the way `redact_sensitive` masks live entries
+    if results.iter().any(|e| e.is_sensitive) {
+        let config = AppConfig::load(&app.state::<ConfigPath>().0);
+        for entry in results.iter_mut().filter(|e| e.is_sensitive) {
+            entry.masked_preview = entry
+                .text_content
+                .take()
+                .map(|text| crate::sensitive::masked_preview(&text, &config.language, &config.sensitive_regions));
+        }
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub fn get_source_urls(app: tauri::AppHandle, app_id: i64) -> Result<Vec<SourceInfo>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_source_urls(app_id).map_err(Into::into)
+}
+
+#[derive(Serialize)]
+pub struct SettingsResponse {
+    pub data_path: String,
+    pub auto_clear_midnight: bool,
+    pub auto_start: bool,
+    pub close_to_tray: bool,
+    pub language: String,
+    pub shortcut: String,
+    pub theme: String,
+    pub show_copy_toast: bool,
+    pub retention_policy: String,
+    pub expander_enabled: bool,
+    pub hotkey_mode: String,
+    pub window_position: String,
+    pub always_on_top: bool,
+    pub hide_on_blur: bool,
+    pub mini_width: u32,
+    pub mini_height: u32,
+    pub mini_opacity: f64,
+    pub ignore_clipboard_owners: String,
+    pub group_remote_sessions: bool,
+    pub auto_lock_after_minutes: u32,
+    pub app_lock_enabled: bool,
+    pub use_windows_hello: bool,
+    pub exclude_from_capture: bool,
+    pub auto_hide_after_seconds: u32,
+    pub archive_policy: String,
+    pub export_readable_image_names: bool,
+    pub export_markdown_images: bool,
+    pub export_markdown_front_matter: bool,
+    pub daily_note_folder: String,
+    pub normalize_source_urls: bool,
+    pub audit_log_enabled: bool,
+    pub audit_log_retention_days: u32,
+    pub image_cache_mb: u32,
+    pub icon_cache_mb: u32,
+    pub max_database_size_mb: u32,
+    pub event_stream_port: u32,
+    pub event_stream_token: String,
+    pub mcp_enabled: bool,
+    pub ai_enabled: bool,
+    pub ai_endpoint: String,
+    pub ai_model: String,
+    pub sensitive_regions: String,
+    pub double_tap_key: String,
+    pub auto_link_window_secs: u32,
+    pub context_tagging: bool,
+    pub store_plain_text_only: bool,
+    pub capture_text: bool,
+    pub capture_images: bool,
+    pub text_retention_days: u32,
+    pub image_retention_days: u32,
+    pub min_free_space_mb: u64,
+    pub screenshot_tools: String,
+    pub screenshot_retention_days: u32,
+    pub max_image_side: u32,
+    pub screenshot_keep_original: bool,
+    pub notify_types: String,
+    pub quiet_hours: String,
+    pub sound_volume: u32,
+    pub sound_text: String,
+    pub sound_image: String,
+    pub sound_sensitive: String,
+}
+
+#[tauri::command]
+pub fn get_settings(app: tauri::AppHandle) -> Result<SettingsResponse, CutboardError> {
+    let config_path = app.state::<ConfigPath>();
+    let config = AppConfig::load(&config_path.0);
+    Ok(SettingsResponse {
+        data_path: config.data_path,
+        auto_clear_midnight: config.auto_clear_midnight,
+        auto_start: config.auto_start,
+        close_to_tray: config.close_to_tray,
+        language: config.language,
+        shortcut: config.shortcut,
+        theme: config.theme,
+        show_copy_toast: config.show_copy_toast,
+        retention_policy: config.retention_policy,
+        expander_enabled: config.expander_enabled,
+        hotkey_mode: config.hotkey_mode,
+        window_position: config.window_position,
+        always_on_top: config.always_on_top,
+        hide_on_blur: config.hide_on_blur,
+        mini_width: config.mini_width,
+        mini_height: config.mini_height,
+        mini_opacity: config.mini_opacity,
+        ignore_clipboard_owners: config.ignore_clipboard_owners,
+        group_remote_sessions: config.group_remote_sessions,
+        auto_lock_after_minutes: config.auto_lock_after_minutes,
+        app_lock_enabled: !config.app_lock_hash.is_empty(),
+        use_windows_hello: config.use_windows_hello,
+        exclude_from_capture: config.exclude_from_capture,
+        auto_hide_after_seconds: config.auto_hide_after_seconds,
+        archive_policy: config.archive_policy,
+        export_readable_image_names: config.export_readable_image_names,
+        export_markdown_images: config.export_markdown_images,
+        export_markdown_front_matter: config.export_markdown_front_matter,
+        daily_note_folder: config.daily_note_folder,
+        normalize_source_urls: config.normalize_source_urls,
+        audit_log_enabled: config.audit_log_enabled,
+        audit_log_retention_days: config.audit_log_retention_days,
+        image_cache_mb: config.image_cache_mb,
+        icon_cache_mb: config.icon_cache_mb,
+        max_database_size_mb: config.max_database_size_mb,
+        event_stream_port: config.event_stream_port,
+        // The token reads captured text over HTTP, so it stays hidden behind the lock
+        event_stream_token: if crate::lock::is_locked() { String::new() } else { config.event_stream_token },
+        mcp_enabled: config.mcp_enabled,
+        ai_enabled: config.ai_enabled,
+        ai_endpoint: config.ai_endpoint,
+        ai_model: config.ai_model,
+        sensitive_regions: config.sensitive_regions,
+        double_tap_key: config.double_tap_key,
+        auto_link_window_secs: config.auto_link_window_secs,
+        context_tagging: config.context_tagging,
+        store_plain_text_only: config.store_plain_text_only,
+        capture_text: config.capture_text,
+        capture_images: config.capture_images,
+        text_retention_days: config.text_retention_days,
+        image_retention_days: config.image_retention_days,
+        min_free_space_mb: config.min_free_space_mb,
+        screenshot_tools: config.screenshot_tools,
+        screenshot_retention_days: config.screenshot_retention_days,
+        max_image_side: config.max_image_side,
+        screenshot_keep_original: config.screenshot_keep_original,
+        notify_types: config.notify_types,
+        quiet_hours: config.quiet_hours,
+        sound_volume: config.sound_volume,
+        sound_text: config.sound_text,
+        sound_image: config.sound_image,
+        sound_sensitive: config.sound_sensitive,
+    })
+}
+
+/// Settings sent by the settings panel. Fields left out keep their saved value.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsUpdate {
+    pub data_path: String,
+    pub auto_clear_midnight: bool,
+    pub auto_start: bool,
+    pub close_to_tray: bool,
+    pub language: String,
+    pub shortcut: Option<String>,
+    pub theme: Option<String>,
+    pub show_copy_toast: Option<bool>,
+    pub retention_policy: Option<String>,
+    pub expander_enabled: Option<bool>,
+    pub hotkey_mode: Option<String>,
+    pub window_position: Option<String>,
+    pub always_on_top: Option<bool>,
+    pub hide_on_blur: Option<bool>,
+    pub mini_width: Option<u32>,
+    pub mini_height: Option<u32>,
+    pub mini_opacity: Option<f64>,
+    pub ignore_clipboard_owners: Option<String>,
+    pub group_remote_sessions: Option<bool>,
+    pub auto_lock_after_minutes: Option<u32>,
+    pub use_windows_hello: Option<bool>,
+    pub exclude_from_capture: Option<bool>,
+    pub auto_hide_after_seconds: Option<u32>,
+    pub archive_policy: Option<String>,
+    pub export_readable_image_names: Option<bool>,
+    pub export_markdown_images: Option<bool>,
+    pub export_markdown_front_matter: Option<bool>,
+    pub daily_note_folder: Option<String>,
+    pub normalize_source_urls: Option<bool>,
+    pub audit_log_enabled: Option<bool>,
+    pub audit_log_retention_days: Option<u32>,
+    pub image_cache_mb: Option<u32>,
+    pub icon_cache_mb: Option<u32>,
+    pub max_database_size_mb: Option<u32>,
+    pub event_stream_port: Option<u32>,
+    pub event_stream_token: Option<String>,
+    pub mcp_enabled: Option<bool>,
+    pub ai_enabled: Option<bool>,
+    pub ai_endpoint: Option<String>,
+    pub ai_model: Option<String>,
+    pub sensitive_regions: Option<String>,
+    pub double_tap_key: Option<String>,
+    pub auto_link_window_secs: Option<u32>,
+    pub context_tagging: Option<bool>,
+    pub store_plain_text_only: Option<bool>,
+    pub capture_text: Option<bool>,
+    pub capture_images: Option<bool>,
+    pub text_retention_days: Option<u32>,
+    pub image_retention_days: Option<u32>,
+    pub min_free_space_mb: Option<u64>,
+    pub screenshot_tools: Option<String>,
+    pub screenshot_retention_days: Option<u32>,
+    pub max_image_side: Option<u32>,
+    pub screenshot_keep_original: Option<bool>,
+    pub notify_types: Option<String>,
+    pub quiet_hours: Option<String>,
+    pub sound_volume: Option<u32>,
+    pub sound_text: Option<String>,
+    pub sound_image: Option<String>,
+    pub sound_sensitive: Option<String>,
+}
+
+#[tauri::command]
+pub fn save_settings(app: tauri::AppHandle, settings: SettingsUpdate) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let SettingsUpdate {
+        data_path,
+        auto_clear_midnight,
+        auto_start,
+        close_to_tray,
+        language,
+        shortcut,
+        theme,
+        show_copy_toast,
+        retention_policy,
+        expander_enabled,
+        hotkey_mode,
+        window_position,
+        always_on_top,
+        hide_on_blur,
+        mini_width,
+        mini_height,
+        mini_opacity,
+        ignore_clipboard_owners,
+        group_remote_sessions,
+        auto_lock_after_minutes,
+        use_windows_hello,
+        exclude_from_capture,
+        auto_hide_after_seconds,
+        archive_policy,
+        export_readable_image_names,
+        export_markdown_images,
+        export_markdown_front_matter,
+        daily_note_folder,
+        normalize_source_urls,
+        audit_log_enabled,
+        audit_log_retention_days,
+        image_cache_mb,
+        icon_cache_mb,
+        max_database_size_mb,
+        event_stream_port,
+        event_stream_token,
+        mcp_enabled,
+        ai_enabled,
+        ai_endpoint,
+        ai_model,
+        sensitive_regions,
+        double_tap_key,
+        auto_link_window_secs,
+        context_tagging,
+        store_plain_text_only,
+        capture_text,
+        capture_images,
+        text_retention_days,
+        image_retention_days,
+        min_free_space_mb,
+        screenshot_tools,
+        screenshot_retention_days,
+        max_image_side,
+        screenshot_keep_original,
+        notify_types,
+        quiet_hours,
+        sound_volume,
+        sound_text,
+        sound_image,
+        sound_sensitive,
+    } = settings;
+    if let Some(gesture) = &double_tap_key {
+        if crate::hotkey::parse_double_tap(gesture).is_none() {
+            return Err(CutboardError::invalid_input(format!("Unknown double-tap gesture: {}", gesture)));
+        }
+    }
+    let config_path = app.state::<ConfigPath>();
+    let old_config = AppConfig::load(&config_path.0);
+
+    let data_dir = std::path::PathBuf::from(&data_path);
+    std::fs::create_dir_all(&data_dir).map_err(|e| CutboardError::path_invalid(format!("Invalid data path: {}", e)))?;
+    // Open the new database before saving, so a bad path leaves the settings untouched
+    let new_db = if data_dir != std::path::Path::new(&old_config.data_path) {
+        match crate::database::Database::new(&data_dir) {
+            Ok(db) => Some(db),
+            Err(e) => {
+                if let CutboardError::DataPathInUse { owner, .. } = &e {
+                    let _ = app.emit(
+                        "db-busy",
+                        serde_json::json!({ "path": data_dir.to_string_lossy(), "owner": owner }),
+                    );
+                }
+                return Err(e);
+            }
+        }
+    } else {
+        None
+    };
+
+    let config = AppConfig {
+        data_path,
+        auto_clear_midnight,
+        auto_start,
+        close_to_tray,
+        language,
+        shortcut: shortcut.unwrap_or(old_config.shortcut.clone()),
+        theme: theme.unwrap_or(old_config.theme.clone()),
+        show_copy_toast: show_copy_toast.unwrap_or(old_config.show_copy_toast),
+        retention_policy: retention_policy.unwrap_or(old_config.retention_policy.clone()),
+        expander_enabled: expander_enabled.unwrap_or(old_config.expander_enabled),
+        hotkey_mode: hotkey_mode.unwrap_or(old_config.hotkey_mode.clone()),
+        window_position: window_position.unwrap_or(old_config.window_position.clone()),
+        always_on_top: always_on_top.unwrap_or(old_config.always_on_top),
+        hide_on_blur: hide_on_blur.unwrap_or(old_config.hide_on_blur),
+        mini_width: mini_width.unwrap_or(old_config.mini_width),
+        mini_height: mini_height.unwrap_or(old_config.mini_height),
+        mini_opacity: mini_opacity.unwrap_or(old_config.mini_opacity),
+        window_geometry: old_config.window_geometry.clone(),
+        ignore_clipboard_owners: ignore_clipboard_owners.unwrap_or(old_config.ignore_clipboard_owners.clone()),
+        group_remote_sessions: group_remote_sessions.unwrap_or(old_config.group_remote_sessions),
+        app_lock_hash: old_config.app_lock_hash.clone(),
+        auto_lock_after_minutes: auto_lock_after_minutes.unwrap_or(old_config.auto_lock_after_minutes),
+        use_windows_hello: use_windows_hello.unwrap_or(old_config.use_windows_hello),
+        exclude_from_capture: exclude_from_capture.unwrap_or(old_config.exclude_from_capture),
+        auto_hide_after_seconds: auto_hide_after_seconds.unwrap_or(old_config.auto_hide_after_seconds),
+        onboarding_done: old_config.onboarding_done,
+        attached_archives: old_config.attached_archives.clone(),
+        archive_policy: archive_policy.unwrap_or(old_config.archive_policy.clone()),
+        export_readable_image_names: export_readable_image_names.unwrap_or(old_config.export_readable_image_names),
+        export_markdown_images: export_markdown_images.unwrap_or(old_config.export_markdown_images),
+        export_markdown_front_matter: export_markdown_front_matter.unwrap_or(old_config.export_markdown_front_matter),
+        daily_note_folder: daily_note_folder.unwrap_or(old_config.daily_note_folder.clone()),
+        daily_note_exported_until: old_config.daily_note_exported_until.clone(),
+        normalize_source_urls: normalize_source_urls.unwrap_or(old_config.normalize_source_urls),
+        audit_log_enabled: audit_log_enabled.unwrap_or(old_config.audit_log_enabled),
+        audit_log_retention_days: audit_log_retention_days.unwrap_or(old_config.audit_log_retention_days),
+        image_cache_mb: image_cache_mb.unwrap_or(old_config.image_cache_mb),
+        icon_cache_mb: icon_cache_mb.unwrap_or(old_config.icon_cache_mb),
+        max_database_size_mb: max_database_size_mb.unwrap_or(old_config.max_database_size_mb),
+        event_stream_port: event_stream_port.unwrap_or(old_config.event_stream_port),
+        event_stream_token: event_stream_token.unwrap_or(old_config.event_stream_token.clone()),
+        mcp_enabled: mcp_enabled.unwrap_or(old_config.mcp_enabled),
+        ai_enabled: ai_enabled.unwrap_or(old_config.ai_enabled),
+        ai_endpoint: ai_endpoint.unwrap_or(old_config.ai_endpoint.clone()),
+        ai_model: ai_model.unwrap_or(old_config.ai_model.clone()),
+        ai_api_key: old_config.ai_api_key.clone(),
+        sensitive_regions: sensitive_regions.unwrap_or(old_config.sensitive_regions.clone()),
+        double_tap_key: double_tap_key.unwrap_or(old_config.double_tap_key.clone()),
+        auto_link_window_secs: auto_link_window_secs.unwrap_or(old_config.auto_link_window_secs),
+        context_tagging: context_tagging.unwrap_or(old_config.context_tagging),
+        store_plain_text_only: store_plain_text_only.unwrap_or(old_config.store_plain_text_only),
+        capture_text: capture_text.unwrap_or(old_config.capture_text),
+        capture_images: capture_images.unwrap_or(old_config.capture_images),
+        text_retention_days: text_retention_days.unwrap_or(old_config.text_retention_days),
+        image_retention_days: image_retention_days.unwrap_or(old_config.image_retention_days),
+        min_free_space_mb: min_free_space_mb.unwrap_or(old_config.min_free_space_mb),
+        export_templates: old_config.export_templates.clone(),
+        screenshot_tools: screenshot_tools.unwrap_or(old_config.screenshot_tools.clone()),
+        screenshot_retention_days: screenshot_retention_days.unwrap_or(old_config.screenshot_retention_days),
+        max_image_side: max_image_side.unwrap_or(old_config.max_image_side),
+        screenshot_keep_original: screenshot_keep_original.unwrap_or(old_config.screenshot_keep_original),
+        notify_types: notify_types.unwrap_or(old_config.notify_types.clone()),
+        quiet_hours: quiet_hours.unwrap_or(old_config.quiet_hours.clone()),
+        sound_volume: sound_volume.unwrap_or(old_config.sound_volume),
+        sound_text: sound_text.unwrap_or(old_config.sound_text.clone()),
+        sound_image: sound_image.unwrap_or(old_config.sound_image.clone()),
+        sound_sensitive: sound_sensitive.unwrap_or(old_config.sound_sensitive.clone()),
+        dnd_until: old_config.dnd_until,
+    };
+    config.save(&config_path.0);
+    if let Some(db) = new_db {
+        switch_database(&app, db)?;
+    }
+    {
+        let state = app.state::<DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        audit(&app, &db, "settings_changed", None, None);
+    }
+
+    apply_config_changes(&app, &old_config, &config).map_err(Into::into)
+}
+
+/// Replaces the open database in place, so every holder of the shared `DbState` sees the new one.
+fn switch_database(app: &tauri::AppHandle, new_db: crate::database::Database) -> Result<(), String> {
+    let state = app.state::<DbState>();
+    let mut db = state.0.lock().map_err(|e| e.to_string())?;
+    *db = new_db;
+    crate::expander::reload(&db);
+    crate::sensitive::reload_allowlist(&db);
+    crate::context::reload(&db);
+    drop(db);
+
+    if let Ok(mut cache) = IMAGE_B64_CACHE.lock() { cache.clear(); }
+    let _ = app.emit("clipboard-changed", ());
+    Ok(())
+}
+
+/// Applies the runtime side effects of a settings change.
+fn apply_config_changes(app: &tauri::AppHandle, old_config: &AppConfig, config: &AppConfig) -> Result<(), String> {
+    if old_config.auto_start != config.auto_start {
+        set_auto_start_registry(config.auto_start)?;
+    }
+
+    if config.shortcut != old_config.shortcut {
+        crate::hotkey::update(&config.shortcut);
+    }
+
+    if config.hotkey_mode != old_config.hotkey_mode {
+        crate::hotkey::set_mode(&config.hotkey_mode);
+    }
+
+    if config.double_tap_key != old_config.double_tap_key {
+        crate::hotkey::set_double_tap(&config.double_tap_key);
+    }
+
+    if config.always_on_top != old_config.always_on_top {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.set_always_on_top(config.always_on_top);
+        }
+    }
+
+    if config.mini_opacity != old_config.mini_opacity {
+        if let Some(window) = app.get_webview_window(MINI_WINDOW_LABEL) {
+            apply_window_opacity(&window, config.mini_opacity);
+        }
+    }
+
+    if config.exclude_from_capture != old_config.exclude_from_capture {
+        for label in ["main", MINI_WINDOW_LABEL] {
+            if let Some(window) = app.get_webview_window(label) {
+                apply_capture_protection(&window, config.exclude_from_capture);
+            }
+        }
+    }
+
+    if config.expander_enabled != old_config.expander_enabled {
+        crate::expander::set_enabled(app, config.expander_enabled);
+    }
+
+    if config.language != old_config.language || config.show_copy_toast != old_config.show_copy_toast {
+        crate::clipboard::invalidate_notification_cache();
+    }
+
+    if config.image_cache_mb != old_config.image_cache_mb || config.icon_cache_mb != old_config.icon_cache_mb {
+        apply_cache_budgets(config);
+    }
+
+    if config.event_stream_port != old_config.event_stream_port
+        || config.event_stream_token != old_config.event_stream_token
+    {
+        crate::event_stream::configure(config.event_stream_port, &config.event_stream_token);
+    }
+
+    Ok(())
+}
+
+const SETTINGS_EXPORT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct ExportedRule {
+    name: String,
+    enabled: bool,
+    conditions: crate::rules::RuleConditions,
+    actions: crate::rules::RuleActions,
+}
+
+/// Portable setup: settings (including ignored clipboard owners) and capture rules.
+#[derive(Serialize, Deserialize)]
+struct SettingsExport {
+    version: u32,
+    config: AppConfig,
+    #[serde(default)]
+    rules: Vec<ExportedRule>,
+}
+
+#[tauri::command]
+pub fn export_settings(app: tauri::AppHandle, path: String) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    // The PIN hash, window placement and archive locations belong to this machine. The AI key is
+    // encrypted for this Windows user and the event stream token is a secret, so neither leaves it
+    config.app_lock_hash.clear();
+    config.ai_api_key.clear();
+    config.event_stream_token.clear();
+    config.window_geometry.clear();
+    config.attached_archives.clear();
+    config.daily_note_exported_until.clear();
+
+    let rules = {
+        let state = app.state::<DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        db.get_rules()?
+    };
+    let export = SettingsExport {
+        version: SETTINGS_EXPORT_VERSION,
+        config,
+        rules: rules
+            .into_iter()
+            .map(|r| ExportedRule { name: r.name, enabled: r.enabled, conditions: r.conditions, actions: r.actions })
+            .collect(),
+    };
+    let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(Into::into)
+}
+
+/// Applies settings exported with `export_settings`. The data path, app lock, AI key, event
+/// stream token, window placement and attached archives of this machine are kept; imported rules
+/// replace existing rules with the same name.
+#[tauri::command]
+pub fn import_settings(app: tauri::AppHandle, path: String) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let json = std::fs::read_to_string(&path)?;
+    let import: SettingsExport = serde_json::from_str(&json).map_err(|e| format!("Invalid settings file: {}", e))?;
+    if import.version > SETTINGS_EXPORT_VERSION {
+        return Err(CutboardError::invalid_input("Settings file was created by a newer version of CutBoard"));
+    }
+    for rule in &import.rules {
+        crate::rules::validate(&rule.conditions, &rule.actions)?;
+    }
+
+    let config_path = app.state::<ConfigPath>();
+    let old_config = AppConfig::load(&config_path.0);
+    let config = AppConfig {
+        data_path: old_config.data_path.clone(),
+        window_geometry: old_config.window_geometry.clone(),
+        app_lock_hash: old_config.app_lock_hash.clone(),
+        ai_api_key: old_config.ai_api_key.clone(),
+        event_stream_token: old_config.event_stream_token.clone(),
+        onboarding_done: old_config.onboarding_done,
+        attached_archives: old_config.attached_archives.clone(),
+        daily_note_exported_until: old_config.daily_note_exported_until.clone(),
+        ..import.config
+    };
+
+    {
+        let state = app.state::<DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        let existing = db.get_rules()?;
+        for rule in &import.rules {
+            let id = existing.iter().find(|r| r.name == rule.name).map(|r| r.id);
+            let conditions = serde_json::to_string(&rule.conditions).map_err(|e| e.to_string())?;
+            let actions = serde_json::to_string(&rule.actions).map_err(|e| e.to_string())?;
+            db.save_rule(id, &rule.name, rule.enabled, &conditions, &actions)?;
+        }
+    }
+
+    config.save(&config_path.0);
+    {
+        let state = app.state::<DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        audit(&app, &db, "settings_imported", None, Some(&path));
+    }
+    apply_config_changes(&app, &old_config, &config).map_err(Into::into)
+}
+
+// Runs off the main thread: the Windows Hello prompt blocks until the user answers
+#[tauri::command(async)]
+pub fn reveal_sensitive_entry(app: tauri::AppHandle, id: i64) -> Result<String, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    if windows_hello_required(&app) {
+        let window = app.get_webview_window("main");
+        if !crate::hello::verify(window.as_ref(), "CutBoard: show sensitive entry")? {
+            return Err(CutboardError::verification_failed("Verification failed"));
+        }
+    }
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let entry = db.get_entry_by_id(id)?;
+    audit(&app, &db, "sensitive_revealed", Some(id), None);
+    entry.text_content.ok_or_else(|| CutboardError::not_found("Text content is empty"))
+}
+
+#[derive(Serialize)]
+pub struct LockState {
+    pub enabled: bool,
+    pub locked: bool,
+    /// Unlocking with Windows Hello is offered instead of typing the PIN.
+    pub windows_hello: bool,
+}
+
+#[tauri::command]
+pub fn get_lock_state(app: tauri::AppHandle) -> Result<LockState, CutboardError> {
+    Ok(LockState {
+        enabled: crate::lock::is_enabled(&app),
+        locked: crate::lock::is_locked(),
+        windows_hello: windows_hello_required(&app),
+    })
+}
+
+/// Sets, changes or (with an empty `pin`) removes the app lock. Changing an existing lock
+/// requires the current PIN.
+// Runs off the main thread: hashing and verifying the PIN take a noticeable moment
+#[tauri::command(async)]
+pub fn set_app_lock(app: tauri::AppHandle, pin: String, current_pin: Option<String>) -> Result<(), CutboardError> {
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    if !config.app_lock_hash.is_empty()
+        && !crate::lock::verify_secret(current_pin.as_deref().unwrap_or(""), &config.app_lock_hash)
+    {
+        return Err(CutboardError::verification_failed("Current PIN is incorrect"));
+    }
+    if pin.is_empty() {
+        config.app_lock_hash.clear();
+    } else {
+        if pin.chars().count() < 4 {
+            return Err(CutboardError::invalid_input("PIN must be at least 4 characters"));
+        }
+        config.app_lock_hash = crate::lock::hash_secret(&pin)?;
+    }
+    config.save(&config_path.0);
+    crate::lock::set_locked(&app, false);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn lock_app(app: tauri::AppHandle) -> Result<(), CutboardError> {
+    crate::lock::set_locked(&app, true);
+    Ok(())
+}
+
+// Runs off the main thread, like the PIN check and the delay after a wrong one
+#[tauri::command(async)]
+pub fn unlock_app(app: tauri::AppHandle, pin: String) -> Result<(), CutboardError> {
+    let config_path = app.state::<ConfigPath>();
+    let config = AppConfig::load(&config_path.0);
+    if config.app_lock_hash.is_empty() || crate::lock::verify_secret(&pin, &config.app_lock_hash) {
+        crate::lock::set_locked(&app, false);
+        return Ok(());
+    }
+    // Slow down guessing
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    Err(CutboardError::verification_failed("Invalid PIN"))
+}
+
+#[tauri::command(async)]
+pub fn unlock_with_windows_hello(app: tauri::AppHandle) -> Result<(), CutboardError> {
+    if !windows_hello_required(&app) {
+        return Err(CutboardError::unsupported("Windows Hello is not enabled"));
+    }
+    let window = app.get_webview_window("main");
+    if !crate::hello::verify(window.as_ref(), "CutBoard: unlock")? {
+        return Err(CutboardError::verification_failed("Verification failed"));
+    }
+    crate::lock::set_locked(&app, false);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn toggle_entry_favorite(app: tauri::AppHandle, id: i64) -> Result<bool, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.toggle_entry_favorite(id).map_err(Into::into)
+}
+
+/// Locks or unlocks an entry. Locked entries survive clearing, retention, expiry, archiving and
+/// the size cap, and can only be deleted once unlocked.
+#[tauri::command]
+pub fn toggle_entry_locked(app: tauri::AppHandle, id: i64) -> Result<bool, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let locked = db.toggle_entry_locked(id)?;
+    audit(&app, &db, if locked { "entry_locked" } else { "entry_unlocked" }, Some(id), None);
+    Ok(locked)
+}
+
+#[tauri::command]
+pub fn toggle_app_favorite(app: tauri::AppHandle, id: i64) -> Result<bool, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.toggle_app_favorite(id).map_err(Into::into)
+}
+
+/// Sets the app's sidebar note and accent color ("#rrggbb"). Empty values clear them.
+#[tauri::command]
+pub fn update_app_metadata(
+    app: tauri::AppHandle,
+    id: i64,
+    note: Option<String>,
+    color: Option<String>,
+) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let note = note.as_deref().map(str::trim);
+    let color = color.as_deref().map(str::trim);
+    if let Some(color) = color.filter(|c| !c.is_empty()) {
+        let hex = color.strip_prefix('#').unwrap_or("");
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(CutboardError::invalid_input(format!("Color must look like #rrggbb: {}", color)));
+        }
+    }
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.update_app_metadata(id, note, color.map(str::to_lowercase).as_deref()).map_err(Into::into)
+}
+
+/// Hides the app from the sidebar, or shows it again with `hidden` false. Its entries are kept
+/// and stay searchable.
+#[tauri::command]
+pub fn hide_app(app: tauri::AppHandle, id: i64, hidden: Option<bool>) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.set_app_hidden(id, hidden.unwrap_or(true)).map_err(Into::into)
+}
+
+/// Puts the listed apps first in that order; apps left out go back to sorting by entry count.
+#[tauri::command]
+pub fn reorder_apps(app: tauri::AppHandle, ids: Vec<i64>) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.reorder_apps(&ids).map_err(Into::into)
+}
+
+/// Pins a source domain: it sorts first in the domain list and its entries are kept by
+/// retention. Returns whether the domain is now a favorite.
+#[tauri::command]
+pub fn toggle_domain_favorite(app: tauri::AppHandle, domain: String) -> Result<bool, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let domain = crate::database::extract_domain(&domain);
+    if domain.is_empty() {
+        return Err(CutboardError::invalid_input("Domain is empty"));
+    }
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.toggle_domain_favorite(&domain).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn toggle_sensitive(app: tauri::AppHandle, id: i64) -> Result<bool, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.toggle_sensitive(id).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_sensitive_allowlist(app: tauri::AppHandle) -> Result<Vec<crate::database::AllowlistItem>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_sensitive_allowlist().map_err(Into::into)
+}
+
+/// "Never flag this again": allowlists every match that made the entry sensitive and clears its
+/// flag. Returns the number of values added.
+#[tauri::command]
+pub fn allow_sensitive_entry(app: tauri::AppHandle, id: i64) -> Result<usize, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let config = AppConfig::load(&app.state::<ConfigPath>().0);
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let entry = db.get_entry_by_id(id)?;
+    let text = entry.text_content.as_deref().unwrap_or("");
+    let matches = crate::sensitive::sensitive_matches(text, &config.language, &config.sensitive_regions);
+    for value in &matches {
+        let label = crate::sensitive::mask(value);
+        db.add_sensitive_allowlist("hash", &crate::sensitive::value_hash(value), Some(&label))?;
+    }
+    crate::sensitive::reload_allowlist(&db);
+    if entry.is_sensitive {
+        db.toggle_sensitive(id)?;
+    }
+    audit(&app, &db, "sensitive_allowed", Some(id), Some(&format!("{} values", matches.len())));
+    Ok(matches.len())
+}
+
+const RESCAN_BATCH: i64 = 200;
+
+#[derive(Serialize, Clone, Default)]
+pub struct RescanProgress {
+    pub scanned: i64,
+    pub total: i64,
+    /// Entries newly marked sensitive so far.
+    pub flagged: i64,
+    /// Entries no longer considered sensitive so far.
+    pub cleared: i64,
+}
+
+/// Re-runs detection over stored text, e.g. after changing the allowlist or `sensitive_regions`.
+/// Flags the user set or cleared by hand are kept. `language` stands in for the UI language when
+/// picking regional patterns. Emits "sensitive-rescan-progress" after each batch.
+#[tauri::command(async)]
+pub fn rescan_sensitive(app: tauri::AppHandle, language: Option<String>) -> Result<RescanProgress, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let config = AppConfig::load(&app.state::<ConfigPath>().0);
+    let language = language
+        .filter(|lang| !lang.trim().is_empty())
+        .unwrap_or_else(|| config.language.clone());
+    let state = app.state::<DbState>();
+    let mut progress = RescanProgress {
+        total: state.0.lock().map_err(|e| e.to_string())?.count_sensitive_rescan_entries()?,
+        ..Default::default()
+    };
+
+    let mut after_id = 0;
+    loop {
+        // Locked per batch so captures are not held up by a long re-scan
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        let batch = db.get_sensitive_rescan_batch(after_id, RESCAN_BATCH)?;
+        let Some(last) = batch.last() else { break };
+        after_id = last.0;
+        let mut updates = Vec::new();
+        for (id, text, is_sensitive, kind) in &batch {
+            let detected = crate::sensitive::sensitive_kind(text, &language, &config.sensitive_regions);
+            if detected == kind.as_deref() {
+                continue;
+            }
+            match (detected.is_some(), *is_sensitive) {
+                (true, false) => progress.flagged += 1,
+                (false, true) => progress.cleared += 1,
+                _ => {}
+            }
+            updates.push((*id, detected, text.as_str()));
+        }
+        db.set_detected_sensitive(&updates)?;
+        drop(db);
+        progress.scanned += batch.len() as i64;
+        let _ = app.emit("sensitive-rescan-progress", progress.clone());
+    }
+
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let detail = format!("{} flagged, {} cleared", progress.flagged, progress.cleared);
+    audit(&app, &db, "sensitive_rescanned", None, Some(&detail));
+    drop(db);
+    if progress.flagged + progress.cleared > 0 {
+        let _ = app.emit("clipboard-changed", "text");
+    }
+    Ok(progress)
+}
+
+/// Allowlists values that fully match `pattern`, e.g. `10\.1\.\d+\.\d+` for an office network.
+#[tauri::command]
+pub fn add_sensitive_allow_pattern(app: tauri::AppHandle, pattern: String, label: Option<String>) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Err(CutboardError::invalid_input("Pattern is empty"));
+    }
+    crate::sensitive::compile_allow_pattern(pattern)
+        .map_err(|e| CutboardError::invalid_input(format!("Invalid pattern: {}", e)))?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.add_sensitive_allowlist("pattern", pattern, label.as_deref())?;
+    crate::sensitive::reload_allowlist(&db);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_sensitive_allowlist_item(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.delete_sensitive_allowlist(id)?;
+    crate::sensitive::reload_allowlist(&db);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_context_patterns(app: tauri::AppHandle) -> Result<Vec<crate::database::ContextPattern>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_context_patterns().map_err(Into::into)
+}
+
+/// Adds a regex run against the foreground window title at capture time; its first capture
+/// group (or the whole match) becomes a `context:` tag.
+#[tauri::command]
+pub fn add_context_pattern(app: tauri::AppHandle, pattern: String, label: Option<String>) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Err(CutboardError::invalid_input("Pattern is empty"));
+    }
+    crate::context::compile(pattern).map_err(|e| CutboardError::invalid_input(format!("Invalid pattern: {}", e)))?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.add_context_pattern(pattern, label.as_deref())?;
+    crate::context::reload(&db);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_context_pattern(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.delete_context_pattern(id)?;
+    crate::context::reload(&db);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_favorite_entries(
+    app: tauri::AppHandle,
+    content_type: String,
+    page: Option<i64>,
+    page_size: Option<i64>,
+) -> Result<Vec<ClipboardEntry>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_favorite_entries(&content_type, page.unwrap_or(1), page_size.unwrap_or(20))
+        .map(|entries| redact_sensitive(&app, entries))
+        .map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_favorite_counts(app: tauri::AppHandle) -> Result<EntryCounts, CutboardError> {
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let (text_count, image_count) = db.get_favorite_counts()?;
+    Ok(EntryCounts { text_count, image_count })
+}
+
+#[tauri::command]
+pub fn get_collections(app: tauri::AppHandle) -> Result<Vec<CollectionInfo>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_collections().map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn create_collection(app: tauri::AppHandle, name: String) -> Result<i64, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(CutboardError::invalid_input("Collection name is empty"));
+    }
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.create_collection(name).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn rename_collection(app: tauri::AppHandle, id: i64, name: String) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(CutboardError::invalid_input("Collection name is empty"));
+    }
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.rename_collection(id, name).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn delete_collection(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.delete_collection(id).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn reorder_collections(app: tauri::AppHandle, ids: Vec<i64>) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.reorder_collections(&ids).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn add_entry_to_collection(app: tauri::AppHandle, collection_id: i64, entry_id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.add_entry_to_collection(collection_id, entry_id).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn remove_entry_from_collection(app: tauri::AppHandle, collection_id: i64, entry_id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.remove_entry_from_collection(collection_id, entry_id).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn reorder_collection_entries(app: tauri::AppHandle, collection_id: i64, entry_ids: Vec<i64>) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.reorder_collection_entries(collection_id, &entry_ids).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_entry_collections(app: tauri::AppHandle, entry_id: i64) -> Result<Vec<i64>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_entry_collections(entry_id).map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_collection_entries(
+    app: tauri::AppHandle,
+    collection_id: i64,
+    content_type: Option<String>,
+    page: Option<i64>,
+    page_size: Option<i64>,
+) -> Result<Vec<ClipboardEntry>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_collection_entries(
+        collection_id,
+        content_type.as_deref().unwrap_or(""),
+        page.unwrap_or(1),
+        page_size.unwrap_or(20),
+    )
+    .map(|entries| redact_sensitive(&app, entries))
+    .map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn get_templates(app: tauri::AppHandle) -> Result<Vec<TemplateInfo>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_templates().map_err(Into::into)
+}
+
+#[tauri::command]
+pub fn save_template(
+    app: tauri::AppHandle,
+    id: Option<i64>,
+    name: String,
+    content: String,
+    abbreviation: Option<String>,
+) -> Result<i64, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(CutboardError::invalid_input("Template name is empty"));
+    }
+    let abbreviation = abbreviation.as_deref().map(str::trim).filter(|a| !a.is_empty());
+    if abbreviation.is_some_and(|a| a.chars().any(char::is_whitespace)) {
+        return Err(CutboardError::invalid_input("Abbreviation must not contain whitespace"));
+    }
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let id = db.save_template(id, name, &content, abbreviation)?;
+    crate::expander::reload(&db);
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn delete_template(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.delete_template(id)?;
+    crate::expander::reload(&db);
+    Ok(())
 }
 
-#[derive(Serialize)]
-pub struct EntryCounts {
-    pub text_count: i64,
-    pub image_count: i64,
+#[tauri::command]
+pub fn get_rules(app: tauri::AppHandle) -> Result<Vec<RuleInfo>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.get_rules().map_err(Into::into)
 }
 
 #[tauri::command]
-pub fn get_entry_counts(
+pub fn save_rule(
     app: tauri::AppHandle,
-    app_id: i64,
-    source_domain: Option<String>,
-) -> Result<EntryCounts, String> {
+    id: Option<i64>,
+    name: String,
+    enabled: bool,
+    conditions: crate::rules::RuleConditions,
+    actions: crate::rules::RuleActions,
+) -> Result<i64, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(CutboardError::invalid_input("Rule name is empty"));
+    }
+    crate::rules::validate(&conditions, &actions)?;
+    let conditions = serde_json::to_string(&conditions).map_err(|e| e.to_string())?;
+    let actions = serde_json::to_string(&actions).map_err(|e| e.to_string())?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    let (text_count, image_count) = db
-        .get_entry_counts(app_id, source_domain.as_deref().unwrap_or(""))
-        .map_err(|e| e.to_string())?;
-    Ok(EntryCounts { text_count, image_count })
+    db.save_rule(id, name, enabled, &conditions, &actions).map_err(Into::into)
 }
 
-#[derive(Serialize)]
-pub struct StorageStats {
-    pub db_size: u64,
-    pub images_size: u64,
-    pub images_count: u64,
+#[tauri::command]
+pub fn delete_rule(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.delete_rule(id).map_err(Into::into)
 }
 
 #[tauri::command]
-pub fn get_storage_stats(app: tauri::AppHandle) -> Result<StorageStats, String> {
+pub fn reorder_rules(app: tauri::AppHandle, ids: Vec<i64>) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
+    db.reorder_rules(&ids).map_err(Into::into)
+}
 
-    let db_path = db.db_path();
-    let db_size = std::fs::metadata(&db_path).map(|m| m.len()).unwrap_or(0);
+/// Renders a stored template without touching the clipboard.
+pub(crate) fn render_template_text(
+    app: &tauri::AppHandle,
+    id: i64,
+    inputs: &std::collections::HashMap<String, String>,
+) -> Result<String, String> {
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let content = db.get_template_content(id).map_err(|e| e.to_string())?;
 
-    let images_dir = db.images_dir();
-    let mut images_size: u64 = 0;
-    let mut images_count: u64 = 0;
-    if images_dir.exists() {
-        if let Ok(entries) = std::fs::read_dir(&images_dir) {
-            for entry in entries.flatten() {
-                if let Ok(meta) = entry.metadata() {
-                    if meta.is_file() {
-                        images_size += meta.len();
-                        images_count += 1;
-                    }
-                }
-            }
-        }
-    }
+    let clipboard_text = if content.contains("{clipboard}") {
+        clipboard::read_clipboard_text()
+    } else {
+        None
+    };
+    crate::template::render(
+        &content,
+        inputs,
+        clipboard_text.as_deref(),
+        &mut |name| db.next_template_counter(name).map_err(|e| e.to_string()),
+    )
+}
 
-    Ok(StorageStats { db_size, images_size, images_count })
+#[tauri::command]
+pub fn render_template(
+    app: tauri::AppHandle,
+    id: i64,
+    inputs: Option<std::collections::HashMap<String, String>>,
+) -> Result<String, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let rendered = render_template_text(&app, id, &inputs.unwrap_or_default())?;
+    copy_text_without_recording(rendered.clone())?;
+    Ok(rendered)
 }
 
+/// Shows or hides the main window like the global hotkey, for when the hotkey is unavailable.
+/// Also tries to bring the hotkey back.
 #[tauri::command]
-pub fn get_source_urls(app: tauri::AppHandle, app_id: i64) -> Result<Vec<SourceInfo>, String> {
-    let state = app.state::<DbState>();
-    let db = state.0.lock().map_err(|e| e.to_string())?;
-    db.get_source_urls(app_id).map_err(|e| e.to_string())
+pub fn toggle_main_window(app: tauri::AppHandle) -> Result<(), CutboardError> {
+    app.get_webview_window("main").ok_or_else(|| CutboardError::not_found("Main window not found"))?;
+    crate::hotkey::toggle_window(&app);
+    crate::hotkey::refresh();
+    Ok(())
+}
+
+/// Key names accepted after the modifiers in a shortcut, e.g. "Insert" in "Ctrl+Shift+Insert".
+#[tauri::command]
+pub fn get_supported_keys() -> Result<Vec<String>, CutboardError> {
+    Ok(crate::hotkey::supported_keys())
+}
+
+/// End of the do not disturb period in Unix seconds, or 0 when it is off.
+#[tauri::command]
+pub fn get_dnd_until(app: tauri::AppHandle) -> Result<i64, CutboardError> {
+    Ok(crate::dnd::until(&AppConfig::load(&app.state::<ConfigPath>().0)))
+}
+
+/// Silences notifications, sounds and the sensitive banner until `until` (Unix seconds); 0 or a
+/// past time turns do not disturb off.
+#[tauri::command]
+pub fn set_dnd_until(app: tauri::AppHandle, until: i64) -> Result<(), CutboardError> {
+    crate::dnd::set(&app, until)
 }
 
 #[derive(Serialize)]
-pub struct SettingsResponse {
-    pub data_path: String,
-    pub auto_clear_midnight: bool,
-    pub auto_start: bool,
-    pub close_to_tray: bool,
-    pub language: String,
-    pub shortcut: String,
-    pub theme: String,
-    pub show_copy_toast: bool,
-    pub retention_policy: String,
+pub struct WindowState {
+    pub always_on_top: bool,
+    pub pinned: bool,
 }
 
 #[tauri::command]
-pub fn get_settings(app: tauri::AppHandle) -> Result<SettingsResponse, String> {
+pub fn get_window_state(app: tauri::AppHandle) -> Result<WindowState, CutboardError> {
     let config_path = app.state::<ConfigPath>();
     let config = AppConfig::load(&config_path.0);
-    Ok(SettingsResponse {
-        data_path: config.data_path,
-        auto_clear_midnight: config.auto_clear_midnight,
-        auto_start: config.auto_start,
-        close_to_tray: config.close_to_tray,
-        language: config.language,
-        shortcut: config.shortcut,
-        theme: config.theme,
-        show_copy_toast: config.show_copy_toast,
-        retention_policy: config.retention_policy,
+    Ok(WindowState {
+        always_on_top: config.always_on_top,
+        pinned: crate::WINDOW_PINNED.load(Ordering::SeqCst),
     })
 }
 
 #[tauri::command]
-pub fn save_settings(
-    app: tauri::AppHandle,
-    data_path: String,
-    auto_clear_midnight: bool,
-    auto_start: bool,
-    close_to_tray: bool,
-    language: String,
-    shortcut: Option<String>,
-    theme: Option<String>,
-    show_copy_toast: Option<bool>,
-    retention_policy: Option<String>,
-) -> Result<(), String> {
-    let config_path = app.state::<ConfigPath>();
-    let old_config = AppConfig::load(&config_path.0);
-
-    let data_dir = std::path::PathBuf::from(&data_path);
-    std::fs::create_dir_all(&data_dir).map_err(|e| format!("Invalid data path: {}", e))?;
-
-    let new_shortcut = shortcut.unwrap_or(old_config.shortcut.clone());
-    let config = AppConfig {
-        data_path,
-        auto_clear_midnight,
-        auto_start,
-        close_to_tray,
-        language,
-        shortcut: new_shortcut.clone(),
-        theme: theme.unwrap_or(old_config.theme.clone()),
-        show_copy_toast: show_copy_toast.unwrap_or(old_config.show_copy_toast),
-        retention_policy: retention_policy.unwrap_or(old_config.retention_policy.clone()),
-    };
-    config.save(&config_path.0);
-
-    if old_config.auto_start != auto_start {
-        set_auto_start_registry(auto_start)?;
-    }
+pub fn set_always_on_top(app: tauri::AppHandle, on_top: bool) -> Result<(), CutboardError> {
+    let window = app.get_webview_window("main").ok_or_else(|| CutboardError::not_found("Main window not found"))?;
+    window.set_always_on_top(on_top).map_err(|e| e.to_string())?;
 
-    if new_shortcut != old_config.shortcut {
-        crate::hotkey::update(&new_shortcut);
-    }
-
-    if config.language != old_config.language || config.show_copy_toast != old_config.show_copy_toast {
-        crate::clipboard::invalidate_notification_cache();
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    if config.always_on_top != on_top {
+        config.always_on_top = on_top;
+        config.save(&config_path.0);
     }
-
     Ok(())
 }
 
 #[tauri::command]
-pub fn toggle_entry_favorite(app: tauri::AppHandle, id: i64) -> Result<bool, String> {
-    let state = app.state::<DbState>();
-    let db = state.0.lock().map_err(|e| e.to_string())?;
-    db.toggle_entry_favorite(id).map_err(|e| e.to_string())
-}
-
-#[tauri::command]
-pub fn toggle_app_favorite(app: tauri::AppHandle, id: i64) -> Result<bool, String> {
-    let state = app.state::<DbState>();
-    let db = state.0.lock().map_err(|e| e.to_string())?;
-    db.toggle_app_favorite(id).map_err(|e| e.to_string())
+pub fn set_window_pinned(pinned: bool) -> Result<(), CutboardError> {
+    crate::WINDOW_PINNED.store(pinned, Ordering::SeqCst);
+    Ok(())
 }
 
 #[tauri::command]
-pub fn toggle_sensitive(app: tauri::AppHandle, id: i64) -> Result<bool, String> {
+pub fn get_recent_entries(app: tauri::AppHandle, limit: Option<i64>) -> Result<Vec<ClipboardEntry>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    db.toggle_sensitive(id).map_err(|e| e.to_string())
+    db.get_recent_entries(limit.unwrap_or(20).clamp(1, 200))
+        .map(|entries| redact_sensitive(&app, entries))
+        .map_err(Into::into)
 }
 
+/// Images copied by screenshot tools, across all apps.
 #[tauri::command]
-pub fn get_favorite_entries(
+pub fn get_screenshots(
     app: tauri::AppHandle,
-    content_type: String,
     page: Option<i64>,
     page_size: Option<i64>,
-) -> Result<Vec<ClipboardEntry>, String> {
+) -> Result<Vec<ClipboardEntry>, CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let state = app.state::<DbState>();
     let db = state.0.lock().map_err(|e| e.to_string())?;
-    db.get_favorite_entries(&content_type, page.unwrap_or(1), page_size.unwrap_or(20))
-        .map_err(|e| e.to_string())
+    db.get_screenshots(page.unwrap_or(1), page_size.unwrap_or(20)).map_err(Into::into)
 }
 
+pub const MINI_WINDOW_LABEL: &str = "mini";
+
+/// Shows or hides the compact overlay window, creating it on first use.
 #[tauri::command]
-pub fn get_favorite_counts(app: tauri::AppHandle) -> Result<EntryCounts, String> {
-    let state = app.state::<DbState>();
-    let db = state.0.lock().map_err(|e| e.to_string())?;
-    let (text_count, image_count) = db.get_favorite_counts().map_err(|e| e.to_string())?;
-    Ok(EntryCounts { text_count, image_count })
+pub fn toggle_mini_window(app: tauri::AppHandle) -> Result<bool, CutboardError> {
+    if let Some(window) = app.get_webview_window(MINI_WINDOW_LABEL) {
+        if window.is_visible().unwrap_or(false) {
+            save_mini_window_size(&app, &window);
+            window.hide().map_err(|e| e.to_string())?;
+            return Ok(false);
+        }
+        window.show().map_err(|e| e.to_string())?;
+        return Ok(true);
+    }
+
+    let config_path = app.state::<ConfigPath>();
+    let config = AppConfig::load(&config_path.0);
+    let window = tauri::WebviewWindowBuilder::new(&app, MINI_WINDOW_LABEL, tauri::WebviewUrl::default())
+        .title("CutBoard Mini")
+        .inner_size(config.mini_width as f64, config.mini_height as f64)
+        .min_inner_size(200.0, 48.0)
+        .decorations(false)
+        .transparent(true)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(true)
+        .build()
+        .map_err(|e| e.to_string())?;
+    apply_window_opacity(&window, config.mini_opacity);
+    apply_capture_protection(&window, config.exclude_from_capture);
+    Ok(true)
+}
+
+/// Persists the mini window's logical size so it reopens with the same dimensions.
+pub fn save_mini_window_size(app: &tauri::AppHandle, window: &tauri::WebviewWindow) {
+    let (Ok(size), Ok(scale)) = (window.inner_size(), window.scale_factor()) else {
+        return;
+    };
+    let logical = size.to_logical::<f64>(scale);
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    let (w, h) = (logical.width.round() as u32, logical.height.round() as u32);
+    if config.mini_width != w || config.mini_height != h {
+        config.mini_width = w;
+        config.mini_height = h;
+        config.save(&config_path.0);
+    }
+}
+
+#[cfg(windows)]
+fn apply_window_opacity(window: &tauri::WebviewWindow, opacity: f64) {
+    use windows::Win32::Foundation::{COLORREF, HWND};
+    use windows::Win32::UI::WindowsAndMessaging::*;
+
+    let hwnd = match window.hwnd() {
+        Ok(h) => HWND(h.0),
+        Err(_) => return,
+    };
+    let alpha = (opacity.clamp(0.2, 1.0) * 255.0).round() as u8;
+    unsafe {
+        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style | WS_EX_LAYERED.0 as isize);
+        let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA);
+    }
+}
+
+#[cfg(not(windows))]
+fn apply_window_opacity(_window: &tauri::WebviewWindow, _opacity: f64) {}
+
+/// Hides the window from screenshots and screen sharing so history doesn't leak during meetings.
+#[cfg(windows)]
+pub fn apply_capture_protection(window: &tauri::WebviewWindow, enabled: bool) {
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::WindowsAndMessaging::*;
+
+    let hwnd = match window.hwnd() {
+        Ok(h) => HWND(h.0),
+        Err(_) => return,
+    };
+    unsafe {
+        if !enabled {
+            let _ = SetWindowDisplayAffinity(hwnd, WDA_NONE);
+        } else if SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE).is_err() {
+            // Before Windows 10 2004 the window can only be captured as a black rectangle
+            let _ = SetWindowDisplayAffinity(hwnd, WDA_MONITOR);
+        }
+    }
 }
 
+#[cfg(not(windows))]
+pub fn apply_capture_protection(_window: &tauri::WebviewWindow, _enabled: bool) {}
+
 #[cfg(windows)]
 fn set_auto_start_registry(enabled: bool) -> Result<(), String> {
     use std::os::windows::process::CommandExt;
     const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-    let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
+    let exe_path = std::env::current_exe()?;
     let exe_str = exe_path.to_string_lossy().to_string();
 
     if enabled {
@@ -428,8 +2489,7 @@ fn set_auto_start_registry(enabled: bool) -> Result<(), String> {
                 "/f",
             ])
             .creation_flags(CREATE_NO_WINDOW)
-            .output()
-            .map_err(|e| e.to_string())?;
+            .output()?;
         if !output.status.success() {
             return Err("Failed to set auto-start registry".into());
         }
@@ -454,87 +2514,141 @@ fn set_auto_start_registry(_enabled: bool) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn open_data_dir(app: tauri::AppHandle) -> Result<(), String> {
+pub fn open_data_dir(app: tauri::AppHandle) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
     let config_path = app.state::<ConfigPath>();
     let config = AppConfig::load(&config_path.0);
-    std::process::Command::new("explorer")
-        .arg(&config.data_path)
-        .spawn()
-        .map_err(|e| e.to_string())?;
+    std::process::Command::new("explorer").arg(&config.data_path).spawn()?;
     Ok(())
 }
 
-#[tauri::command]
+// Runs off the main thread so the window stays responsive and `cancel_export` can get through
+#[tauri::command(async)]
 pub fn export_entries(
     app: tauri::AppHandle,
     app_id: i64,
     content_type: String,
     app_name: String,
     save_path: String,
-) -> Result<String, String> {
+    template: Option<String>,
+) -> Result<String, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let _guard = crate::export::ExportGuard::acquire()?;
+    let config = {
+        let config_path = app.state::<ConfigPath>();
+        AppConfig::load(&config_path.0)
+    };
+    let template = match template.as_deref().filter(|name| !name.is_empty()) {
+        Some(_) if content_type != "text" => {
+            return Err(CutboardError::invalid_input("Export templates only apply to text"));
+        }
+        Some(name) => Some(
+            crate::export::parse_templates(&config.export_templates)
+                .into_iter()
+                .find(|t| t.name == name)
+                .ok_or_else(|| CutboardError::not_found(format!("No export template named {}", name)))?,
+        ),
+        None => None,
+    };
+    let markdown = crate::export::MarkdownOptions {
+        images: config.export_markdown_images,
+        front_matter: config.export_markdown_front_matter,
+    };
     let state = app.state::<DbState>();
-    let (entries, images_dir) = {
+    let (entries, app_names, images_dir) = {
         let db = state.0.lock().map_err(|e| e.to_string())?;
-        let entries = db
-            .get_entries(app_id, &content_type, "", "", 1, 100_000)
-            .map_err(|e| e.to_string())?;
+        let mut entries = db.get_entries(app_id, &content_type, "", "", 1, 100_000)?;
+        if content_type == "text" && markdown.images {
+            entries.extend(db.get_entries(app_id, "image", "", "", 1, 100_000)?);
+            entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        }
+        let app_names = db
+            .get_apps(true)?
+            .into_iter()
+            .map(|a| (a.id, a.name))
+            .collect::<std::collections::HashMap<_, _>>();
         let images_dir = db.images_dir();
-        (entries, images_dir)
+        (entries, app_names, images_dir)
     };
 
     if entries.is_empty() {
-        return Err("没有可导出的记录".into());
+        return Err(CutboardError::not_found("没有可导出的记录"));
     }
 
     let out_path = std::path::PathBuf::from(&save_path);
+    let out_dir = out_path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(&out_path);
+    if let Some(free) = crate::disk::low_space(out_dir, config.min_free_space_mb) {
+        let _ = app.emit("low-disk-space", free);
+        return Err(CutboardError::low_disk_space(free));
+    }
 
     match content_type.as_str() {
-        "image" => {
-            let file = std::fs::File::create(&out_path).map_err(|e| e.to_string())?;
-            let mut zip = zip::ZipWriter::new(file);
-            let options = zip::write::SimpleFileOptions::default();
-
-            let total = entries.len();
-            for (i, entry) in entries.iter().enumerate() {
-                if let Some(image_filename) = &entry.image_path {
-                    let image_full = images_dir.join(image_filename);
-                    if image_full.exists() {
-                        zip.start_file(image_filename.as_str(), options)
-                            .map_err(|e| e.to_string())?;
-                        let data = std::fs::read(&image_full).map_err(|e| e.to_string())?;
-                        zip.write_all(&data).map_err(|e| e.to_string())?;
-                    }
-                }
-                let progress = ((i + 1) as f64 / total as f64 * 100.0) as u32;
-                let _ = app.emit("export-progress", progress);
-            }
-            zip.finish().map_err(|e| e.to_string())?;
+        "image" => crate::export::export_images(
+            &app,
+            &entries,
+            &app_names,
+            &images_dir,
+            &out_path,
+            config.export_readable_image_names,
+        )?,
+        "text" => match &template {
+            Some(template) => crate::export::export_with_template(&app, &entries, &app_name, &out_path, template)?,
+            None => crate::export::export_text(&app, &entries, &app_name, &images_dir, &out_path, markdown)?,
+        },
+        _ => return Err(CutboardError::invalid_input("未知内容类型")),
+    }
 
-            reveal_in_explorer(&out_path);
-            Ok(out_path.to_string_lossy().to_string())
-        }
-        "text" => {
-            let mut content = format!("# CutBoard - {} 文本记录\n\n", app_name);
-
-            let total = entries.len();
-            for (i, entry) in entries.iter().enumerate() {
-                if let Some(text) = &entry.text_content {
-                    content.push_str(&format!(
-                        "### {}\n\n{}\n\n",
-                        entry.created_at, text
-                    ));
-                }
-                let progress = ((i + 1) as f64 / total as f64 * 100.0) as u32;
-                let _ = app.emit("export-progress", progress);
-            }
+    {
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        let detail = format!("{} {} -> {}", content_type, entries.len(), out_path.display());
+        audit(&app, &db, "exported", None, Some(&detail));
+    }
+    reveal_in_explorer(&out_path);
+    Ok(out_path.to_string_lossy().to_string())
+}
 
-            std::fs::write(&out_path, content.as_bytes()).map_err(|e| e.to_string())?;
+#[tauri::command]
+pub fn get_export_templates(app: tauri::AppHandle) -> Vec<crate::export::ExportTemplate> {
+    let config = AppConfig::load(&app.state::<ConfigPath>().0);
+    crate::export::parse_templates(&config.export_templates)
+}
 
-            reveal_in_explorer(&out_path);
-            Ok(out_path.to_string_lossy().to_string())
-        }
-        _ => Err("未知内容类型".into()),
+/// Adds the template, or replaces the one with the same name.
+#[tauri::command]
+pub fn save_export_template(app: tauri::AppHandle, template: crate::export::ExportTemplate) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    crate::export::validate_template(&template)?;
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    let mut templates = crate::export::parse_templates(&config.export_templates);
+    match templates.iter_mut().find(|t| t.name == template.name) {
+        Some(existing) => *existing = template,
+        None => templates.push(template),
+    }
+    config.export_templates = crate::export::serialize_templates(&templates);
+    config.save(&config_path.0);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_export_template(app: tauri::AppHandle, name: String) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    let mut templates = crate::export::parse_templates(&config.export_templates);
+    let before = templates.len();
+    templates.retain(|t| t.name != name);
+    if templates.len() == before {
+        return Err(CutboardError::not_found(format!("No export template named {}", name)));
     }
+    config.export_templates = crate::export::serialize_templates(&templates);
+    config.save(&config_path.0);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn cancel_export() {
+    crate::export::cancel();
 }
 
 fn reveal_in_explorer(path: &std::path::Path) {
@@ -547,6 +2661,97 @@ fn reveal_in_explorer(path: &std::path::Path) {
     }
 }
 
+/// Hands `target` to the shell's default handler without going through a command line, so
+/// nothing in it is interpreted as arguments or shell syntax.
+fn shell_open(target: &str) -> Result<(), CutboardError> {
+    #[cfg(windows)]
+    {
+        use windows::core::{HSTRING, PCWSTR};
+        use windows::Win32::UI::Shell::ShellExecuteW;
+        use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+        let result = unsafe {
+            ShellExecuteW(
+                None,
+                &HSTRING::from("open"),
+                &HSTRING::from(target),
+                PCWSTR::null(),
+                PCWSTR::null(),
+                SW_SHOWNORMAL,
+            )
+        };
+        // Values above 32 mean success
+        if result.0 as isize <= 32 {
+            return Err(format!("Failed to open {}", target).into());
+        }
+        Ok(())
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = target;
+        Err(CutboardError::unsupported("Opening links is only supported on Windows"))
+    }
+}
+
+/// Accepts only plain http(s) URLs with a host and no whitespace or control characters.
+fn validate_web_url(url: &str) -> Result<&str, CutboardError> {
+    let url = url.trim();
+    let lower = url.to_ascii_lowercase();
+    let rest = lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"))
+        .ok_or_else(|| CutboardError::invalid_input("Only http and https links can be opened"))?;
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    if host.rsplit('@').next().unwrap_or("").is_empty() {
+        return Err(CutboardError::invalid_input("Link has no host"));
+    }
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(CutboardError::invalid_input("Link contains invalid characters"));
+    }
+    Ok(url)
+}
+
+#[tauri::command]
+pub fn open_source_url(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let url = {
+        let state = app.state::<DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        db.get_entry_by_id(id)?.source_url
+    };
+    let url = url.ok_or_else(|| CutboardError::not_found("Entry has no source URL"))?;
+    shell_open(validate_web_url(&url)?)
+}
+
+/// The entry's text as a local path, checked to still exist.
+fn entry_path(app: &tauri::AppHandle, id: i64) -> Result<std::path::PathBuf, CutboardError> {
+    let text = {
+        let state = app.state::<DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        db.get_entry_by_id(id)?.text_content
+    };
+    let path = text.as_deref().and_then(crate::filepath::parse).ok_or_else(|| CutboardError::path_invalid("Entry is not a file path"))?;
+    if !path.exists() {
+        return Err(CutboardError::path_invalid(format!("{} no longer exists", path.display())));
+    }
+    Ok(path)
+}
+
+#[tauri::command]
+pub fn open_path_entry(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let path = entry_path(&app, id)?;
+    shell_open(&path.to_string_lossy())
+}
+
+#[tauri::command]
+pub fn reveal_path_entry(app: tauri::AppHandle, id: i64) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let path = entry_path(&app, id)?;
+    reveal_in_explorer(&path);
+    Ok(())
+}
+
 pub fn find_language_dir() -> Option<std::path::PathBuf> {
     if let Ok(exe) = std::env::current_exe() {
         if let Some(dir) = exe.parent() {
@@ -567,17 +2772,27 @@ pub fn find_language_dir() -> Option<std::path::PathBuf> {
     None
 }
 
+/// Parsed language files. The TTL lets edited translations show up without a restart.
+static LANGUAGE_CACHE: std::sync::LazyLock<std::sync::Mutex<LruCache<String, std::collections::HashMap<String, String>>>> =
+    std::sync::LazyLock::new(|| std::sync::Mutex::new(LruCache::new().max_entries(4).ttl(std::time::Duration::from_secs(300))));
+
 pub fn load_language_map(lang: &str) -> Result<std::collections::HashMap<String, String>, String> {
+    if let Some(map) = LANGUAGE_CACHE.lock().unwrap_or_else(|e| e.into_inner()).get(lang) {
+        return Ok(map.clone());
+    }
     let lang_dir = find_language_dir().ok_or("Language directory not found")?;
     let path = lang_dir.join(format!("{}.json", lang));
     let content = std::fs::read_to_string(&path)
         .map_err(|e| format!("Failed to read {}.json: {}", lang, e))?;
-    serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}.json: {}", lang, e))
+    let map: std::collections::HashMap<String, String> =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}.json: {}", lang, e))?;
+    LANGUAGE_CACHE.lock().unwrap_or_else(|e| e.into_inner()).insert(lang.to_string(), map.clone());
+    Ok(map)
 }
 
 #[tauri::command]
-pub fn get_language_strings(lang: String) -> Result<std::collections::HashMap<String, String>, String> {
-    load_language_map(&lang)
+pub fn get_language_strings(lang: String) -> Result<std::collections::HashMap<String, String>, CutboardError> {
+    load_language_map(&lang).map_err(Into::into)
 }
 
 #[derive(Serialize)]
@@ -586,15 +2801,30 @@ pub struct LanguageInfo {
     pub display_name: String,
 }
 
+/// Resolved favicon URLs by domain, `None` when the page had no icon link. Kept for a day so
+/// scrolling the source list does not refetch every site's home page.
+static FAVICON_CACHE: std::sync::LazyLock<std::sync::Mutex<LruCache<String, Option<String>>>> =
+    std::sync::LazyLock::new(|| std::sync::Mutex::new(LruCache::new().max_entries(500).ttl(std::time::Duration::from_secs(24 * 3600))));
+
 #[tauri::command]
-pub fn resolve_favicon(domain: String) -> Result<String, String> {
+pub fn resolve_favicon(domain: String) -> Result<String, CutboardError> {
+    if let Some(cached) = FAVICON_CACHE.lock().unwrap_or_else(|e| e.into_inner()).get(&domain) {
+        return cached.clone().ok_or_else(|| CutboardError::not_found("No favicon link found"));
+    }
+    let resolved = fetch_favicon_url(&domain)?;
+    FAVICON_CACHE.lock().unwrap_or_else(|e| e.into_inner()).insert(domain, resolved.clone());
+    resolved.ok_or_else(|| CutboardError::not_found("No favicon link found"))
+}
+
+/// Network errors are returned so they are retried on the next call; a page without an icon
+/// link is a cacheable `None`.
+fn fetch_favicon_url(domain: &str) -> Result<Option<String>, CutboardError> {
     let url = format!("https://{}", domain);
     let body = ureq::get(&url)
         .timeout(std::time::Duration::from_secs(5))
         .call()
         .map_err(|e| e.to_string())?
-        .into_string()
-        .map_err(|e| e.to_string())?;
+        .into_string()?;
 
     // Use ASCII-only lowercase to keep byte offsets identical
     let lower = body.to_ascii_lowercase();
@@ -607,19 +2837,19 @@ pub fn resolve_favicon(domain: String) -> Result<String, String> {
 
             if let Some(href) = extract_href(region) {
                 if href.starts_with("http://") || href.starts_with("https://") {
-                    return Ok(href);
+                    return Ok(Some(href));
                 } else if href.starts_with("//") {
-                    return Ok(format!("https:{}", href));
+                    return Ok(Some(format!("https:{}", href)));
                 } else if href.starts_with('/') {
-                    return Ok(format!("https://{}{}", domain, href));
+                    return Ok(Some(format!("https://{}{}", domain, href)));
                 } else {
-                    return Ok(format!("https://{}/{}", domain, href));
+                    return Ok(Some(format!("https://{}/{}", domain, href)));
                 }
             }
         }
     }
 
-    Err("No favicon link found".into())
+    Ok(None)
 }
 
 fn safe_substr(s: &str, start: usize, end: usize) -> &str {
@@ -648,10 +2878,10 @@ fn extract_href(tag_region: &str) -> Option<String> {
 }
 
 #[tauri::command]
-pub fn get_available_languages() -> Result<Vec<LanguageInfo>, String> {
-    let lang_dir = find_language_dir().ok_or("Language directory not found")?;
+pub fn get_available_languages() -> Result<Vec<LanguageInfo>, CutboardError> {
+    let lang_dir = find_language_dir().ok_or_else(|| CutboardError::not_found("Language directory not found"))?;
     let mut languages = Vec::new();
-    for entry in std::fs::read_dir(&lang_dir).map_err(|e| e.to_string())? {
+    for entry in std::fs::read_dir(&lang_dir)? {
         let entry = match entry {
             Ok(e) => e,
             Err(_) => continue,
@@ -683,7 +2913,7 @@ pub fn get_available_languages() -> Result<Vec<LanguageInfo>, String> {
 }
 
 #[tauri::command]
-pub fn dismiss_crash(app: tauri::AppHandle) -> Result<(), String> {
+pub fn dismiss_crash(app: tauri::AppHandle) -> Result<(), CutboardError> {
     let config_path = app.state::<ConfigPath>();
     let cfg = AppConfig::load(&config_path.0);
     let data_dir = std::path::PathBuf::from(&cfg.data_path);
@@ -701,13 +2931,98 @@ pub fn dismiss_crash(app: tauri::AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn get_crash_log_content(app: tauri::AppHandle, file: String) -> Result<String, String> {
+pub fn get_crash_log_content(app: tauri::AppHandle, file: String) -> Result<String, CutboardError> {
     let config_path = app.state::<ConfigPath>();
     let cfg = AppConfig::load(&config_path.0);
     let data_dir = std::path::PathBuf::from(&cfg.data_path);
     let log_path = data_dir.join("log").join(&file);
     if !log_path.exists() {
-        return Err("Log file not found".into());
+        return Err(CutboardError::not_found("Log file not found"));
+    }
+    std::fs::read_to_string(&log_path).map_err(Into::into)
+}
+
+/// Stores the key for the AI endpoint encrypted; an empty key removes it.
+#[tauri::command]
+pub fn set_ai_api_key(app: tauri::AppHandle, key: String) -> Result<(), CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    config.ai_api_key = crate::ai::protect_key(key.trim())?;
+    config.save(&config_path.0);
+    Ok(())
+}
+
+/// Returns an entry's text with a rule transform applied, e.g. `{ "kind": "clean_code" }`, for
+/// previewing or copying with `copy_text_without_recording`. The entry is left as is;
+/// `html_to_markdown` converts its stored HTML when it has some.
+#[tauri::command]
+pub fn transform_entry_text(app: tauri::AppHandle, id: i64, transform: crate::rules::Transform) -> Result<String, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let entry = db.get_entry_by_id(id)?;
+    drop(db);
+    // The result goes to the webview, which only gets sensitive text through `reveal_sensitive_entry`
+    if entry.is_sensitive {
+        return Err(CutboardError::invalid_input("Sensitive entries are not transformed"));
+    }
+    let text = entry
+        .text_content
+        .filter(|_| entry.content_type == "text")
+        .ok_or_else(|| CutboardError::invalid_input("Only text entries can be transformed"))?;
+    Ok(crate::rules::apply_transform(&transform, &text, entry.html_content.as_deref()))
+}
+
+/// Runs an entry's text through the configured AI endpoint and stores the reply as a new entry
+/// derived from it. `target_lang` defaults to the UI language. Returns the new entry's id.
+#[tauri::command]
+pub async fn ai_transform(
+    app: tauri::AppHandle,
+    id: i64,
+    kind: String,
+    target_lang: Option<String>,
+) -> Result<i64, CutboardError> {
+    crate::lock::ensure_unlocked()?;
+    let kind = crate::ai::TransformKind::parse(&kind)
+        .ok_or_else(|| CutboardError::invalid_input(format!("Unknown transform: {}", kind)))?;
+    let config = AppConfig::load(&app.state::<ConfigPath>().0);
+    if !config.ai_enabled || config.ai_endpoint.trim().is_empty() {
+        return Err(CutboardError::unsupported("AI transforms are off; enable them and set an endpoint first"));
     }
-    std::fs::read_to_string(&log_path).map_err(|e| e.to_string())
+
+    let source = {
+        let state = app.state::<DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        db.get_entry_by_id(id)?
+    };
+    if source.is_sensitive {
+        return Err(CutboardError::invalid_input("Sensitive entries are never sent to the AI endpoint"));
+    }
+    let text = source
+        .text_content
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| CutboardError::invalid_input("Only text entries can be transformed"))?;
+    let api_key = crate::ai::unprotect_key(&config.ai_api_key)?;
+    let target_lang = target_lang
+        .filter(|lang| !lang.trim().is_empty())
+        .unwrap_or_else(|| config.language.clone());
+
+    // The request can take a while; keep it off the runtime's async workers
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        crate::ai::transform(&config.ai_endpoint, &config.ai_model, &api_key, kind, &target_lang, &text)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let hash = clipboard::compute_content_hash(result.as_bytes());
+    let state = app.state::<DbState>();
+    let db = state.0.lock().map_err(|e| e.to_string())?;
+    let new_id = db.insert_derived_entry(&source, &result, &hash, kind.as_str())?;
+    audit(&app, &db, "entry_ai_transformed", Some(id), Some(kind.as_str()));
+    crate::event_stream::entry_created(&db, new_id);
+    drop(db);
+    let _ = app.emit("clipboard-changed", "text");
+    Ok(new_id)
 }
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
 fn detect_system_language() -> String {
@@ -53,7 +54,9 @@ fn map_locale_to_language(locale: &str) -> String {
     "en".to_string()
 }
 
-#[derive(Debug, Clone)]
+/// Also (de)serialized as JSON for settings export; missing keys fall back to defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     pub data_path: String,
     pub auto_clear_midnight: bool,
@@ -64,6 +67,71 @@ pub struct AppConfig {
     pub theme: String,
     pub show_copy_toast: bool,
     pub retention_policy: String,
+    pub expander_enabled: bool,
+    pub hotkey_mode: String,
+    pub window_position: String,
+    pub always_on_top: bool,
+    pub hide_on_blur: bool,
+    pub mini_width: u32,
+    pub mini_height: u32,
+    pub mini_opacity: f64,
+    pub window_geometry: String,
+    pub ignore_clipboard_owners: String,
+    pub group_remote_sessions: bool,
+    pub app_lock_hash: String,
+    pub auto_lock_after_minutes: u32,
+    pub use_windows_hello: bool,
+    pub exclude_from_capture: bool,
+    pub auto_hide_after_seconds: u32,
+    pub onboarding_done: bool,
+    pub attached_archives: String,
+    pub archive_policy: String,
+    pub export_readable_image_names: bool,
+    pub export_markdown_images: bool,
+    pub export_markdown_front_matter: bool,
+    pub daily_note_folder: String,
+    pub daily_note_exported_until: String,
+    pub normalize_source_urls: bool,
+    pub audit_log_enabled: bool,
+    pub audit_log_retention_days: u32,
+    pub image_cache_mb: u32,
+    pub icon_cache_mb: u32,
+    pub max_database_size_mb: u32,
+    pub event_stream_port: u32,
+    pub event_stream_token: String,
+    pub mcp_enabled: bool,
+    pub ai_enabled: bool,
+    pub ai_endpoint: String,
+    pub ai_model: String,
+    pub ai_api_key: String,
+    pub sensitive_regions: String,
+    pub double_tap_key: String,
+    pub auto_link_window_secs: u32,
+    pub context_tagging: bool,
+    pub store_plain_text_only: bool,
+    pub capture_text: bool,
+    pub capture_images: bool,
+    pub text_retention_days: u32,
+    pub image_retention_days: u32,
+    pub min_free_space_mb: u64,
+    pub export_templates: String,
+    pub screenshot_tools: String,
+    pub screenshot_retention_days: u32,
+    pub max_image_side: u32,
+    pub screenshot_keep_original: bool,
+    pub notify_types: String,
+    pub quiet_hours: String,
+    pub sound_volume: u32,
+    pub sound_text: String,
+    pub sound_image: String,
+    pub sound_sensitive: String,
+    pub dnd_until: i64,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self::with_default_path("")
+    }
 }
 
 impl AppConfig {
@@ -82,6 +150,65 @@ impl AppConfig {
         let mut theme = String::from("system");
         let mut show_copy_toast = true;
         let mut retention_policy = String::from("none");
+        let mut expander_enabled = false;
+        let mut hotkey_mode = String::from("register");
+        let mut window_position = String::from("remember");
+        let mut always_on_top = false;
+        let mut hide_on_blur = false;
+        let mut mini_width = 480;
+        let mut mini_height = 96;
+        let mut mini_opacity = 0.9;
+        let mut window_geometry = String::from("");
+        let mut ignore_clipboard_owners = String::from("");
+        let mut group_remote_sessions = false;
+        let mut app_lock_hash = String::from("");
+        let mut auto_lock_after_minutes = 5;
+        let mut use_windows_hello = false;
+        let mut exclude_from_capture = false;
+        let mut auto_hide_after_seconds = 0;
+        let mut onboarding_done = false;
+        let mut attached_archives = String::from("");
+        let mut archive_policy = String::from("none");
+        let mut export_readable_image_names = false;
+        let mut export_markdown_images = false;
+        let mut export_markdown_front_matter = false;
+        let mut daily_note_folder = String::from("");
+        let mut daily_note_exported_until = String::from("");
+        let mut normalize_source_urls = false;
+        let mut audit_log_enabled = false;
+        let mut audit_log_retention_days = 90;
+        let mut image_cache_mb = 64;
+        let mut icon_cache_mb = 8;
+        let mut max_database_size_mb = 0;
+        let mut event_stream_port = 0;
+        let mut event_stream_token = String::from("");
+        let mut mcp_enabled = false;
+        let mut ai_enabled = false;
+        let mut ai_endpoint = String::from("");
+        let mut ai_model = String::from("");
+        let mut ai_api_key = String::from("");
+        let mut sensitive_regions = String::from("auto");
+        let mut double_tap_key = String::from("");
+        let mut auto_link_window_secs = 10;
+        let mut context_tagging = false;
+        let mut store_plain_text_only = false;
+        let mut capture_text = true;
+        let mut capture_images = true;
+        let mut text_retention_days = 0;
+        let mut image_retention_days = 0;
+        let mut min_free_space_mb = 200;
+        let mut export_templates = String::from("");
+        let mut screenshot_tools = String::from("");
+        let mut screenshot_retention_days = 0;
+        let mut max_image_side = 0;
+        let mut screenshot_keep_original = true;
+        let mut notify_types = String::from("text,image");
+        let mut quiet_hours = String::from("");
+        let mut sound_volume = 0;
+        let mut sound_text = String::from("");
+        let mut sound_image = String::from("");
+        let mut sound_sensitive = String::from("");
+        let mut dnd_until = 0;
 
         for line in content.lines() {
             let line = line.trim();
@@ -99,6 +226,65 @@ impl AppConfig {
                     "theme" => theme = value.trim().to_string(),
                     "show_copy_toast" => show_copy_toast = value.trim() != "false",
                     "retention_policy" => retention_policy = value.trim().to_string(),
+                    "expander_enabled" => expander_enabled = value.trim() == "true",
+                    "hotkey_mode" => hotkey_mode = value.trim().to_string(),
+                    "window_position" => window_position = value.trim().to_string(),
+                    "always_on_top" => always_on_top = value.trim() == "true",
+                    "hide_on_blur" => hide_on_blur = value.trim() == "true",
+                    "mini_width" => mini_width = value.trim().parse().unwrap_or(480),
+                    "mini_height" => mini_height = value.trim().parse().unwrap_or(96),
+                    "mini_opacity" => mini_opacity = value.trim().parse().unwrap_or(0.9),
+                    "window_geometry" => window_geometry = value.trim().to_string(),
+                    "ignore_clipboard_owners" => ignore_clipboard_owners = value.trim().to_string(),
+                    "group_remote_sessions" => group_remote_sessions = value.trim() == "true",
+                    "app_lock_hash" => app_lock_hash = value.trim().to_string(),
+                    "auto_lock_after_minutes" => auto_lock_after_minutes = value.trim().parse().unwrap_or(5),
+                    "use_windows_hello" => use_windows_hello = value.trim() == "true",
+                    "exclude_from_capture" => exclude_from_capture = value.trim() == "true",
+                    "auto_hide_after_seconds" => auto_hide_after_seconds = value.trim().parse().unwrap_or(0),
+                    "onboarding_done" => onboarding_done = value.trim() == "true",
+                    "attached_archives" => attached_archives = value.trim().to_string(),
+                    "archive_policy" => archive_policy = value.trim().to_string(),
+                    "export_readable_image_names" => export_readable_image_names = value.trim() == "true",
+                    "export_markdown_images" => export_markdown_images = value.trim() == "true",
+                    "export_markdown_front_matter" => export_markdown_front_matter = value.trim() == "true",
+                    "daily_note_folder" => daily_note_folder = value.trim().to_string(),
+                    "daily_note_exported_until" => daily_note_exported_until = value.trim().to_string(),
+                    "normalize_source_urls" => normalize_source_urls = value.trim() == "true",
+                    "audit_log_enabled" => audit_log_enabled = value.trim() == "true",
+                    "audit_log_retention_days" => audit_log_retention_days = value.trim().parse().unwrap_or(90),
+                    "image_cache_mb" => image_cache_mb = value.trim().parse().unwrap_or(64),
+                    "icon_cache_mb" => icon_cache_mb = value.trim().parse().unwrap_or(8),
+                    "max_database_size_mb" => max_database_size_mb = value.trim().parse().unwrap_or(0),
+                    "event_stream_port" => event_stream_port = value.trim().parse().unwrap_or(0),
+                    "event_stream_token" => event_stream_token = value.trim().to_string(),
+                    "mcp_enabled" => mcp_enabled = value.trim() == "true",
+                    "ai_enabled" => ai_enabled = value.trim() == "true",
+                    "ai_endpoint" => ai_endpoint = value.trim().to_string(),
+                    "ai_model" => ai_model = value.trim().to_string(),
+                    "ai_api_key" => ai_api_key = value.trim().to_string(),
+                    "sensitive_regions" => sensitive_regions = value.trim().to_string(),
+                    "double_tap_key" => double_tap_key = value.trim().to_string(),
+                    "auto_link_window_secs" => auto_link_window_secs = value.trim().parse().unwrap_or(10),
+                    "context_tagging" => context_tagging = value.trim() == "true",
+                    "store_plain_text_only" => store_plain_text_only = value.trim() == "true",
+                    "capture_text" => capture_text = value.trim() != "false",
+                    "capture_images" => capture_images = value.trim() != "false",
+                    "text_retention_days" => text_retention_days = value.trim().parse().unwrap_or(0),
+                    "image_retention_days" => image_retention_days = value.trim().parse().unwrap_or(0),
+                    "min_free_space_mb" => min_free_space_mb = value.trim().parse().unwrap_or(200),
+                    "export_templates" => export_templates = value.trim().to_string(),
+                    "screenshot_tools" => screenshot_tools = value.trim().to_string(),
+                    "screenshot_retention_days" => screenshot_retention_days = value.trim().parse().unwrap_or(0),
+                    "max_image_side" => max_image_side = value.trim().parse().unwrap_or(0),
+                    "screenshot_keep_original" => screenshot_keep_original = value.trim() != "false",
+                    "notify_types" => notify_types = value.trim().to_string(),
+                    "quiet_hours" => quiet_hours = value.trim().to_string(),
+                    "sound_volume" => sound_volume = value.trim().parse().unwrap_or(0),
+                    "sound_text" => sound_text = value.trim().to_string(),
+                    "sound_image" => sound_image = value.trim().to_string(),
+                    "sound_sensitive" => sound_sensitive = value.trim().to_string(),
+                    "dnd_until" => dnd_until = value.trim().parse().unwrap_or(0),
                     _ => {}
                 }
             }
@@ -119,6 +305,65 @@ impl AppConfig {
             theme,
             show_copy_toast,
             retention_policy,
+            expander_enabled,
+            hotkey_mode,
+            window_position,
+            always_on_top,
+            hide_on_blur,
+            mini_width,
+            mini_height,
+            mini_opacity,
+            window_geometry,
+            ignore_clipboard_owners,
+            group_remote_sessions,
+            app_lock_hash,
+            auto_lock_after_minutes,
+            use_windows_hello,
+            exclude_from_capture,
+            auto_hide_after_seconds,
+            onboarding_done,
+            attached_archives,
+            archive_policy,
+            export_readable_image_names,
+            export_markdown_images,
+            export_markdown_front_matter,
+            daily_note_folder,
+            daily_note_exported_until,
+            normalize_source_urls,
+            audit_log_enabled,
+            audit_log_retention_days,
+            image_cache_mb,
+            icon_cache_mb,
+            max_database_size_mb,
+            event_stream_port,
+            event_stream_token,
+            mcp_enabled,
+            ai_enabled,
+            ai_endpoint,
+            ai_model,
+            ai_api_key,
+            sensitive_regions,
+            double_tap_key,
+            auto_link_window_secs,
+            context_tagging,
+            store_plain_text_only,
+            capture_text,
+            capture_images,
+            text_retention_days,
+            image_retention_days,
+            min_free_space_mb,
+            export_templates,
+            screenshot_tools,
+            screenshot_retention_days,
+            max_image_side,
+            screenshot_keep_original,
+            notify_types,
+            quiet_hours,
+            sound_volume,
+            sound_text,
+            sound_image,
+            sound_sensitive,
+            dnd_until,
         }
     }
 
@@ -133,7 +378,66 @@ impl AppConfig {
              shortcut={}\n\
              theme={}\n\
              show_copy_toast={}\n\
-             retention_policy={}\n",
+             retention_policy={}\n\
+             expander_enabled={}\n\
+             hotkey_mode={}\n\
+             window_position={}\n\
+             always_on_top={}\n\
+             hide_on_blur={}\n\
+             mini_width={}\n\
+             mini_height={}\n\
+             mini_opacity={}\n\
+             window_geometry={}\n\
+             ignore_clipboard_owners={}\n\
+             group_remote_sessions={}\n\
+             app_lock_hash={}\n\
+             auto_lock_after_minutes={}\n\
+             use_windows_hello={}\n\
+             exclude_from_capture={}\n\
+             auto_hide_after_seconds={}\n\
+             onboarding_done={}\n\
+             attached_archives={}\n\
+             archive_policy={}\n\
+             export_readable_image_names={}\n\
+             export_markdown_images={}\n\
+             export_markdown_front_matter={}\n\
+             daily_note_folder={}\n\
+             daily_note_exported_until={}\n\
+             normalize_source_urls={}\n\
+             audit_log_enabled={}\n\
+             audit_log_retention_days={}\n\
+             image_cache_mb={}\n\
+             icon_cache_mb={}\n\
+             max_database_size_mb={}\n\
+             event_stream_port={}\n\
+             event_stream_token={}\n\
+             mcp_enabled={}\n\
+             ai_enabled={}\n\
+             ai_endpoint={}\n\
+             ai_model={}\n\
+             ai_api_key={}\n\
+             sensitive_regions={}\n\
+             double_tap_key={}\n\
+             auto_link_window_secs={}\n\
+             context_tagging={}\n\
+             store_plain_text_only={}\n\
+             capture_text={}\n\
+             capture_images={}\n\
+             text_retention_days={}\n\
+             image_retention_days={}\n\
+             min_free_space_mb={}\n\
+             export_templates={}\n\
+             screenshot_tools={}\n\
+             screenshot_retention_days={}\n\
+             max_image_side={}\n\
+             screenshot_keep_original={}\n\
+             notify_types={}\n\
+             quiet_hours={}\n\
+             sound_volume={}\n\
+             sound_text={}\n\
+             sound_image={}\n\
+             sound_sensitive={}\n\
+             dnd_until={}\n",
             self.data_path,
             self.auto_clear_midnight,
             self.auto_start,
@@ -143,6 +447,65 @@ impl AppConfig {
             self.theme,
             self.show_copy_toast,
             self.retention_policy,
+            self.expander_enabled,
+            self.hotkey_mode,
+            self.window_position,
+            self.always_on_top,
+            self.hide_on_blur,
+            self.mini_width,
+            self.mini_height,
+            self.mini_opacity,
+            self.window_geometry,
+            self.ignore_clipboard_owners,
+            self.group_remote_sessions,
+            self.app_lock_hash,
+            self.auto_lock_after_minutes,
+            self.use_windows_hello,
+            self.exclude_from_capture,
+            self.auto_hide_after_seconds,
+            self.onboarding_done,
+            self.attached_archives,
+            self.archive_policy,
+            self.export_readable_image_names,
+            self.export_markdown_images,
+            self.export_markdown_front_matter,
+            self.daily_note_folder,
+            self.daily_note_exported_until,
+            self.normalize_source_urls,
+            self.audit_log_enabled,
+            self.audit_log_retention_days,
+            self.image_cache_mb,
+            self.icon_cache_mb,
+            self.max_database_size_mb,
+            self.event_stream_port,
+            self.event_stream_token,
+            self.mcp_enabled,
+            self.ai_enabled,
+            self.ai_endpoint,
+            self.ai_model,
+            self.ai_api_key,
+            self.sensitive_regions,
+            self.double_tap_key,
+            self.auto_link_window_secs,
+            self.context_tagging,
+            self.store_plain_text_only,
+            self.capture_text,
+            self.capture_images,
+            self.text_retention_days,
+            self.image_retention_days,
+            self.min_free_space_mb,
+            self.export_templates,
+            self.screenshot_tools,
+            self.screenshot_retention_days,
+            self.max_image_side,
+            self.screenshot_keep_original,
+            self.notify_types,
+            self.quiet_hours,
+            self.sound_volume,
+            self.sound_text,
+            self.sound_image,
+            self.sound_sensitive,
+            self.dnd_until,
         );
         if let Some(parent) = config_path.parent() {
             if let Err(e) = std::fs::create_dir_all(parent) {
@@ -166,6 +529,65 @@ impl AppConfig {
             theme: String::from("system"),
             show_copy_toast: true,
             retention_policy: String::from("none"),
+            expander_enabled: false,
+            hotkey_mode: String::from("register"),
+            window_position: String::from("remember"),
+            always_on_top: false,
+            hide_on_blur: false,
+            mini_width: 480,
+            mini_height: 96,
+            mini_opacity: 0.9,
+            window_geometry: String::from(""),
+            ignore_clipboard_owners: String::from(""),
+            group_remote_sessions: false,
+            app_lock_hash: String::from(""),
+            auto_lock_after_minutes: 5,
+            use_windows_hello: false,
+            exclude_from_capture: false,
+            auto_hide_after_seconds: 0,
+            onboarding_done: false,
+            attached_archives: String::from(""),
+            archive_policy: String::from("none"),
+            export_readable_image_names: false,
+            export_markdown_images: false,
+            export_markdown_front_matter: false,
+            daily_note_folder: String::from(""),
+            daily_note_exported_until: String::from(""),
+            normalize_source_urls: false,
+            audit_log_enabled: false,
+            audit_log_retention_days: 90,
+            image_cache_mb: 64,
+            icon_cache_mb: 8,
+            max_database_size_mb: 0,
+            event_stream_port: 0,
+            event_stream_token: String::from(""),
+            mcp_enabled: false,
+            ai_enabled: false,
+            ai_endpoint: String::from(""),
+            ai_model: String::from(""),
+            ai_api_key: String::from(""),
+            sensitive_regions: String::from("auto"),
+            double_tap_key: String::from(""),
+            auto_link_window_secs: 10,
+            context_tagging: false,
+            store_plain_text_only: false,
+            capture_text: true,
+            capture_images: true,
+            text_retention_days: 0,
+            image_retention_days: 0,
+            min_free_space_mb: 200,
+            export_templates: String::from(""),
+            screenshot_tools: String::from(""),
+            screenshot_retention_days: 0,
+            max_image_side: 0,
+            screenshot_keep_original: true,
+            notify_types: String::from("text,image"),
+            quiet_hours: String::from(""),
+            sound_volume: 0,
+            sound_text: String::from(""),
+            sound_image: String::from(""),
+            sound_sensitive: String::from(""),
+            dnd_until: 0,
         }
     }
 
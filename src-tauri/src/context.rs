@@ -0,0 +1,51 @@
+//! Workspace context tags. With `context_tagging` on, the foreground window title at capture time
+//! is run through the patterns in the `context_patterns` table, and each match is added to the
+//! entry as a `context:<value>` tag, e.g. the repo in a VS Code title or a Jira key in a browser
+//! tab. A pattern's first capture group is the value, or the whole match when it has none.
+
+use crate::database::Database;
+use fancy_regex::Regex;
+use std::sync::{LazyLock, RwLock};
+
+pub const TAG_PREFIX: &str = "context:";
+/// Longer values are more likely a whole title than a context name.
+const MAX_VALUE_CHARS: usize = 80;
+
+static PATTERNS: LazyLock<RwLock<Vec<Regex>>> = LazyLock::new(|| RwLock::new(Vec::new()));
+
+pub fn compile(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| e.to_string())
+}
+
+pub fn reload(db: &Database) {
+    let patterns = db
+        .get_context_patterns()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|p| compile(&p.pattern).ok())
+        .collect();
+    *PATTERNS.write().unwrap_or_else(|e| e.into_inner()) = patterns;
+}
+
+/// Context tags for a window title, each at most once.
+pub fn tags(window_title: &str) -> Vec<String> {
+    let patterns = PATTERNS.read().unwrap_or_else(|e| e.into_inner());
+    let mut tags: Vec<String> = Vec::new();
+    for re in patterns.iter() {
+        let Ok(Some(caps)) = re.captures(window_title) else {
+            continue;
+        };
+        let Some(value) = caps.get(1).or_else(|| caps.get(0)) else {
+            continue;
+        };
+        let value = value.as_str().trim();
+        if value.is_empty() || value.chars().count() > MAX_VALUE_CHARS {
+            continue;
+        }
+        let tag = format!("{}{}", TAG_PREFIX, value);
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
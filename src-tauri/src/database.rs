@@ -1,5 +1,6 @@
-use rusqlite::{params, Connection, Result};
-use serde::Serialize;
+use crate::query::Query;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 #[derive(Debug, Serialize, Clone)]
@@ -10,6 +11,13 @@ pub struct AppInfo {
     pub icon_base64: Option<String>,
     pub entry_count: i64,
     pub is_favorite: bool,
+    pub note: Option<String>,
+    /// Accent color as "#rrggbb".
+    pub color: Option<String>,
+    /// Position set by `reorder_apps`; apps without one follow, busiest first.
+    pub sort_order: Option<i64>,
+    /// Left out of the sidebar; its entries still show up in global search.
+    pub is_hidden: bool,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -24,12 +32,206 @@ pub struct ClipboardEntry {
     pub is_favorite: bool,
     pub is_sensitive: bool,
     pub html_content: Option<String>,
+    pub source_exe: Option<String>,
+    pub owner_exe: Option<String>,
+    pub remote_session: Option<String>,
+    pub title: Option<String>,
+    /// "path" when the text is an existing local file or folder, "screenshot" for images copied
+    /// by a screenshot tool.
+    pub detected_kind: Option<String>,
+    pub file_size: Option<i64>,
+    pub file_ext: Option<String>,
+    /// Entry this one was generated from, e.g. by an AI summary.
+    pub derived_from: Option<i64>,
+    /// "summarize", "translate" or "rewrite" for derived entries.
+    pub derived_kind: Option<String>,
+    /// Detector category that flagged the entry, or "manual" when the user set the flag.
+    pub sensitive_kind: Option<String>,
+    /// For sensitive entries in listings, shown in place of the text, which is left out.
+    pub masked_preview: Option<String>,
+    /// Kept through every bulk delete and retention run, and only deletable once unlocked.
+    pub is_locked: bool,
+    /// Related entries; only filled in by `get_entry_by_id`.
+    pub links: Option<Vec<EntryLink>>,
+}
+
+/// An entry linked to another, shown as a related item.
+/// One row of an entry list, enough for the frontend to lay out a virtualized list before it
+/// fetches the rows in view with `get_entries_by_ids`.
+#[derive(Debug, Serialize, Clone)]
+pub struct EntryStub {
+    pub id: i64,
+    pub created_at: String,
+    pub is_favorite: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EntryLink {
+    pub id: i64,
+    /// "auto" when captured close together from the same app, "manual" when linked by hand.
+    pub kind: String,
+    pub content_type: String,
+    pub title: Option<String>,
+    pub created_at: String,
+}
+
+/// Where a captured entry came from, beyond the app it is attributed to.
+#[derive(Default)]
+pub struct EntryOrigin<'a> {
+    pub source_exe: Option<&'a str>,
+    pub owner_exe: Option<&'a str>,
+    pub remote_session: Option<&'a str>,
+}
+
+/// A captured text clip and the other forms stored with it.
+#[derive(Default)]
+pub struct TextCapture<'a> {
+    pub text: &'a str,
+    pub hash: &'a str,
+    pub html: Option<&'a str>,
+    /// The image flavor copied along with the text, already saved under this name.
+    pub image_path: Option<&'a str>,
+    pub sensitive_kind: Option<&'a str>,
+}
+
+/// An entry a sensitive re-scan may change, as (id, text, is_sensitive, sensitive_kind).
+pub type RescanRow = (i64, String, bool, Option<String>);
+
+/// Column list matching `entry_from_row`; queries alias `clipboard_entries` as `e`.
+const ENTRY_COLUMNS: &str = "e.id, e.app_id, e.content_type, e.text_content, e.image_path, e.created_at, e.source_url, COALESCE(e.is_favorite,0), COALESCE(e.is_sensitive,0), e.html_content, e.source_exe, e.owner_exe, e.remote_session, e.title, e.detected_kind, e.file_size, e.file_ext, e.derived_from, e.derived_kind, e.sensitive_kind, e.is_locked";
+
+fn entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<ClipboardEntry> {
+    Ok(ClipboardEntry {
+        id: row.get(0)?,
+        app_id: row.get(1)?,
+        content_type: row.get(2)?,
+        text_content: row.get(3)?,
+        image_path: row.get(4)?,
+        created_at: row.get(5)?,
+        source_url: row.get(6)?,
+        is_favorite: row.get::<_, i64>(7)? != 0,
+        is_sensitive: row.get::<_, i64>(8)? != 0,
+        html_content: row.get(9)?,
+        source_exe: row.get(10)?,
+        owner_exe: row.get(11)?,
+        remote_session: row.get(12)?,
+        title: row.get(13)?,
+        detected_kind: row.get(14)?,
+        file_size: row.get(15)?,
+        file_ext: row.get(16)?,
+        derived_from: row.get(17)?,
+        derived_kind: row.get(18)?,
+        sensitive_kind: row.get(19)?,
+        masked_preview: None,
+        is_locked: row.get::<_, i64>(20)? != 0,
+        links: None,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AppSettings {
+    /// Copy entries without their HTML formatting.
+    pub plain_text: bool,
+    /// Favorite every entry captured from the app.
+    pub auto_favorite: bool,
+    /// Discard the HTML formatting when capturing from the app.
+    #[serde(default)]
+    pub store_plain_text: bool,
+    /// Content types to notify about, e.g. "image", overriding `notify_types` in the config. An
+    /// empty string mutes the app; `None` follows the config.
+    #[serde(default)]
+    pub notify: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CollectionInfo {
+    pub id: i64,
+    pub name: String,
+    pub sort_order: i64,
+    pub entry_count: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TemplateInfo {
+    pub id: i64,
+    pub name: String,
+    pub content: String,
+    pub abbreviation: Option<String>,
+    pub inputs: Vec<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AuditRecord {
+    pub id: i64,
+    pub action: String,
+    pub entry_id: Option<i64>,
+    pub detail: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AllowlistItem {
+    pub id: i64,
+    /// "hash" for an approved value, "pattern" for an approved regex.
+    pub kind: String,
+    pub value: String,
+    /// Masked value or user note, since hashes mean nothing to the user.
+    pub label: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ContextPattern {
+    pub id: i64,
+    pub pattern: String,
+    pub label: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EntryEvent {
+    /// "captured" when the content was copied in another app, "reused" when copied back from history.
+    pub kind: String,
+    pub app_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RuleInfo {
+    pub id: i64,
+    pub name: String,
+    pub enabled: bool,
+    pub sort_order: i64,
+    pub conditions: crate::rules::RuleConditions,
+    pub actions: crate::rules::RuleActions,
+}
+
+/// What a bulk delete removes, reported before it runs (dry run) and after.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct DeleteSummary {
+    pub entries: i64,
+    /// Image files no remaining entry uses, which are removed from disk.
+    pub images: i64,
+    /// Text, HTML and image bytes. The database file itself only shrinks on the next vacuum.
+    pub freed_bytes: u64,
+    pub dry_run: bool,
+}
+
+pub struct EvictionCandidate {
+    pub id: i64,
+    /// Text and HTML stored in the database; image files are sized separately.
+    pub bytes: i64,
+    pub image_path: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct DeletedEntry {
     pub id: i64,
     pub app_id: i64,
+    /// Lets `restore_entry` recreate the app if it was pruned after the delete.
+    pub app_name: String,
+    pub app_exe: String,
     pub content_type: String,
     pub text_content: Option<String>,
     pub image_path: Option<String>,
@@ -45,6 +247,7 @@ pub struct DeletedEntry {
 pub struct SourceInfo {
     pub domain: String,
     pub count: i64,
+    pub is_favorite: bool,
 }
 
 pub fn extract_domain(url: &str) -> String {
@@ -97,81 +300,92 @@ fn extract_base_domain(host: &str) -> String {
     parts[len - 2..].join(".").to_lowercase()
 }
 
-const DOMAIN_FILTER_SQL: &str = "(source_url LIKE '%://' || ?{d} || '/%' OR source_url LIKE '%://' || ?{d} OR source_url LIKE '%://%.' || ?{d} || '/%' OR source_url LIKE '%://%.' || ?{d})";
+/// Pseudo exe path of the app bucket that holds entries added by hand.
+pub const MANUAL_APP_EXE: &str = "cutboard://manual";
+
+/// Pseudo exe path of the app bucket for copies made while no window had focus.
+pub const SYSTEM_APP_EXE: &str = "cutboard://system";
+
+/// `{p}` is the domain placeholder; see `Query::filter`.
+const DOMAIN_FILTER_SQL: &str = "(source_url LIKE '%://' || {p} || '/%' OR source_url LIKE '%://' || {p} OR source_url LIKE '%://%.' || {p} || '/%' OR source_url LIKE '%://%.' || {p})";
+
+/// Entries retention may remove: not favorites, not locked and not from a favorite domain.
+static EVICTABLE_SQL: std::sync::LazyLock<String> = std::sync::LazyLock::new(|| {
+    format!(
+        "is_favorite = 0 AND is_locked = 0 AND NOT EXISTS (SELECT 1 FROM favorite_domains f WHERE {})",
+        DOMAIN_FILTER_SQL.replace("{p}", "f.domain")
+    )
+});
+
+fn app_from_row(row: &rusqlite::Row) -> Result<AppInfo> {
+    Ok(AppInfo {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        exe_path: row.get(2)?,
+        icon_base64: row.get(3)?,
+        entry_count: row.get(4)?,
+        is_favorite: row.get::<_, i64>(5)? != 0,
+        note: row.get(6)?,
+        color: row.get(7)?,
+        sort_order: row.get(8)?,
+        is_hidden: row.get::<_, i64>(9)? != 0,
+    })
+}
+
+pub(crate) fn table_columns(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA {}.table_info({})", schema, table))?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    rows.collect()
+}
+
+/// Distinct SQL texts kept prepared by `prepare_cached`: the hot paths plus each filter combination
+/// of `get_entries`.
+const STATEMENT_CACHE_CAPACITY: usize = 32;
+
+/// How long SQLite itself waits on a lock before reporting the database busy.
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+const BUSY_RETRIES: u32 = 3;
+
+/// Runs `op` again with doubling backoff while the database stays busy past `BUSY_TIMEOUT`.
+/// For writes that would otherwise be lost, such as recording a capture.
+pub fn retry_busy<T>(mut op: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut delay = std::time::Duration::from_millis(100);
+    for _ in 0..BUSY_RETRIES {
+        match op() {
+            Err(e) if crate::error::DbErrorCode::of(&e) == crate::error::DbErrorCode::Busy => {
+                std::thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    op()
+}
 
 pub struct Database {
     conn: Connection,
     data_dir: std::path::PathBuf,
+    _writer: crate::writer_lock::WriterLock,
 }
 
 impl Database {
-    pub fn new(data_dir: &Path) -> Result<Self> {
+    pub fn new(data_dir: &Path) -> std::result::Result<Self, crate::error::CutboardError> {
         let db_path = data_dir.join("cutboard.db");
         let images_dir = data_dir.join("images");
-        std::fs::create_dir_all(&images_dir)
-            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        std::fs::create_dir_all(&images_dir)?;
+        let writer = crate::writer_lock::acquire(data_dir)?;
 
         let conn = Connection::open(&db_path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
         conn.execute_batch("PRAGMA journal_mode=WAL;")?;
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS apps (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                exe_path TEXT NOT NULL UNIQUE,
-                icon_base64 TEXT
-            );
-            CREATE TABLE IF NOT EXISTS clipboard_entries (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                app_id INTEGER NOT NULL REFERENCES apps(id),
-                content_type TEXT NOT NULL,
-                text_content TEXT,
-                image_path TEXT,
-                content_hash TEXT,
-                created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
-            );
-            CREATE INDEX IF NOT EXISTS idx_entries_app ON clipboard_entries(app_id);
-            CREATE INDEX IF NOT EXISTS idx_entries_type ON clipboard_entries(content_type);
-            CREATE INDEX IF NOT EXISTS idx_entries_created ON clipboard_entries(created_at);",
-        )?;
-
-        let columns: Vec<String> = conn
-            .prepare("PRAGMA table_info(clipboard_entries)")?
-            .query_map([], |row| row.get::<_, String>(1))?
-            .collect::<Result<Vec<_>>>()?;
-
-        if !columns.iter().any(|c| c == "content_hash") {
-            conn.execute("ALTER TABLE clipboard_entries ADD COLUMN content_hash TEXT", [])?;
-        }
-        if !columns.iter().any(|c| c == "source_url") {
-            conn.execute("ALTER TABLE clipboard_entries ADD COLUMN source_url TEXT", [])?;
-        }
-        if !columns.iter().any(|c| c == "is_favorite") {
-            conn.execute("ALTER TABLE clipboard_entries ADD COLUMN is_favorite INTEGER DEFAULT 0", [])?;
-        }
-        if !columns.iter().any(|c| c == "is_sensitive") {
-            conn.execute("ALTER TABLE clipboard_entries ADD COLUMN is_sensitive INTEGER DEFAULT 0", [])?;
-        }
-        if !columns.iter().any(|c| c == "html_content") {
-            conn.execute("ALTER TABLE clipboard_entries ADD COLUMN html_content TEXT", [])?;
-        }
-
-        // Migrate apps table
-        let app_columns: Vec<String> = conn
-            .prepare("PRAGMA table_info(apps)")?
-            .query_map([], |row| row.get::<_, String>(1))?
-            .collect::<Result<Vec<_>>>()?;
-        if !app_columns.iter().any(|c| c == "is_favorite") {
-            conn.execute("ALTER TABLE apps ADD COLUMN is_favorite INTEGER DEFAULT 0", [])?;
-        }
-
-        conn.execute_batch(
-            "CREATE INDEX IF NOT EXISTS idx_entries_hash ON clipboard_entries(content_hash);
-             CREATE INDEX IF NOT EXISTS idx_entries_app_type_hash ON clipboard_entries(app_id, content_type, content_hash);",
-        )?;
+        crate::migrations::run(&conn)?;
+        crate::event_stream::watch_deletions(&conn);
 
         Ok(Self {
             conn,
             data_dir: data_dir.to_path_buf(),
+            _writer: writer,
         })
     }
 
@@ -183,114 +397,160 @@ impl Database {
         self.data_dir.join("images")
     }
 
+    pub fn archive_dir(&self) -> std::path::PathBuf {
+        self.data_dir.join("archive")
+    }
+
     pub fn get_or_create_app(
         &self,
         name: &str,
         exe_path: &str,
         icon_base64: Option<&str>,
-    ) -> Result<i64> {
+        icon_stamp: Option<&str>,
+    ) -> Result<(i64, bool)> {
         if let Ok(id) = self.conn.query_row(
             "SELECT id FROM apps WHERE exe_path = ?1",
             params![exe_path],
             |row| row.get::<_, i64>(0),
         ) {
+            // A stamp that differs from the stored one means the exe was updated since the icon
+            // was saved; without a stamp only a missing icon is filled in.
             if let Some(icon) = icon_base64 {
                 self.conn.execute(
-                    "UPDATE apps SET icon_base64 = ?1 WHERE id = ?2 AND icon_base64 IS NULL",
-                    params![icon, id],
+                    "UPDATE apps SET icon_base64 = ?1, icon_stamp = ?2 WHERE id = ?3
+                     AND (icon_base64 IS NULL OR (?2 IS NOT NULL AND icon_stamp IS NOT ?2))",
+                    params![icon, icon_stamp, id],
                 )?;
             }
-            return Ok(id);
+            return Ok((id, false));
         }
 
         self.conn.execute(
-            "INSERT INTO apps (name, exe_path, icon_base64) VALUES (?1, ?2, ?3)",
-            params![name, exe_path, icon_base64],
+            "INSERT INTO apps (name, exe_path, icon_base64, icon_stamp) VALUES (?1, ?2, ?3, ?4)",
+            params![name, exe_path, icon_base64, icon_stamp],
         )?;
-        Ok(self.conn.last_insert_rowid())
+        Ok((self.conn.last_insert_rowid(), true))
+    }
+
+    /// The newest entry of `content_type` with this content hash, from any app.
+    pub fn find_entry_by_hash(&self, content_type: &str, hash: &str) -> Result<Option<i64>> {
+        self.conn
+            .query_row(
+                "SELECT id FROM clipboard_entries WHERE content_type = ?1 AND content_hash = ?2 ORDER BY created_at DESC LIMIT 1",
+                params![content_type, hash],
+                |row| row.get(0),
+            )
+            .optional()
     }
 
     pub fn upsert_text_entry(&self, app_id: i64, text: &str, hash: &str, source_url: Option<&str>) -> Result<i64> {
-        if let Ok(id) = self.conn.query_row(
+        if let Ok(id) = self.conn.prepare_cached(
             "SELECT id FROM clipboard_entries WHERE app_id = ?1 AND content_type = 'text' AND content_hash = ?2",
-            params![app_id, hash],
-            |row| row.get::<_, i64>(0),
-        ) {
+        )?.query_row(params![app_id, hash], |row| row.get::<_, i64>(0)) {
             self.conn.execute(
                 "UPDATE clipboard_entries SET created_at = datetime('now', 'localtime'), source_url = COALESCE(?2, source_url) WHERE id = ?1",
                 params![id, source_url],
             )?;
+            self.record_entry_event(id, "captured", Some(app_id))?;
             return Ok(id);
         }
 
         self.conn.execute(
-            "INSERT INTO clipboard_entries (app_id, content_type, text_content, content_hash, source_url) VALUES (?1, 'text', ?2, ?3, ?4)",
-            params![app_id, text, hash, source_url],
+            "INSERT INTO clipboard_entries (app_id, content_type, text_content, content_hash, source_url, title) VALUES (?1, 'text', ?2, ?3, ?4, ?5)",
+            params![app_id, text, hash, source_url, crate::title::generate(text)],
         )?;
-        Ok(self.conn.last_insert_rowid())
+        let id = self.conn.last_insert_rowid();
+        self.record_entry_event(id, "captured", Some(app_id))?;
+        Ok(id)
     }
 
-    pub fn upsert_image_entry(&self, app_id: i64, image_filename: &str, hash: &str, source_url: Option<&str>) -> Result<(i64, bool)> {
-        if let Ok(id) = self.conn.query_row(
+    pub fn upsert_image_entry(&self, app_id: i64, image_filename: &str, hash: &str, source_url: Option<&str>, origin: &EntryOrigin) -> Result<(i64, bool)> {
+        let _t = crate::metrics::timer("db.upsert_entry");
+        if let Ok(id) = self.conn.prepare_cached(
             "SELECT id FROM clipboard_entries WHERE app_id = ?1 AND content_type = 'image' AND content_hash = ?2",
-            params![app_id, hash],
-            |row| row.get::<_, i64>(0),
-        ) {
+        )?.query_row(params![app_id, hash], |row| row.get::<_, i64>(0)) {
             self.conn.execute(
-                "UPDATE clipboard_entries SET created_at = datetime('now', 'localtime'), source_url = COALESCE(?2, source_url) WHERE id = ?1",
-                params![id, source_url],
+                "UPDATE clipboard_entries SET created_at = datetime('now', 'localtime'), source_url = COALESCE(?2, source_url), source_exe = COALESCE(?3, source_exe), owner_exe = COALESCE(?4, owner_exe), remote_session = COALESCE(?5, remote_session) WHERE id = ?1",
+                params![id, source_url, origin.source_exe, origin.owner_exe, origin.remote_session],
             )?;
+            self.record_entry_event(id, "captured", Some(app_id))?;
             return Ok((id, true));
         }
 
         self.conn.execute(
-            "INSERT INTO clipboard_entries (app_id, content_type, image_path, content_hash, source_url) VALUES (?1, 'image', ?2, ?3, ?4)",
-            params![app_id, image_filename, hash, source_url],
+            "INSERT INTO clipboard_entries (app_id, content_type, image_path, content_hash, source_url, source_exe, owner_exe, remote_session) VALUES (?1, 'image', ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![app_id, image_filename, hash, source_url, origin.source_exe, origin.owner_exe, origin.remote_session],
         )?;
-        Ok((self.conn.last_insert_rowid(), false))
+        let id = self.conn.last_insert_rowid();
+        self.record_entry_event(id, "captured", Some(app_id))?;
+        Ok((id, false))
     }
 
-    pub fn get_apps(&self) -> Result<Vec<AppInfo>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT a.id, a.name, a.exe_path, a.icon_base64, COUNT(e.id) as cnt, COALESCE(a.is_favorite, 0)
+    pub fn get_apps(&self, include_hidden: bool) -> Result<Vec<AppInfo>> {
+        let _t = crate::metrics::timer("db.get_apps");
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT a.id, a.name, a.exe_path, a.icon_base64, COUNT(e.id) as cnt, COALESCE(a.is_favorite, 0),
+                    a.note, a.color, a.sort_order, a.is_hidden
              FROM apps a
              LEFT JOIN clipboard_entries e ON e.app_id = a.id
+             WHERE ?1 OR a.is_hidden = 0
              GROUP BY a.id
-             ORDER BY a.is_favorite DESC, cnt DESC",
+             ORDER BY a.is_favorite DESC, a.sort_order IS NULL, a.sort_order, cnt DESC",
         )?;
-        let rows = stmt.query_map([], |row| {
-            Ok(AppInfo {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                exe_path: row.get(2)?,
-                icon_base64: row.get(3)?,
-                entry_count: row.get(4)?,
-                is_favorite: row.get::<_, i64>(5)? != 0,
-            })
-        })?;
+        let rows = stmt.query_map(params![include_hidden], app_from_row)?;
         rows.collect()
     }
 
-    pub fn get_entry_counts(&self, app_id: i64, source_domain: &str) -> Result<(i64, i64)> {
-        if source_domain.is_empty() {
-            self.conn.query_row(
-                "SELECT
-                    SUM(CASE WHEN content_type = 'text' THEN 1 ELSE 0 END),
-                    SUM(CASE WHEN content_type = 'image' THEN 1 ELSE 0 END)
-                 FROM clipboard_entries WHERE app_id = ?1",
-                params![app_id],
-                |row| Ok((row.get::<_, Option<i64>>(0)?.unwrap_or(0), row.get::<_, Option<i64>>(1)?.unwrap_or(0))),
-            )
-        } else {
-            self.conn.query_row(
-                &format!("SELECT
-                    SUM(CASE WHEN content_type = 'text' THEN 1 ELSE 0 END),
-                    SUM(CASE WHEN content_type = 'image' THEN 1 ELSE 0 END)
-                 FROM clipboard_entries WHERE app_id = ?1 AND {}", DOMAIN_FILTER_SQL.replace("{d}", "2")),
-                params![app_id, source_domain],
-                |row| Ok((row.get::<_, Option<i64>>(0)?.unwrap_or(0), row.get::<_, Option<i64>>(1)?.unwrap_or(0))),
-            )
+    pub fn get_app_by_id(&self, id: i64) -> Result<AppInfo> {
+        self.conn.query_row(
+            "SELECT a.id, a.name, a.exe_path, a.icon_base64, COUNT(e.id), COALESCE(a.is_favorite, 0),
+                    a.note, a.color, a.sort_order, a.is_hidden
+             FROM apps a
+             LEFT JOIN clipboard_entries e ON e.app_id = a.id
+             WHERE a.id = ?1
+             GROUP BY a.id",
+            params![id],
+            app_from_row,
+        )
+    }
+
+    /// Empty strings clear the note or color.
+    pub fn update_app_metadata(&self, id: i64, note: Option<&str>, color: Option<&str>) -> Result<()> {
+        let changed = self.conn.execute(
+            "UPDATE apps SET note = NULLIF(?1, ''), color = NULLIF(?2, '') WHERE id = ?3",
+            params![note, color, id],
+        )?;
+        if changed == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    /// Gives the listed apps that order; the others lose any custom position.
+    pub fn reorder_apps(&self, ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("UPDATE apps SET sort_order = NULL", [])?;
+        for (i, id) in ids.iter().enumerate() {
+            tx.execute("UPDATE apps SET sort_order = ?1 WHERE id = ?2", params![i as i64, id])?;
         }
+        tx.commit()
+    }
+
+    pub fn get_entry_counts(&self, app_id: i64, source_domain: &str) -> Result<(i64, i64)> {
+        let _t = crate::metrics::timer("db.get_entry_counts");
+        let query = Query::new()
+            .filter("app_id = {p}", &app_id)
+            .filter_if(!source_domain.is_empty(), DOMAIN_FILTER_SQL, &source_domain);
+        let sql = query.sql(
+            "SELECT
+                SUM(CASE WHEN content_type = 'text' THEN 1 ELSE 0 END),
+                SUM(CASE WHEN content_type = 'image' THEN 1 ELSE 0 END)
+             FROM clipboard_entries",
+            "",
+        );
+        self.conn.prepare_cached(&sql)?.query_row(query.params(), |row| {
+            Ok((row.get::<_, Option<i64>>(0)?.unwrap_or(0), row.get::<_, Option<i64>>(1)?.unwrap_or(0)))
+        })
     }
 
     pub fn get_entries(
@@ -302,66 +562,205 @@ impl Database {
         page: i64,
         page_size: i64,
     ) -> Result<Vec<ClipboardEntry>> {
-        let base = "SELECT id, app_id, content_type, text_content, image_path, created_at, source_url, COALESCE(is_favorite,0), COALESCE(is_sensitive,0), html_content FROM clipboard_entries WHERE app_id = ?1 AND content_type = ?2";
-        let domain_filter = &format!(" AND {}", DOMAIN_FILTER_SQL);
-        let order = " ORDER BY is_favorite DESC, created_at DESC";
+        let _t = crate::metrics::timer("db.get_entries");
         let offset = (page - 1) * page_size;
+        let query = Query::new()
+            .filter("app_id = {p}", &app_id)
+            .filter("content_type = {p}", &content_type)
+            .filter_if(!search.is_empty(), "text_content LIKE '%' || {p} || '%'", &search)
+            .filter_if(!source_domain.is_empty(), DOMAIN_FILTER_SQL, &source_domain)
+            .page(&page_size, &offset);
+        let sql = query.sql(
+            &format!("SELECT {} FROM clipboard_entries e", ENTRY_COLUMNS),
+            " ORDER BY is_favorite DESC, created_at DESC",
+        );
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let rows = stmt.query_map(query.params(), entry_from_row)?;
+        rows.collect()
+    }
 
-        let map_row = |row: &rusqlite::Row| -> rusqlite::Result<ClipboardEntry> {
-            Ok(ClipboardEntry {
-                id: row.get(0)?,
-                app_id: row.get(1)?,
-                content_type: row.get(2)?,
-                text_content: row.get(3)?,
-                image_path: row.get(4)?,
-                created_at: row.get(5)?,
-                source_url: row.get(6)?,
-                is_favorite: row.get::<_, i64>(7)? != 0,
-                is_sensitive: row.get::<_, i64>(8)? != 0,
-                html_content: row.get(9)?,
-            })
-        };
+    /// Every entry `get_entries` would page through with the same filters, in the same order.
+    pub fn get_entry_ids(&self, app_id: i64, content_type: &str, search: &str, source_domain: &str) -> Result<Vec<EntryStub>> {
+        let _t = crate::metrics::timer("db.get_entry_ids");
+        let query = Query::new()
+            .filter("app_id = {p}", &app_id)
+            .filter("content_type = {p}", &content_type)
+            .filter_if(!search.is_empty(), "text_content LIKE '%' || {p} || '%'", &search)
+            .filter_if(!source_domain.is_empty(), DOMAIN_FILTER_SQL, &source_domain);
+        let sql = query.sql(
+            "SELECT id, created_at, COALESCE(is_favorite, 0) FROM clipboard_entries e",
+            " ORDER BY is_favorite DESC, created_at DESC",
+        );
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let rows = stmt.query_map(query.params(), |row| {
+            Ok(EntryStub { id: row.get(0)?, created_at: row.get(1)?, is_favorite: row.get(2)? })
+        })?;
+        rows.collect()
+    }
 
-        match (search.is_empty(), source_domain.is_empty()) {
-            (true, true) => {
-                let q = format!("{}{} LIMIT ?3 OFFSET ?4", base, order);
-                self.conn.prepare(&q)?.query_map(params![app_id, content_type, page_size, offset], map_row)?.collect()
-            }
-            (false, true) => {
-                let q = format!("{} AND text_content LIKE '%' || ?3 || '%'{} LIMIT ?4 OFFSET ?5", base, order);
-                self.conn.prepare(&q)?.query_map(params![app_id, content_type, search, page_size, offset], map_row)?.collect()
-            }
-            (true, false) => {
-                let q = format!("{}{}{} LIMIT ?4 OFFSET ?5", base, domain_filter.replace("{d}", "3"), order);
-                self.conn.prepare(&q)?.query_map(params![app_id, content_type, source_domain, page_size, offset], map_row)?.collect()
-            }
-            (false, false) => {
-                let q = format!("{} AND text_content LIKE '%' || ?3 || '%'{}{} LIMIT ?5 OFFSET ?6", base, domain_filter.replace("{d}", "4"), order);
-                self.conn.prepare(&q)?.query_map(params![app_id, content_type, search, source_domain, page_size, offset], map_row)?.collect()
+    /// The entries with the given ids, in the order asked for. Ids that no longer exist are skipped.
+    pub fn get_entries_by_ids(&self, ids: &[i64]) -> Result<Vec<ClipboardEntry>> {
+        let _t = crate::metrics::timer("db.get_entries_by_ids");
+        let mut found = std::collections::HashMap::with_capacity(ids.len());
+        // Stays well under SQLite's limit on bound parameters
+        for chunk in ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(",");
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT {} FROM clipboard_entries e WHERE e.id IN ({})",
+                ENTRY_COLUMNS, placeholders
+            ))?;
+            for entry in stmt.query_map(rusqlite::params_from_iter(chunk), entry_from_row)? {
+                let entry = entry?;
+                found.insert(entry.id, entry);
             }
         }
+        Ok(ids.iter().filter_map(|id| found.remove(id)).collect())
+    }
+
+    pub fn mark_screenshot(&self, id: i64) -> Result<()> {
+        self.conn.execute("UPDATE clipboard_entries SET detected_kind = 'screenshot' WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Screenshots from every app, newest first.
+    pub fn get_screenshots(&self, page: i64, page_size: i64) -> Result<Vec<ClipboardEntry>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {} FROM clipboard_entries e
+             WHERE e.content_type = 'image' AND e.detected_kind = 'screenshot'
+             ORDER BY e.created_at DESC LIMIT ?1 OFFSET ?2",
+            ENTRY_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![page_size, (page - 1).max(0) * page_size], entry_from_row)?;
+        rows.collect()
+    }
+
+    pub fn set_path_info(&self, id: i64, info: &crate::filepath::PathInfo) -> Result<()> {
+        self.conn.execute(
+            "UPDATE clipboard_entries SET detected_kind = 'path', file_size = ?1, file_ext = ?2 WHERE id = ?3",
+            params![info.size, info.extension, id],
+        )?;
+        Ok(())
+    }
+
+    /// For formats recognized from the text alone, such as "json".
+    pub fn set_detected_kind(&self, id: i64, kind: &str) -> Result<()> {
+        self.conn.execute("UPDATE clipboard_entries SET detected_kind = ?1 WHERE id = ?2", params![kind, id])?;
+        Ok(())
+    }
+
+    pub fn set_entry_table(&self, id: i64, rows: &[Vec<String>]) -> Result<()> {
+        let cells = serde_json::to_string(rows).unwrap_or_default();
+        self.conn.execute(
+            "INSERT INTO entry_tables (entry_id, cells) VALUES (?1, ?2)
+             ON CONFLICT (entry_id) DO UPDATE SET cells = excluded.cells",
+            params![id, cells],
+        )?;
+        self.conn.execute("UPDATE clipboard_entries SET detected_kind = 'table' WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// The cells stored when the entry was captured as a table.
+    pub fn get_entry_table(&self, id: i64) -> Result<Option<Vec<Vec<String>>>> {
+        let cells: Option<String> = self
+            .conn
+            .query_row("SELECT cells FROM entry_tables WHERE entry_id = ?1", params![id], |row| row.get(0))
+            .optional()?;
+        Ok(cells.and_then(|c| serde_json::from_str(&c).ok()))
+    }
+
+    pub fn count_entries(&self) -> Result<i64> {
+        self.conn.query_row("SELECT COUNT(*) FROM clipboard_entries", [], |row| row.get(0))
+    }
+
+    /// Today's non-sensitive entries created after `after` (a `created_at` value), oldest first.
+    pub fn get_today_entries_after(&self, after: &str) -> Result<Vec<ClipboardEntry>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM clipboard_entries e
+             WHERE created_at > ?1 AND date(created_at) = date('now', 'localtime') AND COALESCE(is_sensitive, 0) = 0
+             ORDER BY created_at ASC, id ASC",
+            ENTRY_COLUMNS
+        ))?;
+        let result = stmt.query_map(params![after], entry_from_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(result)
+    }
+
+    pub fn get_recent_entries(&self, limit: i64) -> Result<Vec<ClipboardEntry>> {
+        let mut stmt = self.conn.prepare(
+            &format!("SELECT {} FROM clipboard_entries e ORDER BY created_at DESC LIMIT ?1", ENTRY_COLUMNS),
+        )?;
+        let result: Vec<ClipboardEntry> = stmt.query_map(params![limit], entry_from_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(result)
     }
 
     pub fn get_entry_by_id(&self, id: i64) -> Result<ClipboardEntry> {
-        self.conn.query_row(
-            "SELECT id, app_id, content_type, text_content, image_path, created_at, source_url, COALESCE(is_favorite,0), COALESCE(is_sensitive,0), html_content
-             FROM clipboard_entries WHERE id = ?1",
+        let mut entry = self.conn.query_row(
+            &format!("SELECT {} FROM clipboard_entries e WHERE id = ?1", ENTRY_COLUMNS),
             params![id],
-            |row| {
-                Ok(ClipboardEntry {
-                    id: row.get(0)?,
-                    app_id: row.get(1)?,
-                    content_type: row.get(2)?,
-                    text_content: row.get(3)?,
-                    image_path: row.get(4)?,
-                    created_at: row.get(5)?,
-                    source_url: row.get(6)?,
-                    is_favorite: row.get::<_, i64>(7)? != 0,
-                    is_sensitive: row.get::<_, i64>(8)? != 0,
-                    html_content: row.get(9)?,
-                })
-            },
-        )
+            entry_from_row,
+        )?;
+        entry.links = Some(self.get_entry_links(id)?);
+        Ok(entry)
+    }
+
+    pub fn get_entry_links(&self, id: i64) -> Result<Vec<EntryLink>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT e.id, l.kind, e.content_type, e.title, e.created_at
+             FROM entry_links l
+             JOIN clipboard_entries e ON e.id = CASE WHEN l.entry_id = ?1 THEN l.linked_id ELSE l.entry_id END
+             WHERE l.entry_id = ?1 OR l.linked_id = ?1
+             ORDER BY e.created_at, e.id",
+        )?;
+        let rows = stmt.query_map(params![id], |row| {
+            Ok(EntryLink {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                content_type: row.get(2)?,
+                title: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Links two entries by hand; an automatic link between them becomes a manual one.
+    pub fn link_entries(&self, a: i64, b: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO entry_links (entry_id, linked_id, kind) VALUES (?1, ?2, 'manual')
+             ON CONFLICT (entry_id, linked_id) DO UPDATE SET kind = 'manual'",
+            params![a.min(b), a.max(b)],
+        )?;
+        Ok(())
+    }
+
+    pub fn unlink_entries(&self, a: i64, b: i64) -> Result<bool> {
+        let removed = self.conn.execute(
+            "DELETE FROM entry_links WHERE entry_id = ?1 AND linked_id = ?2",
+            params![a.min(b), a.max(b)],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Links a just-captured entry to the previous capture from the same app when that came
+    /// within `window_secs`, such as a screenshot and the text copied right after it.
+    pub fn auto_link_entry(&self, entry_id: i64, app_id: i64, window_secs: u32) -> Result<()> {
+        if window_secs == 0 {
+            return Ok(());
+        }
+        let previous: Option<i64> = self.conn.prepare_cached(
+            "SELECT p.id FROM clipboard_entries p, clipboard_entries c
+             WHERE c.id = ?1 AND p.app_id = ?2 AND p.id != c.id
+               AND p.created_at <= c.created_at AND p.created_at >= datetime(c.created_at, ?3)
+             ORDER BY p.created_at DESC, p.id DESC LIMIT 1",
+        )?
+        .query_row(params![entry_id, app_id, format!("-{} seconds", window_secs)], |row| row.get(0))
+        .optional()?;
+        if let Some(previous) = previous {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO entry_links (entry_id, linked_id, kind) VALUES (?1, ?2, 'auto')",
+                params![previous.min(entry_id), previous.max(entry_id)],
+            )?;
+        }
+        Ok(())
     }
 
     pub fn get_source_urls(&self, app_id: i64) -> Result<Vec<SourceInfo>> {
@@ -383,19 +782,28 @@ impl Database {
             *domain_counts.entry(domain).or_insert(0) += count;
         }
 
+        let favorites = self.get_favorite_domains()?;
         let mut result: Vec<SourceInfo> = domain_counts
             .into_iter()
-            .map(|(domain, count)| SourceInfo { domain, count })
+            .map(|(domain, count)| SourceInfo { is_favorite: favorites.contains(&domain), domain, count })
             .collect();
-        result.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.domain.cmp(&b.domain)));
+        result.sort_by(|a, b| {
+            b.is_favorite.cmp(&a.is_favorite).then_with(|| b.count.cmp(&a.count)).then_with(|| a.domain.cmp(&b.domain))
+        });
         Ok(result)
     }
 
+    pub fn get_favorite_domains(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT domain FROM favorite_domains ORDER BY domain")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
     pub fn get_entry_full(&self, id: i64) -> Result<Option<DeletedEntry>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, app_id, content_type, text_content, image_path, created_at, \
-             content_hash, source_url, is_favorite, is_sensitive, html_content \
-             FROM clipboard_entries WHERE id = ?1"
+            "SELECT e.id, e.app_id, e.content_type, e.text_content, e.image_path, e.created_at, \
+             e.content_hash, e.source_url, e.is_favorite, e.is_sensitive, e.html_content, a.name, a.exe_path \
+             FROM clipboard_entries e JOIN apps a ON a.id = e.app_id WHERE e.id = ?1"
         )?;
         let entry = stmt.query_row(params![id], |row| {
             Ok(DeletedEntry {
@@ -410,11 +818,27 @@ impl Database {
                 is_favorite: row.get(8)?,
                 is_sensitive: row.get(9)?,
                 html_content: row.get(10)?,
+                app_name: row.get(11)?,
+                app_exe: row.get(12)?,
             })
         }).ok();
         Ok(entry)
     }
 
+    pub fn is_entry_locked(&self, id: i64) -> Result<bool> {
+        self.conn.query_row(
+            "SELECT is_locked FROM clipboard_entries WHERE id = ?1",
+            params![id],
+            |row| row.get::<_, i64>(0),
+        ).map(|v| v != 0)
+    }
+
+    pub fn toggle_entry_locked(&self, id: i64) -> Result<bool> {
+        let current = self.is_entry_locked(id)?;
+        self.conn.execute("UPDATE clipboard_entries SET is_locked = ?1 WHERE id = ?2", params![!current, id])?;
+        Ok(!current)
+    }
+
     pub fn delete_entry(&self, id: i64) -> Result<Option<String>> {
         let image_path: Option<String> = self
             .conn
@@ -431,17 +855,18 @@ impl Database {
         )?;
 
         self.cleanup_empty_apps()?;
-        Ok(image_path)
+        Ok(self.release_images(image_path.into_iter().collect())?.pop())
     }
 
     pub fn restore_entry(&self, entry: &DeletedEntry) -> Result<()> {
+        let (app_id, _) = self.get_or_create_app(&entry.app_name, &entry.app_exe, None, None)?;
         self.conn.execute(
             "INSERT OR REPLACE INTO clipboard_entries \
              (id, app_id, content_type, text_content, image_path, created_at, \
               content_hash, source_url, is_favorite, is_sensitive, html_content) \
              VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11)",
             params![
-                entry.id, entry.app_id, entry.content_type, entry.text_content,
+                entry.id, app_id, entry.content_type, entry.text_content,
                 entry.image_path, entry.created_at, entry.content_hash,
                 entry.source_url, entry.is_favorite, entry.is_sensitive, entry.html_content,
             ],
@@ -450,9 +875,9 @@ impl Database {
     }
 
     pub fn delete_entries_by_domain(&self, app_id: i64, domain: &str) -> Result<Vec<String>> {
-        let filter = DOMAIN_FILTER_SQL.replace("{d}", "2");
+        let filter = DOMAIN_FILTER_SQL.replace("{p}", "?2");
         let select_q = format!(
-            "SELECT image_path FROM clipboard_entries WHERE app_id = ?1 AND image_path IS NOT NULL AND {}",
+            "SELECT image_path FROM clipboard_entries WHERE app_id = ?1 AND image_path IS NOT NULL AND is_locked = 0 AND {}",
             filter
         );
         let mut stmt = self.conn.prepare(&select_q)?;
@@ -461,33 +886,78 @@ impl Database {
             .collect::<Result<Vec<_>>>()?;
 
         let delete_q = format!(
-            "DELETE FROM clipboard_entries WHERE app_id = ?1 AND {}",
+            "DELETE FROM clipboard_entries WHERE app_id = ?1 AND is_locked = 0 AND {}",
             filter
         );
         self.conn.execute(&delete_q, params![app_id, domain])?;
         self.cleanup_empty_apps()?;
-        Ok(paths)
+        self.release_images(paths)
+    }
+
+    /// Sums up the entries matching `filter`, and the image files only they use.
+    fn delete_summary(&self, filter: &str, params: &[&dyn rusqlite::ToSql]) -> Result<DeleteSummary> {
+        let (entries, text_bytes): (i64, i64) = self.conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(COALESCE(length(CAST(text_content AS BLOB)), 0) + COALESCE(length(CAST(html_content AS BLOB)), 0)), 0)
+                 FROM clipboard_entries WHERE {}",
+                filter
+            ),
+            params,
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT DISTINCT image_path FROM clipboard_entries WHERE image_path IS NOT NULL AND {0}
+             AND image_path NOT IN (SELECT image_path FROM clipboard_entries WHERE image_path IS NOT NULL AND NOT ({0}))",
+            filter
+        ))?;
+        let images: Vec<String> = stmt.query_map(params, |row| row.get(0))?.collect::<Result<Vec<_>>>()?;
+        let images_dir = self.images_dir();
+        let image_bytes: u64 = images
+            .iter()
+            .filter_map(|f| std::fs::metadata(images_dir.join(f)).ok())
+            .map(|m| m.len())
+            .sum();
+        Ok(DeleteSummary {
+            entries,
+            images: images.len() as i64,
+            freed_bytes: text_bytes.max(0) as u64 + image_bytes,
+            dry_run: true,
+        })
+    }
+
+    pub fn domain_delete_summary(&self, app_id: i64, domain: &str) -> Result<DeleteSummary> {
+        let filter = format!("app_id = ?1 AND is_locked = 0 AND {}", DOMAIN_FILTER_SQL.replace("{p}", "?2"));
+        self.delete_summary(&filter, &[&app_id, &domain])
+    }
+
+    pub fn clear_app_summary(&self, app_id: i64) -> Result<DeleteSummary> {
+        self.delete_summary("app_id = ?1 AND is_locked = 0", &[&app_id])
+    }
+
+    pub fn clear_all_summary(&self) -> Result<DeleteSummary> {
+        self.delete_summary("is_locked = 0", &[])
     }
 
     pub fn clear_app_entries(&self, app_id: i64) -> Result<Vec<String>> {
         let mut stmt = self.conn.prepare(
-            "SELECT image_path FROM clipboard_entries WHERE app_id = ?1 AND image_path IS NOT NULL",
+            "SELECT image_path FROM clipboard_entries WHERE app_id = ?1 AND image_path IS NOT NULL AND is_locked = 0",
         )?;
         let paths: Vec<String> = stmt
             .query_map(params![app_id], |row| row.get(0))?
             .collect::<Result<Vec<_>>>()?;
 
         self.conn.execute(
-            "DELETE FROM clipboard_entries WHERE app_id = ?1",
+            "DELETE FROM clipboard_entries WHERE app_id = ?1 AND is_locked = 0",
             params![app_id],
         )?;
         self.cleanup_empty_apps()?;
-        Ok(paths)
+        self.release_images(paths)
     }
 
+    /// Deletes every entry except locked ones, along with the apps left without entries.
     pub fn clear_all_entries(&self) -> Result<Vec<String>> {
         let mut stmt = self.conn.prepare(
-            "SELECT image_path FROM clipboard_entries WHERE image_path IS NOT NULL",
+            "SELECT image_path FROM clipboard_entries WHERE image_path IS NOT NULL AND is_locked = 0",
         )?;
         let paths: Vec<String> = stmt
             .query_map([], |row| row.get(0))?
@@ -495,11 +965,11 @@ impl Database {
 
         self.conn.execute_batch(
             "BEGIN;
-             DELETE FROM clipboard_entries;
-             DELETE FROM apps;
+             DELETE FROM clipboard_entries WHERE is_locked = 0;
+             DELETE FROM apps WHERE id NOT IN (SELECT DISTINCT app_id FROM clipboard_entries);
              COMMIT;"
         )?;
-        Ok(paths)
+        self.release_images(paths)
     }
 
     pub fn toggle_entry_favorite(&self, id: i64) -> Result<bool> {
@@ -522,39 +992,98 @@ impl Database {
         Ok(new_val != 0)
     }
 
+    pub fn set_app_hidden(&self, id: i64, hidden: bool) -> Result<()> {
+        let changed = self.conn.execute("UPDATE apps SET is_hidden = ?1 WHERE id = ?2", params![hidden, id])?;
+        if changed == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    /// `domain` is a base domain as listed by `get_source_urls`.
+    pub fn toggle_domain_favorite(&self, domain: &str) -> Result<bool> {
+        let removed = self.conn.execute("DELETE FROM favorite_domains WHERE domain = ?1", params![domain])?;
+        if removed > 0 {
+            return Ok(false);
+        }
+        self.conn.execute("INSERT INTO favorite_domains (domain) VALUES (?1)", params![domain])?;
+        Ok(true)
+    }
+
     pub fn toggle_sensitive(&self, id: i64) -> Result<bool> {
         let current: i64 = self.conn.query_row(
             "SELECT COALESCE(is_sensitive, 0) FROM clipboard_entries WHERE id = ?1",
             params![id], |row| row.get(0),
         )?;
         let new_val = if current != 0 { 0 } else { 1 };
-        self.conn.execute("UPDATE clipboard_entries SET is_sensitive = ?1 WHERE id = ?2", params![new_val, id])?;
+        let title = if new_val != 0 {
+            None
+        } else {
+            let text: Option<String> = self.conn.query_row(
+                "SELECT text_content FROM clipboard_entries WHERE id = ?1",
+                params![id], |row| row.get(0),
+            )?;
+            text.as_deref().and_then(crate::title::generate)
+        };
+        // Re-scans leave the user's choice alone, in either direction
+        self.conn.execute(
+            "UPDATE clipboard_entries SET is_sensitive = ?1, sensitive_kind = 'manual', title = ?2 WHERE id = ?3",
+            params![new_val, title, id],
+        )?;
         Ok(new_val != 0)
     }
 
-    pub fn get_favorite_entries(&self, content_type: &str, page: i64, page_size: i64) -> Result<Vec<ClipboardEntry>> {
-        let offset = (page - 1) * page_size;
-        let mut stmt = self.conn.prepare(
-            "SELECT e.id, e.app_id, e.content_type, e.text_content, e.image_path, e.created_at, e.source_url, COALESCE(e.is_favorite,0), COALESCE(e.is_sensitive,0), e.html_content
-             FROM clipboard_entries e
-             LEFT JOIN apps a ON e.app_id = a.id
-             WHERE (e.is_favorite = 1 OR COALESCE(a.is_favorite,0) = 1) AND e.content_type = ?1
-             ORDER BY e.created_at DESC LIMIT ?2 OFFSET ?3",
-        )?;
-        let result: Vec<ClipboardEntry> = stmt.query_map(params![content_type, page_size, offset], |row| {
-            Ok(ClipboardEntry {
-                id: row.get(0)?,
-                app_id: row.get(1)?,
-                content_type: row.get(2)?,
-                text_content: row.get(3)?,
-                image_path: row.get(4)?,
-                created_at: row.get(5)?,
-                source_url: row.get(6)?,
-                is_favorite: row.get::<_, i64>(7)? != 0,
-                is_sensitive: row.get::<_, i64>(8)? != 0,
-                html_content: row.get(9)?,
-            })
-        })?.collect::<Result<Vec<_>>>()?;
+    /// Text entries a re-scan may change: all but those the user flagged or cleared by hand.
+    pub fn count_sensitive_rescan_entries(&self) -> Result<i64> {
+        self.conn.query_row(
+            "SELECT COUNT(*) FROM clipboard_entries
+             WHERE content_type = 'text' AND text_content IS NOT NULL AND sensitive_kind IS NOT 'manual'",
+            [], |row| row.get(0),
+        )
+    }
+
+    /// The next `limit` of those entries after `after_id`.
+    pub fn get_sensitive_rescan_batch(&self, after_id: i64, limit: i64) -> Result<Vec<RescanRow>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, text_content, COALESCE(is_sensitive, 0), sensitive_kind FROM clipboard_entries
+             WHERE id > ?1 AND content_type = 'text' AND text_content IS NOT NULL AND sensitive_kind IS NOT 'manual'
+             ORDER BY id LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![after_id, limit], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? != 0, row.get(3)?))
+        })?;
+        rows.collect()
+    }
+
+    /// Stores re-scan results as (id, detected kind, text) in one transaction. Entries that are
+    /// no longer sensitive get their title back.
+    pub fn set_detected_sensitive(&self, updates: &[(i64, Option<&str>, &str)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "UPDATE clipboard_entries SET is_sensitive = ?2, sensitive_kind = ?3, title = ?4 WHERE id = ?1",
+            )?;
+            for (id, kind, text) in updates {
+                let title = if kind.is_some() { None } else { crate::title::generate(text) };
+                stmt.execute(params![id, kind.is_some() as i64, kind, title])?;
+            }
+        }
+        tx.commit()
+    }
+
+    pub fn get_favorite_entries(&self, content_type: &str, page: i64, page_size: i64) -> Result<Vec<ClipboardEntry>> {
+        let offset = (page - 1) * page_size;
+        let mut stmt = self.conn.prepare(
+            &format!(
+                "SELECT {}
+                 FROM clipboard_entries e
+                 LEFT JOIN apps a ON e.app_id = a.id
+                 WHERE (e.is_favorite = 1 OR COALESCE(a.is_favorite,0) = 1) AND e.content_type = ?1
+                 ORDER BY e.created_at DESC LIMIT ?2 OFFSET ?3",
+                ENTRY_COLUMNS
+            ),
+        )?;
+        let result: Vec<ClipboardEntry> = stmt.query_map(params![content_type, page_size, offset], entry_from_row)?.collect::<Result<Vec<_>>>()?;
         Ok(result)
     }
 
@@ -571,63 +1100,299 @@ impl Database {
         )
     }
 
-    pub fn upsert_text_entry_with_html(&self, app_id: i64, text: &str, hash: &str, source_url: Option<&str>, html: Option<&str>, is_sensitive: bool, image_path: Option<&str>) -> Result<i64> {
-        if let Ok(id) = self.conn.query_row(
+    pub fn get_collections(&self) -> Result<Vec<CollectionInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.name, c.sort_order, COUNT(ce.entry_id)
+             FROM collections c
+             LEFT JOIN collection_entries ce ON ce.collection_id = c.id
+             GROUP BY c.id
+             ORDER BY c.sort_order ASC, c.id ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(CollectionInfo {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                sort_order: row.get(2)?,
+                entry_count: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn create_collection(&self, name: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO collections (name, sort_order) VALUES (?1, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM collections))",
+            params![name],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn rename_collection(&self, id: i64, name: &str) -> Result<()> {
+        let changed = self.conn.execute("UPDATE collections SET name = ?1 WHERE id = ?2", params![name, id])?;
+        if changed == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    pub fn delete_collection(&self, id: i64) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM collection_entries WHERE collection_id = ?1", params![id])?;
+        tx.execute("DELETE FROM collections WHERE id = ?1", params![id])?;
+        tx.commit()
+    }
+
+    pub fn reorder_collections(&self, ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (i, id) in ids.iter().enumerate() {
+            tx.execute("UPDATE collections SET sort_order = ?1 WHERE id = ?2", params![i as i64, id])?;
+        }
+        tx.commit()
+    }
+
+    pub fn add_entry_to_collection(&self, collection_id: i64, entry_id: i64) -> Result<()> {
+        self.conn.query_row("SELECT id FROM collections WHERE id = ?1", params![collection_id], |row| row.get::<_, i64>(0))?;
+        self.conn.query_row("SELECT id FROM clipboard_entries WHERE id = ?1", params![entry_id], |row| row.get::<_, i64>(0))?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO collection_entries (collection_id, entry_id, sort_order)
+             VALUES (?1, ?2, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM collection_entries WHERE collection_id = ?1))",
+            params![collection_id, entry_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_entry_from_collection(&self, collection_id: i64, entry_id: i64) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM collection_entries WHERE collection_id = ?1 AND entry_id = ?2",
+            params![collection_id, entry_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn reorder_collection_entries(&self, collection_id: i64, entry_ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (i, entry_id) in entry_ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE collection_entries SET sort_order = ?1 WHERE collection_id = ?2 AND entry_id = ?3",
+                params![i as i64, collection_id, entry_id],
+            )?;
+        }
+        tx.commit()
+    }
+
+    pub fn get_entry_collections(&self, entry_id: i64) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT collection_id FROM collection_entries WHERE entry_id = ?1 ORDER BY collection_id",
+        )?;
+        let rows = stmt.query_map(params![entry_id], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    pub fn get_collection_entries(&self, collection_id: i64, content_type: &str, page: i64, page_size: i64) -> Result<Vec<ClipboardEntry>> {
+        let offset = (page - 1) * page_size;
+        let mut stmt = self.conn.prepare(
+            &format!(
+                "SELECT {}
+                 FROM collection_entries ce
+                 JOIN clipboard_entries e ON e.id = ce.entry_id
+                 WHERE ce.collection_id = ?1 AND (?2 = '' OR e.content_type = ?2)
+                 ORDER BY ce.sort_order ASC, e.created_at DESC LIMIT ?3 OFFSET ?4",
+                ENTRY_COLUMNS
+            ),
+        )?;
+        let result: Vec<ClipboardEntry> = stmt.query_map(params![collection_id, content_type, page_size, offset], entry_from_row)?.collect::<Result<Vec<_>>>()?;
+        Ok(result)
+    }
+
+    pub fn add_entry_tags(&self, entry_id: i64, tags: &[String]) -> Result<()> {
+        for tag in tags {
+            let tag = tag.trim();
+            if tag.is_empty() {
+                continue;
+            }
+            self.conn.execute(
+                "INSERT OR IGNORE INTO entry_tags (entry_id, tag) VALUES (?1, ?2)",
+                params![entry_id, tag],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn set_entry_tags(&self, entry_id: i64, tags: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM entry_tags WHERE entry_id = ?1", params![entry_id])?;
+        self.add_entry_tags(entry_id, tags)?;
+        tx.commit()
+    }
+
+    pub fn get_entry_tags(&self, entry_id: i64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT tag FROM entry_tags WHERE entry_id = ?1 ORDER BY tag")?;
+        let rows = stmt.query_map(params![entry_id], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    pub fn get_templates(&self) -> Result<Vec<TemplateInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, content, abbreviation, created_at FROM templates ORDER BY name COLLATE NOCASE",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let content: String = row.get(2)?;
+            Ok(TemplateInfo {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                inputs: crate::template::input_names(&content),
+                content,
+                abbreviation: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn get_template_content(&self, id: i64) -> Result<String> {
+        self.conn.query_row("SELECT content FROM templates WHERE id = ?1", params![id], |row| row.get(0))
+    }
+
+    pub fn get_template_abbreviations(&self) -> Result<Vec<(String, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT abbreviation, id FROM templates WHERE abbreviation IS NOT NULL AND abbreviation != ''",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    pub fn save_template(&self, id: Option<i64>, name: &str, content: &str, abbreviation: Option<&str>) -> Result<i64> {
+        match id {
+            Some(id) => {
+                let changed = self.conn.execute(
+                    "UPDATE templates SET name = ?1, content = ?2, abbreviation = ?3 WHERE id = ?4",
+                    params![name, content, abbreviation, id],
+                )?;
+                if changed == 0 {
+                    return Err(rusqlite::Error::QueryReturnedNoRows);
+                }
+                Ok(id)
+            }
+            None => {
+                self.conn.execute(
+                    "INSERT INTO templates (name, content, abbreviation) VALUES (?1, ?2, ?3)",
+                    params![name, content, abbreviation],
+                )?;
+                Ok(self.conn.last_insert_rowid())
+            }
+        }
+    }
+
+    pub fn delete_template(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM templates WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn next_template_counter(&self, name: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO template_counters (name, value) VALUES (?1, 1)
+             ON CONFLICT(name) DO UPDATE SET value = value + 1",
+            params![name],
+        )?;
+        self.conn.query_row("SELECT value FROM template_counters WHERE name = ?1", params![name], |row| row.get(0))
+    }
+
+    pub fn upsert_text_entry_with_html(&self, app_id: i64, capture: &TextCapture, source_url: Option<&str>, origin: &EntryOrigin) -> Result<i64> {
+        let _t = crate::metrics::timer("db.upsert_entry");
+        let TextCapture { text, hash, html, image_path, sensitive_kind } = *capture;
+        if let Ok(id) = self.conn.prepare_cached(
             "SELECT id FROM clipboard_entries WHERE app_id = ?1 AND content_type = 'text' AND content_hash = ?2",
-            params![app_id, hash],
-            |row| row.get::<_, i64>(0),
-        ) {
+        )?.query_row(params![app_id, hash], |row| row.get::<_, i64>(0)) {
             self.conn.execute(
-                "UPDATE clipboard_entries SET created_at = datetime('now', 'localtime'), source_url = COALESCE(?2, source_url), html_content = COALESCE(?3, html_content), image_path = COALESCE(?4, image_path) WHERE id = ?1",
-                params![id, source_url, html, image_path],
+                "UPDATE clipboard_entries SET created_at = datetime('now', 'localtime'), source_url = COALESCE(?2, source_url), html_content = COALESCE(?3, html_content), image_path = COALESCE(?4, image_path), source_exe = COALESCE(?5, source_exe), owner_exe = COALESCE(?6, owner_exe), remote_session = COALESCE(?7, remote_session) WHERE id = ?1",
+                params![id, source_url, html, image_path, origin.source_exe, origin.owner_exe, origin.remote_session],
             )?;
+            self.record_entry_event(id, "captured", Some(app_id))?;
             return Ok(id);
         }
 
-        let sensitive_val: i64 = if is_sensitive { 1 } else { 0 };
+        let sensitive_val: i64 = if sensitive_kind.is_some() { 1 } else { 0 };
+        // Titles are shown even while an entry is masked, so sensitive clips never get one
+        let title = if sensitive_kind.is_some() { None } else { crate::title::generate(text) };
         self.conn.execute(
-            "INSERT INTO clipboard_entries (app_id, content_type, text_content, content_hash, source_url, html_content, is_sensitive, image_path) VALUES (?1, 'text', ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![app_id, text, hash, source_url, html, sensitive_val, image_path],
+            "INSERT INTO clipboard_entries (app_id, content_type, text_content, content_hash, source_url, html_content, is_sensitive, sensitive_kind, image_path, source_exe, owner_exe, remote_session, title) VALUES (?1, 'text', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![app_id, text, hash, source_url, html, sensitive_val, sensitive_kind, image_path, origin.source_exe, origin.owner_exe, origin.remote_session, title],
         )?;
-        Ok(self.conn.last_insert_rowid())
+        let id = self.conn.last_insert_rowid();
+        self.record_entry_event(id, "captured", Some(app_id))?;
+        Ok(id)
+    }
+
+    /// Stores generated text as a new entry of the source's app that links back to `source`.
+    pub fn insert_derived_entry(&self, source: &ClipboardEntry, text: &str, hash: &str, kind: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO clipboard_entries (app_id, content_type, text_content, content_hash, source_url, title, derived_from, derived_kind)
+             VALUES (?1, 'text', ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![source.app_id, text, hash, source.source_url, source.title, source.id, kind],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        self.record_entry_event(id, "derived", Some(source.app_id))?;
+        Ok(id)
+    }
+
+    /// Stores an edited copy of an image entry, kept in the source's app and linked back to it.
+    pub fn insert_derived_image_entry(&self, source: &ClipboardEntry, image_filename: &str, hash: &str, kind: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO clipboard_entries (app_id, content_type, image_path, content_hash, source_url, title, detected_kind, derived_from, derived_kind)
+             VALUES (?1, 'image', ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![source.app_id, image_filename, hash, source.source_url, source.title, source.detected_kind, source.id, kind],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        self.record_entry_event(id, "derived", Some(source.app_id))?;
+        Ok(id)
     }
 
     pub fn apply_retention_policy(&self, policy: &str) -> Result<Vec<String>> {
         let tx = self.conn.unchecked_transaction()?;
-        let result = match policy {
+        let result: Result<Vec<String>> = match policy {
             "1d" | "3d" | "7d" | "30d" => {
                 let days: i64 = policy.trim_end_matches('d').parse().unwrap_or(1);
                 let cutoff = format!("-{} days", days);
-                let mut stmt = tx.prepare(
-                    "SELECT image_path FROM clipboard_entries WHERE image_path IS NOT NULL AND is_favorite = 0 AND created_at < datetime('now', 'localtime', ?1)",
-                )?;
+                let mut stmt = tx.prepare(&format!(
+                    "SELECT image_path FROM clipboard_entries WHERE image_path IS NOT NULL AND {} AND created_at < datetime('now', 'localtime', ?1)",
+                    *EVICTABLE_SQL
+                ))?;
                 let paths: Vec<String> = stmt.query_map(params![cutoff], |row| row.get(0))?.collect::<Result<Vec<_>>>()?;
-                tx.execute("DELETE FROM clipboard_entries WHERE is_favorite = 0 AND created_at < datetime('now', 'localtime', ?1)", params![cutoff])?;
+                tx.execute(
+                    &format!("DELETE FROM clipboard_entries WHERE {} AND created_at < datetime('now', 'localtime', ?1)", *EVICTABLE_SQL),
+                    params![cutoff],
+                )?;
                 Ok(paths)
             }
             "500" | "1000" | "5000" => {
                 let max: i64 = policy.parse().unwrap_or(1000);
-                let total: i64 = tx.query_row("SELECT COUNT(*) FROM clipboard_entries WHERE is_favorite = 0", [], |row| row.get(0))?;
+                let total: i64 =
+                    tx.query_row(&format!("SELECT COUNT(*) FROM clipboard_entries WHERE {}", *EVICTABLE_SQL), [], |row| row.get(0))?;
                 if total <= max {
                     return Ok(vec![]);
                 }
                 let to_delete = total - max;
-                let mut stmt = tx.prepare(
-                    "SELECT image_path FROM clipboard_entries WHERE image_path IS NOT NULL AND is_favorite = 0 ORDER BY created_at ASC LIMIT ?1",
-                )?;
+                let mut stmt = tx.prepare(&format!(
+                    "SELECT image_path FROM clipboard_entries WHERE image_path IS NOT NULL AND {} ORDER BY created_at ASC LIMIT ?1",
+                    *EVICTABLE_SQL
+                ))?;
                 let paths: Vec<String> = stmt.query_map(params![to_delete], |row| row.get(0))?.collect::<Result<Vec<_>>>()?;
                 tx.execute(
-                    "DELETE FROM clipboard_entries WHERE is_favorite = 0 AND id IN (SELECT id FROM clipboard_entries WHERE is_favorite = 0 ORDER BY created_at ASC LIMIT ?1)",
+                    &format!(
+                        "DELETE FROM clipboard_entries WHERE id IN (SELECT id FROM clipboard_entries WHERE {} ORDER BY created_at ASC LIMIT ?1)",
+                        *EVICTABLE_SQL
+                    ),
                     params![to_delete],
                 )?;
                 Ok(paths)
             }
             "midnight" => {
-                let mut stmt = tx.prepare(
-                    "SELECT image_path FROM clipboard_entries WHERE image_path IS NOT NULL AND is_favorite = 0",
-                )?;
+                let mut stmt = tx.prepare(&format!(
+                    "SELECT image_path FROM clipboard_entries WHERE image_path IS NOT NULL AND {}",
+                    *EVICTABLE_SQL
+                ))?;
                 let paths: Vec<String> = stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<_>>>()?;
-                tx.execute("DELETE FROM clipboard_entries WHERE is_favorite = 0", [])?;
+                tx.execute(&format!("DELETE FROM clipboard_entries WHERE {}", *EVICTABLE_SQL), [])?;
                 Ok(paths)
             }
             _ => Ok(vec![]),
@@ -639,7 +1404,444 @@ impl Database {
             )?;
             tx.commit()?;
         }
-        result
+        self.release_images(result?)
+    }
+
+    pub fn record_entry_event(&self, entry_id: i64, kind: &str, app_id: Option<i64>) -> Result<()> {
+        self.conn
+            .prepare_cached("INSERT INTO entry_events (entry_id, kind, app_id) VALUES (?1, ?2, ?3)")?
+            .execute(params![entry_id, kind, app_id])?;
+        Ok(())
+    }
+
+    pub fn log_audit(&self, action: &str, entry_id: Option<i64>, detail: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO audit_log (action, entry_id, detail) VALUES (?1, ?2, ?3)",
+            params![action, entry_id, detail],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_audit_log(&self, limit: i64, offset: i64) -> Result<Vec<AuditRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, action, entry_id, detail, created_at FROM audit_log ORDER BY id DESC LIMIT ?1 OFFSET ?2",
+        )?;
+        let rows = stmt.query_map(params![limit, offset], |row| {
+            Ok(AuditRecord {
+                id: row.get(0)?,
+                action: row.get(1)?,
+                entry_id: row.get(2)?,
+                detail: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn get_sensitive_allowlist(&self) -> Result<Vec<AllowlistItem>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, kind, value, label, created_at FROM sensitive_allowlist ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(AllowlistItem {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                value: row.get(2)?,
+                label: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Ignores values that are already on the list.
+    pub fn add_sensitive_allowlist(&self, kind: &str, value: &str, label: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO sensitive_allowlist (kind, value, label) VALUES (?1, ?2, ?3)",
+            params![kind, value, label],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_sensitive_allowlist(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM sensitive_allowlist WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn get_context_patterns(&self) -> Result<Vec<ContextPattern>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, pattern, label, created_at FROM context_patterns ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ContextPattern {
+                id: row.get(0)?,
+                pattern: row.get(1)?,
+                label: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn add_context_pattern(&self, pattern: &str, label: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO context_patterns (pattern, label) VALUES (?1, ?2)",
+            params![pattern, label],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_context_pattern(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM context_patterns WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Deletes audit records older than `days`. Returns the number removed.
+    pub fn purge_audit_log(&self, days: u32) -> Result<usize> {
+        self.conn.execute(
+            "DELETE FROM audit_log WHERE created_at < datetime('now', 'localtime', ?1)",
+            params![format!("-{} days", days)],
+        )
+    }
+
+    pub fn get_entry_events(&self, entry_id: i64) -> Result<Vec<EntryEvent>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT kind, app_id, created_at FROM entry_events WHERE entry_id = ?1 ORDER BY created_at DESC, id DESC",
+        )?;
+        let rows = stmt.query_map(params![entry_id], |row| {
+            Ok(EntryEvent {
+                kind: row.get(0)?,
+                app_id: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn get_rules(&self) -> Result<Vec<RuleInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, enabled, sort_order, conditions, actions FROM rules ORDER BY sort_order, id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let conditions: String = row.get(4)?;
+            let actions: String = row.get(5)?;
+            Ok(RuleInfo {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                enabled: row.get::<_, i64>(2)? != 0,
+                sort_order: row.get(3)?,
+                conditions: serde_json::from_str(&conditions).unwrap_or_default(),
+                actions: serde_json::from_str(&actions).unwrap_or_default(),
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn save_rule(&self, id: Option<i64>, name: &str, enabled: bool, conditions: &str, actions: &str) -> Result<i64> {
+        match id {
+            Some(id) => {
+                let changed = self.conn.execute(
+                    "UPDATE rules SET name = ?1, enabled = ?2, conditions = ?3, actions = ?4 WHERE id = ?5",
+                    params![name, enabled as i64, conditions, actions, id],
+                )?;
+                if changed == 0 {
+                    return Err(rusqlite::Error::QueryReturnedNoRows);
+                }
+                Ok(id)
+            }
+            None => {
+                self.conn.execute(
+                    "INSERT INTO rules (name, enabled, conditions, actions, sort_order)
+                     VALUES (?1, ?2, ?3, ?4, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM rules))",
+                    params![name, enabled as i64, conditions, actions],
+                )?;
+                Ok(self.conn.last_insert_rowid())
+            }
+        }
+    }
+
+    pub fn delete_rule(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM rules WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn reorder_rules(&self, ids: &[i64]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (i, id) in ids.iter().enumerate() {
+            tx.execute("UPDATE rules SET sort_order = ?1 WHERE id = ?2", params![i as i64, id])?;
+        }
+        tx.commit()
+    }
+
+    /// Applies the favorite/retention part of a rule outcome to a freshly captured entry.
+    pub fn get_app_settings(&self, app_id: i64) -> Result<AppSettings> {
+        let settings = self
+            .conn
+            .query_row(
+                "SELECT s.plain_text, s.auto_favorite, s.store_plain_text, s.notify FROM app_settings s JOIN apps a ON a.exe_path = s.exe_path WHERE a.id = ?1",
+                params![app_id],
+                |row| {
+                    Ok(AppSettings {
+                        plain_text: row.get::<_, i64>(0)? != 0,
+                        auto_favorite: row.get::<_, i64>(1)? != 0,
+                        store_plain_text: row.get::<_, i64>(2)? != 0,
+                        notify: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub fn set_app_settings(&self, app_id: i64, settings: &AppSettings) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO app_settings (exe_path, plain_text, auto_favorite, store_plain_text, notify)
+             SELECT exe_path, ?2, ?3, ?4, ?5 FROM apps WHERE id = ?1
+             ON CONFLICT(exe_path) DO UPDATE SET plain_text = excluded.plain_text,
+                 auto_favorite = excluded.auto_favorite, store_plain_text = excluded.store_plain_text,
+                 notify = excluded.notify",
+            params![
+                app_id,
+                settings.plain_text as i64,
+                settings.auto_favorite as i64,
+                settings.store_plain_text as i64,
+                settings.notify
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn apply_rule_flags(&self, entry_id: i64, favorite: bool, retention_days: Option<i64>) -> Result<()> {
+        if favorite {
+            self.conn.execute("UPDATE clipboard_entries SET is_favorite = 1 WHERE id = ?1", params![entry_id])?;
+        }
+        if let Some(days) = retention_days {
+            self.conn.execute(
+                "UPDATE clipboard_entries SET expires_at = datetime('now', 'localtime', ?1) WHERE id = ?2",
+                params![format!("+{} days", days), entry_id],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Deletes entries whose rule-assigned retention has run out (favorites and locked entries
+    /// are kept).
+    /// Returns the number of deleted entries and the image files to remove.
+    pub fn purge_expired_entries(&self) -> Result<(usize, Vec<String>)> {
+        let tx = self.conn.unchecked_transaction()?;
+        let paths: Vec<String> = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT image_path FROM clipboard_entries
+                 WHERE image_path IS NOT NULL AND {} AND expires_at < datetime('now', 'localtime')",
+                *EVICTABLE_SQL
+            ))?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        let removed = tx.execute(
+            &format!("DELETE FROM clipboard_entries WHERE {} AND expires_at < datetime('now', 'localtime')", *EVICTABLE_SQL),
+            [],
+        )?;
+        tx.commit()?;
+        if removed > 0 {
+            self.cleanup_empty_apps()?;
+        }
+        Ok((removed, self.release_images(paths)?))
+    }
+
+    /// Removes unprotected entries of `kind` captured more than `days` days ago, for the per-type
+    /// retention settings. `kind` is "text", "image" or "screenshot"; screenshots only follow
+    /// their own setting.
+    pub fn purge_type_older_than(&self, kind: &str, days: u32) -> Result<(usize, Vec<String>)> {
+        let kind_filter = match kind {
+            "screenshot" => "content_type = 'image' AND detected_kind = 'screenshot'",
+            "image" => "content_type = 'image' AND COALESCE(detected_kind, '') != 'screenshot'",
+            _ => "content_type = 'text'",
+        };
+        let cutoff = format!("-{} days", days);
+        let tx = self.conn.unchecked_transaction()?;
+        let paths: Vec<String> = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT image_path FROM clipboard_entries
+                 WHERE image_path IS NOT NULL AND {} AND {} AND created_at < datetime('now', 'localtime', ?1)",
+                *EVICTABLE_SQL, kind_filter
+            ))?;
+            let rows = stmt.query_map(params![cutoff], |row| row.get(0))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        let removed = tx.execute(
+            &format!(
+                "DELETE FROM clipboard_entries
+                 WHERE {} AND {} AND created_at < datetime('now', 'localtime', ?1)",
+                *EVICTABLE_SQL, kind_filter
+            ),
+            params![cutoff],
+        )?;
+        tx.commit()?;
+        if removed > 0 {
+            self.cleanup_empty_apps()?;
+        }
+        Ok((removed, self.release_images(paths)?))
+    }
+
+    /// Copies the WAL back into the database file and truncates it, so a clean exit leaves no
+    /// large `-wal` file behind.
+    pub fn checkpoint(&self) -> Result<()> {
+        self.conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+    }
+
+    /// Fails when the database cannot be written, e.g. a read-only file or a full drive. The probe
+    /// is rolled back, so nothing changes.
+    pub fn check_writable(&self) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute_batch("CREATE TABLE self_test_probe (x INTEGER); INSERT INTO self_test_probe VALUES (1);")?;
+        tx.rollback()
+    }
+
+    /// Bytes of the database file in use. Deleting rows frees pages for reuse rather than
+    /// shrinking the file, so this drops as entries are removed while the file size does not.
+    pub fn used_bytes(&self) -> Result<i64> {
+        self.conn.query_row(
+            "SELECT (page_count - freelist_count) * page_size FROM pragma_page_count, pragma_freelist_count, pragma_page_size",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Entries retention may remove, oldest first, with the bytes of text and HTML each stores.
+    pub fn oldest_evictable_entries(&self, limit: i64, offset: i64) -> Result<Vec<EvictionCandidate>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, COALESCE(length(CAST(text_content AS BLOB)), 0) + COALESCE(length(CAST(html_content AS BLOB)), 0), image_path
+             FROM clipboard_entries WHERE {}
+             ORDER BY created_at ASC, id ASC LIMIT ?1 OFFSET ?2",
+            *EVICTABLE_SQL
+        ))?;
+        let rows = stmt.query_map(params![limit, offset], |row| {
+            Ok(EvictionCandidate { id: row.get(0)?, bytes: row.get(1)?, image_path: row.get(2)? })
+        })?;
+        rows.collect()
+    }
+
+    /// Deletes the given entries, skipping locked ones, and returns the image files no entry
+    /// references any more.
+    pub fn delete_entries(&self, ids: &[i64]) -> Result<Vec<String>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut paths = Vec::new();
+        for id in ids {
+            let image: Option<String> = tx
+                .query_row("SELECT image_path FROM clipboard_entries WHERE id = ?1 AND is_locked = 0", params![id], |row| row.get(0))
+                .optional()?
+                .flatten();
+            paths.extend(image);
+            tx.execute("DELETE FROM clipboard_entries WHERE id = ?1 AND is_locked = 0", params![id])?;
+        }
+        tx.commit()?;
+        self.cleanup_empty_apps()?;
+        self.release_images(paths)
+    }
+
+    /// Moves entries retention may remove that are older than `days` into per-year
+    /// `archive/archive_YYYY.db` files (along with their apps) and returns how many moved plus
+    /// their image file names.
+    pub fn archive_entries_older_than(&self, days: i64) -> Result<(usize, Vec<String>)> {
+        let cutoff = format!("-{} days", days);
+        let years: Vec<String> = {
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT DISTINCT substr(created_at, 1, 4) FROM clipboard_entries
+                 WHERE {} AND created_at < datetime('now', 'localtime', ?1)",
+                *EVICTABLE_SQL
+            ))?;
+            let rows = stmt.query_map(params![cutoff], |row| row.get(0))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        if years.is_empty() {
+            return Ok((0, Vec::new()));
+        }
+        std::fs::create_dir_all(self.archive_dir())
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+        let mut moved = 0;
+        let mut paths = Vec::new();
+        for year in years {
+            let archive_path = self.archive_dir().join(format!("archive_{}.db", year));
+            self.conn.execute("ATTACH DATABASE ?1 AS arc", params![archive_path.to_string_lossy()])?;
+            let result = self.move_year_to_archive(&year, &cutoff);
+            self.conn.execute("DETACH DATABASE arc", [])?;
+            let (count, mut images) = result?;
+            moved += count;
+            paths.append(&mut images);
+        }
+        self.cleanup_empty_apps()?;
+        Ok((moved, paths))
+    }
+
+    fn move_year_to_archive(&self, year: &str, cutoff: &str) -> Result<(usize, Vec<String>)> {
+        let filter = format!(
+            "{} AND created_at < datetime('now', 'localtime', ?1) AND substr(created_at, 1, 4) = ?2",
+            *EVICTABLE_SQL
+        );
+        let tx = self.conn.unchecked_transaction()?;
+        for table in ["apps", "clipboard_entries"] {
+            tx.execute_batch(&format!("CREATE TABLE IF NOT EXISTS arc.{0} AS SELECT * FROM main.{0} WHERE 0", table))?;
+            // The live schema may have gained columns since the archive was created
+            let archived = table_columns(&tx, "arc", table)?;
+            let columns = table_columns(&tx, "main", table)?;
+            for col in columns.iter().filter(|c| !archived.contains(c)) {
+                tx.execute_batch(&format!("ALTER TABLE arc.{} ADD COLUMN {}", table, col))?;
+            }
+        }
+        let entry_cols = table_columns(&tx, "main", "clipboard_entries")?.join(", ");
+        let app_cols = table_columns(&tx, "main", "apps")?.join(", ");
+
+        let paths: Vec<String> = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT image_path FROM main.clipboard_entries WHERE image_path IS NOT NULL AND {}",
+                filter
+            ))?;
+            let rows = stmt.query_map(params![cutoff, year], |row| row.get(0))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        tx.execute(
+            &format!(
+                "INSERT OR REPLACE INTO arc.apps ({0}) SELECT {0} FROM main.apps
+                 WHERE id IN (SELECT app_id FROM main.clipboard_entries WHERE {1})",
+                app_cols, filter
+            ),
+            params![cutoff, year],
+        )?;
+        tx.execute(
+            &format!(
+                "INSERT OR REPLACE INTO arc.clipboard_entries ({0}) SELECT {0} FROM main.clipboard_entries WHERE {1}",
+                entry_cols, filter
+            ),
+            params![cutoff, year],
+        )?;
+        let moved = tx.execute(&format!("DELETE FROM main.clipboard_entries WHERE {}", filter), params![cutoff, year])?;
+        tx.commit()?;
+        Ok((moved, paths))
+    }
+
+    /// Of the given image files whose entries were just removed, returns those no entry references
+    /// any more (each once), so the caller can delete them from disk.
+    pub fn release_images(&self, mut candidates: Vec<String>) -> Result<Vec<String>> {
+        candidates.sort();
+        candidates.dedup();
+        let mut released = Vec::new();
+        for filename in candidates {
+            let removed = self.conn.execute(
+                "DELETE FROM image_refs WHERE filename = ?1 AND ref_count <= 0",
+                params![filename],
+            )?;
+            let referenced: bool = self.conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM image_refs WHERE filename = ?1)",
+                params![filename],
+                |row| row.get(0),
+            )?;
+            if removed > 0 || !referenced {
+                released.push(filename);
+            }
+        }
+        Ok(released)
     }
 
     fn cleanup_empty_apps(&self) -> Result<()> {
@@ -650,3 +1852,52 @@ impl Database {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database in its own directory under the system temp dir.
+    fn open(name: &str) -> (Database, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("cutboard-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        (Database::new(&dir).unwrap(), dir)
+    }
+
+    #[test]
+    fn shared_image_is_kept_until_its_last_entry_is_gone() {
+        let (db, dir) = open("shared-image");
+        let (first_app, _) = db.get_or_create_app("First", "first.exe", None, None).unwrap();
+        let (second_app, _) = db.get_or_create_app("Second", "second.exe", None, None).unwrap();
+        let (first, _) = db.upsert_image_entry(first_app, "abc.png", "abc", None, &EntryOrigin::default()).unwrap();
+        let (second, _) = db.upsert_image_entry(second_app, "abc.png", "abc", None, &EntryOrigin::default()).unwrap();
+        assert_ne!(first, second);
+
+        assert_eq!(db.delete_entry(first).unwrap(), None);
+        assert_eq!(db.delete_entry(second).unwrap(), Some("abc.png".to_string()));
+        drop(db);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn legacy_named_images_are_released() {
+        let (db, dir) = open("legacy-image");
+        // An entry saved before images were named by hash and counted
+        crate::migrations::revert_to(&db.conn, 15).unwrap();
+        db.conn
+            .execute_batch(
+                "INSERT INTO apps (id, name, exe_path) VALUES (1, 'Paint', 'paint.exe');
+                 INSERT INTO clipboard_entries (id, app_id, content_type, image_path)
+                     VALUES (1, 1, 'image', '20240101_120000_000.png');",
+            )
+            .unwrap();
+        crate::migrations::run(&db.conn).unwrap();
+
+        assert_eq!(db.delete_entry(1).unwrap(), Some("20240101_120000_000.png".to_string()));
+        // A file no entry was ever counted for is not kept either
+        assert_eq!(db.release_images(vec!["untracked.png".to_string()]).unwrap(), vec!["untracked.png".to_string()]);
+        drop(db);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,30 @@
+//! Free space checks, so image captures and exports stop before the data drive fills up.
+
+use std::path::Path;
+
+/// Bytes available to the user on the drive holding `dir`, or `None` when it cannot be told.
+#[cfg(windows)]
+pub fn free_space(dir: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut free = 0u64;
+    unsafe { GetDiskFreeSpaceExW(PCWSTR(wide.as_ptr()), Some(&mut free as *mut u64), None, None) }.ok()?;
+    Some(free)
+}
+
+#[cfg(not(windows))]
+pub fn free_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+/// The free bytes on `dir`'s drive when they are below `min_mb`. Space that cannot be told, or a
+/// threshold of 0, never counts as low.
+pub fn low_space(dir: &Path, min_mb: u64) -> Option<u64> {
+    if min_mb == 0 {
+        return None;
+    }
+    free_space(dir).filter(|&free| free < min_mb * 1024 * 1024)
+}
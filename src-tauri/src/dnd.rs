@@ -0,0 +1,60 @@
+//! Do not disturb: until `dnd_until` (Unix seconds), capture notifications, sounds and the
+//! sensitive-content banner stay quiet. Capture itself carries on, and the state survives a
+//! restart until it runs out.
+
+use crate::config::AppConfig;
+use crate::error::CutboardError;
+use crate::ConfigPath;
+use tauri::{Emitter, Manager};
+
+/// Length of the quiet period started from the tray.
+pub const TRAY_DURATION_SECS: i64 = 3600;
+
+fn now_secs() -> i64 {
+    chrono::Local::now().timestamp()
+}
+
+pub fn is_active(config: &AppConfig) -> bool {
+    config.dnd_until > now_secs()
+}
+
+/// The end of the current quiet period, or 0 when there is none.
+pub fn until(config: &AppConfig) -> i64 {
+    if is_active(config) {
+        config.dnd_until
+    } else {
+        0
+    }
+}
+
+/// Starts a quiet period ending at `until`, or ends it early when `until` is not in the future.
+pub fn set(app: &tauri::AppHandle, until: i64) -> Result<(), CutboardError> {
+    let config_path = app.state::<ConfigPath>();
+    let mut config = AppConfig::load(&config_path.0);
+    config.dnd_until = until.max(0);
+    config.save(&config_path.0);
+    changed(app, self::until(&config));
+    schedule_expiry(app, config.dnd_until);
+    Ok(())
+}
+
+/// Tells the UI and the tray once `until` passes, unless the period was changed meanwhile.
+pub fn schedule_expiry(app: &tauri::AppHandle, until: i64) {
+    let remaining = until - now_secs();
+    if remaining <= 0 {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_secs(remaining as u64));
+        let current = app.try_state::<ConfigPath>().map(|cp| AppConfig::load(&cp.0).dnd_until);
+        if current == Some(until) {
+            changed(&app, 0);
+        }
+    });
+}
+
+fn changed(app: &tauri::AppHandle, until: i64) {
+    let _ = app.emit("dnd-changed", until);
+    crate::refresh_tray_menu(app);
+}
@@ -0,0 +1,143 @@
+//! Errors returned from commands. They serialize as `{ kind, ...fields, message }` so the frontend
+//! can localize and react by kind (retry a busy database, prompt to unlock, ...) without parsing
+//! message text; `message` is the English fallback.
+
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DbErrorCode {
+    /// Another connection holds a lock; retrying later should succeed.
+    Busy,
+    Corrupt,
+    ReadOnly,
+    Full,
+    CannotOpen,
+    Other,
+}
+
+impl DbErrorCode {
+    pub fn of(e: &rusqlite::Error) -> Self {
+        use rusqlite::ErrorCode;
+        match e {
+            rusqlite::Error::SqliteFailure(err, _) => match err.code {
+                ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => DbErrorCode::Busy,
+                ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase => DbErrorCode::Corrupt,
+                ErrorCode::ReadOnly => DbErrorCode::ReadOnly,
+                ErrorCode::DiskFull => DbErrorCode::Full,
+                ErrorCode::CannotOpen => DbErrorCode::CannotOpen,
+                _ => DbErrorCode::Other,
+            },
+            _ => DbErrorCode::Other,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CutboardError {
+    Db { code: DbErrorCode, message: String },
+    /// The data directory is held by another machine or user (see `writer_lock`).
+    DataPathInUse { owner: String, message: String },
+    NotFound { message: String },
+    /// The app lock is engaged.
+    Locked { message: String },
+    /// A PIN or Windows Hello check did not pass.
+    VerificationFailed { message: String },
+    /// Another process has the OS clipboard open. `holder` names it when known.
+    ClipboardBusy { holder: Option<String>, message: String },
+    PathInvalid { message: String },
+    InvalidInput { message: String },
+    Cancelled { message: String },
+    /// The target drive has less free space than `min_free_space_mb`.
+    LowDiskSpace { free_bytes: u64, message: String },
+    Unsupported { message: String },
+    Other { message: String },
+}
+
+impl CutboardError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        CutboardError::NotFound { message: message.into() }
+    }
+
+    pub fn path_invalid(message: impl Into<String>) -> Self {
+        CutboardError::PathInvalid { message: message.into() }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        CutboardError::InvalidInput { message: message.into() }
+    }
+
+    pub fn verification_failed(message: impl Into<String>) -> Self {
+        CutboardError::VerificationFailed { message: message.into() }
+    }
+
+    pub fn clipboard_busy(holder: Option<String>) -> Self {
+        let message = match &holder {
+            Some(name) => format!("The clipboard is in use by {}", name),
+            None => "The clipboard is in use by another application".into(),
+        };
+        CutboardError::ClipboardBusy { holder, message }
+    }
+
+    pub fn low_disk_space(free_bytes: u64) -> Self {
+        let message = format!("Only {} MB of disk space left", free_bytes / (1024 * 1024));
+        CutboardError::LowDiskSpace { free_bytes, message }
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        CutboardError::Unsupported { message: message.into() }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            CutboardError::Db { message, .. }
+            | CutboardError::DataPathInUse { message, .. }
+            | CutboardError::NotFound { message }
+            | CutboardError::Locked { message }
+            | CutboardError::VerificationFailed { message }
+            | CutboardError::ClipboardBusy { message, .. }
+            | CutboardError::PathInvalid { message }
+            | CutboardError::InvalidInput { message }
+            | CutboardError::Cancelled { message }
+            | CutboardError::LowDiskSpace { message, .. }
+            | CutboardError::Unsupported { message }
+            | CutboardError::Other { message } => message,
+        }
+    }
+}
+
+impl std::fmt::Display for CutboardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for CutboardError {}
+
+impl From<rusqlite::Error> for CutboardError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => CutboardError::not_found(e.to_string()),
+            _ => CutboardError::Db { code: DbErrorCode::of(&e), message: e.to_string() },
+        }
+    }
+}
+
+impl From<String> for CutboardError {
+    fn from(message: String) -> Self {
+        CutboardError::Other { message }
+    }
+}
+
+impl From<&str> for CutboardError {
+    fn from(message: &str) -> Self {
+        CutboardError::Other { message: message.to_string() }
+    }
+}
+
+impl From<std::io::Error> for CutboardError {
+    fn from(e: std::io::Error) -> Self {
+        CutboardError::Other { message: e.to_string() }
+    }
+}
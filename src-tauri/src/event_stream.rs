@@ -0,0 +1,412 @@
+//! Local long-poll endpoint for external tools such as stream overlays and automation scripts:
+//! `GET http://127.0.0.1:<event_stream_port>/events?since=<seq>&timeout=<secs>` answers with the
+//! entry events after `since` as soon as there are any, or an empty list after the timeout.
+//!
+//! Captures are published from the capture path, so re-copies of an existing entry are reported
+//! too. Deletions come from an SQLite update hook, which sees every path that removes entries
+//! (undo, retention, quota, archiving) and only reports them once their transaction commits.
+//!
+//! With `event_stream_token` set, requests must pass it as `?token=` or a bearer token and may
+//! come from web pages (CORS is allowed). Without a token, browser requests are refused, since any
+//! site could otherwise read the user's copies. While the app is locked, requests get `423 Locked`.
+
+use crate::database::Database;
+use rusqlite::hooks::Action;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Events kept for clients that fall behind; older ones are reported as `missed`.
+const BUFFER_CAPACITY: usize = 512;
+const DEFAULT_TIMEOUT_SECS: u64 = 25;
+const MAX_TIMEOUT_SECS: u64 = 120;
+const ACCEPT_POLL: Duration = Duration::from_millis(250);
+const MAX_REQUEST_HEAD: u64 = 8 * 1024;
+/// Clients that keep their `since` get the events still buffered once the app is unlocked.
+const LOCKED_ERROR: &str = "CutBoard is locked";
+
+#[derive(Serialize, Clone)]
+pub struct StreamEvent {
+    pub seq: u64,
+    /// "entry-created" or "entry-deleted".
+    pub kind: &'static str,
+    pub entry_id: i64,
+    pub at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Omitted for sensitive entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PollResponse<'a> {
+    /// Pass back as `since` on the next request.
+    next: u64,
+    /// True when events between `since` and the oldest buffered one were dropped.
+    missed: bool,
+    events: &'a [StreamEvent],
+}
+
+struct Bus {
+    last_seq: u64,
+    events: VecDeque<StreamEvent>,
+}
+
+static BUS: Mutex<Bus> = Mutex::new(Bus { last_seq: 0, events: VecDeque::new() });
+static BUS_CHANGED: Condvar = Condvar::new();
+
+/// Entry ids deleted by the transaction in progress.
+static PENDING_DELETES: Mutex<Vec<i64>> = Mutex::new(Vec::new());
+
+/// Bumped whenever the listener moves or stops, so the one for the older port shuts down.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+/// Read on every request, so changing it needs no restart.
+static TOKEN: Mutex<String> = Mutex::new(String::new());
+/// The port being served and its listener thread.
+static LISTENER: Mutex<Option<(u16, std::thread::JoinHandle<()>)>> = Mutex::new(None);
+
+fn publish(mut events: Vec<StreamEvent>) {
+    if events.is_empty() {
+        return;
+    }
+    let mut bus = BUS.lock().unwrap_or_else(|e| e.into_inner());
+    for mut event in events.drain(..) {
+        bus.last_seq += 1;
+        event.seq = bus.last_seq;
+        bus.events.push_back(event);
+    }
+    while bus.events.len() > BUFFER_CAPACITY {
+        bus.events.pop_front();
+    }
+    BUS_CHANGED.notify_all();
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+/// Publishes a capture; call after the entry is stored, with the database still locked.
+pub fn entry_created(db: &Database, entry_id: i64) {
+    let Ok(entry) = db.get_entry_by_id(entry_id) else {
+        return;
+    };
+    let app_name = db.get_app_by_id(entry.app_id).ok().map(|a| a.name);
+    publish(vec![StreamEvent {
+        seq: 0,
+        kind: "entry-created",
+        entry_id,
+        at: now(),
+        app_name,
+        text: if entry.is_sensitive { None } else { entry.text_content },
+        content_type: Some(entry.content_type),
+        source_url: entry.source_url,
+    }]);
+}
+
+/// Reports deleted entries once the deleting transaction commits.
+pub fn watch_deletions(conn: &Connection) {
+    conn.update_hook(Some(|action: Action, _db: &str, table: &str, rowid: i64| {
+        if action == Action::SQLITE_DELETE && table == "clipboard_entries" {
+            PENDING_DELETES.lock().unwrap_or_else(|e| e.into_inner()).push(rowid);
+        }
+    }));
+    conn.commit_hook(Some(|| {
+        let ids = std::mem::take(&mut *PENDING_DELETES.lock().unwrap_or_else(|e| e.into_inner()));
+        let at = now();
+        publish(
+            ids.into_iter()
+                .map(|entry_id| StreamEvent {
+                    seq: 0,
+                    kind: "entry-deleted",
+                    entry_id,
+                    at: at.clone(),
+                    app_name: None,
+                    content_type: None,
+                    text: None,
+                    source_url: None,
+                })
+                .collect(),
+        );
+        // false lets the commit proceed
+        false
+    }));
+    conn.rollback_hook(Some(|| {
+        PENDING_DELETES.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }));
+}
+
+/// Starts or moves the endpoint; port 0 turns it off. A new token alone applies to the next
+/// request without restarting the listener.
+pub fn configure(port: u32, token: &str) {
+    *TOKEN.lock().unwrap_or_else(|e| e.into_inner()) = token.to_string();
+    let port = u16::try_from(port).unwrap_or_else(|_| {
+        eprintln!("Event stream port {} is out of range", port);
+        0
+    });
+    let mut listener = LISTENER.lock().unwrap_or_else(|e| e.into_inner());
+    if port != 0 && listener.as_ref().is_some_and(|(current, handle)| *current == port && !handle.is_finished()) {
+        return;
+    }
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let previous = listener.take().map(|(_, handle)| handle);
+    if port == 0 {
+        return;
+    }
+    let handle = std::thread::spawn(move || {
+        // The old listener notices the new generation within one poll; wait until it has let go
+        // of its port, which may be this one
+        if let Some(previous) = previous {
+            let _ = previous.join();
+        }
+        if let Err(e) = listen(port, generation) {
+            eprintln!("Event stream on port {} failed: {}", port, e);
+        }
+    });
+    *listener = Some((port, handle));
+}
+
+fn listen(port: u16, generation: u64) -> std::io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    listener.set_nonblocking(true)?;
+    while GENERATION.load(Ordering::SeqCst) == generation {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                std::thread::spawn(move || {
+                    let _ = handle(stream, port);
+                });
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_POLL),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+}
+
+fn read_request(stream: &TcpStream) -> std::io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_HEAD));
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let mut request = Request { method: method.to_string(), path: path.to_string(), query, headers: Vec::new() };
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            request.headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Ok(Some(request))
+}
+
+fn respond(mut stream: &TcpStream, status: &str, cors: bool, body: &str) -> std::io::Result<()> {
+    let cors = if cors { "Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: Authorization\r\n" } else { "" };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nCache-Control: no-store\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        cors,
+        body.len(),
+        body
+    )
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn handle(stream: TcpStream, port: u16) -> std::io::Result<()> {
+    // Accepted sockets inherit the listener's non-blocking mode on Windows
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let Some(request) = read_request(&stream)? else {
+        return respond(&stream, "400 Bad Request", false, &error_body("malformed request"));
+    };
+
+    if !host_ok(&request, port) {
+        return respond(&stream, "403 Forbidden", false, &error_body("unexpected host"));
+    }
+
+    let token = TOKEN.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let cors = !token.is_empty();
+    match authorize(&request, &token) {
+        Access::Allowed => {}
+        Access::Preflight => return respond(&stream, "204 No Content", true, ""),
+        Access::Denied(status, message) => return respond(&stream, status, cors, &error_body(message)),
+    }
+    if request.method != "GET" || request.path != "/events" {
+        return respond(&stream, "404 Not Found", cors, &error_body("use GET /events"));
+    }
+
+    let timeout = request
+        .param("timeout")
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(DEFAULT_TIMEOUT_SECS)
+        .min(MAX_TIMEOUT_SECS);
+    let since = request.param("since").and_then(|s| s.parse::<u64>().ok());
+    // Checked again after the wait, which the lock may engage during. `is_locked` is used rather
+    // than `ensure_unlocked`, since polling is not user activity and must not hold off the idle lock.
+    if crate::lock::is_locked() {
+        return respond(&stream, "423 Locked", cors, &error_body(LOCKED_ERROR));
+    }
+    let body = poll(since, Duration::from_secs(timeout));
+    if crate::lock::is_locked() {
+        return respond(&stream, "423 Locked", cors, &error_body(LOCKED_ERROR));
+    }
+    respond(&stream, "200 OK", cors, &body)
+}
+
+/// A rebound DNS name would carry its own host, letting a remote page reach this port.
+fn host_ok(request: &Request, port: u16) -> bool {
+    request.header("Host").is_some_and(|host| {
+        host == format!("127.0.0.1:{}", port) || host == format!("localhost:{}", port)
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum Access {
+    Allowed,
+    /// A CORS preflight, answered without a token since browsers don't send one.
+    Preflight,
+    Denied(&'static str, &'static str),
+}
+
+/// Without a token only non-browser clients get in; with one, anyone who passes it does.
+fn authorize(request: &Request, token: &str) -> Access {
+    if token.is_empty() {
+        if request.header("Origin").is_some() {
+            return Access::Denied("403 Forbidden", "set event_stream_token to allow browser clients");
+        }
+        return Access::Allowed;
+    }
+    if request.method == "OPTIONS" {
+        return Access::Preflight;
+    }
+    let given = request
+        .param("token")
+        .or_else(|| request.header("Authorization").and_then(|v| v.strip_prefix("Bearer ")));
+    if given.is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes())) {
+        Access::Allowed
+    } else {
+        Access::Denied("401 Unauthorized", "invalid token")
+    }
+}
+
+/// Compares without stopping at the first differing byte, so response times don't reveal how much
+/// of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Waits until there are events after `since` (default: only new ones) or the timeout passes.
+fn poll(since: Option<u64>, timeout: Duration) -> String {
+    let deadline = Instant::now() + timeout;
+    let mut bus = BUS.lock().unwrap_or_else(|e| e.into_inner());
+    let since = since.unwrap_or(bus.last_seq).min(bus.last_seq);
+    while bus.last_seq == since {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        bus = BUS_CHANGED.wait_timeout(bus, remaining).unwrap_or_else(|e| e.into_inner()).0;
+    }
+
+    let oldest = bus.events.front().map_or(bus.last_seq + 1, |e| e.seq);
+    let events: Vec<StreamEvent> = bus.events.iter().filter(|e| e.seq > since).cloned().collect();
+    serde_json::to_string(&PollResponse { next: bus.last_seq, missed: since + 1 < oldest, events: &events })
+        .unwrap_or_else(|e| error_body(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, query: &[(&str, &str)], headers: &[(&str, &str)]) -> Request {
+        let pairs = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Request { method: method.to_string(), path: "/events".to_string(), query: pairs(query), headers: pairs(headers) }
+    }
+
+    #[test]
+    fn only_loopback_hosts_on_this_port() {
+        assert!(host_ok(&request("GET", &[], &[("Host", "127.0.0.1:7000")]), 7000));
+        assert!(host_ok(&request("GET", &[], &[("host", "localhost:7000")]), 7000));
+        assert!(!host_ok(&request("GET", &[], &[("Host", "127.0.0.1:7001")]), 7000));
+        assert!(!host_ok(&request("GET", &[], &[("Host", "evil.example:7000")]), 7000));
+        assert!(!host_ok(&request("GET", &[], &[]), 7000));
+    }
+
+    #[test]
+    fn without_a_token_browsers_are_refused() {
+        assert_eq!(authorize(&request("GET", &[], &[]), ""), Access::Allowed);
+        assert!(matches!(
+            authorize(&request("GET", &[], &[("Origin", "https://example.com")]), ""),
+            Access::Denied("403 Forbidden", _)
+        ));
+        assert!(matches!(
+            authorize(&request("OPTIONS", &[], &[("Origin", "https://example.com")]), ""),
+            Access::Denied("403 Forbidden", _)
+        ));
+    }
+
+    #[test]
+    fn with_a_token_it_must_match() {
+        let origin = ("Origin", "https://example.com");
+        assert_eq!(authorize(&request("GET", &[("token", "s3cret")], &[origin]), "s3cret"), Access::Allowed);
+        assert_eq!(authorize(&request("GET", &[], &[("Authorization", "Bearer s3cret")]), "s3cret"), Access::Allowed);
+        assert_eq!(authorize(&request("OPTIONS", &[], &[origin]), "s3cret"), Access::Preflight);
+        for denied in [
+            request("GET", &[], &[]),
+            request("GET", &[("token", "s3cre")], &[]),
+            request("GET", &[("token", "s3cret!")], &[]),
+            request("GET", &[], &[("Authorization", "Basic s3cret")]),
+        ] {
+            assert_eq!(authorize(&denied, "s3cret"), Access::Denied("401 Unauthorized", "invalid token"));
+        }
+    }
+
+    #[test]
+    fn constant_time_eq_compares_whole_values() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"tok"));
+    }
+}
@@ -0,0 +1,228 @@
+//! Abbreviation expander.
+//!
+//! A low-level keyboard hook keeps a short buffer of recently typed characters. When the
+//! buffer ends with a template abbreviation (e.g. `;addr`), the typed abbreviation is erased
+//! with backspaces and the rendered template is pasted through the clipboard, whose previous
+//! contents are put back afterwards.
+
+use crate::database::Database;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static ABBREVIATIONS: Mutex<Vec<(String, i64)>> = Mutex::new(Vec::new());
+/// Set before the hook thread is spawned, so two quick enables cannot both start one.
+static HOOK_STARTED: AtomicBool = AtomicBool::new(false);
+/// 0 until the hook thread is running.
+static EXPANDER_THREAD_ID: AtomicU32 = AtomicU32::new(0);
+static TYPED: Mutex<String> = Mutex::new(String::new());
+
+const WM_EXPAND: u32 = 0x0402;
+const MAX_TYPED_CHARS: usize = 64;
+
+/// Reloads the abbreviation table from the templates table.
+pub fn reload(db: &Database) {
+    let list = db.get_template_abbreviations().unwrap_or_default();
+    if let Ok(mut abbrs) = ABBREVIATIONS.lock() {
+        *abbrs = list;
+    }
+}
+
+pub fn set_enabled(app: &tauri::AppHandle, enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+    if let Ok(mut typed) = TYPED.lock() {
+        typed.clear();
+    }
+
+    #[cfg(windows)]
+    if enabled && !HOOK_STARTED.swap(true, Ordering::SeqCst) {
+        let app = app.clone();
+        std::thread::spawn(move || run_hook_loop(app));
+    }
+
+    #[cfg(not(windows))]
+    let _ = app;
+}
+
+/// Appends a typed character and returns the matching template when the buffer now ends
+/// with an abbreviation.
+fn push_typed(c: char) -> Option<(i64, usize)> {
+    let mut typed = TYPED.lock().ok()?;
+    typed.push(c);
+    let excess = typed.chars().count().saturating_sub(MAX_TYPED_CHARS);
+    if excess > 0 {
+        let cut = typed.char_indices().nth(excess).map(|(i, _)| i).unwrap_or(0);
+        typed.drain(..cut);
+    }
+
+    let abbrs = ABBREVIATIONS.lock().ok()?;
+    let (abbr, id) = abbrs
+        .iter()
+        .filter(|(abbr, _)| typed.ends_with(abbr.as_str()))
+        .max_by_key(|(abbr, _)| abbr.len())?;
+    typed.clear();
+    Some((*id, abbr.chars().count()))
+}
+
+#[cfg(windows)]
+fn run_hook_loop(app: tauri::AppHandle) {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{HINSTANCE, LPARAM, LRESULT, WPARAM};
+    use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows::Win32::System::Threading::GetCurrentThreadId;
+    use windows::Win32::UI::Input::KeyboardAndMouse::*;
+    use windows::Win32::UI::WindowsAndMessaging::*;
+
+    unsafe extern "system" fn hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        let msg = wparam.0 as u32;
+        if code == HC_ACTION as i32
+            && (msg == WM_KEYDOWN || msg == WM_SYSKEYDOWN)
+            && ENABLED.load(Ordering::Relaxed)
+        {
+            let kb = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+            if !kb.flags.contains(LLKHF_INJECTED) {
+                handle_key(kb.vkCode, kb.scanCode);
+            }
+        }
+        CallNextHookEx(None, code, wparam, lparam)
+    }
+
+    unsafe fn handle_key(vk: u32, scan: u32) {
+        let pressed = |k: VIRTUAL_KEY| GetAsyncKeyState(k.0 as i32) < 0;
+
+        if vk == VK_BACK.0 as u32 {
+            if let Ok(mut typed) = TYPED.lock() {
+                typed.pop();
+            }
+            return;
+        }
+        if matches!(vk, 0x10..=0x12 | 0xA0..=0xA5 | 0x14) {
+            // Bare modifier / caps lock presses don't change the typed text
+            return;
+        }
+        if pressed(VK_CONTROL) || pressed(VK_MENU) || pressed(VK_LWIN) || pressed(VK_RWIN) {
+            if let Ok(mut typed) = TYPED.lock() {
+                typed.clear();
+            }
+            return;
+        }
+
+        let mut state = [0u8; 256];
+        if pressed(VK_SHIFT) {
+            state[VK_SHIFT.0 as usize] = 0x80;
+        }
+        if GetKeyState(VK_CAPITAL.0 as i32) & 1 != 0 {
+            state[VK_CAPITAL.0 as usize] = 0x01;
+        }
+        let mut buf = [0u16; 4];
+        // Flag 0x4: don't alter the keyboard state (keeps dead keys working in the target app)
+        let n = ToUnicode(vk, scan, Some(&state), &mut buf, 0x4);
+        let c = if n == 1 { char::from_u32(buf[0] as u32) } else { None };
+
+        match c {
+            Some(c) if !c.is_control() && !c.is_whitespace() => {
+                if let Some((id, len)) = push_typed(c) {
+                    let tid = EXPANDER_THREAD_ID.load(Ordering::SeqCst);
+                    if tid != 0 {
+                        let _ = PostThreadMessageW(tid, WM_EXPAND, WPARAM(id as usize), LPARAM(len as isize));
+                    }
+                }
+            }
+            _ => {
+                if let Ok(mut typed) = TYPED.lock() {
+                    typed.clear();
+                }
+            }
+        }
+    }
+
+    unsafe {
+        EXPANDER_THREAD_ID.store(GetCurrentThreadId(), Ordering::SeqCst);
+
+        let module = GetModuleHandleW(PCWSTR::null()).ok().map(HINSTANCE::from);
+        let hook = match SetWindowsHookExW(WH_KEYBOARD_LL, Some(hook_proc), module, 0) {
+            Ok(h) => h,
+            Err(e) => {
+                eprintln!("Failed to install expander keyboard hook: {:?}", e);
+                // Lets the next enable try again
+                EXPANDER_THREAD_ID.store(0, Ordering::SeqCst);
+                HOOK_STARTED.store(false, Ordering::SeqCst);
+                return;
+            }
+        };
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            if msg.message == WM_EXPAND {
+                // Injected keystrokes pass through our own hook, so this thread must keep
+                // pumping messages while the expansion runs
+                let template_id = msg.wParam.0 as i64;
+                let erase = msg.lParam.0 as usize;
+                let app = app.clone();
+                std::thread::spawn(move || expand(&app, template_id, erase));
+            }
+        }
+
+        let _ = UnhookWindowsHookEx(hook);
+    }
+}
+
+#[cfg(windows)]
+fn expand(app: &tauri::AppHandle, template_id: i64, erase: usize) {
+    use crate::clipboard;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{VK_BACK, VK_CONTROL, VK_V};
+
+    let text = match crate::commands::render_template_text(app, template_id, &Default::default()) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Expansion of template {} failed: {}", template_id, e);
+            return;
+        }
+    };
+
+    // Every format is put back afterwards, so copied images, files and rich text survive too
+    let previous = clipboard::snapshot_clipboard();
+
+    let mut keys = Vec::with_capacity(erase * 2);
+    for _ in 0..erase {
+        keys.push((VK_BACK, false));
+        keys.push((VK_BACK, true));
+    }
+    send_keys(&keys);
+
+    if clipboard::write_text_to_clipboard(&text).is_err() {
+        return;
+    }
+    send_keys(&[(VK_CONTROL, false), (VK_V, false), (VK_V, true), (VK_CONTROL, true)]);
+
+    // Give the target app time to read the clipboard before restoring what was there
+    if let Some(prev) = previous {
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        let _ = clipboard::restore_clipboard(&prev);
+    }
+}
+
+/// Sends a sequence of (virtual key, key-up) events through SendInput.
+#[cfg(windows)]
+pub fn send_keys(keys: &[(windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY, bool)]) {
+    use windows::Win32::UI::Input::KeyboardAndMouse::*;
+
+    let inputs: Vec<INPUT> = keys
+        .iter()
+        .map(|&(vk, up)| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: vk,
+                    wScan: 0,
+                    dwFlags: if up { KEYEVENTF_KEYUP } else { KEYBD_EVENT_FLAGS(0) },
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        })
+        .collect();
+    unsafe {
+        SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+    }
+}
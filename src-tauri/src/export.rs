@@ -0,0 +1,461 @@
+//! History export. Files are streamed in chunks so large exports stay within a bounded buffer,
+//! report byte-level progress, and can be cancelled between chunks.
+
+use crate::database::ClipboardEntry;
+use crate::error::CutboardError;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::Emitter;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+pub const CANCELLED_ERROR: &str = "Export cancelled";
+
+#[derive(Serialize, Clone)]
+pub struct ExportProgress {
+    pub percent: u32,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+/// Marks an export as running until dropped, so only one runs at a time.
+pub struct ExportGuard;
+
+impl ExportGuard {
+    pub fn acquire() -> Result<Self, CutboardError> {
+        if RUNNING.swap(true, Ordering::SeqCst) {
+            return Err(CutboardError::invalid_input("An export is already running"));
+        }
+        CANCELLED.store(false, Ordering::SeqCst);
+        Ok(ExportGuard)
+    }
+}
+
+impl Drop for ExportGuard {
+    fn drop(&mut self) {
+        RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+pub fn cancel() {
+    if RUNNING.load(Ordering::SeqCst) {
+        CANCELLED.store(true, Ordering::SeqCst);
+    }
+}
+
+struct Progress<'a> {
+    app: &'a tauri::AppHandle,
+    done: u64,
+    total: u64,
+    last_percent: u32,
+}
+
+impl<'a> Progress<'a> {
+    fn new(app: &'a tauri::AppHandle, total: u64) -> Self {
+        Self { app, done: 0, total, last_percent: 0 }
+    }
+
+    fn advance(&mut self, bytes: u64) -> Result<(), CutboardError> {
+        if CANCELLED.load(Ordering::SeqCst) {
+            return Err(CutboardError::Cancelled { message: CANCELLED_ERROR.into() });
+        }
+        self.done += bytes;
+        let percent = self.done.saturating_mul(100).checked_div(self.total).map_or(100, |p| p.min(100) as u32);
+        // Only emit when the visible percentage changes
+        if percent != self.last_percent {
+            self.last_percent = percent;
+            let _ = self.app.emit(
+                "export-progress",
+                ExportProgress { percent, bytes_done: self.done, bytes_total: self.total },
+            );
+        }
+        Ok(())
+    }
+}
+
+fn copy_chunked(reader: &mut impl Read, writer: &mut impl Write, progress: &mut Progress) -> Result<(), CutboardError> {
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        writer.write_all(&buf[..n])?;
+        progress.advance(n as u64)?;
+    }
+}
+
+/// Runs `write` and deletes the partially written file if it fails or is cancelled.
+fn write_or_remove(out_path: &Path, write: impl FnOnce() -> Result<(), CutboardError>) -> Result<(), CutboardError> {
+    let result = write();
+    if result.is_err() {
+        let _ = std::fs::remove_file(out_path);
+    }
+    result
+}
+
+#[derive(Serialize)]
+struct ManifestItem<'a> {
+    file: String,
+    original_file: &'a str,
+    app: &'a str,
+    source_url: Option<&'a str>,
+    created_at: &'a str,
+}
+
+/// Characters Windows does not allow in file and folder names.
+fn sanitize_path_segment(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if matches!(c, '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.').to_string();
+    if cleaned.is_empty() { "_".into() } else { cleaned }
+}
+
+/// Zips the images plus a `manifest.json` describing each one. With `readable_names`, files are
+/// stored as `<app>/<date>_<n>.png` instead of their internal names.
+pub fn export_images(
+    app: &tauri::AppHandle,
+    entries: &[ClipboardEntry],
+    app_names: &std::collections::HashMap<i64, String>,
+    images_dir: &Path,
+    out_path: &Path,
+    readable_names: bool,
+) -> Result<(), CutboardError> {
+    let files: Vec<(&ClipboardEntry, &str, std::path::PathBuf, u64)> = entries
+        .iter()
+        .filter_map(|e| {
+            let name = e.image_path.as_deref()?;
+            let path = images_dir.join(name);
+            let len = std::fs::metadata(&path).ok()?.len();
+            Some((e, name, path, len))
+        })
+        .collect();
+    let mut progress = Progress::new(app, files.iter().map(|(_, _, _, len)| len).sum());
+
+    let mut counters: std::collections::HashMap<(String, String), u32> = std::collections::HashMap::new();
+    let mut manifest = Vec::with_capacity(files.len());
+    for (entry, name, _, _) in &files {
+        let app_name = app_names.get(&entry.app_id).map(String::as_str).unwrap_or("");
+        let file = if readable_names {
+            let folder = sanitize_path_segment(app_name);
+            let date = entry.created_at.get(..10).unwrap_or(&entry.created_at).to_string();
+            let n = counters.entry((folder.clone(), date.clone())).or_insert(0);
+            *n += 1;
+            format!("{}/{}_{}.png", folder, date, n)
+        } else {
+            name.to_string()
+        };
+        manifest.push(ManifestItem {
+            file,
+            original_file: name,
+            app: app_name,
+            source_url: entry.source_url.as_deref(),
+            created_at: &entry.created_at,
+        });
+    }
+
+    write_or_remove(out_path, || {
+        let file = std::fs::File::create(out_path)?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default();
+        for ((_, _, path, _), item) in files.iter().zip(&manifest) {
+            let mut source = std::fs::File::open(path)?;
+            zip.start_file(item.file.as_str(), options).map_err(|e| e.to_string())?;
+            copy_chunked(&mut source, &mut zip, &mut progress)?;
+        }
+        zip.start_file("manifest.json", options).map_err(|e| e.to_string())?;
+        serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|e| e.to_string())?;
+        zip.finish().map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
+
+/// Extras for the Markdown export, for dropping the file into a notes vault.
+#[derive(Default, Clone, Copy)]
+pub struct MarkdownOptions {
+    /// Copy images next to the file and link them from their sections.
+    pub images: bool,
+    /// YAML front-matter for the file plus `key:: value` properties per section, which
+    /// Obsidian (Dataview) and Logseq both read.
+    pub front_matter: bool,
+}
+
+fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', " "))
+}
+
+/// Angle brackets keep spaces and parentheses in the link target working.
+fn markdown_link(label: &str, target: &str) -> String {
+    format!("[{}](<{}>)", label.replace(['[', ']'], ""), target.replace('>', "%3E"))
+}
+
+/// Writes text entries as Markdown sections. With `options.images`, image entries are copied into
+/// a `<name>_assets` folder beside the file and embedded with relative links.
+pub fn export_text(
+    app: &tauri::AppHandle,
+    entries: &[ClipboardEntry],
+    app_name: &str,
+    images_dir: &Path,
+    out_path: &Path,
+    options: MarkdownOptions,
+) -> Result<(), CutboardError> {
+    let stem = out_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let assets_name = format!("{}_assets", stem);
+    let assets_dir = out_path.with_file_name(&assets_name);
+
+    let images: Vec<(&str, std::path::PathBuf, u64)> = if options.images {
+        entries
+            .iter()
+            .filter_map(|e| {
+                let name = e.image_path.as_deref()?;
+                let path = images_dir.join(name);
+                let len = std::fs::metadata(&path).ok()?.len();
+                Some((name, path, len))
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let text_total: u64 = entries.iter().filter_map(|e| e.text_content.as_ref()).map(|t| t.len() as u64).sum();
+    let mut progress = Progress::new(app, text_total + images.iter().map(|(_, _, len)| len).sum::<u64>());
+
+    let result = write_or_remove(out_path, || {
+        if !images.is_empty() {
+            std::fs::create_dir_all(&assets_dir)?;
+            for (name, path, _) in &images {
+                let mut source = std::fs::File::open(path)?;
+                let mut dest = std::fs::File::create(assets_dir.join(name))?;
+                copy_chunked(&mut source, &mut dest, &mut progress)?;
+            }
+        }
+
+        let file = std::fs::File::create(out_path)?;
+        let mut out = std::io::BufWriter::new(file);
+        if options.front_matter {
+            write!(
+                out,
+                "---\napp: {}\nexported: {}\nentries: {}\ntags: [cutboard]\n---\n\n",
+                yaml_string(app_name),
+                yaml_string(&chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+                entries.len()
+            )?;
+        }
+        write!(out, "# CutBoard - {} 文本记录\n\n", app_name)?;
+        for entry in entries {
+            let image = entry
+                .image_path
+                .as_deref()
+                .filter(|name| images.iter().any(|(n, _, _)| n == name));
+            if entry.text_content.is_none() && image.is_none() {
+                continue;
+            }
+            write!(out, "### {}\n\n", entry.created_at)?;
+            if options.front_matter {
+                write!(out, "created:: {}\ntype:: {}\n", entry.created_at, entry.content_type)?;
+                if let Some(url) = &entry.source_url {
+                    writeln!(out, "source:: {}", url)?;
+                }
+                out.write_all(b"\n")?;
+            } else if let Some(url) = &entry.source_url {
+                write!(out, "{}\n\n", markdown_link(url, url))?;
+            }
+            if let Some(text) = &entry.text_content {
+                copy_chunked(&mut text.as_bytes(), &mut out, &mut progress)?;
+                out.write_all(b"\n\n")?;
+            }
+            if let Some(name) = image {
+                write!(out, "!{}\n\n", markdown_link("", &format!("{}/{}", assets_name, name)))?;
+            }
+        }
+        out.flush().map_err(Into::into)
+    });
+    if result.is_err() && !images.is_empty() {
+        let _ = std::fs::remove_dir_all(&assets_dir);
+    }
+    result
+}
+
+/// A named layout for the text export, kept in config as JSON under `export_templates`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportTemplate {
+    pub name: String,
+    /// Written once before the entries; takes `{app}`, `{exported_at}` and `{count}`.
+    #[serde(default)]
+    pub header: String,
+    /// Written for each entry; takes the `ENTRY_FIELDS` placeholders.
+    pub entry: String,
+    /// Written once after the entries, with the same placeholders as `header`.
+    #[serde(default)]
+    pub footer: String,
+}
+
+const PAGE_FIELDS: &[&str] = &["app", "exported_at", "count"];
+const ENTRY_FIELDS: &[&str] = &["created_at", "date", "time", "text", "source_url", "title", "app", "type"];
+
+pub fn parse_templates(stored: &str) -> Vec<ExportTemplate> {
+    if stored.trim().is_empty() {
+        return Vec::new();
+    }
+    serde_json::from_str(stored).unwrap_or_default()
+}
+
+pub fn serialize_templates(templates: &[ExportTemplate]) -> String {
+    serde_json::to_string(templates).unwrap_or_default()
+}
+
+/// Rejects an unnamed template, an empty entry format, or a placeholder the export cannot fill,
+/// which would otherwise be written out literally.
+pub fn validate_template(template: &ExportTemplate) -> Result<(), CutboardError> {
+    if template.name.trim().is_empty() {
+        return Err(CutboardError::invalid_input("Template name is empty"));
+    }
+    if template.entry.trim().is_empty() {
+        return Err(CutboardError::invalid_input("Template entry format is empty"));
+    }
+    let check = |format: &str, fields: &[&str]| {
+        placeholders(format).find(|p| !fields.contains(p)).map_or(Ok(()), |p| {
+            Err(CutboardError::invalid_input(format!("Unknown placeholder {{{}}}, expected one of {}", p, fields.join(", "))))
+        })
+    };
+    check(&template.header, PAGE_FIELDS)?;
+    check(&template.entry, ENTRY_FIELDS)?;
+    check(&template.footer, PAGE_FIELDS)
+}
+
+/// Names inside `{...}` that look like placeholders, so literal braces in code are left alone.
+fn placeholders(format: &str) -> impl Iterator<Item = &str> {
+    format.split('{').skip(1).filter_map(|part| {
+        let name = &part[..part.find('}')?];
+        (!name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_')).then_some(name)
+    })
+}
+
+/// Fills the placeholders `value` knows in one pass, so text containing `{text}` is not expanded
+/// again. Anything else in braces is kept as written.
+fn render(format: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(format.len());
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}').and_then(|end| value(&after[..end]).map(|v| (end, v))) {
+            Some((end, v)) => {
+                out.push_str(&v);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Writes the text entries through a user template instead of the built-in Markdown layout.
+pub fn export_with_template(
+    app: &tauri::AppHandle,
+    entries: &[ClipboardEntry],
+    app_name: &str,
+    out_path: &Path,
+    template: &ExportTemplate,
+) -> Result<(), CutboardError> {
+    let texts: Vec<(&ClipboardEntry, &str)> =
+        entries.iter().filter_map(|e| e.text_content.as_deref().map(|t| (e, t))).collect();
+    let mut progress = Progress::new(app, texts.iter().map(|(_, t)| t.len() as u64).sum());
+    let exported_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let page = |name: &str| match name {
+        "app" => Some(app_name.to_string()),
+        "exported_at" => Some(exported_at.clone()),
+        "count" => Some(texts.len().to_string()),
+        _ => None,
+    };
+
+    write_or_remove(out_path, || {
+        let file = std::fs::File::create(out_path)?;
+        let mut out = std::io::BufWriter::new(file);
+        out.write_all(render(&template.header, page).as_bytes())?;
+        for (entry, text) in &texts {
+            let rendered = render(&template.entry, |name| match name {
+                "created_at" => Some(entry.created_at.clone()),
+                "date" => Some(entry.created_at.get(..10).unwrap_or(&entry.created_at).to_string()),
+                "time" => Some(entry.created_at.get(11..).unwrap_or("").to_string()),
+                "text" => Some(text.to_string()),
+                "source_url" => Some(entry.source_url.clone().unwrap_or_default()),
+                "title" => Some(entry.title.clone().unwrap_or_default()),
+                "app" => Some(app_name.to_string()),
+                "type" => Some(entry.content_type.clone()),
+                _ => None,
+            });
+            out.write_all(rendered.as_bytes())?;
+            progress.advance(text.len() as u64)?;
+        }
+        out.write_all(render(&template.footer, page).as_bytes())?;
+        out.flush().map_err(Into::into)
+    })
+}
+
+/// Appends today's new clips to `<folder>/<YYYY-MM-DD>.md`, copying images into `<folder>/assets`.
+/// Progress is tracked through `daily_note_exported_until` so each clip is written once.
+pub fn append_daily_note(db: &crate::database::Database, config_path: &Path) -> Result<usize, CutboardError> {
+    let mut config = crate::config::AppConfig::load(config_path);
+    let folder = config.daily_note_folder.trim();
+    if folder.is_empty() {
+        return Ok(0);
+    }
+    let folder = std::path::PathBuf::from(folder);
+    let entries = db.get_today_entries_after(&config.daily_note_exported_until)?;
+    let Some(last) = entries.last().map(|e| e.created_at.clone()) else {
+        return Ok(0);
+    };
+    let app_names: std::collections::HashMap<i64, String> =
+        db.get_apps(true)?.into_iter().map(|a| (a.id, a.name)).collect();
+    let images_dir = db.images_dir();
+
+    std::fs::create_dir_all(&folder)?;
+    // Left for a later run, which picks up where this one would have started
+    if let Some(free) = crate::disk::low_space(&folder, config.min_free_space_mb) {
+        return Err(CutboardError::low_disk_space(free));
+    }
+    let note_path = folder.join(format!("{}.md", chrono::Local::now().format("%Y-%m-%d")));
+    let is_new = !note_path.exists();
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&note_path)?;
+    let mut out = std::io::BufWriter::new(file);
+    if is_new {
+        write!(out, "# CutBoard - {}\n\n", chrono::Local::now().format("%Y-%m-%d"))?;
+    }
+    for entry in &entries {
+        let app_name = app_names.get(&entry.app_id).map(String::as_str).unwrap_or("");
+        let time = entry.created_at.get(11..).unwrap_or(&entry.created_at);
+        write!(out, "### {} · {}\n\n", time, app_name)?;
+        if let Some(url) = &entry.source_url {
+            write!(out, "{}\n\n", markdown_link(url, url))?;
+        }
+        if let Some(text) = &entry.text_content {
+            write!(out, "{}\n\n", text)?;
+        }
+        if let Some(name) = &entry.image_path {
+            let assets = folder.join("assets");
+            std::fs::create_dir_all(&assets)?;
+            if std::fs::copy(images_dir.join(name), assets.join(name)).is_ok() {
+                write!(out, "!{}\n\n", markdown_link("", &format!("assets/{}", name)))?;
+            }
+        }
+    }
+    out.flush()?;
+
+    config.daily_note_exported_until = last;
+    config.save(config_path);
+    Ok(entries.len())
+}
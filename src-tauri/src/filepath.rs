@@ -0,0 +1,57 @@
+//! Recognises clips that are a path to an existing local file or folder.
+
+use std::path::PathBuf;
+
+/// Metadata captured for a path clip. `size` is `None` for folders.
+pub struct PathInfo {
+    pub size: Option<i64>,
+    pub extension: Option<String>,
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Parses a single-line drive path (`C:\...`, optionally quoted) or a local `file:///` URI.
+/// UNC and `file://server/` paths are ignored so capture never waits on the network.
+pub fn parse(text: &str) -> Option<PathBuf> {
+    let text = text.trim();
+    if text.is_empty() || text.contains('\n') {
+        return None;
+    }
+    let text = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')).unwrap_or(text);
+    let path = match text.get(..8) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("file:///") => percent_decode(&text[8..])?.replace('/', "\\"),
+        _ => text.to_string(),
+    };
+    let bytes = path.as_bytes();
+    let is_drive_path = bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && matches!(bytes[2], b'\\' | b'/');
+    is_drive_path.then(|| PathBuf::from(path))
+}
+
+/// Returns the path's metadata when `text` names a file or folder that exists.
+pub fn detect(text: &str) -> Option<PathInfo> {
+    let path = parse(text)?;
+    let meta = std::fs::metadata(&path).ok()?;
+    Some(PathInfo {
+        size: meta.is_file().then_some(meta.len() as i64),
+        extension: if meta.is_file() {
+            path.extension().map(|e| e.to_string_lossy().to_lowercase())
+        } else {
+            None
+        },
+    })
+}
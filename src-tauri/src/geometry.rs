@@ -0,0 +1,128 @@
+//! Main window geometry remembered per monitor layout.
+//!
+//! Stored in config as `window_geometry`, a `;`-separated list of `layout:x,y,w,h` entries where
+//! `layout` identifies the set of connected monitors. Docking and undocking a laptop therefore
+//! restores the geometry last used with that set of screens.
+
+use crate::config::AppConfig;
+use tauri::Manager;
+
+const MAX_LAYOUTS: usize = 8;
+/// How much of the window must overlap a monitor for it to count as reachable.
+const MIN_VISIBLE: i32 = 64;
+
+#[derive(Clone, Copy)]
+struct Rect {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+}
+
+fn monitor_rects(app: &tauri::AppHandle) -> Vec<Rect> {
+    app.available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|m| Rect {
+            x: m.position().x,
+            y: m.position().y,
+            w: m.size().width as i32,
+            h: m.size().height as i32,
+        })
+        .collect()
+}
+
+fn layout_key(monitors: &[Rect]) -> String {
+    let mut parts: Vec<String> = monitors
+        .iter()
+        .map(|m| format!("{}x{}+{}+{}", m.w, m.h, m.x, m.y))
+        .collect();
+    parts.sort();
+    parts.join(",")
+}
+
+fn parse_entries(stored: &str) -> Vec<(String, Rect)> {
+    stored
+        .split(';')
+        .filter_map(|entry| {
+            let (layout, rect) = entry.rsplit_once(':')?;
+            let nums: Vec<i32> = rect.split(',').filter_map(|n| n.trim().parse().ok()).collect();
+            if layout.is_empty() || nums.len() != 4 || nums[2] <= 0 || nums[3] <= 0 {
+                return None;
+            }
+            Some((layout.to_string(), Rect { x: nums[0], y: nums[1], w: nums[2], h: nums[3] }))
+        })
+        .collect()
+}
+
+fn is_reachable(rect: Rect, monitors: &[Rect]) -> bool {
+    monitors.iter().any(|m| {
+        let overlap_w = (rect.x + rect.w).min(m.x + m.w) - rect.x.max(m.x);
+        // Only the top strip matters: that is where the user grabs the window to move it
+        let overlap_h = (rect.y + MIN_VISIBLE).min(m.y + m.h) - rect.y.max(m.y);
+        overlap_w >= MIN_VISIBLE && overlap_h > 0
+    })
+}
+
+/// Records the main window's current geometry for the active monitor layout.
+pub fn save(app: &tauri::AppHandle, window: &tauri::WebviewWindow) {
+    if window.is_minimized().unwrap_or(false) || window.is_maximized().unwrap_or(false) {
+        return;
+    }
+    let (Ok(pos), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+        return;
+    };
+    let monitors = monitor_rects(app);
+    if monitors.is_empty() {
+        return;
+    }
+    let rect = Rect { x: pos.x, y: pos.y, w: size.width as i32, h: size.height as i32 };
+    let layout = layout_key(&monitors);
+
+    let Some(config_path) = app.try_state::<crate::ConfigPath>() else {
+        return;
+    };
+    let mut config = AppConfig::load(&config_path.0);
+    let mut entries = parse_entries(&config.window_geometry);
+    entries.retain(|(l, _)| *l != layout);
+    entries.insert(0, (layout, rect));
+    entries.truncate(MAX_LAYOUTS);
+
+    let serialized = entries
+        .iter()
+        .map(|(l, r)| format!("{}:{},{},{},{}", l, r.x, r.y, r.w, r.h))
+        .collect::<Vec<_>>()
+        .join(";");
+    if serialized != config.window_geometry {
+        config.window_geometry = serialized;
+        config.save(&config_path.0);
+    }
+}
+
+/// Applies the geometry saved for the active monitor layout, then makes sure the window is
+/// reachable on some monitor, centering it if it would otherwise open off-screen.
+pub fn restore(app: &tauri::AppHandle, window: &tauri::WebviewWindow) {
+    let monitors = monitor_rects(app);
+    if monitors.is_empty() {
+        return;
+    }
+    if let Some(config_path) = app.try_state::<crate::ConfigPath>() {
+        let config = AppConfig::load(&config_path.0);
+        let layout = layout_key(&monitors);
+        if let Some((_, rect)) = parse_entries(&config.window_geometry).into_iter().find(|(l, _)| *l == layout) {
+            let _ = window.set_size(tauri::PhysicalSize::new(rect.w as u32, rect.h as u32));
+            let _ = window.set_position(tauri::PhysicalPosition::new(rect.x, rect.y));
+        }
+    }
+    ensure_on_screen(window, &monitors);
+}
+
+fn ensure_on_screen(window: &tauri::WebviewWindow, monitors: &[Rect]) {
+    let (Ok(pos), Ok(size)) = (window.outer_position(), window.outer_size()) else {
+        return;
+    };
+    let rect = Rect { x: pos.x, y: pos.y, w: size.width as i32, h: size.height as i32 };
+    if !is_reachable(rect, monitors) {
+        let _ = window.center();
+    }
+}
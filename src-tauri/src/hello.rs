@@ -0,0 +1,55 @@
+//! Windows Hello confirmation (face, fingerprint or device PIN) via `UserConsentVerifier`.
+
+/// Checked once per run; the query round-trips to the credential broker and is slow.
+#[cfg(windows)]
+pub fn is_available() -> bool {
+    use windows::Security::Credentials::UI::{UserConsentVerifier, UserConsentVerifierAvailability};
+
+    static AVAILABLE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        // Query from a worker thread so the caller's COM apartment doesn't matter
+        std::thread::spawn(|| {
+            UserConsentVerifier::CheckAvailabilityAsync()
+                .and_then(|op| op.get())
+                .is_ok_and(|a| a == UserConsentVerifierAvailability::Available)
+        })
+        .join()
+        .unwrap_or(false)
+    })
+}
+
+#[cfg(not(windows))]
+pub fn is_available() -> bool {
+    false
+}
+
+/// Shows the Windows Hello prompt over `window` and blocks until the user answers.
+/// Returns `Ok(false)` when the user cancels or verification fails.
+#[cfg(windows)]
+pub fn verify(window: Option<&tauri::WebviewWindow>, message: &str) -> Result<bool, String> {
+    use windows::core::{factory, HSTRING};
+    use windows::Foundation::IAsyncOperation;
+    use windows::Security::Credentials::UI::{UserConsentVerificationResult, UserConsentVerifier};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::WinRT::IUserConsentVerifierInterop;
+
+    let message = HSTRING::from(message);
+    let hwnd = window.and_then(|w| w.hwnd().ok()).map(|h| HWND(h.0));
+    let op: IAsyncOperation<UserConsentVerificationResult> = match hwnd {
+        // Parenting the prompt keeps it in front of our window instead of behind it
+        Some(hwnd) => unsafe {
+            factory::<UserConsentVerifier, IUserConsentVerifierInterop>()
+                .and_then(|interop| interop.RequestVerificationForWindowAsync(hwnd, &message))
+        },
+        None => UserConsentVerifier::RequestVerificationAsync(&message),
+    }
+    .map_err(|e| e.to_string())?;
+
+    let result = op.get().map_err(|e| e.to_string())?;
+    Ok(result == UserConsentVerificationResult::Verified)
+}
+
+#[cfg(not(windows))]
+pub fn verify(_window: Option<&tauri::WebviewWindow>, _message: &str) -> Result<bool, String> {
+    Err("Windows Hello is not supported on this platform".into())
+}
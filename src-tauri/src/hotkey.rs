@@ -1,10 +1,46 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::OnceLock;
 use tauri::Manager;
 
-static HOTKEY_THREAD_ID: OnceLock<u32> = OnceLock::new();
+/// Id of the thread running the message loop, or 0 while none is.
+static HOTKEY_THREAD_ID: AtomicU32 = AtomicU32::new(0);
+/// Set from `start` until the hotkey thread exits, so `refresh` can tell a dead thread apart
+/// from one that is still starting.
+static THREAD_RUNNING: AtomicBool = AtomicBool::new(false);
+/// Set by `stop` at exit, so the thread ending is expected and `refresh` leaves it down.
+static STOPPING: AtomicBool = AtomicBool::new(false);
+static APP: OnceLock<tauri::AppHandle> = OnceLock::new();
+/// Whether RegisterHotKey currently holds the chord, and whether the low-level hook is in place.
+static REGISTERED: AtomicBool = AtomicBool::new(false);
+static HOOK_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+// Low-level hook mode: the chord is matched in a WH_KEYBOARD_LL hook instead of RegisterHotKey,
+// which works for chords already taken by other apps and lets us swallow the keystroke.
+static HOOK_MODE: AtomicBool = AtomicBool::new(false);
+static HOOK_MODS: AtomicU32 = AtomicU32::new(0);
+static HOOK_VK: AtomicU32 = AtomicU32::new(0);
+static SUPPRESSED_VK: AtomicU32 = AtomicU32::new(0);
+
+// Double-tap gesture ("Ctrl Ctrl"): modifier flag to watch for, or 0 when off. Also needs the
+// hook, so it is installed whenever this is set, whatever the hotkey mode.
+static DOUBLE_TAP_MOD: AtomicU32 = AtomicU32::new(0);
+/// Whether the watched modifier is down with no other key pressed since.
+static TAP_DOWN: AtomicBool = AtomicBool::new(false);
+static TAP_DOWN_AT: AtomicU32 = AtomicU32::new(0);
+/// Hook timestamp of the first tap's release, or 0 while waiting for a first tap.
+static LAST_TAP_UP: AtomicU32 = AtomicU32::new(0);
+/// Max time for each tap, and between the two taps.
+const DOUBLE_TAP_MS: u32 = 400;
 
 const HOTKEY_ID: i32 = 9001;
 const WM_REREGISTER: u32 = 0x0401;
+const WM_SET_MODE: u32 = 0x0402;
+const WM_HOOK_TRIGGER: u32 = 0x0403;
+const WM_REFRESH: u32 = 0x0404;
+const WM_SET_DOUBLE_TAP: u32 = 0x0405;
+
+/// Message id explorer.exe broadcasts when the taskbar is (re)created; 0 until registered.
+static TASKBAR_CREATED: AtomicU32 = AtomicU32::new(0);
 
 #[cfg(debug_assertions)]
 fn hk_log(msg: &str) {
@@ -31,6 +67,125 @@ fn hk_log(msg: &str) {
 #[cfg(not(debug_assertions))]
 fn hk_log(_msg: &str) {}
 
+/// Named keys accepted after the modifiers, besides single letters and digits.
+const NAMED_KEYS: &[(&str, u32)] = &[
+    ("F1", 0x70),
+    ("F2", 0x71),
+    ("F3", 0x72),
+    ("F4", 0x73),
+    ("F5", 0x74),
+    ("F6", 0x75),
+    ("F7", 0x76),
+    ("F8", 0x77),
+    ("F9", 0x78),
+    ("F10", 0x79),
+    ("F11", 0x7A),
+    ("F12", 0x7B),
+    ("F13", 0x7C),
+    ("F14", 0x7D),
+    ("F15", 0x7E),
+    ("F16", 0x7F),
+    ("F17", 0x80),
+    ("F18", 0x81),
+    ("F19", 0x82),
+    ("F20", 0x83),
+    ("F21", 0x84),
+    ("F22", 0x85),
+    ("F23", 0x86),
+    ("F24", 0x87),
+    ("Space", 0x20),
+    ("Enter", 0x0D),
+    ("Tab", 0x09),
+    ("Escape", 0x1B),
+    ("Backspace", 0x08),
+    ("Insert", 0x2D),
+    ("Delete", 0x2E),
+    ("Home", 0x24),
+    ("End", 0x23),
+    ("PageUp", 0x21),
+    ("PageDown", 0x22),
+    ("Up", 0x26),
+    ("Down", 0x28),
+    ("Left", 0x25),
+    ("Right", 0x27),
+    ("PrintScreen", 0x2C),
+    ("Pause", 0x13),
+    ("Numpad0", 0x60),
+    ("Numpad1", 0x61),
+    ("Numpad2", 0x62),
+    ("Numpad3", 0x63),
+    ("Numpad4", 0x64),
+    ("Numpad5", 0x65),
+    ("Numpad6", 0x66),
+    ("Numpad7", 0x67),
+    ("Numpad8", 0x68),
+    ("Numpad9", 0x69),
+    ("NumpadMultiply", 0x6A),
+    ("NumpadAdd", 0x6B),
+    ("NumpadSubtract", 0x6D),
+    ("NumpadDecimal", 0x6E),
+    ("NumpadDivide", 0x6F),
+    // OEM keys, named by their character on a US layout
+    ("`", 0xC0),
+    ("-", 0xBD),
+    ("=", 0xBB),
+    ("[", 0xDB),
+    ("]", 0xDD),
+    ("\\", 0xDC),
+    (";", 0xBA),
+    ("'", 0xDE),
+    (",", 0xBC),
+    (".", 0xBE),
+    ("/", 0xBF),
+];
+
+/// Alternative spellings, mostly the `KeyboardEvent.key` names the settings recorder sees.
+const KEY_ALIASES: &[(&str, &str)] = &[
+    ("Esc", "Escape"),
+    ("Return", "Enter"),
+    ("Ins", "Insert"),
+    ("Del", "Delete"),
+    ("PgUp", "PageUp"),
+    ("PgDn", "PageDown"),
+    ("ArrowUp", "Up"),
+    ("ArrowDown", "Down"),
+    ("ArrowLeft", "Left"),
+    ("ArrowRight", "Right"),
+    ("Backquote", "`"),
+    ("Minus", "-"),
+    ("Equal", "="),
+    ("BracketLeft", "["),
+    ("BracketRight", "]"),
+    ("Backslash", "\\"),
+    ("Semicolon", ";"),
+    ("Quote", "'"),
+    ("Comma", ","),
+    ("Period", "."),
+    ("Slash", "/"),
+];
+
+fn key_vk(key: &str) -> Option<u32> {
+    let key = KEY_ALIASES.iter().find(|(alias, _)| *alias == key).map_or(key, |(_, name)| name);
+    if let Some((_, vk)) = NAMED_KEYS.iter().find(|(name, _)| *name == key) {
+        return Some(*vk);
+    }
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphabetic() => Some(c.to_ascii_uppercase() as u32),
+        (Some(c), None) if c.is_ascii_digit() => Some(c as u32),
+        _ => None,
+    }
+}
+
+/// Every key name `parse_hotkey` accepts after the modifiers, for the settings UI.
+pub fn supported_keys() -> Vec<String> {
+    ('A'..='Z')
+        .chain('0'..='9')
+        .map(String::from)
+        .chain(NAMED_KEYS.iter().map(|(name, _)| name.to_string()))
+        .collect()
+}
+
 pub fn parse_hotkey(s: &str) -> Option<(u32, u32)> {
     let parts: Vec<&str> = s.split('+').collect();
     if parts.is_empty() {
@@ -50,36 +205,7 @@ pub fn parse_hotkey(s: &str) -> Option<(u32, u32)> {
         }
     }
 
-    let vk: u32 = if key_part.len() == 1 {
-        let c = key_part.chars().next()?;
-        if c.is_ascii_alphabetic() {
-            c.to_ascii_uppercase() as u32
-        } else if c.is_ascii_digit() {
-            c as u32
-        } else {
-            return None;
-        }
-    } else {
-        match key_part {
-            "F1" => 0x70,
-            "F2" => 0x71,
-            "F3" => 0x72,
-            "F4" => 0x73,
-            "F5" => 0x74,
-            "F6" => 0x75,
-            "F7" => 0x76,
-            "F8" => 0x77,
-            "F9" => 0x78,
-            "F10" => 0x79,
-            "F11" => 0x7A,
-            "F12" => 0x7B,
-            "Space" => 0x20,
-            "Enter" => 0x0D,
-            "Tab" => 0x09,
-            "Escape" => 0x1B,
-            _ => return None,
-        }
-    };
+    let vk = key_vk(key_part)?;
 
     if mod_flags & 0x000F == 0 {
         return None;
@@ -88,8 +214,29 @@ pub fn parse_hotkey(s: &str) -> Option<(u32, u32)> {
     Some((mod_flags, vk))
 }
 
-pub fn start(app: tauri::AppHandle, shortcut: &str) {
-    hk_log(&format!("start() called with shortcut='{}'", shortcut));
+/// Parses a double-tap gesture such as "Ctrl Ctrl" (or just "Ctrl") into its modifier flag.
+/// Empty means the gesture is off.
+pub fn parse_double_tap(s: &str) -> Option<u32> {
+    let mut flag = 0;
+    for part in s.split_whitespace() {
+        let f = match part {
+            "Alt" => 0x0001,
+            "Ctrl" | "Control" => 0x0002,
+            "Shift" => 0x0004,
+            "Super" | "Meta" | "Win" => 0x0008,
+            _ => return None,
+        };
+        if flag != 0 && f != flag {
+            return None;
+        }
+        flag = f;
+    }
+    Some(flag)
+}
+
+pub fn start(app: tauri::AppHandle, shortcut: &str, mode: &str) {
+    hk_log(&format!("start() called with shortcut='{}', mode='{}'", shortcut, mode));
+    APP.set(app.clone()).ok();
 
     let (mod_flags, vk) = match parse_hotkey(shortcut) {
         Some(v) => {
@@ -104,61 +251,323 @@ pub fn start(app: tauri::AppHandle, shortcut: &str) {
             return;
         }
     };
+    HOOK_MODS.store(mod_flags & 0x000F, Ordering::SeqCst);
+    HOOK_VK.store(vk, Ordering::SeqCst);
+    HOOK_MODE.store(mode == "hook", Ordering::SeqCst);
 
     #[cfg(windows)]
-    std::thread::spawn(move || {
-        hk_log("hotkey thread started");
-        run_hotkey_loop(app, mod_flags, vk);
-        hk_log("hotkey thread EXITED (unexpected)");
-    });
+    {
+        if THREAD_RUNNING.swap(true, Ordering::SeqCst) {
+            hk_log("start: hotkey thread already running");
+            return;
+        }
+        std::thread::spawn(move || {
+            hk_log("hotkey thread started");
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                run_hotkey_loop(app, mod_flags, vk)
+            }));
+            if STOPPING.load(Ordering::SeqCst) && result.is_ok() {
+                hk_log("hotkey thread stopped");
+            } else {
+                hk_log(&format!("hotkey thread EXITED (unexpected, panicked={})", result.is_err()));
+            }
+            HOTKEY_THREAD_ID.store(0, Ordering::SeqCst);
+            THREAD_RUNNING.store(false, Ordering::SeqCst);
+        });
+    }
 
     #[cfg(not(windows))]
     let _ = (app, mod_flags, vk);
 }
 
+#[cfg(windows)]
+unsafe extern "system" fn keyboard_hook_proc(
+    code: i32,
+    wparam: windows::Win32::Foundation::WPARAM,
+    lparam: windows::Win32::Foundation::LPARAM,
+) -> windows::Win32::Foundation::LRESULT {
+    use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+    use windows::Win32::UI::Input::KeyboardAndMouse::*;
+    use windows::Win32::UI::WindowsAndMessaging::*;
+
+    if code == HC_ACTION as i32 {
+        let kb = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        let msg = wparam.0 as u32;
+        let is_down = msg == WM_KEYDOWN || msg == WM_SYSKEYDOWN;
+        let is_up = msg == WM_KEYUP || msg == WM_SYSKEYUP;
+
+        if !kb.flags.contains(LLKHF_INJECTED) && DOUBLE_TAP_MOD.load(Ordering::Relaxed) != 0 {
+            track_double_tap(kb.vkCode, kb.time, is_down, is_up);
+        }
+
+        if !kb.flags.contains(LLKHF_INJECTED) && HOOK_MODE.load(Ordering::Relaxed) {
+            if is_up && SUPPRESSED_VK.load(Ordering::Relaxed) == kb.vkCode {
+                SUPPRESSED_VK.store(0, Ordering::Relaxed);
+                return LRESULT(1);
+            }
+            if is_down && kb.vkCode == HOOK_VK.load(Ordering::Relaxed) {
+                let pressed = |k: VIRTUAL_KEY| GetAsyncKeyState(k.0 as i32) < 0;
+                let mut mods = 0u32;
+                if pressed(VK_MENU) {
+                    mods |= 0x0001;
+                }
+                if pressed(VK_CONTROL) {
+                    mods |= 0x0002;
+                }
+                if pressed(VK_SHIFT) {
+                    mods |= 0x0004;
+                }
+                if pressed(VK_LWIN) || pressed(VK_RWIN) {
+                    mods |= 0x0008;
+                }
+                if mods == HOOK_MODS.load(Ordering::Relaxed) {
+                    let first_press = SUPPRESSED_VK.swap(kb.vkCode, Ordering::Relaxed) != kb.vkCode;
+                    if first_press {
+                        let tid = HOTKEY_THREAD_ID.load(Ordering::SeqCst);
+                        if tid != 0 {
+                            let _ = PostThreadMessageW(tid, WM_HOOK_TRIGGER, WPARAM(0), LPARAM(0));
+                        }
+                    }
+                    return LRESULT(1);
+                }
+            }
+        }
+    }
+    CallNextHookEx(None, code, wparam, lparam)
+}
+
+/// Fires the trigger on two quick press-and-release taps of the watched modifier. Any other key
+/// in between, or a tap held too long, starts over, so chords like Ctrl+C never count.
+#[cfg(windows)]
+unsafe fn track_double_tap(vk: u32, time: u32, is_down: bool, is_up: bool) {
+    use windows::Win32::Foundation::{LPARAM, WPARAM};
+    use windows::Win32::UI::Input::KeyboardAndMouse::*;
+    use windows::Win32::UI::WindowsAndMessaging::PostThreadMessageW;
+
+    let key = VIRTUAL_KEY(vk as u16);
+    let flag = if [VK_MENU, VK_LMENU, VK_RMENU].contains(&key) {
+        0x0001
+    } else if [VK_CONTROL, VK_LCONTROL, VK_RCONTROL].contains(&key) {
+        0x0002
+    } else if [VK_SHIFT, VK_LSHIFT, VK_RSHIFT].contains(&key) {
+        0x0004
+    } else if [VK_LWIN, VK_RWIN].contains(&key) {
+        0x0008
+    } else {
+        0
+    };
+    let reset = || {
+        TAP_DOWN.store(false, Ordering::Relaxed);
+        LAST_TAP_UP.store(0, Ordering::Relaxed);
+    };
+
+    if flag != DOUBLE_TAP_MOD.load(Ordering::Relaxed) {
+        if is_down {
+            reset();
+        }
+        return;
+    }
+    if is_down {
+        // Auto-repeat sends more key-downs while held; only the first one starts a tap
+        if !TAP_DOWN.swap(true, Ordering::Relaxed) {
+            TAP_DOWN_AT.store(time, Ordering::Relaxed);
+        }
+    } else if is_up && TAP_DOWN.swap(false, Ordering::Relaxed) {
+        if time.wrapping_sub(TAP_DOWN_AT.load(Ordering::Relaxed)) > DOUBLE_TAP_MS {
+            LAST_TAP_UP.store(0, Ordering::Relaxed);
+            return;
+        }
+        let last = LAST_TAP_UP.load(Ordering::Relaxed);
+        if last != 0 && time.wrapping_sub(last) <= DOUBLE_TAP_MS {
+            LAST_TAP_UP.store(0, Ordering::Relaxed);
+            let tid = HOTKEY_THREAD_ID.load(Ordering::SeqCst);
+            if tid != 0 {
+                let _ = PostThreadMessageW(tid, WM_HOOK_TRIGGER, WPARAM(0), LPARAM(0));
+            }
+        } else {
+            LAST_TAP_UP.store(time, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The hook is needed for hook mode and for the double-tap gesture.
+#[cfg(windows)]
+fn hook_wanted() -> bool {
+    HOOK_MODE.load(Ordering::SeqCst) || DOUBLE_TAP_MOD.load(Ordering::SeqCst) != 0
+}
+
+#[cfg(windows)]
+unsafe fn install_keyboard_hook() -> Option<windows::Win32::UI::WindowsAndMessaging::HHOOK> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::HINSTANCE;
+    use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows::Win32::UI::WindowsAndMessaging::{SetWindowsHookExW, WH_KEYBOARD_LL};
+
+    let module = GetModuleHandleW(PCWSTR::null()).ok().map(HINSTANCE::from);
+    match SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook_proc), module, 0) {
+        Ok(h) => {
+            hk_log("keyboard hook installed");
+            Some(h)
+        }
+        Err(e) => {
+            hk_log(&format!("SetWindowsHookExW FAILED: {:?}", e));
+            None
+        }
+    }
+}
+
+#[cfg(windows)]
+unsafe fn register_with_retry(mod_flags: u32, vk: u32, attempts: u32, delay_ms: u64) -> bool {
+    use windows::Win32::UI::Input::KeyboardAndMouse::{RegisterHotKey, HOT_KEY_MODIFIERS};
+
+    for attempt in 0..attempts {
+        match RegisterHotKey(None, HOTKEY_ID, HOT_KEY_MODIFIERS(mod_flags), vk) {
+            Ok(_) => {
+                hk_log(&format!(
+                    "RegisterHotKey OK on attempt {} (mod=0x{:04x}, vk=0x{:02x})",
+                    attempt + 1,
+                    mod_flags,
+                    vk
+                ));
+                REGISTERED.store(true, Ordering::SeqCst);
+                return true;
+            }
+            Err(e) => {
+                hk_log(&format!(
+                    "RegisterHotKey attempt {} FAILED: {:?}",
+                    attempt + 1,
+                    e
+                ));
+                std::thread::sleep(std::time::Duration::from_millis(delay_ms));
+            }
+        }
+    }
+    REGISTERED.store(false, Ordering::SeqCst);
+    false
+}
+
+/// Handles the notifications after which the hotkey may need registering again: resume from
+/// sleep, unlock and RDP reconnects. A restarted explorer.exe also drops the tray icon.
+#[cfg(windows)]
+unsafe extern "system" fn notification_wnd_proc(
+    hwnd: windows::Win32::Foundation::HWND,
+    msg: u32,
+    wparam: windows::Win32::Foundation::WPARAM,
+    lparam: windows::Win32::Foundation::LPARAM,
+) -> windows::Win32::Foundation::LRESULT {
+    use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+    use windows::Win32::UI::WindowsAndMessaging::{DefWindowProcW, PostThreadMessageW};
+
+    const WM_WTSSESSION_CHANGE: u32 = 0x02B1;
+    const WM_POWERBROADCAST: u32 = 0x0218;
+    const WTS_CONSOLE_CONNECT: usize = 0x1;
+    const WTS_REMOTE_CONNECT: usize = 0x3;
+    const WTS_SESSION_UNLOCK: usize = 0x8;
+    const PBT_APMRESUMEAUTOMATIC: usize = 0x12;
+
+    let refresh = || {
+        let _ = PostThreadMessageW(HOTKEY_THREAD_ID.load(Ordering::SeqCst), WM_REFRESH, WPARAM(0), LPARAM(0));
+    };
+    match msg {
+        WM_WTSSESSION_CHANGE => {
+            if matches!(wparam.0, WTS_CONSOLE_CONNECT | WTS_REMOTE_CONNECT | WTS_SESSION_UNLOCK) {
+                hk_log(&format!("session change 0x{:x}, refreshing", wparam.0));
+                refresh();
+            }
+            LRESULT(0)
+        }
+        WM_POWERBROADCAST => {
+            if wparam.0 == PBT_APMRESUMEAUTOMATIC {
+                hk_log("resumed from sleep, refreshing");
+                refresh();
+            }
+            LRESULT(1)
+        }
+        _ if msg != 0 && msg == TASKBAR_CREATED.load(Ordering::SeqCst) => {
+            hk_log("TaskbarCreated received, restoring tray icon");
+            if let Some(app) = APP.get() {
+                crate::recreate_tray(app);
+            }
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// A hidden top-level window, since message-only windows miss broadcasts such as
+/// WM_POWERBROADCAST and TaskbarCreated.
+#[cfg(windows)]
+unsafe fn create_notification_window() -> Option<windows::Win32::Foundation::HWND> {
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::System::RemoteDesktop::{WTSRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION};
+    use windows::Win32::UI::WindowsAndMessaging::*;
+
+    let class_name = w!("CutBoardHotkeyListener");
+    let wc = WNDCLASSEXW {
+        cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+        lpfnWndProc: Some(notification_wnd_proc),
+        lpszClassName: class_name,
+        ..std::mem::zeroed()
+    };
+    // Fails harmlessly when a restarted hotkey thread registers the class again
+    RegisterClassExW(&wc);
+
+    let hwnd = CreateWindowExW(
+        WINDOW_EX_STYLE::default(),
+        class_name,
+        PCWSTR::null(),
+        WS_OVERLAPPED,
+        0,
+        0,
+        0,
+        0,
+        None,
+        None,
+        None,
+        None,
+    )
+    .ok()?;
+
+    let taskbar_created = RegisterWindowMessageW(w!("TaskbarCreated"));
+    TASKBAR_CREATED.store(taskbar_created, Ordering::SeqCst);
+    // Lets the broadcast through UIPI when CutBoard runs elevated
+    let _ = ChangeWindowMessageFilterEx(hwnd, taskbar_created, MSGFLT_ALLOW, None);
+    let _ = WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION);
+    Some(hwnd)
+}
+
 #[cfg(windows)]
 fn run_hotkey_loop(app: tauri::AppHandle, initial_mod: u32, initial_vk: u32) {
     use windows::Win32::System::Threading::GetCurrentThreadId;
-    use windows::Win32::UI::Input::KeyboardAndMouse::{
-        RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS,
+    use windows::Win32::UI::Input::KeyboardAndMouse::UnregisterHotKey;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        DestroyWindow, DispatchMessageW, GetMessageW, UnhookWindowsHookEx, MSG, WM_HOTKEY,
     };
-    use windows::Win32::UI::WindowsAndMessaging::{GetMessageW, MSG, WM_HOTKEY};
 
     std::thread::sleep(std::time::Duration::from_millis(500));
 
     unsafe {
         let tid = GetCurrentThreadId();
-        HOTKEY_THREAD_ID.set(tid).ok();
+        HOTKEY_THREAD_ID.store(tid, Ordering::SeqCst);
         hk_log(&format!("thread id={}, starting registration", tid));
 
-        let mut registered = false;
-        for attempt in 0..20 {
-            match RegisterHotKey(
-                None,
-                HOTKEY_ID,
-                HOT_KEY_MODIFIERS(initial_mod),
-                initial_vk,
-            ) {
-                Ok(_) => {
-                    hk_log(&format!("RegisterHotKey OK on attempt {}", attempt + 1));
-                    registered = true;
-                    break;
-                }
-                Err(e) => {
-                    hk_log(&format!(
-                        "RegisterHotKey attempt {} FAILED: {:?}",
-                        attempt + 1,
-                        e
-                    ));
-                    std::thread::sleep(std::time::Duration::from_millis(500));
-                }
-            }
+        let notification_window = create_notification_window();
+        if notification_window.is_none() {
+            hk_log("notification window FAILED, no refresh after sleep or unlock");
         }
 
-        if !registered {
+        let mut cur_mod = initial_mod;
+        let mut cur_vk = initial_vk;
+        let mut hook = None;
+
+        if hook_wanted() {
+            hook = install_keyboard_hook();
+        }
+        if !HOOK_MODE.load(Ordering::SeqCst) && !register_with_retry(cur_mod, cur_vk, 20, 500) {
             hk_log("GIVING UP after 20 attempts");
         }
 
+        HOOK_INSTALLED.store(hook.is_some(), Ordering::SeqCst);
         hk_log("entering GetMessageW loop");
         let mut msg = MSG::default();
         loop {
@@ -169,40 +578,93 @@ fn run_hotkey_loop(app: tauri::AppHandle, initial_mod: u32, initial_vk: u32) {
             if msg.message == WM_HOTKEY {
                 hk_log("WM_HOTKEY received, toggling window");
                 toggle_window(&app);
+            } else if msg.message == WM_HOOK_TRIGGER {
+                hk_log("hook chord or double tap matched, toggling window");
+                // Injected keys pass through our own hook, which this thread has to keep serving,
+                // so the work runs elsewhere
+                let app = app.clone();
+                std::thread::spawn(move || {
+                    // Unassigned VK 0xE8: keeps a lone Alt/Win release from opening the menu bar or
+                    // Start menu, and makes us the last input source so SetForegroundWindow succeeds
+                    use windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY;
+                    crate::expander::send_keys(&[(VIRTUAL_KEY(0xE8), false), (VIRTUAL_KEY(0xE8), true)]);
+                    toggle_window(&app);
+                });
             } else if msg.message == WM_REREGISTER {
                 hk_log("WM_REREGISTER received");
-                let _ = UnregisterHotKey(None, HOTKEY_ID);
-                let new_mod = msg.wParam.0 as u32;
-                let new_vk = msg.lParam.0 as u32;
-                for attempt in 0..5 {
-                    if RegisterHotKey(
-                        None,
-                        HOTKEY_ID,
-                        HOT_KEY_MODIFIERS(new_mod),
-                        new_vk,
-                    )
-                    .is_ok()
-                    {
-                        hk_log(&format!(
-                            "re-register OK on attempt {} (mod=0x{:04x}, vk=0x{:02x})",
-                            attempt + 1,
-                            new_mod,
-                            new_vk
-                        ));
-                        break;
+                cur_mod = msg.wParam.0 as u32;
+                cur_vk = msg.lParam.0 as u32;
+                HOOK_MODS.store(cur_mod & 0x000F, Ordering::SeqCst);
+                HOOK_VK.store(cur_vk, Ordering::SeqCst);
+                if !HOOK_MODE.load(Ordering::SeqCst) {
+                    let _ = UnregisterHotKey(None, HOTKEY_ID);
+                    if !register_with_retry(cur_mod, cur_vk, 5, 300) {
+                        hk_log("re-register FAILED");
+                    }
+                }
+            } else if msg.message == WM_SET_MODE {
+                let use_hook = msg.wParam.0 != 0;
+                hk_log(&format!("WM_SET_MODE received (hook={})", use_hook));
+                if use_hook {
+                    let _ = UnregisterHotKey(None, HOTKEY_ID);
+                    REGISTERED.store(false, Ordering::SeqCst);
+                    if hook.is_none() {
+                        hook = install_keyboard_hook();
                     }
-                    hk_log(&format!("re-register attempt {} failed", attempt + 1));
-                    std::thread::sleep(std::time::Duration::from_millis(300));
+                } else {
+                    if !hook_wanted() {
+                        if let Some(h) = hook.take() {
+                            let _ = UnhookWindowsHookEx(h);
+                        }
+                    }
+                    let _ = UnregisterHotKey(None, HOTKEY_ID);
+                    register_with_retry(cur_mod, cur_vk, 5, 300);
+                }
+            } else if msg.message == WM_SET_DOUBLE_TAP {
+                hk_log(&format!("WM_SET_DOUBLE_TAP received (mod=0x{:x})", msg.wParam.0));
+                if hook_wanted() {
+                    if hook.is_none() {
+                        hook = install_keyboard_hook();
+                    }
+                } else if let Some(h) = hook.take() {
+                    let _ = UnhookWindowsHookEx(h);
                 }
+            } else if msg.message == WM_REFRESH {
+                hk_log("WM_REFRESH received");
+                // Windows silently drops low-level hooks that time out, e.g. across sleep
+                if let Some(h) = hook.take() {
+                    let _ = UnhookWindowsHookEx(h);
+                }
+                if hook_wanted() {
+                    hook = install_keyboard_hook();
+                }
+                if !HOOK_MODE.load(Ordering::SeqCst) {
+                    let _ = UnregisterHotKey(None, HOTKEY_ID);
+                    if !register_with_retry(cur_mod, cur_vk, 5, 300) {
+                        hk_log("refresh: re-register FAILED");
+                    }
+                }
+            } else if !msg.hwnd.0.is_null() {
+                DispatchMessageW(&msg);
             } else {
                 hk_log(&format!("other msg: 0x{:04x}", msg.message));
             }
+            HOOK_INSTALLED.store(hook.is_some(), Ordering::SeqCst);
+        }
+        if let Some(h) = hook {
+            let _ = UnhookWindowsHookEx(h);
+        }
+        let _ = UnregisterHotKey(None, HOTKEY_ID);
+        HOOK_INSTALLED.store(false, Ordering::SeqCst);
+        REGISTERED.store(false, Ordering::SeqCst);
+        if let Some(hwnd) = notification_window {
+            let _ = DestroyWindow(hwnd);
         }
         hk_log("GetMessageW loop ended");
     }
 }
 
-fn toggle_window(app: &tauri::AppHandle) {
+pub fn toggle_window(app: &tauri::AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         #[cfg(windows)]
         {
@@ -228,8 +690,12 @@ fn toggle_window(app: &tauri::AppHandle) {
                 ));
 
                 if visible && is_foreground {
+                    crate::geometry::save(app, &window);
                     let _ = window.hide();
                 } else {
+                    if !visible {
+                        position_window(app, &window);
+                    }
                     let _ = window.show();
                     let _ = ShowWindow(hwnd, SW_RESTORE);
                     let _ = SetForegroundWindow(hwnd);
@@ -242,8 +708,12 @@ fn toggle_window(app: &tauri::AppHandle) {
             let visible = window.is_visible().unwrap_or(false);
             let focused = window.is_focused().unwrap_or(false);
             if visible && focused {
+                crate::geometry::save(app, &window);
                 let _ = window.hide();
             } else {
+                if !visible {
+                    position_window(app, &window);
+                }
                 let _ = window.show();
                 let _ = window.unminimize();
                 let _ = window.set_focus();
@@ -254,14 +724,110 @@ fn toggle_window(app: &tauri::AppHandle) {
     }
 }
 
+/// Moves the window according to the `window_position` setting before it is shown.
+fn position_window(app: &tauri::AppHandle, window: &tauri::WebviewWindow) {
+    let mode = match app.try_state::<crate::ConfigPath>() {
+        Some(cp) => crate::config::AppConfig::load(&cp.0).window_position,
+        None => return,
+    };
+    crate::geometry::restore(app, window);
+    match mode.as_str() {
+        "center" => {
+            let _ = window.center();
+        }
+        #[cfg(windows)]
+        "cursor" | "caret" => {
+            let anchor = if mode == "caret" {
+                caret_position().or_else(cursor_position)
+            } else {
+                cursor_position()
+            };
+            if let Some((x, y)) = anchor {
+                place_near(window, x, y);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(windows)]
+fn cursor_position() -> Option<(i32, i32)> {
+    use windows::Win32::Foundation::POINT;
+    use windows::Win32::UI::WindowsAndMessaging::GetCursorPos;
+
+    let mut pt = POINT::default();
+    unsafe { GetCursorPos(&mut pt).ok()? };
+    Some((pt.x, pt.y))
+}
+
+/// Screen position just below the text caret of the foreground window, if it has one.
+#[cfg(windows)]
+fn caret_position() -> Option<(i32, i32)> {
+    use windows::Win32::Foundation::POINT;
+    use windows::Win32::Graphics::Gdi::ClientToScreen;
+    use windows::Win32::UI::WindowsAndMessaging::*;
+
+    unsafe {
+        let fg = GetForegroundWindow();
+        if fg.0.is_null() {
+            return None;
+        }
+        let tid = GetWindowThreadProcessId(fg, None);
+        let mut info: GUITHREADINFO = std::mem::zeroed();
+        info.cbSize = std::mem::size_of::<GUITHREADINFO>() as u32;
+        GetGUIThreadInfo(tid, &mut info).ok()?;
+        if info.hwndCaret.0.is_null() {
+            return None;
+        }
+        let mut pt = POINT {
+            x: info.rcCaret.left,
+            y: info.rcCaret.bottom,
+        };
+        if !ClientToScreen(info.hwndCaret, &mut pt).as_bool() {
+            return None;
+        }
+        Some((pt.x, pt.y))
+    }
+}
+
+/// Places the window's top-left corner at (x, y), clamped to the work area of that monitor.
+#[cfg(windows)]
+fn place_near(window: &tauri::WebviewWindow, x: i32, y: i32) {
+    use windows::Win32::Foundation::POINT;
+    use windows::Win32::Graphics::Gdi::{
+        GetMonitorInfoW, MonitorFromPoint, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+    };
+
+    let size = match window.outer_size() {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    let (w, h) = (size.width as i32, size.height as i32);
+
+    let work = unsafe {
+        let monitor = MonitorFromPoint(POINT { x, y }, MONITOR_DEFAULTTONEAREST);
+        let mut info: MONITORINFO = std::mem::zeroed();
+        info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            return;
+        }
+        info.rcWork
+    };
+
+    let nx = x.min(work.right - w).max(work.left);
+    // Flip above the anchor when there is no room below it
+    let ny = if y + h > work.bottom { y - h } else { y };
+    let ny = ny.min(work.bottom - h).max(work.top);
+    let _ = window.set_position(tauri::PhysicalPosition::new(nx, ny));
+}
+
 pub fn update(new_shortcut: &str) {
     hk_log(&format!("update() called with '{}'", new_shortcut));
 
     #[cfg(windows)]
     {
-        if let (Some(&tid), Some((mod_flags, vk))) =
-            (HOTKEY_THREAD_ID.get(), parse_hotkey(new_shortcut))
-        {
+        let tid = HOTKEY_THREAD_ID.load(Ordering::SeqCst);
+        if let (true, Some((mod_flags, vk))) = (tid != 0, parse_hotkey(new_shortcut)) {
             use windows::Win32::Foundation::LPARAM;
             use windows::Win32::Foundation::WPARAM;
             use windows::Win32::UI::WindowsAndMessaging::PostThreadMessageW;
@@ -285,3 +851,116 @@ pub fn update(new_shortcut: &str) {
     #[cfg(not(windows))]
     let _ = new_shortcut;
 }
+
+/// Switches between RegisterHotKey ("register") and the low-level keyboard hook ("hook").
+pub fn set_mode(mode: &str) {
+    let use_hook = mode == "hook";
+    HOOK_MODE.store(use_hook, Ordering::SeqCst);
+    hk_log(&format!("set_mode() called with '{}'", mode));
+
+    #[cfg(windows)]
+    {
+        let tid = HOTKEY_THREAD_ID.load(Ordering::SeqCst);
+        if tid != 0 {
+            use windows::Win32::Foundation::{LPARAM, WPARAM};
+            use windows::Win32::UI::WindowsAndMessaging::PostThreadMessageW;
+            unsafe {
+                let _ = PostThreadMessageW(tid, WM_SET_MODE, WPARAM(use_hook as usize), LPARAM(0));
+            }
+        }
+    }
+}
+
+/// Sets the double-tap gesture from the `double_tap_key` setting; empty or unknown turns it off.
+pub fn set_double_tap(gesture: &str) {
+    let flag = parse_double_tap(gesture).unwrap_or_else(|| {
+        hk_log(&format!("set_double_tap: unknown gesture '{}'", gesture));
+        0
+    });
+    DOUBLE_TAP_MOD.store(flag, Ordering::SeqCst);
+    TAP_DOWN.store(false, Ordering::SeqCst);
+    LAST_TAP_UP.store(0, Ordering::SeqCst);
+
+    #[cfg(windows)]
+    {
+        let tid = HOTKEY_THREAD_ID.load(Ordering::SeqCst);
+        if tid != 0 {
+            use windows::Win32::Foundation::{LPARAM, WPARAM};
+            use windows::Win32::UI::WindowsAndMessaging::PostThreadMessageW;
+            unsafe {
+                let _ = PostThreadMessageW(tid, WM_SET_DOUBLE_TAP, WPARAM(flag as usize), LPARAM(0));
+            }
+        }
+    }
+}
+
+/// Ends the hotkey thread at exit, releasing the hotkey and the keyboard hook.
+pub fn stop() {
+    STOPPING.store(true, Ordering::SeqCst);
+
+    #[cfg(windows)]
+    {
+        let tid = HOTKEY_THREAD_ID.load(Ordering::SeqCst);
+        if tid != 0 {
+            use windows::Win32::Foundation::{LPARAM, WPARAM};
+            use windows::Win32::UI::WindowsAndMessaging::{PostThreadMessageW, WM_QUIT};
+            unsafe {
+                let _ = PostThreadMessageW(tid, WM_QUIT, WPARAM(0), LPARAM(0));
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct HotkeyStatus {
+    pub thread_running: bool,
+    pub hook_mode: bool,
+    pub registered: bool,
+    pub hook_installed: bool,
+    pub double_tap: bool,
+}
+
+/// What the hotkey thread has in place right now, for the self-test.
+pub fn status() -> HotkeyStatus {
+    HotkeyStatus {
+        thread_running: THREAD_RUNNING.load(Ordering::SeqCst),
+        hook_mode: HOOK_MODE.load(Ordering::SeqCst),
+        registered: REGISTERED.load(Ordering::SeqCst),
+        hook_installed: HOOK_INSTALLED.load(Ordering::SeqCst),
+        double_tap: DOUBLE_TAP_MOD.load(Ordering::SeqCst) != 0,
+    }
+}
+
+/// Registers the hotkey (or reinstalls the hook) again, and starts a new hotkey thread if the
+/// old one has died. Called when the window is toggled some other way, which suggests the
+/// hotkey is not working.
+pub fn refresh() {
+    hk_log("refresh() called");
+    if STOPPING.load(Ordering::SeqCst) {
+        return;
+    }
+
+    #[cfg(windows)]
+    {
+        use windows::Win32::Foundation::{LPARAM, WPARAM};
+        use windows::Win32::UI::WindowsAndMessaging::PostThreadMessageW;
+
+        let tid = HOTKEY_THREAD_ID.load(Ordering::SeqCst);
+        if tid != 0 && unsafe { PostThreadMessageW(tid, WM_REFRESH, WPARAM(0), LPARAM(0)) }.is_ok() {
+            return;
+        }
+        if THREAD_RUNNING.load(Ordering::SeqCst) {
+            // Still starting up; it registers on its own
+            return;
+        }
+        let Some(app) = APP.get() else {
+            return;
+        };
+        if let Some(cp) = app.try_state::<crate::ConfigPath>() {
+            let config = crate::config::AppConfig::load(&cp.0);
+            let shortcut = if config.shortcut.is_empty() { "Alt+Q" } else { config.shortcut.as_str() };
+            hk_log("refresh: hotkey thread is gone, restarting it");
+            start(app.clone(), shortcut, &config.hotkey_mode);
+        }
+    }
+}
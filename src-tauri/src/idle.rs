@@ -0,0 +1,64 @@
+//! Hides the main window and locks the app once the user has been away for the configured time.
+
+use crate::config::AppConfig;
+use crate::ConfigPath;
+use std::sync::atomic::Ordering;
+use tauri::Manager;
+
+const POLL_SECS: u64 = 5;
+
+/// Seconds since the last keyboard or mouse input anywhere on the system.
+#[cfg(windows)]
+fn idle_secs() -> u64 {
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+        return crate::lock::app_idle_secs();
+    }
+    // Both are 32-bit tick counts, so wrapping subtraction survives the 49-day rollover
+    let now = unsafe { GetTickCount() };
+    (now.wrapping_sub(info.dwTime) / 1000) as u64
+}
+
+#[cfg(not(windows))]
+fn idle_secs() -> u64 {
+    crate::lock::app_idle_secs()
+}
+
+pub fn start(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(POLL_SECS));
+        let Some(config_path) = app.try_state::<ConfigPath>() else {
+            continue;
+        };
+        let cfg = AppConfig::load(&config_path.0);
+        if cfg.auto_hide_after_seconds == 0 && cfg.auto_lock_after_minutes == 0 {
+            continue;
+        }
+        let idle = idle_secs();
+
+        if cfg.auto_hide_after_seconds > 0
+            && idle >= cfg.auto_hide_after_seconds as u64
+            && !crate::WINDOW_PINNED.load(Ordering::SeqCst)
+        {
+            if let Some(window) = app.get_webview_window("main") {
+                if window.is_visible().unwrap_or(false) {
+                    crate::geometry::save(&app, &window);
+                    let _ = window.hide();
+                }
+            }
+        }
+
+        if cfg.auto_lock_after_minutes > 0
+            && idle >= cfg.auto_lock_after_minutes as u64 * 60
+            && !crate::lock::is_locked()
+        {
+            crate::lock::set_locked(&app, true);
+        }
+    });
+}
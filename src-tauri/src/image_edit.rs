@@ -0,0 +1,117 @@
+//! Edits applied to a stored image before it is shared: rotating, cropping and blacking out
+//! rectangles, by hand or wherever OCR finds sensitive text. The result is saved as a new entry
+//! and the original is left untouched.
+
+use crate::error::CutboardError;
+use serde::Deserialize;
+
+/// One step of an edit. Steps run in order, and each one's coordinates refer to the image as the
+/// previous steps left it.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ImageOp {
+    /// Clockwise, in multiples of 90 degrees; negative values turn counterclockwise.
+    Rotate { degrees: i32 },
+    Crop { x: u32, y: u32, width: u32, height: u32 },
+    /// Paints the rectangle solid black.
+    Redact { x: u32, y: u32, width: u32, height: u32 },
+}
+
+/// Runs `ops` over a PNG and returns the edited PNG.
+pub fn apply(png: &[u8], ops: &[ImageOp]) -> Result<Vec<u8>, CutboardError> {
+    if ops.is_empty() {
+        return Err(CutboardError::invalid_input("No edits given"));
+    }
+    let _t = crate::metrics::timer("image.edit");
+    let mut img = decode(png)?;
+
+    for op in ops {
+        match *op {
+            ImageOp::Rotate { degrees } => {
+                img = match degrees.rem_euclid(360) {
+                    0 => img,
+                    90 => image::imageops::rotate90(&img),
+                    180 => image::imageops::rotate180(&img),
+                    270 => image::imageops::rotate270(&img),
+                    _ => return Err(CutboardError::invalid_input("Rotation must be a multiple of 90 degrees")),
+                };
+            }
+            ImageOp::Crop { x, y, width, height } => {
+                let (width, height) = clamp(img.dimensions(), x, y, width, height)
+                    .ok_or_else(|| CutboardError::invalid_input("Crop area is outside the image"))?;
+                img = image::imageops::crop_imm(&img, x, y, width, height).to_image();
+            }
+            ImageOp::Redact { x, y, width, height } => {
+                black_out(&mut img, x, y, width, height);
+            }
+        }
+    }
+    encode(&img)
+}
+
+/// Reads the text in a PNG and blacks out every word that sensitive detection flags, e.g. card
+/// numbers, keys or what follows a password label. Returns `None` when nothing was found.
+pub fn auto_redact(png: &[u8], ui_language: &str, regions: &str) -> Result<Option<Vec<u8>>, CutboardError> {
+    let mut img = decode(png)?;
+    let mut redacted = false;
+    for words in crate::ocr::recognize(&img)? {
+        // Matches can span words, e.g. a card number in groups of four, so whole lines are scanned
+        let mut line = String::new();
+        let mut ranges = Vec::with_capacity(words.len());
+        for word in &words {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            ranges.push((line.len(), line.len() + word.text.len()));
+            line.push_str(&word.text);
+        }
+        let spans = crate::sensitive::sensitive_spans(&line, ui_language, regions);
+        for (word, (start, end)) in words.iter().zip(ranges) {
+            if spans.iter().any(|&(s, e)| start < e && s < end) {
+                // A little margin so antialiased edges of the glyphs don't show
+                black_out(
+                    &mut img,
+                    word.x.saturating_sub(REDACT_MARGIN),
+                    word.y.saturating_sub(REDACT_MARGIN),
+                    word.width + 2 * REDACT_MARGIN,
+                    word.height + 2 * REDACT_MARGIN,
+                );
+                redacted = true;
+            }
+        }
+    }
+    if redacted { encode(&img).map(Some) } else { Ok(None) }
+}
+
+const REDACT_MARGIN: u32 = 2;
+
+fn decode(png: &[u8]) -> Result<image::RgbaImage, CutboardError> {
+    let img = image::load_from_memory_with_format(png, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    Ok(img.to_rgba8())
+}
+
+fn encode(img: &image::RgbaImage) -> Result<Vec<u8>, CutboardError> {
+    let mut buf = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(buf)
+}
+
+/// A rectangle partly off the image still covers the part that is on it.
+fn black_out(img: &mut image::RgbaImage, x: u32, y: u32, width: u32, height: u32) {
+    if let Some((width, height)) = clamp(img.dimensions(), x, y, width, height) {
+        for py in y..y + height {
+            for px in x..x + width {
+                img.put_pixel(px, py, image::Rgba([0, 0, 0, 255]));
+            }
+        }
+    }
+}
+
+/// The part of the rectangle inside an image of `size`, or `None` when nothing of it is.
+fn clamp((img_width, img_height): (u32, u32), x: u32, y: u32, width: u32, height: u32) -> Option<(u32, u32)> {
+    let width = width.min(img_width.saturating_sub(x));
+    let height = height.min(img_height.saturating_sub(y));
+    (width > 0 && height > 0).then_some((width, height))
+}
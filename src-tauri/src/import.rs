@@ -0,0 +1,143 @@
+//! Files added to the history by hand: dropped on a window, which hands over their contents, or
+//! sent with Explorer's "Send to" menu, which starts CutBoard with the paths (see
+//! `clipboard::forward_files`). They go into the same app bucket as entries added by hand.
+
+use crate::config::AppConfig;
+use crate::database::{AppInfo, Database, EntryOrigin, TextCapture, MANUAL_APP_EXE};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "webp"];
+/// Same limit as captured text.
+const MAX_TEXT_BYTES: u64 = 5 * 1024 * 1024;
+
+#[derive(Serialize, Default)]
+pub struct ImportSummary {
+    pub entry_ids: Vec<i64>,
+    /// Paths left out: folders, unreadable or binary files, text over the size limit, and images
+    /// while the drive is low on space.
+    pub skipped: Vec<String>,
+}
+
+/// A file dropped on the webview, which sees its name and contents but not its path.
+#[derive(Deserialize)]
+pub struct DroppedFile {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Also returns the app bucket when this import created it.
+pub fn import_files(
+    db: &Database,
+    config: &AppConfig,
+    paths: &[String],
+) -> rusqlite::Result<(ImportSummary, Option<AppInfo>)> {
+    import_each(db, paths, String::as_str, |app_id, path| import_file(db, config, app_id, Path::new(path)))
+}
+
+/// Like `import_files`, for files dropped on the window.
+pub fn import_dropped(
+    db: &Database,
+    config: &AppConfig,
+    files: &[DroppedFile],
+) -> rusqlite::Result<(ImportSummary, Option<AppInfo>)> {
+    import_each(db, files, |file| &file.name, |app_id, file| {
+        import_content(db, config, app_id, Path::new(&file.name), &file.data)
+    })
+}
+
+fn import_each<T>(
+    db: &Database,
+    items: &[T],
+    name: impl Fn(&T) -> &str,
+    mut import: impl FnMut(i64, &T) -> rusqlite::Result<Option<i64>>,
+) -> rusqlite::Result<(ImportSummary, Option<AppInfo>)> {
+    let (app_id, app_created) = db.get_or_create_app("CutBoard", MANUAL_APP_EXE, None, None)?;
+    let mut summary = ImportSummary::default();
+    for item in items {
+        match import(app_id, item)? {
+            Some(id) => {
+                crate::event_stream::entry_created(db, id);
+                summary.entry_ids.push(id);
+            }
+            None => summary.skipped.push(name(item).to_string()),
+        }
+    }
+    let new_app = if app_created { db.get_app_by_id(app_id).ok() } else { None };
+    Ok((summary, new_app))
+}
+
+/// The new or refreshed entry's id, or `None` when the file cannot be imported.
+fn import_file(db: &Database, config: &AppConfig, app_id: i64, path: &Path) -> rusqlite::Result<Option<i64>> {
+    let Ok(meta) = std::fs::metadata(path) else {
+        return Ok(None);
+    };
+    // Checked before reading so a large text file is not loaded only to be skipped
+    if !meta.is_file() || (meta.len() > MAX_TEXT_BYTES && !is_image(path)) {
+        return Ok(None);
+    }
+    let Ok(bytes) = std::fs::read(path) else {
+        return Ok(None);
+    };
+    import_content(db, config, app_id, path, &bytes)
+}
+
+fn is_image(name: &Path) -> bool {
+    let ext = name.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    IMAGE_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// Imports a file's contents as an image or text entry, going by the extension of `name`.
+fn import_content(
+    db: &Database,
+    config: &AppConfig,
+    app_id: i64,
+    name: &Path,
+    bytes: &[u8],
+) -> rusqlite::Result<Option<i64>> {
+    if is_image(name) {
+        let images_dir = db.images_dir();
+        if crate::disk::low_space(&images_dir, config.min_free_space_mb).is_some() {
+            return Ok(None);
+        }
+        // Stored as PNG like captured images, whatever the source format
+        let Some(png) = to_png(bytes) else {
+            return Ok(None);
+        };
+        let hash = crate::clipboard::compute_content_hash(&png);
+        let filename = crate::clipboard::image_filename(&hash);
+        let image_path = images_dir.join(&filename);
+        if !image_path.exists() && crate::clipboard::write_image_file(&image_path, &png).is_err() {
+            return Ok(None);
+        }
+        let (id, _) = db.upsert_image_entry(app_id, &filename, &hash, None, &EntryOrigin::default())?;
+        return Ok(Some(id));
+    }
+
+    if bytes.len() as u64 > MAX_TEXT_BYTES {
+        return Ok(None);
+    }
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return Ok(None);
+    };
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    if text.trim().is_empty() || text.contains('\0') {
+        return Ok(None);
+    }
+    let hash = crate::clipboard::compute_content_hash(text.as_bytes());
+    let sensitive_kind = crate::sensitive::sensitive_kind(text, &config.language, &config.sensitive_regions);
+    db.upsert_text_entry_with_html(
+        app_id,
+        &TextCapture { text, hash: &hash, sensitive_kind, ..Default::default() },
+        None,
+        &EntryOrigin::default(),
+    )
+        .map(Some)
+}
+
+fn to_png(bytes: &[u8]) -> Option<Vec<u8>> {
+    let img = image::load_from_memory(bytes).ok()?;
+    let mut buf = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png).ok()?;
+    Some(buf)
+}
@@ -0,0 +1,282 @@
+//! Derived values for short numeric clips: the result of an arithmetic expression ("1299*1.21")
+//! or common conversions of a quantity ("5 mi").
+
+use serde::Serialize;
+
+const MAX_INPUT_CHARS: usize = 200;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Insight {
+    /// "result" or "conversion".
+    pub kind: String,
+    /// The text offered for copying.
+    pub value: String,
+}
+
+pub fn for_text(text: &str) -> Vec<Insight> {
+    let text = text.trim();
+    if text.is_empty() || text.chars().count() > MAX_INPUT_CHARS || text.contains('\n') {
+        return Vec::new();
+    }
+    if let Some(result) = evaluate(text) {
+        return vec![Insight { kind: "result".into(), value: format_number(result) }];
+    }
+    convert(text)
+        .into_iter()
+        .map(|value| Insight { kind: "conversion".into(), value })
+        .collect()
+}
+
+/// Up to six decimals, without trailing zeros.
+fn format_number(value: f64) -> String {
+    let s = format!("{:.6}", value);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".into() } else { s.into() }
+}
+
+// ---- Arithmetic ----
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Num(f64),
+    Op(char),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' | '\t' => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut num = String::new();
+                while let Some(&d) = chars.peek() {
+                    if d.is_ascii_digit() || d == '.' {
+                        num.push(d);
+                        chars.next();
+                    } else if d == '_' {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Num(num.parse().ok()?));
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                tokens.push(Token::Op(c));
+                chars.next();
+            }
+            '×' | 'x' => {
+                tokens.push(Token::Op('*'));
+                chars.next();
+            }
+            '÷' => {
+                tokens.push(Token::Op('/'));
+                chars.next();
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                chars.next();
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                chars.next();
+            }
+            _ => return None,
+        }
+    }
+    Some(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.peek();
+        self.pos += 1;
+        t
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek() {
+            self.next();
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Some(value)
+    }
+
+    // term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek() {
+            self.next();
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Some(value)
+    }
+
+    // unary := '-' unary | power
+    fn unary(&mut self) -> Option<f64> {
+        if self.peek() == Some(Token::Op('-')) {
+            self.next();
+            return Some(-self.unary()?);
+        }
+        self.power()
+    }
+
+    // power := atom ('^' unary)?, right-associative
+    fn power(&mut self) -> Option<f64> {
+        let base = self.atom()?;
+        if self.peek() == Some(Token::Op('^')) {
+            self.next();
+            return Some(base.powf(self.unary()?));
+        }
+        Some(base)
+    }
+
+    fn atom(&mut self) -> Option<f64> {
+        match self.next()? {
+            Token::Num(n) => Some(n),
+            Token::Open => {
+                let value = self.expr()?;
+                (self.next()? == Token::Close).then_some(value)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Evaluates text made only of numbers, operators and parentheses. A lone number, or a leading
+/// minus on one, is not an expression.
+fn evaluate(text: &str) -> Option<f64> {
+    let tokens = tokenize(text)?;
+    let has_operator = tokens
+        .iter()
+        .enumerate()
+        .any(|(i, t)| matches!(t, Token::Op(_)) && !(i == 0 && *t == Token::Op('-')));
+    if !has_operator {
+        return None;
+    }
+    let mut parser = Parser { tokens, pos: 0 };
+    let value = parser.expr()?;
+    (parser.pos == parser.tokens.len() && value.is_finite()).then_some(value)
+}
+
+// ---- Units ----
+
+/// (symbol, aliases, units per base unit of the dimension, conversions to show)
+type Unit = (&'static str, &'static [&'static str], f64, &'static [&'static str]);
+
+const UNITS: &[&[Unit]] = &[
+    // Length, in metres
+    &[
+        ("mm", &["mm", "millimeter", "millimeters", "millimetre", "millimetres"], 0.001, &["in"]),
+        ("cm", &["cm", "centimeter", "centimeters", "centimetre", "centimetres"], 0.01, &["in"]),
+        ("m", &["m", "meter", "meters", "metre", "metres"], 1.0, &["ft", "yd"]),
+        ("km", &["km", "kilometer", "kilometers", "kilometre", "kilometres"], 1000.0, &["mi"]),
+        ("in", &["in", "inch", "inches", "\""], 0.0254, &["cm", "mm"]),
+        ("ft", &["ft", "foot", "feet", "'"], 0.3048, &["m", "cm"]),
+        ("yd", &["yd", "yard", "yards"], 0.9144, &["m"]),
+        ("mi", &["mi", "mile", "miles"], 1609.344, &["km", "m"]),
+    ],
+    // Mass, in kilograms
+    &[
+        ("g", &["g", "gram", "grams"], 0.001, &["oz"]),
+        ("kg", &["kg", "kilogram", "kilograms", "kilo", "kilos"], 1.0, &["lb"]),
+        ("lb", &["lb", "lbs", "pound", "pounds"], 0.45359237, &["kg", "g"]),
+        ("oz", &["oz", "ounce", "ounces"], 0.028349523125, &["g"]),
+    ],
+    // Volume, in litres
+    &[
+        ("ml", &["ml", "milliliter", "milliliters", "millilitre", "millilitres"], 0.001, &["fl oz"]),
+        ("l", &["l", "liter", "liters", "litre", "litres"], 1.0, &["gal"]),
+        ("gal", &["gal", "gallon", "gallons"], 3.785411784, &["l"]),
+        ("fl oz", &["fl oz", "floz"], 0.0295735295625, &["ml"]),
+    ],
+    // Speed, in metres per second
+    &[
+        ("km/h", &["km/h", "kmh", "kph"], 1.0 / 3.6, &["mph"]),
+        ("mph", &["mph"], 0.44704, &["km/h"]),
+        ("m/s", &["m/s"], 1.0, &["km/h"]),
+        ("kn", &["kn", "knot", "knots"], 0.514444, &["km/h"]),
+    ],
+    // Data, in bytes
+    &[
+        ("KB", &["kb", "kib"], 1024.0, &["B"]),
+        ("MB", &["mb", "mib"], 1048576.0, &["KB", "GB"]),
+        ("GB", &["gb", "gib"], 1073741824.0, &["MB", "TB"]),
+        ("TB", &["tb", "tib"], 1099511627776.0, &["GB"]),
+        ("B", &["b", "byte", "bytes"], 1.0, &["KB"]),
+    ],
+];
+
+/// Splits "5 mi", "5mi" or "-3.5 °C" into the number and the unit text.
+fn split_quantity(text: &str) -> Option<(f64, String)> {
+    let end = text
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && c == '-')))
+        .map(|(i, _)| i)?;
+    let value: f64 = text[..end].parse().ok()?;
+    let unit = text[end..].trim().to_lowercase();
+    (!unit.is_empty()).then_some((value, unit))
+}
+
+fn convert_temperature(value: f64, unit: &str) -> Option<Vec<String>> {
+    let unit = unit.trim_start_matches('°');
+    let celsius = match unit {
+        "c" | "celsius" => value,
+        "f" | "fahrenheit" => (value - 32.0) * 5.0 / 9.0,
+        "k" | "kelvin" => value - 273.15,
+        _ => return None,
+    };
+    let mut out = Vec::new();
+    if unit.starts_with('c') || unit.starts_with('k') {
+        out.push(format!("{} °F", format_number(celsius * 9.0 / 5.0 + 32.0)));
+    }
+    if !unit.starts_with('c') {
+        out.push(format!("{} °C", format_number(celsius)));
+    }
+    if unit.starts_with('c') {
+        out.push(format!("{} K", format_number(celsius + 273.15)));
+    }
+    Some(out)
+}
+
+fn convert(text: &str) -> Vec<String> {
+    let Some((value, unit)) = split_quantity(text) else {
+        return Vec::new();
+    };
+    if let Some(out) = convert_temperature(value, &unit) {
+        return out;
+    }
+    for dimension in UNITS {
+        let Some((_, _, factor, targets)) = dimension.iter().find(|(_, aliases, _, _)| aliases.contains(&unit.as_str())) else {
+            continue;
+        };
+        return targets
+            .iter()
+            .filter_map(|target| dimension.iter().find(|(symbol, _, _, _)| symbol == target))
+            .map(|(symbol, _, target_factor, _)| format!("{} {}", format_number(value * factor / target_factor), symbol))
+            .collect();
+    }
+    Vec::new()
+}
@@ -1,10 +1,49 @@
+mod ai;
+mod archive;
+mod cache;
 mod clipboard;
+mod code;
 mod commands;
 mod config;
+mod context;
 mod database;
+mod disk;
+mod dnd;
+mod error;
+mod event_stream;
+mod expander;
+mod export;
+mod filepath;
+mod geometry;
+mod hello;
 pub mod hotkey;
+mod idle;
+mod image_edit;
+mod import;
+mod insights;
+mod lock;
+mod markdown;
+mod mcp;
+mod metrics;
+mod migrations;
+mod ocr;
+mod onboarding;
+mod print;
+mod query;
+mod quota;
+mod rules;
+mod screenshot;
+mod selftest;
 mod sensitive;
+mod shutdown;
+mod sound;
+mod structured;
+mod table;
+mod template;
+mod title;
+mod toast;
 mod window_tracker;
+mod writer_lock;
 
 use chrono::Timelike;
 use config::AppConfig;
@@ -13,10 +52,14 @@ use tauri::{Emitter, Manager};
 
 pub struct DbState(pub Arc<Mutex<database::Database>>);
 pub struct ConfigPath(pub std::path::PathBuf);
-struct TrayState(#[allow(dead_code)] tauri::tray::TrayIcon);
+/// Replaced by `recreate_tray`; the old icon goes away once dropped.
+struct TrayState(Mutex<tauri::tray::TrayIcon>);
 
 static LOG_DIR: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
 
+/// Pinned windows stay visible when they lose focus.
+pub(crate) static WINDOW_PINNED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
 fn setup_crash_handler(log_dir: &std::path::Path) {
     std::fs::create_dir_all(log_dir).ok();
     LOG_DIR.set(log_dir.to_path_buf()).ok();
@@ -87,11 +130,57 @@ fn check_last_crash(log_dir: &std::path::Path) -> Option<String> {
     Some(filename)
 }
 
+fn revert_schema(target: Option<String>) -> i32 {
+    let Some(target) = target.and_then(|t| t.parse::<u32>().ok()) else {
+        eprintln!("Usage: cutboard --revert-schema <version>");
+        return 2;
+    };
+    // Same config lookup as the MCP server
+    let app_data_dir = std::env::var_os("APPDATA")
+        .map(|dir| std::path::PathBuf::from(dir).join("cutboard"))
+        .unwrap_or_default();
+    let config = AppConfig::load(&AppConfig::config_file_path(&app_data_dir));
+    let data_dir = std::path::Path::new(&config.data_path);
+    // Refuses while CutBoard is running on the same data
+    let _writer = match writer_lock::acquire(data_dir) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let result = rusqlite::Connection::open(data_dir.join("cutboard.db"))
+        .map_err(|e| e.to_string())
+        .and_then(|conn| migrations::revert_to(&conn, target));
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Cannot revert the schema: {}", e);
+            1
+        }
+    }
+}
+
 pub fn run() {
+    // Launched by an AI assistant as its MCP server; runs beside the app without a window
+    if std::env::args().skip(1).any(|arg| arg == "--mcp") {
+        std::process::exit(mcp::serve_stdio());
+    }
+    // Takes the history back to an older schema version, e.g. before installing an older build
+    let mut args = std::env::args().skip_while(|arg| arg != "--revert-schema");
+    if args.next().is_some() {
+        std::process::exit(revert_schema(args.next()));
+    }
+
+    // Paths passed by the "Send to" shortcut, which starts CutBoard with `--import`
+    let files: Vec<String> = std::env::args().skip_while(|arg| arg != "--import").skip(1).collect();
+
     #[cfg(windows)]
     {
         if !acquire_single_instance_lock() {
-            activate_existing_instance();
+            if files.is_empty() || !clipboard::forward_files(&files) {
+                activate_existing_instance();
+            }
             return;
         }
     }
@@ -114,9 +203,10 @@ pub fn run() {
         }
     }
 
-    tauri::Builder::default()
+    let app = tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
-        .setup(|app| {
+        .setup(move |app| {
+            metrics::init();
             let default_data_dir = app.path().app_data_dir()?;
             std::fs::create_dir_all(&default_data_dir)?;
 
@@ -141,6 +231,8 @@ pub fn run() {
             if need_save {
                 cfg.save(&config_path);
             }
+            commands::apply_cache_budgets(&cfg);
+            event_stream::configure(cfg.event_stream_port, &cfg.event_stream_token);
 
             let log_dir = data_dir.join("log");
             setup_crash_handler(&log_dir);
@@ -157,30 +249,82 @@ pub fn run() {
                 });
             }
 
-            let db = database::Database::new(&data_dir)?;
+            let db = match database::Database::new(&data_dir) {
+                // Another machine or user owns a shared data_path: run from the local default
+                // for this session rather than risk corrupting theirs
+                Err(error::CutboardError::DataPathInUse { owner, .. }) if data_dir != default_data_dir => {
+                    eprintln!("Data path '{}' is in use, falling back to default", data_dir.display());
+                    let payload = serde_json::json!({
+                        "path": data_dir.to_string_lossy(),
+                        "owner": owner,
+                    });
+                    let app_handle = app.handle().clone();
+                    std::thread::spawn(move || {
+                        std::thread::sleep(std::time::Duration::from_secs(2));
+                        let _ = app_handle.emit("db-busy", payload);
+                    });
+                    database::Database::new(&default_data_dir)?
+                }
+                result => result?,
+            };
             let db_state = Arc::new(Mutex::new(db));
             app.manage(DbState(db_state.clone()));
             app.manage(ConfigPath(config_path.clone()));
+            lock::start(app.handle());
+            idle::start(app.handle().clone());
 
             let sc_str = if cfg.shortcut.is_empty() {
                 "Alt+Q".to_string()
             } else {
                 cfg.shortcut.clone()
             };
-            hotkey::start(app.handle().clone(), &sc_str);
+            hotkey::set_double_tap(&cfg.double_tap_key);
+            hotkey::start(app.handle().clone(), &sc_str, &cfg.hotkey_mode);
 
             clipboard::start_monitor(app.handle().clone());
-            let tray = setup_tray(app, &cfg.language)?;
-            app.manage(TrayState(tray));
+            if let Ok(db) = db_state.lock() {
+                expander::reload(&db);
+                sensitive::reload_allowlist(&db);
+                context::reload(&db);
+            }
+            expander::set_enabled(app.handle(), cfg.expander_enabled);
+            if let Some(window) = app.get_webview_window("main") {
+                geometry::restore(app.handle(), &window);
+                if cfg.always_on_top {
+                    let _ = window.set_always_on_top(true);
+                }
+                if cfg.exclude_from_capture {
+                    commands::apply_capture_protection(&window, true);
+                }
+            }
+
+            let tray = setup_tray(app.handle(), &cfg.language)?;
+            app.manage(TrayState(Mutex::new(tray)));
+            dnd::schedule_expiry(app.handle(), cfg.dnd_until);
+            start_expiry_timer(app.handle().clone(), config_path.clone(), db_state.clone());
             start_midnight_timer(app.handle().clone(), config_path, db_state);
 
+            if !files.is_empty() {
+                let _ = commands::import_files(app.handle().clone(), files);
+            }
+
             Ok(())
         })
-        .on_window_event(|window, event| {
-            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::CloseRequested { api, .. } if window.label() == commands::MINI_WINDOW_LABEL => {
+                if let Some(mini) = window.app_handle().get_webview_window(commands::MINI_WINDOW_LABEL) {
+                    commands::save_mini_window_size(window.app_handle(), &mini);
+                }
+                let _ = window.hide();
+                api.prevent_close();
+            }
+            tauri::WindowEvent::CloseRequested { api, .. } => {
                 let app = window.app_handle();
                 let config_path = app.state::<ConfigPath>();
                 let cfg = AppConfig::load(&config_path.0);
+                if let Some(main) = app.get_webview_window("main") {
+                    geometry::save(app, &main);
+                }
                 if cfg.close_to_tray {
                     let _ = window.hide();
                     api.prevent_close();
@@ -188,12 +332,56 @@ pub fn run() {
                     app.exit(0);
                 }
             }
+            tauri::WindowEvent::Focused(true) => lock::touch(),
+            tauri::WindowEvent::Focused(false) => {
+                if window.label() != "main" || WINDOW_PINNED.load(std::sync::atomic::Ordering::SeqCst) {
+                    return;
+                }
+                let app = window.app_handle();
+                let config_path = app.state::<ConfigPath>();
+                let cfg = AppConfig::load(&config_path.0);
+                // Focus moving to one of our own windows (e.g. a file dialog) is not a dismissal
+                let focus_is_ours = window_tracker::get_foreground_app().is_some_and(|a| a.is_self);
+                if cfg.hide_on_blur && !focus_is_ours {
+                    if let Some(main) = app.get_webview_window("main") {
+                        geometry::save(app, &main);
+                    }
+                    let _ = window.hide();
+                }
+            }
+            _ => {}
         })
         .invoke_handler(tauri::generate_handler![
             commands::get_apps,
             commands::get_entries,
+            commands::get_entry_ids,
+            commands::get_entries_by_ids,
             commands::delete_entry,
             commands::copy_entry_to_clipboard,
+            commands::copy_entries_as_list,
+            commands::print_entry,
+            commands::copy_image_as_data_uri,
+            commands::copy_image_as_markdown,
+            commands::edit_image,
+            commands::get_entry_table,
+            commands::format_entry,
+            commands::copy_entry_as_table,
+            commands::auto_redact_image,
+            commands::import_files,
+            commands::import_dropped_files,
+            commands::copy_text_without_recording,
+            commands::get_current_clipboard,
+            commands::capture_now,
+            commands::toggle_main_window,
+            commands::get_supported_keys,
+            commands::clear_system_clipboard,
+            commands::add_manual_entry,
+            commands::get_entry_by_id,
+            commands::link_entries,
+            commands::unlink_entries,
+            commands::get_entry_tags,
+            commands::get_entry_events,
+            commands::set_entry_tags,
             commands::clear_app_entries,
             commands::delete_entries_by_domain,
             commands::clear_database,
@@ -202,23 +390,103 @@ pub fn run() {
             commands::get_entry_counts,
             commands::get_settings,
             commands::save_settings,
+            commands::export_settings,
+            commands::import_settings,
             commands::open_data_dir,
             commands::export_entries,
+            commands::cancel_export,
+            commands::get_export_templates,
+            commands::save_export_template,
+            commands::delete_export_template,
+            commands::open_source_url,
+            commands::open_path_entry,
+            commands::reveal_path_entry,
+            commands::get_entry_insights,
+            commands::find_in_entry,
+            commands::get_entry_stats,
+            commands::run_self_test,
+            commands::get_app_settings,
+            commands::set_app_settings,
+            commands::get_audit_log,
+            commands::get_perf_metrics,
+            commands::set_ai_api_key,
+            commands::transform_entry_text,
+            commands::ai_transform,
             commands::get_language_strings,
             commands::get_available_languages,
             commands::get_source_urls,
             commands::get_storage_stats,
+            commands::get_archives,
+            commands::attach_archive,
+            commands::detach_archive,
+            commands::search_archives,
+            commands::is_first_run,
+            commands::set_onboarding_done,
+            commands::generate_sample_data,
+            commands::detect_conflicting_clipboard_managers,
             commands::resolve_favicon,
             commands::toggle_entry_favorite,
+            commands::toggle_entry_locked,
             commands::toggle_app_favorite,
+            commands::update_app_metadata,
+            commands::reorder_apps,
+            commands::hide_app,
+            commands::toggle_domain_favorite,
             commands::toggle_sensitive,
+            commands::get_sensitive_allowlist,
+            commands::allow_sensitive_entry,
+            commands::rescan_sensitive,
+            commands::add_sensitive_allow_pattern,
+            commands::remove_sensitive_allowlist_item,
+            commands::get_context_patterns,
+            commands::add_context_pattern,
+            commands::remove_context_pattern,
             commands::get_favorite_entries,
             commands::get_favorite_counts,
+            commands::get_collections,
+            commands::create_collection,
+            commands::rename_collection,
+            commands::delete_collection,
+            commands::reorder_collections,
+            commands::add_entry_to_collection,
+            commands::remove_entry_from_collection,
+            commands::reorder_collection_entries,
+            commands::get_entry_collections,
+            commands::get_collection_entries,
+            commands::get_templates,
+            commands::save_template,
+            commands::delete_template,
+            commands::render_template,
+            commands::get_rules,
+            commands::save_rule,
+            commands::delete_rule,
+            commands::reorder_rules,
+            commands::get_recent_entries,
+            commands::get_dnd_until,
+            commands::set_dnd_until,
+            commands::get_screenshots,
+            commands::reveal_sensitive_entry,
+            commands::get_lock_state,
+            commands::set_app_lock,
+            commands::lock_app,
+            commands::unlock_app,
+            commands::unlock_with_windows_hello,
+            commands::toggle_mini_window,
+            commands::set_always_on_top,
+            commands::set_window_pinned,
+            commands::get_window_state,
             commands::dismiss_crash,
             commands::get_crash_log_content,
         ])
-        .run(tauri::generate_context!())
-        .unwrap_or_else(|e| eprintln!("Application error: {}", e));
+        .build(tauri::generate_context!());
+    match app {
+        Ok(app) => app.run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                shutdown::run(app);
+            }
+        }),
+        Err(e) => eprintln!("Application error: {}", e),
+    }
 }
 
 fn start_midnight_timer(
@@ -249,18 +517,134 @@ fn start_midnight_timer(
     });
 }
 
-fn setup_tray(app: &mut tauri::App, lang: &str) -> Result<tauri::tray::TrayIcon, Box<dyn std::error::Error>> {
-    use tauri::menu::{Menu, MenuItem};
-    use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+/// Purges entries whose rule-assigned or per-type retention has expired, moves entries due for
+/// archiving and enforces the history size cap, checking every few minutes.
+fn start_expiry_timer(app_handle: tauri::AppHandle, config_path: std::path::PathBuf, db_state: Arc<Mutex<database::Database>>) {
+    std::thread::spawn(move || loop {
+        let config = AppConfig::load(&config_path);
+        let purged = match db_state.lock() {
+            Ok(db) => {
+                if let Err(e) = export::append_daily_note(&db, &config_path) {
+                    eprintln!("Daily note export failed: {}", e);
+                }
+                let mut removed = match db.purge_expired_entries() {
+                    Ok((removed, image_files)) => {
+                        let images_dir = db.images_dir();
+                        for f in image_files {
+                            std::fs::remove_file(images_dir.join(&f)).ok();
+                        }
+                        removed
+                    }
+                    Err(_) => 0,
+                };
+                let type_retention = [
+                    ("text", config.text_retention_days),
+                    ("image", config.image_retention_days),
+                    ("screenshot", config.screenshot_retention_days),
+                ];
+                for (kind, days) in type_retention {
+                    if days == 0 {
+                        continue;
+                    }
+                    if let Ok((count, image_files)) = db.purge_type_older_than(kind, days) {
+                        let images_dir = db.images_dir();
+                        for f in image_files {
+                            std::fs::remove_file(images_dir.join(&f)).ok();
+                        }
+                        removed += count;
+                    }
+                }
+                if config.audit_log_retention_days > 0 {
+                    let _ = db.purge_audit_log(config.audit_log_retention_days);
+                }
+                let archived = archive::apply_policy(&db, &config.archive_policy).unwrap_or_else(|e| {
+                    eprintln!("Archiving failed: {}", e);
+                    0
+                });
+                let evicted = match quota::enforce(&db, config.max_database_size_mb) {
+                    Ok(Some(report)) => {
+                        let removed = report.removed;
+                        let _ = app_handle.emit("quota-enforced", report);
+                        removed
+                    }
+                    Ok(None) => 0,
+                    Err(e) => {
+                        eprintln!("Quota enforcement failed: {}", e);
+                        0
+                    }
+                };
+                removed + archived + evicted
+            }
+            Err(_) => 0,
+        };
+        if purged > 0 {
+            let _ = app_handle.emit("clipboard-changed", "cleared");
+        }
+        std::thread::sleep(std::time::Duration::from_secs(600));
+    });
+}
 
-    let lang_map = commands::load_language_map(lang).unwrap_or_default();
+/// Builds the tray icon again after explorer.exe restarts, which drops icons added before.
+pub(crate) fn recreate_tray(app: &tauri::AppHandle) {
+    let app = app.clone();
+    // Tray icons belong to the main thread's event loop
+    let _ = app.clone().run_on_main_thread(move || {
+        let Some(state) = app.try_state::<TrayState>() else {
+            return;
+        };
+        let language = app
+            .try_state::<ConfigPath>()
+            .map(|cp| AppConfig::load(&cp.0).language)
+            .unwrap_or_default();
+        match setup_tray(&app, &language) {
+            Ok(tray) => {
+                let mut current = state.0.lock().unwrap_or_else(|e| e.into_inner());
+                let old_id = current.id().clone();
+                *current = tray;
+                app.remove_tray_by_id(&old_id);
+            }
+            Err(e) => eprintln!("Failed to recreate the tray icon: {}", e),
+        }
+    });
+}
+
+/// Rebuilds the tray menu so items that mirror settings, like do not disturb, show their state.
+pub(crate) fn refresh_tray_menu(app: &tauri::AppHandle) {
+    let app = app.clone();
+    let _ = app.clone().run_on_main_thread(move || {
+        let (Some(state), Some(config_path)) = (app.try_state::<TrayState>(), app.try_state::<ConfigPath>()) else {
+            return;
+        };
+        let config = AppConfig::load(&config_path.0);
+        if let Ok(menu) = tray_menu(&app, &config) {
+            let _ = state.0.lock().unwrap_or_else(|e| e.into_inner()).set_menu(Some(menu));
+        }
+    });
+}
+
+fn tray_menu(app: &tauri::AppHandle, config: &AppConfig) -> tauri::Result<tauri::menu::Menu<tauri::Wry>> {
+    use tauri::menu::{CheckMenuItem, Menu, MenuItem};
+
+    let lang_map = commands::load_language_map(&config.language).unwrap_or_default();
     let show_text = lang_map.get("tray.show").cloned().unwrap_or_else(|| "显示主窗口".into());
+    let clear_text = lang_map.get("tray.clear_clipboard").cloned().unwrap_or_else(|| "清空系统剪贴板".into());
+    let dnd_text = lang_map.get("tray.dnd").cloned().unwrap_or_else(|| "免打扰 1 小时".into());
     let quit_text = lang_map.get("tray.quit").cloned().unwrap_or_else(|| "退出".into());
-    let tooltip_text = lang_map.get("app.tray_tooltip").cloned().unwrap_or_else(|| "CutBoard - 剪切板管理器".into());
 
     let show = MenuItem::with_id(app, "show", &show_text, true, None::<&str>)?;
+    let clear = MenuItem::with_id(app, "clear_clipboard", &clear_text, true, None::<&str>)?;
+    let dnd = CheckMenuItem::with_id(app, "dnd", &dnd_text, true, dnd::is_active(config), None::<&str>)?;
     let quit = MenuItem::with_id(app, "quit", &quit_text, true, None::<&str>)?;
-    let menu = Menu::with_items(app, &[&show, &quit])?;
+    Menu::with_items(app, &[&show, &clear, &dnd, &quit])
+}
+
+fn setup_tray(app: &tauri::AppHandle, lang: &str) -> Result<tauri::tray::TrayIcon, Box<dyn std::error::Error>> {
+    use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+
+    let lang_map = commands::load_language_map(lang).unwrap_or_default();
+    let tooltip_text = lang_map.get("app.tray_tooltip").cloned().unwrap_or_else(|| "CutBoard - 剪切板管理器".into());
+    let config = app.try_state::<ConfigPath>().map(|cp| AppConfig::load(&cp.0)).unwrap_or_default();
+    let menu = tray_menu(app, &AppConfig { language: lang.to_string(), ..config })?;
 
     let icon = app
         .default_window_icon()
@@ -274,13 +658,22 @@ fn setup_tray(app: &mut tauri::App, lang: &str) -> Result<tauri::tray::TrayIcon,
         .on_menu_event(|app, event| match event.id.as_ref() {
             "show" => {
                 if let Some(window) = app.get_webview_window("main") {
+                    if !window.is_visible().unwrap_or(false) {
+                        geometry::restore(app, &window);
+                    }
                     let _ = window.show();
                     let _ = window.set_focus();
                 }
             }
-            "quit" => {
-                app.exit(0);
+            "clear_clipboard" => {
+                let _ = commands::clear_system_clipboard();
             }
+            "dnd" => {
+                let active = app.try_state::<ConfigPath>().is_some_and(|cp| dnd::is_active(&AppConfig::load(&cp.0)));
+                let until = if active { 0 } else { chrono::Local::now().timestamp() + dnd::TRAY_DURATION_SECS };
+                let _ = dnd::set(app, until);
+            }
+            "quit" => app.exit(0),
             _ => {}
         })
         .on_tray_icon_event(|tray, event| {
@@ -292,9 +685,16 @@ fn setup_tray(app: &mut tauri::App, lang: &str) -> Result<tauri::tray::TrayIcon,
             {
                 let app = tray.app_handle();
                 if let Some(window) = app.get_webview_window("main") {
+                    if !window.is_visible().unwrap_or(false) {
+                        geometry::restore(app, &window);
+                    }
                     let _ = window.show();
                     let _ = window.set_focus();
                 }
+            } else if let TrayIconEvent::DoubleClick { button: MouseButton::Left, .. } = event {
+                // Same path as the global hotkey, which may be unavailable
+                hotkey::toggle_window(tray.app_handle());
+                hotkey::refresh();
             }
         })
         .build(app)?;
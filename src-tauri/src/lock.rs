@@ -0,0 +1,82 @@
+//! Optional app lock. The PIN/passphrase is stored as an argon2 hash in config; while locked,
+//! commands that return clipboard content or change settings refuse to run, so the UI cannot
+//! bypass the lock.
+
+use crate::config::AppConfig;
+use crate::error::CutboardError;
+use crate::ConfigPath;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use tauri::{Emitter, Manager};
+
+static LOCKED: AtomicBool = AtomicBool::new(false);
+static LAST_ACTIVITY: AtomicI64 = AtomicI64::new(0);
+
+pub const LOCKED_ERROR: &str = "App is locked";
+
+fn now_secs() -> i64 {
+    chrono::Local::now().timestamp()
+}
+
+pub fn hash_secret(secret: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(secret.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| e.to_string())
+}
+
+pub fn verify_secret(secret: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(secret.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+pub fn is_enabled(app: &tauri::AppHandle) -> bool {
+    app.try_state::<ConfigPath>()
+        .is_some_and(|cp| !AppConfig::load(&cp.0).app_lock_hash.is_empty())
+}
+
+pub fn is_locked() -> bool {
+    LOCKED.load(Ordering::SeqCst)
+}
+
+/// Records user activity inside the app, postponing the idle lock where system-wide input
+/// times are unavailable.
+pub fn touch() {
+    LAST_ACTIVITY.store(now_secs(), Ordering::SeqCst);
+}
+
+/// Seconds since the last recorded in-app activity.
+pub fn app_idle_secs() -> u64 {
+    (now_secs() - LAST_ACTIVITY.load(Ordering::SeqCst)).max(0) as u64
+}
+
+/// Fails with `CutboardError::Locked` while the app is locked; otherwise counts as activity.
+pub fn ensure_unlocked() -> Result<(), CutboardError> {
+    if is_locked() {
+        return Err(CutboardError::Locked { message: LOCKED_ERROR.into() });
+    }
+    touch();
+    Ok(())
+}
+
+/// Without a PIN, locking only blurs the window until the user dismisses it.
+pub fn set_locked(app: &tauri::AppHandle, locked: bool) {
+    if LOCKED.swap(locked, Ordering::SeqCst) != locked {
+        let _ = app.emit("app-lock-changed", locked);
+    }
+    if !locked {
+        touch();
+    }
+}
+
+/// Starts locked when a PIN is configured. Idle locking is driven by `crate::idle`.
+pub fn start(app: &tauri::AppHandle) {
+    touch();
+    if is_enabled(app) {
+        LOCKED.store(true, Ordering::SeqCst);
+    }
+}
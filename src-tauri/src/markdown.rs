@@ -0,0 +1,17 @@
+//! Markdown conversions behind the `html_to_markdown` and `markdown_to_html` transforms and
+//! "Copy as Markdown".
+
+use pulldown_cmark::{html, Options, Parser};
+
+/// Markdown for an HTML fragment, such as the `html_content` captured alongside copied text.
+pub fn from_html(fragment: &str) -> String {
+    html2md::parse_html(fragment).trim().to_string()
+}
+
+/// HTML for Markdown text, with GitHub-style tables, strikethrough and task lists.
+pub fn to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut out = String::new();
+    html::push_html(&mut out, Parser::new_ext(markdown, options));
+    out
+}
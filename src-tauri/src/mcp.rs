@@ -0,0 +1,293 @@
+//! Model Context Protocol server so local AI assistants can look up recent copies. Assistants
+//! launch `cutboard --mcp` and talk JSON-RPC over stdin/stdout, one message per line.
+//!
+//! The server is a separate process that opens the history read-only next to the running app.
+//! It exposes read-only tools, refuses to start unless `mcp_enabled` is set, never returns
+//! sensitive entries and stays off while an app lock is configured, since it cannot tell whether
+//! the app is currently locked.
+
+use crate::config::AppConfig;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+const PROTOCOL_VERSION: &str = "2025-06-18";
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+/// Search and listing results carry a preview; `get_entry` returns the full text.
+const PREVIEW_CHARS: usize = 300;
+
+#[derive(Serialize)]
+struct EntrySummary {
+    id: i64,
+    app: String,
+    content_type: String,
+    created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preview: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EntryDetail {
+    id: i64,
+    app: String,
+    content_type: String,
+    created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    tags: Vec<String>,
+}
+
+/// Runs the server until stdin closes. Returns the process exit code.
+pub fn serve_stdio() -> i32 {
+    // Same fallback as Tauri's app data dir on Windows; the config normally sits next to the exe
+    let app_data_dir = std::env::var_os("APPDATA")
+        .map(|dir| std::path::PathBuf::from(dir).join("cutboard"))
+        .unwrap_or_default();
+    let config_path = AppConfig::config_file_path(&app_data_dir);
+    let config = AppConfig::load(&config_path);
+    if let Err(e) = check_enabled(&config) {
+        eprintln!("{}", e);
+        return 1;
+    }
+    let conn = match open_history(Path::new(&config.data_path)) {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("Cannot open clipboard history: {}", e);
+            return 1;
+        }
+    };
+
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle_message(&conn, &message),
+            Err(e) => Some(error_reply(Value::Null, -32700, &format!("Parse error: {}", e))),
+        };
+        if let Some(reply) = reply {
+            if writeln!(stdout, "{}", reply).and_then(|_| stdout.flush()).is_err() {
+                break;
+            }
+        }
+    }
+    0
+}
+
+fn check_enabled(config: &AppConfig) -> Result<(), &'static str> {
+    if !config.mcp_enabled {
+        return Err("The MCP server is off; set mcp_enabled=true in CutBoard's config.ini to allow assistants to read clipboard history");
+    }
+    if !config.app_lock_hash.is_empty() {
+        return Err("The MCP server is unavailable while an app lock is configured");
+    }
+    if config.data_path.is_empty() {
+        return Err("Start CutBoard once before using the MCP server");
+    }
+    Ok(())
+}
+
+fn open_history(data_dir: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open_with_flags(
+        data_dir.join("cutboard.db"),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+    Ok(conn)
+}
+
+/// Requests get a reply; notifications (no `id`) do not.
+fn handle_message(conn: &Connection, message: &Value) -> Option<Value> {
+    let id = message.get("id").cloned()?;
+    let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    let result = match method {
+        "initialize" => json!({
+            "protocolVersion": params.get("protocolVersion").and_then(Value::as_str).unwrap_or(PROTOCOL_VERSION),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "cutboard", "version": env!("CARGO_PKG_VERSION") },
+            "instructions": "Read-only access to the user's clipboard history. Sensitive entries are never returned.",
+        }),
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": tool_definitions() }),
+        "tools/call" => call_tool(conn, &params),
+        _ => return Some(error_reply(id, -32601, &format!("Method not found: {}", method))),
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+fn error_reply(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "search_history",
+            "description": "Search copied text, newest first. Returns ids and previews; use get_entry for the full text.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Text to look for" },
+                    "app": { "type": "string", "description": "Only entries copied from this app" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_LIMIT },
+                },
+                "required": ["query"],
+            },
+            "annotations": { "readOnlyHint": true },
+        },
+        {
+            "name": "get_entry",
+            "description": "Full content and details of one clipboard entry.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "id": { "type": "integer" } },
+                "required": ["id"],
+            },
+            "annotations": { "readOnlyHint": true },
+        },
+        {
+            "name": "recent_entries",
+            "description": "The most recently copied entries, newest first.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "content_type": { "type": "string", "enum": ["text", "image"] },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_LIMIT },
+                },
+            },
+            "annotations": { "readOnlyHint": true },
+        },
+    ])
+}
+
+/// Tool failures are reported in the result with `isError`, so the assistant sees them.
+fn call_tool(conn: &Connection, params: &Value) -> Value {
+    let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+    let args = params.get("arguments").cloned().unwrap_or(Value::Null);
+    let limit = args
+        .get("limit")
+        .and_then(Value::as_i64)
+        .unwrap_or(DEFAULT_LIMIT)
+        .clamp(1, MAX_LIMIT);
+    let result = match name {
+        "search_history" => match args.get("query").and_then(Value::as_str) {
+            Some(query) => search_history(conn, query, args.get("app").and_then(Value::as_str), limit)
+                .map(|entries| json!(entries)),
+            None => return tool_error("query is required"),
+        },
+        "get_entry" => match args.get("id").and_then(Value::as_i64) {
+            Some(id) => match get_entry(conn, id) {
+                Ok(Some(entry)) => Ok(json!(entry)),
+                Ok(None) => return tool_error(&format!("No entry with id {}", id)),
+                Err(e) => Err(e),
+            },
+            None => return tool_error("id is required"),
+        },
+        "recent_entries" => recent_entries(conn, args.get("content_type").and_then(Value::as_str), limit)
+            .map(|entries| json!(entries)),
+        _ => return tool_error(&format!("Unknown tool: {}", name)),
+    };
+    match result {
+        Ok(value) => json!({
+            "content": [{ "type": "text", "text": value.to_string() }],
+            "isError": false,
+        }),
+        Err(e) => tool_error(&e.to_string()),
+    }
+}
+
+fn tool_error(message: &str) -> Value {
+    json!({ "content": [{ "type": "text", "text": message }], "isError": true })
+}
+
+const SUMMARY_SELECT: &str = "SELECT e.id, COALESCE(a.name, ''), e.content_type, e.created_at, e.text_content, e.source_url
+     FROM clipboard_entries e LEFT JOIN apps a ON a.id = e.app_id";
+
+fn preview(text: Option<String>) -> Option<String> {
+    text.map(|t| {
+        if t.chars().count() > PREVIEW_CHARS {
+            format!("{}…", t.chars().take(PREVIEW_CHARS).collect::<String>())
+        } else {
+            t
+        }
+    })
+}
+
+fn summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<EntrySummary> {
+    Ok(EntrySummary {
+        id: row.get(0)?,
+        app: row.get(1)?,
+        content_type: row.get(2)?,
+        created_at: row.get(3)?,
+        preview: preview(row.get(4)?),
+        source_url: row.get(5)?,
+    })
+}
+
+fn search_history(conn: &Connection, query: &str, app: Option<&str>, limit: i64) -> rusqlite::Result<Vec<EntrySummary>> {
+    let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    let sql = format!(
+        "{} WHERE COALESCE(e.is_sensitive, 0) = 0
+            AND (e.text_content LIKE ?1 ESCAPE '\\' OR e.source_url LIKE ?1 ESCAPE '\\' OR e.title LIKE ?1 ESCAPE '\\')
+            AND (?2 IS NULL OR a.name = ?2 COLLATE NOCASE)
+         ORDER BY e.created_at DESC LIMIT ?3",
+        SUMMARY_SELECT
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![pattern, app, limit], summary_from_row)?;
+    rows.collect()
+}
+
+fn recent_entries(conn: &Connection, content_type: Option<&str>, limit: i64) -> rusqlite::Result<Vec<EntrySummary>> {
+    let sql = format!(
+        "{} WHERE COALESCE(e.is_sensitive, 0) = 0 AND (?1 IS NULL OR e.content_type = ?1)
+         ORDER BY e.created_at DESC LIMIT ?2",
+        SUMMARY_SELECT
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![content_type, limit], summary_from_row)?;
+    rows.collect()
+}
+
+/// Sensitive entries are reported as missing.
+fn get_entry(conn: &Connection, id: i64) -> rusqlite::Result<Option<EntryDetail>> {
+    let entry = conn
+        .query_row(
+            "SELECT e.id, COALESCE(a.name, ''), e.content_type, e.created_at, e.text_content, e.source_url, e.title
+             FROM clipboard_entries e LEFT JOIN apps a ON a.id = e.app_id
+             WHERE e.id = ?1 AND COALESCE(e.is_sensitive, 0) = 0",
+            params![id],
+            |row| {
+                Ok(EntryDetail {
+                    id: row.get(0)?,
+                    app: row.get(1)?,
+                    content_type: row.get(2)?,
+                    created_at: row.get(3)?,
+                    text: row.get(4)?,
+                    source_url: row.get(5)?,
+                    title: row.get(6)?,
+                    tags: Vec::new(),
+                })
+            },
+        )
+        .optional()?;
+    let Some(mut entry) = entry else {
+        return Ok(None);
+    };
+    let mut stmt = conn.prepare("SELECT tag FROM entry_tags WHERE entry_id = ?1 ORDER BY tag")?;
+    entry.tags = stmt.query_map(params![id], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+    Ok(Some(entry))
+}
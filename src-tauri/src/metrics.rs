@@ -0,0 +1,140 @@
+//! In-process performance counters and timing histograms, read through `get_perf_metrics` to
+//! diagnose slowdowns on large histories without attaching a profiler. Nothing is persisted;
+//! figures cover the current run.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Upper bounds of the histogram buckets in microseconds; the last bucket is open-ended.
+const BUCKETS_US: [u64; 13] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000,
+];
+
+#[derive(Default)]
+struct Histogram {
+    count: u64,
+    total_us: u64,
+    max_us: u64,
+    buckets: [u64; BUCKETS_US.len() + 1],
+}
+
+impl Histogram {
+    fn record(&mut self, us: u64) {
+        self.count += 1;
+        self.total_us += us;
+        self.max_us = self.max_us.max(us);
+        let bucket = BUCKETS_US.iter().position(|&b| us <= b).unwrap_or(BUCKETS_US.len());
+        self.buckets[bucket] += 1;
+    }
+
+    /// Upper bound of the bucket holding the `q` quantile, capped at the observed maximum.
+    fn quantile_us(&self, q: f64) -> u64 {
+        let target = ((self.count as f64) * q).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= target {
+                return BUCKETS_US.get(i).copied().unwrap_or(self.max_us).min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    counters: HashMap<&'static str, u64>,
+    timings: HashMap<&'static str, Histogram>,
+}
+
+static REGISTRY: std::sync::LazyLock<Mutex<Registry>> = std::sync::LazyLock::new(|| Mutex::new(Registry::default()));
+
+pub fn increment(name: &'static str) {
+    if let Ok(mut r) = REGISTRY.lock() {
+        *r.counters.entry(name).or_insert(0) += 1;
+    }
+}
+
+pub fn record(name: &'static str, elapsed: Duration) {
+    if let Ok(mut r) = REGISTRY.lock() {
+        r.timings.entry(name).or_default().record(elapsed.as_micros() as u64);
+    }
+}
+
+/// Records the time until it is dropped.
+pub struct Timer {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        record(self.name, self.start.elapsed());
+    }
+}
+
+pub fn timer(name: &'static str) -> Timer {
+    Timer { name, start: Instant::now() }
+}
+
+#[derive(Serialize)]
+pub struct TimingSummary {
+    pub count: u64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Serialize)]
+pub struct PerfMetrics {
+    pub uptime_secs: u64,
+    pub counters: BTreeMap<String, u64>,
+    pub timings: BTreeMap<String, TimingSummary>,
+    /// Hit rate (0-1) per cache, derived from its `.hit` and `.miss` counters.
+    pub cache_hit_rates: BTreeMap<String, f64>,
+}
+
+static STARTED: std::sync::LazyLock<Instant> = std::sync::LazyLock::new(Instant::now);
+
+/// Starts the uptime clock; call once at startup.
+pub fn init() {
+    std::sync::LazyLock::force(&STARTED);
+}
+
+pub fn snapshot() -> PerfMetrics {
+    let r = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let ms = |us: u64| us as f64 / 1000.0;
+    let timings = r
+        .timings
+        .iter()
+        .map(|(name, h)| {
+            (
+                name.to_string(),
+                TimingSummary {
+                    count: h.count,
+                    mean_ms: if h.count == 0 { 0.0 } else { ms(h.total_us) / h.count as f64 },
+                    p50_ms: ms(h.quantile_us(0.5)),
+                    p95_ms: ms(h.quantile_us(0.95)),
+                    max_ms: ms(h.max_us),
+                },
+            )
+        })
+        .collect();
+    let mut cache_hit_rates = BTreeMap::new();
+    for name in r.counters.keys() {
+        if let Some(cache) = name.strip_suffix(".hit").or_else(|| name.strip_suffix(".miss")) {
+            let get = |suffix: &str| r.counters.get(format!("{}.{}", cache, suffix).as_str()).copied().unwrap_or(0);
+            let (hits, misses) = (get("hit"), get("miss"));
+            cache_hit_rates.insert(cache.to_string(), hits as f64 / (hits + misses) as f64);
+        }
+    }
+    PerfMetrics {
+        uptime_secs: STARTED.elapsed().as_secs(),
+        counters: r.counters.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+        timings,
+        cache_hit_rates,
+    }
+}
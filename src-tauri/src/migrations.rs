@@ -0,0 +1,946 @@
+//! Versioned schema migrations. `PRAGMA user_version` records the last applied migration, and
+//! each run is logged in `schema_migrations`. Migrations run in order at startup, each in its own
+//! transaction, so a failure leaves the database at the previous version.
+//!
+//! Foreign keys are off while migrations run so tables can be rebuilt, and every migration must
+//! leave `PRAGMA foreign_key_check` clean before it commits.
+//!
+//! To change the schema, append a migration with the next version; never edit one that has
+//! shipped.
+
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub up: fn(&Connection) -> Result<()>,
+    /// Reverts `up`, dropping whatever it added; `None` for the baseline, which has nothing older
+    /// to go back to.
+    pub down: Option<fn(&Connection) -> Result<()>>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline schema",
+        up: baseline,
+        down: None,
+    },
+    Migration {
+        version: 2,
+        description: "foreign key actions",
+        up: foreign_keys_up,
+        down: Some(foreign_keys_down),
+    },
+    Migration {
+        version: 3,
+        description: "app icon stamps",
+        up: icon_stamp_up,
+        down: Some(icon_stamp_down),
+    },
+    Migration {
+        version: 4,
+        description: "derived entries",
+        up: derived_entries_up,
+        down: Some(derived_entries_down),
+    },
+    Migration {
+        version: 5,
+        description: "sensitive allowlist",
+        up: sensitive_allowlist_up,
+        down: Some(sensitive_allowlist_down),
+    },
+    Migration {
+        version: 6,
+        description: "sensitive kinds",
+        up: sensitive_kind_up,
+        down: Some(sensitive_kind_down),
+    },
+    Migration {
+        version: 7,
+        description: "locked entries",
+        up: locked_entries_up,
+        down: Some(locked_entries_down),
+    },
+    Migration {
+        version: 8,
+        description: "entry links",
+        up: entry_links_up,
+        down: Some(entry_links_down),
+    },
+    Migration {
+        version: 9,
+        description: "context patterns",
+        up: context_patterns_up,
+        down: Some(context_patterns_down),
+    },
+    Migration {
+        version: 10,
+        description: "plain text capture",
+        up: plain_text_capture_up,
+        down: Some(plain_text_capture_down),
+    },
+    Migration {
+        version: 11,
+        description: "favorite domains",
+        up: favorite_domains_up,
+        down: Some(favorite_domains_down),
+    },
+    Migration {
+        version: 12,
+        description: "app metadata",
+        up: app_metadata_up,
+        down: Some(app_metadata_down),
+    },
+    Migration {
+        version: 13,
+        description: "hidden apps",
+        up: hidden_apps_up,
+        down: Some(hidden_apps_down),
+    },
+    Migration {
+        version: 14,
+        description: "app notification types",
+        up: app_notify_up,
+        down: Some(app_notify_down),
+    },
+    Migration {
+        version: 15,
+        description: "entry tables",
+        up: entry_tables_up,
+        down: Some(entry_tables_down),
+    },
+    Migration {
+        version: 16,
+        description: "image reference counts",
+        up: image_refs_up,
+        down: Some(image_refs_down),
+    },
+];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+pub fn current_version(conn: &Connection) -> Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+fn set_version(conn: &Connection, version: u32) -> Result<()> {
+    // PRAGMA does not take bound parameters
+    conn.execute_batch(&format!("PRAGMA user_version = {}", version))
+}
+
+/// Fails when rows reference missing parents, so the migration's transaction is rolled back.
+fn check_foreign_keys(conn: &Connection) -> Result<()> {
+    let violations: i64 = conn.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| row.get(0))?;
+    if violations > 0 {
+        return Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT_FOREIGNKEY),
+            Some(format!("{} rows violate foreign keys", violations)),
+        ));
+    }
+    Ok(())
+}
+
+/// Brings the database up to `latest_version` and turns foreign key enforcement on. A database
+/// from a newer build is left as is: migrations only add to the schema, so older builds can keep
+/// using it.
+pub fn run(conn: &Connection) -> Result<()> {
+    // Has no effect inside a transaction
+    conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+    let result = apply_pending(conn, latest_version());
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    result
+}
+
+/// Runs the `up` steps after the current version through `target`.
+fn apply_pending(conn: &Connection, target: u32) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            direction TEXT NOT NULL DEFAULT 'up',
+            applied_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );",
+    )?;
+    let current = current_version(conn)?;
+    if current > latest_version() {
+        eprintln!("Database schema version {} is newer than this build ({})", current, latest_version());
+        return Ok(());
+    }
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current && m.version <= target) {
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx)?;
+        check_foreign_keys(&tx)?;
+        set_version(&tx, migration.version)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO schema_migrations (version, description, direction) VALUES (?1, ?2, 'up')",
+            params![migration.version, migration.description],
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Runs `down` steps newest first until the database is at `target`, e.g. before installing an
+/// older build (see `--revert-schema` in `run`). Stops with an error at a migration that cannot
+/// be reverted.
+pub fn revert_to(conn: &Connection, target: u32) -> std::result::Result<(), String> {
+    conn.execute_batch("PRAGMA foreign_keys = OFF;").map_err(|e| e.to_string())?;
+    let result = revert_pending(conn, target);
+    conn.execute_batch("PRAGMA foreign_keys = ON;").map_err(|e| e.to_string())?;
+    result
+}
+
+fn revert_pending(conn: &Connection, target: u32) -> std::result::Result<(), String> {
+    let current = current_version(conn).map_err(|e| e.to_string())?;
+    for migration in MIGRATIONS.iter().rev().filter(|m| m.version > target && m.version <= current) {
+        let down = migration
+            .down
+            .ok_or_else(|| format!("Migration {} ({}) cannot be reverted", migration.version, migration.description))?;
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        down(&tx).map_err(|e| e.to_string())?;
+        check_foreign_keys(&tx).map_err(|e| e.to_string())?;
+        set_version(&tx, migration.version - 1).map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT OR REPLACE INTO schema_migrations (version, description, direction) VALUES (?1, ?2, 'down')",
+            params![migration.version, migration.description],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// The schema as of the first versioned build. Databases from before migrations were versioned
+/// start at 0 with any subset of it, so every step checks what already exists.
+fn baseline(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS apps (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            exe_path TEXT NOT NULL UNIQUE,
+            icon_base64 TEXT
+        );
+        CREATE TABLE IF NOT EXISTS clipboard_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            app_id INTEGER NOT NULL REFERENCES apps(id),
+            content_type TEXT NOT NULL,
+            text_content TEXT,
+            image_path TEXT,
+            content_hash TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_entries_app ON clipboard_entries(app_id);
+        CREATE INDEX IF NOT EXISTS idx_entries_type ON clipboard_entries(content_type);
+        CREATE INDEX IF NOT EXISTS idx_entries_created ON clipboard_entries(created_at);",
+    )?;
+
+    let columns: Vec<String> = conn
+        .prepare("PRAGMA table_info(clipboard_entries)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?;
+
+    if !columns.iter().any(|c| c == "content_hash") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN content_hash TEXT", [])?;
+    }
+    if !columns.iter().any(|c| c == "source_url") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN source_url TEXT", [])?;
+    }
+    if !columns.iter().any(|c| c == "is_favorite") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN is_favorite INTEGER DEFAULT 0", [])?;
+    }
+    if !columns.iter().any(|c| c == "is_sensitive") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN is_sensitive INTEGER DEFAULT 0", [])?;
+    }
+    if !columns.iter().any(|c| c == "html_content") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN html_content TEXT", [])?;
+    }
+    if !columns.iter().any(|c| c == "owner_exe") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN owner_exe TEXT", [])?;
+    }
+    if !columns.iter().any(|c| c == "source_exe") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN source_exe TEXT", [])?;
+    }
+    if !columns.iter().any(|c| c == "remote_session") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN remote_session TEXT", [])?;
+    }
+    if !columns.iter().any(|c| c == "expires_at") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN expires_at TEXT", [])?;
+    }
+    if !columns.iter().any(|c| c == "detected_kind") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN detected_kind TEXT", [])?;
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN file_size INTEGER", [])?;
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN file_ext TEXT", [])?;
+    }
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_entries_expires ON clipboard_entries(expires_at)", [])?;
+    if !columns.iter().any(|c| c == "title") {
+        conn.execute("ALTER TABLE clipboard_entries ADD COLUMN title TEXT", [])?;
+        let existing: Vec<(i64, String)> = conn
+            .prepare("SELECT id, text_content FROM clipboard_entries WHERE content_type = 'text' AND text_content IS NOT NULL AND COALESCE(is_sensitive,0) = 0")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        for (id, text) in existing {
+            if let Some(title) = crate::title::generate(&text) {
+                conn.execute("UPDATE clipboard_entries SET title = ?1 WHERE id = ?2", params![title, id])?;
+            }
+        }
+    }
+
+    // Migrate apps table
+    let app_columns: Vec<String> = conn
+        .prepare("PRAGMA table_info(apps)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?;
+    if !app_columns.iter().any(|c| c == "is_favorite") {
+        conn.execute("ALTER TABLE apps ADD COLUMN is_favorite INTEGER DEFAULT 0", [])?;
+    }
+
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_entries_hash ON clipboard_entries(content_hash);
+         CREATE INDEX IF NOT EXISTS idx_entries_app_type_hash ON clipboard_entries(app_id, content_type, content_hash);",
+    )?;
+
+    // Named favorite collections
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS collections (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );
+        CREATE TABLE IF NOT EXISTS collection_entries (
+            collection_id INTEGER NOT NULL REFERENCES collections(id),
+            entry_id INTEGER NOT NULL REFERENCES clipboard_entries(id),
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (collection_id, entry_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_collection_entries_entry ON collection_entries(entry_id);
+        CREATE TRIGGER IF NOT EXISTS trg_entries_delete_collection_links
+            AFTER DELETE ON clipboard_entries
+            BEGIN
+                DELETE FROM collection_entries WHERE entry_id = OLD.id;
+            END;",
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS entry_tags (
+            entry_id INTEGER NOT NULL REFERENCES clipboard_entries(id),
+            tag TEXT NOT NULL,
+            PRIMARY KEY (entry_id, tag)
+        );
+        CREATE INDEX IF NOT EXISTS idx_entry_tags_tag ON entry_tags(tag);
+        CREATE TRIGGER IF NOT EXISTS trg_entries_delete_tags
+            AFTER DELETE ON clipboard_entries
+            BEGIN
+                DELETE FROM entry_tags WHERE entry_id = OLD.id;
+            END;",
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );
+        CREATE TABLE IF NOT EXISTS template_counters (
+            name TEXT PRIMARY KEY,
+            value INTEGER NOT NULL DEFAULT 0
+        );",
+    )?;
+
+    // Every capture/reuse of an entry; clipboard_entries.created_at only keeps the latest
+    let had_events: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'entry_events')",
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS entry_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entry_id INTEGER NOT NULL REFERENCES clipboard_entries(id),
+            kind TEXT NOT NULL,
+            app_id INTEGER,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_entry_events_entry ON entry_events(entry_id);
+        CREATE TRIGGER IF NOT EXISTS trg_entries_delete_events
+            AFTER DELETE ON clipboard_entries
+            BEGIN
+                DELETE FROM entry_events WHERE entry_id = OLD.id;
+            END;",
+    )?;
+    if !had_events {
+        conn.execute(
+            "INSERT INTO entry_events (entry_id, kind, app_id, created_at)
+             SELECT id, 'captured', app_id, created_at FROM clipboard_entries",
+            [],
+        )?;
+    }
+
+    // User-defined capture rules; conditions/actions are JSON (see rules.rs)
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            conditions TEXT NOT NULL DEFAULT '{}',
+            actions TEXT NOT NULL DEFAULT '{}',
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );",
+    )?;
+
+    // Significant user actions, for shared machines; entry ids are kept after the entry is gone
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            action TEXT NOT NULL,
+            entry_id INTEGER,
+            detail TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_audit_log_created ON audit_log(created_at);",
+    )?;
+
+    // Per-app preferences, keyed by exe path so they survive the app row being pruned
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            exe_path TEXT PRIMARY KEY,
+            plain_text INTEGER NOT NULL DEFAULT 0,
+            auto_favorite INTEGER NOT NULL DEFAULT 0
+        );",
+    )?;
+
+    let template_columns: Vec<String> = conn
+        .prepare("PRAGMA table_info(templates)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?;
+    if !template_columns.iter().any(|c| c == "abbreviation") {
+        conn.execute("ALTER TABLE templates ADD COLUMN abbreviation TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+/// Recreates `table` from `create_sql` (`{table}` stands for the name) and copies the columns both
+/// definitions share for rows matching `keep`: SQLite cannot change constraints in place. Indexes
+/// and triggers on the table are dropped with it and must be recreated by the caller.
+fn rebuild_table(conn: &Connection, table: &str, create_sql: &str, keep: &str) -> Result<()> {
+    let tmp = format!("{}_rebuild", table);
+    conn.execute_batch(&create_sql.replace("{table}", &tmp))?;
+    let old_columns = crate::database::table_columns(conn, "main", table)?;
+    let columns = crate::database::table_columns(conn, "main", &tmp)?
+        .into_iter()
+        .filter(|c| old_columns.contains(c))
+        .collect::<Vec<_>>()
+        .join(", ");
+    // Keep AUTOINCREMENT from reusing ids of deleted rows, which the audit log may still mention
+    let seq: Option<i64> = conn
+        .query_row("SELECT seq FROM sqlite_sequence WHERE name = ?1", params![table], |row| row.get(0))
+        .optional()?;
+    conn.execute_batch(&format!(
+        "INSERT INTO {tmp} ({columns}) SELECT {columns} FROM {table} WHERE {keep};
+         DROP TABLE {table};
+         ALTER TABLE {tmp} RENAME TO {table};"
+    ))?;
+    if let Some(seq) = seq {
+        conn.execute("DELETE FROM sqlite_sequence WHERE name = ?1", params![table])?;
+        conn.execute("INSERT INTO sqlite_sequence (name, seq) VALUES (?1, ?2)", params![table, seq])?;
+    }
+    Ok(())
+}
+
+fn entries_table_sql(on_delete: &str) -> String {
+    format!(
+        "CREATE TABLE {{table}} (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            app_id INTEGER NOT NULL REFERENCES apps(id){on_delete},
+            content_type TEXT NOT NULL,
+            text_content TEXT,
+            image_path TEXT,
+            content_hash TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            source_url TEXT,
+            is_favorite INTEGER DEFAULT 0,
+            is_sensitive INTEGER DEFAULT 0,
+            html_content TEXT,
+            owner_exe TEXT,
+            source_exe TEXT,
+            remote_session TEXT,
+            expires_at TEXT,
+            detected_kind TEXT,
+            file_size INTEGER,
+            file_ext TEXT,
+            title TEXT
+        );"
+    )
+}
+
+fn collection_entries_table_sql(on_delete: &str) -> String {
+    format!(
+        "CREATE TABLE {{table}} (
+            collection_id INTEGER NOT NULL REFERENCES collections(id){on_delete},
+            entry_id INTEGER NOT NULL REFERENCES clipboard_entries(id){on_delete},
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (collection_id, entry_id)
+        );"
+    )
+}
+
+fn entry_tags_table_sql(on_delete: &str) -> String {
+    format!(
+        "CREATE TABLE {{table}} (
+            entry_id INTEGER NOT NULL REFERENCES clipboard_entries(id){on_delete},
+            tag TEXT NOT NULL,
+            PRIMARY KEY (entry_id, tag)
+        );"
+    )
+}
+
+fn entry_events_table_sql(on_delete: &str) -> String {
+    format!(
+        "CREATE TABLE {{table}} (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entry_id INTEGER NOT NULL REFERENCES clipboard_entries(id){on_delete},
+            kind TEXT NOT NULL,
+            app_id INTEGER,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );"
+    )
+}
+
+/// Indexes of the tables `foreign_keys_up` rebuilds.
+const REBUILT_INDEXES: &str = "
+    CREATE INDEX IF NOT EXISTS idx_entries_app ON clipboard_entries(app_id);
+    CREATE INDEX IF NOT EXISTS idx_entries_type ON clipboard_entries(content_type);
+    CREATE INDEX IF NOT EXISTS idx_entries_created ON clipboard_entries(created_at);
+    CREATE INDEX IF NOT EXISTS idx_entries_expires ON clipboard_entries(expires_at);
+    CREATE INDEX IF NOT EXISTS idx_entries_hash ON clipboard_entries(content_hash);
+    CREATE INDEX IF NOT EXISTS idx_entries_app_type_hash ON clipboard_entries(app_id, content_type, content_hash);
+    CREATE INDEX IF NOT EXISTS idx_collection_entries_entry ON collection_entries(entry_id);
+    CREATE INDEX IF NOT EXISTS idx_entry_tags_tag ON entry_tags(tag);
+    CREATE INDEX IF NOT EXISTS idx_entry_events_entry ON entry_events(entry_id);";
+
+/// Entries may not outlive their app (it must be emptied first, so image files get released),
+/// and tags, events and collection links go with their entry or collection. Before enforcement
+/// starts, entries whose app row is gone get a placeholder app and orphaned links are dropped.
+fn foreign_keys_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "INSERT INTO apps (id, name, exe_path)
+            SELECT DISTINCT app_id, 'Unknown', 'unknown://' || app_id FROM clipboard_entries
+            WHERE app_id NOT IN (SELECT id FROM apps);",
+    )?;
+    rebuild_table(conn, "clipboard_entries", &entries_table_sql(" ON DELETE RESTRICT"), "1")?;
+    let live_entry = "entry_id IN (SELECT id FROM clipboard_entries)";
+    rebuild_table(
+        conn,
+        "collection_entries",
+        &collection_entries_table_sql(" ON DELETE CASCADE"),
+        &format!("{} AND collection_id IN (SELECT id FROM collections)", live_entry),
+    )?;
+    rebuild_table(conn, "entry_tags", &entry_tags_table_sql(" ON DELETE CASCADE"), live_entry)?;
+    rebuild_table(conn, "entry_events", &entry_events_table_sql(" ON DELETE CASCADE"), live_entry)?;
+    // The cascades replace the triggers that used to clear links, tags and events
+    conn.execute_batch(REBUILT_INDEXES)
+}
+
+fn foreign_keys_down(conn: &Connection) -> Result<()> {
+    rebuild_table(conn, "clipboard_entries", &entries_table_sql(""), "1")?;
+    rebuild_table(conn, "collection_entries", &collection_entries_table_sql(""), "1")?;
+    rebuild_table(conn, "entry_tags", &entry_tags_table_sql(""), "1")?;
+    rebuild_table(conn, "entry_events", &entry_events_table_sql(""), "1")?;
+    conn.execute_batch(REBUILT_INDEXES)?;
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS trg_entries_delete_collection_links
+            AFTER DELETE ON clipboard_entries
+            BEGIN
+                DELETE FROM collection_entries WHERE entry_id = OLD.id;
+            END;
+        CREATE TRIGGER IF NOT EXISTS trg_entries_delete_tags
+            AFTER DELETE ON clipboard_entries
+            BEGIN
+                DELETE FROM entry_tags WHERE entry_id = OLD.id;
+            END;
+        CREATE TRIGGER IF NOT EXISTS trg_entries_delete_events
+            AFTER DELETE ON clipboard_entries
+            BEGIN
+                DELETE FROM entry_events WHERE entry_id = OLD.id;
+            END;",
+    )
+}
+
+/// `apps.icon_stamp` records the exe modification time and size the icon was extracted at, so it
+/// can be refreshed after the app updates. Existing icons have no stamp and are replaced on the
+/// next capture from their app.
+fn icon_stamp_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE apps ADD COLUMN icon_stamp TEXT")
+}
+
+fn icon_stamp_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE apps DROP COLUMN icon_stamp")
+}
+
+/// Entries made from another one (AI summaries, translations) point back at it. The index keeps
+/// the `SET NULL` action from scanning the table on every delete.
+fn derived_entries_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN derived_from INTEGER REFERENCES clipboard_entries(id) ON DELETE SET NULL;
+         ALTER TABLE clipboard_entries ADD COLUMN derived_kind TEXT;
+         CREATE INDEX IF NOT EXISTS idx_entries_derived_from ON clipboard_entries(derived_from);",
+    )
+}
+
+/// A column used by a foreign key cannot be dropped, so the table is rebuilt without it.
+fn derived_entries_down(conn: &Connection) -> Result<()> {
+    rebuild_table(conn, "clipboard_entries", &entries_table_sql(" ON DELETE RESTRICT"), "1")?;
+    conn.execute_batch(REBUILT_INDEXES)
+}
+
+/// Values and patterns the user approved as not sensitive. `hash` rows hold
+/// `sensitive::value_hash` of a match, `pattern` rows a regex a whole match may fit.
+fn sensitive_allowlist_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sensitive_allowlist (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL CHECK (kind IN ('hash', 'pattern')),
+            value TEXT NOT NULL,
+            label TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            UNIQUE (kind, value)
+        );",
+    )
+}
+
+fn sensitive_allowlist_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("DROP TABLE IF EXISTS sensitive_allowlist")
+}
+
+/// What made an entry sensitive: a detector category such as "card" or "phone", or "manual" when
+/// the user set or cleared the flag, which re-scans leave alone. Flags from before this column
+/// have no kind and are re-evaluated by the next re-scan.
+fn sensitive_kind_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE clipboard_entries ADD COLUMN sensitive_kind TEXT")
+}
+
+fn sensitive_kind_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE clipboard_entries DROP COLUMN sensitive_kind")
+}
+
+fn locked_entries_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE clipboard_entries ADD COLUMN is_locked INTEGER NOT NULL DEFAULT 0")
+}
+
+fn locked_entries_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE clipboard_entries DROP COLUMN is_locked")
+}
+
+/// Related entries, linked automatically when captured close together from the same app or by
+/// hand. Each pair is stored once, lower id first.
+fn entry_links_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS entry_links (
+            entry_id INTEGER NOT NULL REFERENCES clipboard_entries(id) ON DELETE CASCADE,
+            linked_id INTEGER NOT NULL REFERENCES clipboard_entries(id) ON DELETE CASCADE,
+            kind TEXT NOT NULL CHECK (kind IN ('auto', 'manual')),
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            PRIMARY KEY (entry_id, linked_id),
+            CHECK (entry_id < linked_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_entry_links_linked ON entry_links(linked_id);",
+    )
+}
+
+fn entry_links_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("DROP TABLE IF EXISTS entry_links")
+}
+
+/// Regexes that pull a workspace context out of the foreground window title; see `context`.
+/// Seeded with the VS Code folder, a GitHub repo and the "[PROJ-123] Summary - Jira" issue key.
+fn context_patterns_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r"CREATE TABLE IF NOT EXISTS context_patterns (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pattern TEXT NOT NULL UNIQUE,
+            label TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );
+        INSERT OR IGNORE INTO context_patterns (pattern, label) VALUES
+            ('^(?:.* - )?(.+?) - Visual Studio Code', 'VS Code folder'),
+            ('([\w.-]+/[\w.-]+) · GitHub', 'GitHub repository'),
+            ('\[([A-Z][A-Z0-9]+-\d+)\]', 'Jira issue');",
+    )
+}
+
+fn context_patterns_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("DROP TABLE IF EXISTS context_patterns")
+}
+
+fn plain_text_capture_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE app_settings ADD COLUMN store_plain_text INTEGER NOT NULL DEFAULT 0")
+}
+
+fn plain_text_capture_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE app_settings DROP COLUMN store_plain_text")
+}
+
+/// Source domains pinned by the user. Their entries sort first in the domain list and are kept
+/// by retention like favorites.
+fn favorite_domains_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS favorite_domains (
+            domain TEXT PRIMARY KEY,
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        );",
+    )
+}
+
+fn favorite_domains_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("DROP TABLE IF EXISTS favorite_domains")
+}
+
+/// User-set sidebar details for each app: a note, an accent color and a manual position.
+fn app_metadata_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE apps ADD COLUMN note TEXT;
+         ALTER TABLE apps ADD COLUMN color TEXT;
+         ALTER TABLE apps ADD COLUMN sort_order INTEGER;",
+    )
+}
+
+fn app_metadata_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE apps DROP COLUMN note;
+         ALTER TABLE apps DROP COLUMN color;
+         ALTER TABLE apps DROP COLUMN sort_order;",
+    )
+}
+
+fn hidden_apps_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE apps ADD COLUMN is_hidden INTEGER NOT NULL DEFAULT 0")
+}
+
+fn hidden_apps_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE apps DROP COLUMN is_hidden")
+}
+
+/// Content types the app notifies about, overriding `notify_types`; NULL follows the global setting.
+fn app_notify_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE app_settings ADD COLUMN notify TEXT")
+}
+
+fn app_notify_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE app_settings DROP COLUMN notify")
+}
+
+/// Cells of clips detected as tables, as a JSON array of rows, for copying back in other formats.
+fn entry_tables_up(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS entry_tables (
+            entry_id INTEGER PRIMARY KEY REFERENCES clipboard_entries(id) ON DELETE CASCADE,
+            cells TEXT NOT NULL
+        );",
+    )
+}
+
+fn entry_tables_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch("DROP TABLE IF EXISTS entry_tables")
+}
+
+/// Image files are named by content hash and shared between entries, so each file's entries are
+/// counted and the file is only removed once none is left (see `Database::release_images`).
+/// Builds that kept one file per entry created the table outside the versioned migrations, so it
+/// is recounted from the entries rather than trusted.
+fn image_refs_up(conn: &Connection) -> Result<()> {
+    image_refs_down(conn)?;
+    conn.execute_batch(
+        "CREATE TABLE image_refs (
+            filename TEXT PRIMARY KEY,
+            ref_count INTEGER NOT NULL DEFAULT 0
+        );
+        INSERT INTO image_refs (filename, ref_count)
+            SELECT image_path, COUNT(*) FROM clipboard_entries WHERE image_path IS NOT NULL GROUP BY image_path;
+        CREATE TRIGGER trg_entries_insert_image_ref
+            AFTER INSERT ON clipboard_entries WHEN NEW.image_path IS NOT NULL
+            BEGIN
+                INSERT INTO image_refs (filename, ref_count) VALUES (NEW.image_path, 1)
+                    ON CONFLICT(filename) DO UPDATE SET ref_count = ref_count + 1;
+            END;
+        CREATE TRIGGER trg_entries_delete_image_ref
+            AFTER DELETE ON clipboard_entries WHEN OLD.image_path IS NOT NULL
+            BEGIN
+                UPDATE image_refs SET ref_count = ref_count - 1 WHERE filename = OLD.image_path;
+            END;
+        CREATE TRIGGER trg_entries_update_image_ref
+            AFTER UPDATE OF image_path ON clipboard_entries
+            WHEN OLD.image_path IS NOT NEW.image_path
+            BEGIN
+                UPDATE image_refs SET ref_count = ref_count - 1 WHERE filename = OLD.image_path;
+                INSERT INTO image_refs (filename, ref_count) SELECT NEW.image_path, 1 WHERE NEW.image_path IS NOT NULL
+                    ON CONFLICT(filename) DO UPDATE SET ref_count = ref_count + 1;
+            END;",
+    )
+}
+
+fn image_refs_down(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "DROP TRIGGER IF EXISTS trg_entries_insert_image_ref;
+         DROP TRIGGER IF EXISTS trg_entries_delete_image_ref;
+         DROP TRIGGER IF EXISTS trg_entries_update_image_ref;
+         DROP TABLE IF EXISTS image_refs;",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = OFF;").unwrap();
+        conn
+    }
+
+    /// Tables with their columns and foreign keys, plus index and trigger names, in a form that
+    /// ignores column order and the SQL text a rebuilt table was created from.
+    fn schema(conn: &Connection) -> Vec<String> {
+        let objects: Vec<(String, String)> = conn
+            .prepare("SELECT type, name FROM sqlite_master WHERE name NOT LIKE 'sqlite_%' ORDER BY type, name")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        let mut schema = Vec::new();
+        for (kind, name) in objects {
+            schema.push(format!("{} {}", kind, name));
+            if kind != "table" {
+                continue;
+            }
+            let mut columns: Vec<String> = conn
+                .prepare("SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1)")
+                .unwrap()
+                .query_map([&name], |row| {
+                    Ok(format!(
+                        "{}.{} {} notnull={} default={:?} pk={}",
+                        name,
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, i64>(4)?
+                    ))
+                })
+                .unwrap()
+                .collect::<Result<_>>()
+                .unwrap();
+            columns.sort();
+            schema.extend(columns);
+            let mut keys: Vec<String> = conn
+                .prepare("SELECT \"from\", \"table\", \"to\", on_delete FROM pragma_foreign_key_list(?1)")
+                .unwrap()
+                .query_map([&name], |row| {
+                    Ok(format!(
+                        "{}.{} -> {}.{} on delete {}",
+                        name,
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                        row.get::<_, String>(3)?
+                    ))
+                })
+                .unwrap()
+                .collect::<Result<_>>()
+                .unwrap();
+            keys.sort();
+            schema.extend(keys);
+        }
+        schema
+    }
+
+    #[test]
+    fn every_migration_goes_up_and_down() {
+        let conn = open();
+        let mut schemas = Vec::new();
+        for migration in MIGRATIONS {
+            apply_pending(&conn, migration.version).unwrap();
+            assert_eq!(current_version(&conn).unwrap(), migration.version);
+            let after = schema(&conn);
+            assert_ne!(schemas.last(), Some(&after), "migration {} changed nothing", migration.version);
+            schemas.push(after);
+        }
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(1).rev() {
+            revert_to(&conn, migration.version - 1).unwrap();
+            assert_eq!(current_version(&conn).unwrap(), migration.version - 1);
+            assert_eq!(schema(&conn), schemas[i - 1], "after reverting migration {}", migration.version);
+        }
+
+        // The baseline has no down step
+        assert!(revert_to(&conn, 0).is_err());
+        assert_eq!(current_version(&conn).unwrap(), 1);
+
+        run(&conn).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert_eq!(&schema(&conn), schemas.last().unwrap());
+    }
+
+    #[test]
+    fn run_is_a_no_op_when_up_to_date() {
+        let conn = open();
+        run(&conn).unwrap();
+        let before = schema(&conn);
+        run(&conn).unwrap();
+        assert_eq!(schema(&conn), before);
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn newer_schema_is_left_alone() {
+        let conn = open();
+        run(&conn).unwrap();
+        set_version(&conn, latest_version() + 1).unwrap();
+        run(&conn).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), latest_version() + 1);
+    }
+
+    #[test]
+    fn foreign_keys_up_repairs_orphans() {
+        let conn = open();
+        apply_pending(&conn, 1).unwrap();
+        conn.execute_batch(
+            "INSERT INTO apps (id, name, exe_path) VALUES (1, 'Editor', 'C:\\editor.exe');
+             INSERT INTO clipboard_entries (id, app_id, content_type, text_content) VALUES
+                 (10, 1, 'text', 'kept'),
+                 (11, 42, 'text', 'app row gone');
+             INSERT INTO collections (id, name) VALUES (1, 'Snippets');
+             INSERT INTO collection_entries (collection_id, entry_id) VALUES (1, 10), (1, 99), (7, 10);
+             INSERT INTO entry_tags (entry_id, tag) VALUES (10, 'keep'), (99, 'orphan');
+             INSERT INTO entry_events (entry_id, kind) VALUES (10, 'captured'), (99, 'captured');",
+        )
+        .unwrap();
+
+        apply_pending(&conn, 2).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 2);
+
+        let placeholder: (String, String) = conn
+            .query_row("SELECT name, exe_path FROM apps WHERE id = 42", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(placeholder, ("Unknown".to_string(), "unknown://42".to_string()));
+        let count = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM clipboard_entries"), 2);
+        assert_eq!(count("SELECT COUNT(*) FROM collection_entries"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM entry_tags"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM entry_events"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM pragma_foreign_key_check"), 0);
+
+        // Links, tags and events now go with their entry
+        conn.execute_batch("PRAGMA foreign_keys = ON; DELETE FROM clipboard_entries WHERE id = 10;").unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM collection_entries"), 0);
+        assert_eq!(count("SELECT COUNT(*) FROM entry_tags"), 0);
+        assert_eq!(count("SELECT COUNT(*) FROM entry_events"), 0);
+    }
+}
@@ -0,0 +1,81 @@
+//! Text recognition in images with the OCR engine built into Windows, using the user's profile
+//! languages.
+
+use crate::error::CutboardError;
+
+/// A recognized word and its box in image pixels.
+pub struct Word {
+    pub text: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The words of each recognized line, left to right.
+#[cfg(windows)]
+pub fn recognize(img: &image::RgbaImage) -> Result<Vec<Vec<Word>>, CutboardError> {
+    use windows::Graphics::Imaging::{BitmapPixelFormat, SoftwareBitmap};
+    use windows::Media::Ocr::OcrEngine;
+    use windows::Storage::Streams::DataWriter;
+
+    let _t = crate::metrics::timer("image.ocr");
+    let engine = OcrEngine::TryCreateFromUserProfileLanguages()
+        .map_err(|_| CutboardError::unsupported("No OCR language is installed"))?;
+
+    // The engine rejects larger images, so they are scaled down and the boxes scaled back up
+    let max_side = OcrEngine::MaxImageDimension().map_err(|e| e.to_string())?;
+    let (width, height) = img.dimensions();
+    let scale = if width.max(height) > max_side { max_side as f32 / width.max(height) as f32 } else { 1.0 };
+    let scaled;
+    let source = if scale < 1.0 {
+        let w = ((width as f32 * scale) as u32).max(1);
+        let h = ((height as f32 * scale) as u32).max(1);
+        scaled = image::imageops::resize(img, w, h, image::imageops::FilterType::Triangle);
+        &scaled
+    } else {
+        img
+    };
+
+    let result = (|| -> windows::core::Result<Vec<Vec<Word>>> {
+        let mut bgra = source.as_raw().clone();
+        for px in bgra.chunks_exact_mut(4) {
+            px.swap(0, 2);
+        }
+        let writer = DataWriter::new()?;
+        writer.WriteBytes(&bgra)?;
+        let bitmap = SoftwareBitmap::CreateCopyFromBuffer(
+            &writer.DetachBuffer()?,
+            BitmapPixelFormat::Bgra8,
+            source.width() as i32,
+            source.height() as i32,
+        )?;
+        let ocr = engine.RecognizeAsync(&bitmap)?.get()?;
+
+        let mut lines = Vec::new();
+        let ocr_lines = ocr.Lines()?;
+        for i in 0..ocr_lines.Size()? {
+            let ocr_words = ocr_lines.GetAt(i)?.Words()?;
+            let mut words = Vec::new();
+            for j in 0..ocr_words.Size()? {
+                let word = ocr_words.GetAt(j)?;
+                let rect = word.BoundingRect()?;
+                words.push(Word {
+                    text: word.Text()?.to_string(),
+                    x: (rect.X / scale) as u32,
+                    y: (rect.Y / scale) as u32,
+                    width: (rect.Width / scale).ceil() as u32,
+                    height: (rect.Height / scale).ceil() as u32,
+                });
+            }
+            lines.push(words);
+        }
+        Ok(lines)
+    })();
+    result.map_err(|e| CutboardError::from(format!("Text recognition failed: {}", e)))
+}
+
+#[cfg(not(windows))]
+pub fn recognize(_img: &image::RgbaImage) -> Result<Vec<Vec<Word>>, CutboardError> {
+    Err(CutboardError::unsupported("Text recognition is only available on Windows"))
+}
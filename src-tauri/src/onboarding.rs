@@ -0,0 +1,50 @@
+//! First-run support: demo content for the UI tour and detection of other clipboard managers.
+
+use crate::database::{Database, EntryOrigin, TextCapture};
+
+/// Sample apps live under this pseudo exe path so they never merge with real captures.
+pub const SAMPLE_APP_PREFIX: &str = "cutboard://sample/";
+
+/// (app name, app key, text, source url)
+const SAMPLE_ENTRIES: &[(&str, &str, &str, Option<&str>)] = &[
+    ("Browser", "browser", "https://github.com/ericsongcz/cutboard", Some("https://github.com/ericsongcz/cutboard")),
+    ("Browser", "browser", "Clipboard history keeps everything you copy, grouped by the app it came from.", Some("https://example.com/docs/getting-started")),
+    ("Editor", "editor", "fn main() {\n    println!(\"Hello, CutBoard!\");\n}", None),
+    ("Editor", "editor", "# Meeting notes\n\n- Ship the release on Friday\n- Update the changelog\n- Thank the testers", None),
+    ("Chat", "chat", "Can you send me the link to the design doc?", None),
+    ("Chat", "chat", "192.168.1.42", None),
+];
+
+/// Inserts the demo entries and returns how many were added.
+pub fn generate_sample_data(db: &Database) -> rusqlite::Result<usize> {
+    for (name, key, text, url) in SAMPLE_ENTRIES {
+        let exe = format!("{}{}", SAMPLE_APP_PREFIX, key);
+        let (app_id, _) = db.get_or_create_app(name, &exe, None, None)?;
+        let hash = crate::clipboard::compute_content_hash(text.as_bytes());
+        db.upsert_text_entry_with_html(app_id, &TextCapture { text, hash: &hash, ..Default::default() }, *url, &EntryOrigin::default())?;
+    }
+    Ok(SAMPLE_ENTRIES.len())
+}
+
+/// Executable names of clipboard managers that also watch the clipboard and may fight over it.
+const KNOWN_CLIPBOARD_MANAGERS: &[(&str, &str)] = &[
+    ("ditto.exe", "Ditto"),
+    ("copyq.exe", "CopyQ"),
+    ("clipboardfusion.exe", "ClipboardFusion"),
+    ("clipclip.exe", "ClipClip"),
+    ("clipmate.exe", "ClipMate"),
+    ("clipdiary.exe", "Clipdiary"),
+    ("arsclip.exe", "ArsClip"),
+    ("1clipboard.exe", "1Clipboard"),
+    ("clipangel.exe", "ClipAngel"),
+];
+
+/// Names of known clipboard managers that are currently running.
+pub fn detect_conflicting_managers() -> Vec<String> {
+    let running = crate::window_tracker::running_process_names();
+    KNOWN_CLIPBOARD_MANAGERS
+        .iter()
+        .filter(|(exe, _)| running.iter().any(|r| r.eq_ignore_ascii_case(exe)))
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
@@ -0,0 +1,249 @@
+//! Printing entries on the default printer through GDI. Text is set in a monospaced font and
+//! wrapped to the page; images are scaled down to fit, never up past their on-screen size.
+
+use crate::error::CutboardError;
+
+#[cfg(windows)]
+const FONT_POINTS: i32 = 10;
+
+/// A started print job on the default printer. Dropping it before `finish` cancels the job.
+#[cfg(windows)]
+struct PrintJob {
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    finished: bool,
+}
+
+#[cfg(windows)]
+impl PrintJob {
+    fn start(doc_name: &str) -> Result<Self, CutboardError> {
+        use windows::core::{w, PCWSTR, PWSTR};
+        use windows::Win32::Graphics::Gdi::{CreateDCW, DeleteDC};
+        use windows::Win32::Graphics::Printing::GetDefaultPrinterW;
+        use windows::Win32::Storage::Xps::{StartDocW, DOCINFOW};
+
+        unsafe {
+            let mut len = 0u32;
+            let _ = GetDefaultPrinterW(None, &mut len);
+            let mut name = vec![0u16; len as usize];
+            if len == 0 || !GetDefaultPrinterW(Some(PWSTR(name.as_mut_ptr())), &mut len).as_bool() {
+                return Err(CutboardError::not_found("No default printer is set"));
+            }
+            let hdc = CreateDCW(w!("WINSPOOL"), PCWSTR(name.as_ptr()), PCWSTR::null(), None);
+            if hdc.is_invalid() {
+                return Err("The default printer could not be opened".into());
+            }
+            let doc: Vec<u16> = doc_name.encode_utf16().chain(std::iter::once(0)).collect();
+            let info = DOCINFOW {
+                cbSize: std::mem::size_of::<DOCINFOW>() as i32,
+                lpszDocName: PCWSTR(doc.as_ptr()),
+                ..Default::default()
+            };
+            if StartDocW(hdc, &info) <= 0 {
+                let _ = DeleteDC(hdc);
+                return Err("The print job could not be started".into());
+            }
+            Ok(PrintJob { hdc, finished: false })
+        }
+    }
+
+    /// Printable area inside half-inch margins, as (left, top, right, bottom) in device units.
+    fn area(&self) -> (i32, i32, i32, i32) {
+        use windows::Win32::Graphics::Gdi::{GetDeviceCaps, HORZRES, LOGPIXELSX, LOGPIXELSY, VERTRES};
+
+        unsafe {
+            let margin_x = GetDeviceCaps(Some(self.hdc), LOGPIXELSX) / 2;
+            let margin_y = GetDeviceCaps(Some(self.hdc), LOGPIXELSY) / 2;
+            let width = GetDeviceCaps(Some(self.hdc), HORZRES);
+            let height = GetDeviceCaps(Some(self.hdc), VERTRES);
+            (margin_x, margin_y, width - margin_x, height - margin_y)
+        }
+    }
+
+    fn start_page(&self) -> Result<(), CutboardError> {
+        if unsafe { windows::Win32::Storage::Xps::StartPage(self.hdc) } <= 0 {
+            return Err("The printer rejected the page".into());
+        }
+        Ok(())
+    }
+
+    fn end_page(&self) -> Result<(), CutboardError> {
+        if unsafe { windows::Win32::Storage::Xps::EndPage(self.hdc) } <= 0 {
+            return Err("The printer rejected the page".into());
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), CutboardError> {
+        self.finished = true;
+        if unsafe { windows::Win32::Storage::Xps::EndDoc(self.hdc) } <= 0 {
+            return Err("The print job could not be completed".into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Drop for PrintJob {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.finished {
+                windows::Win32::Storage::Xps::AbortDoc(self.hdc);
+            }
+            let _ = windows::Win32::Graphics::Gdi::DeleteDC(self.hdc);
+        }
+    }
+}
+
+/// Splits `line` into pieces that fit in `width` device units, breaking after a space when one
+/// falls on the piece.
+#[cfg(windows)]
+unsafe fn wrap(hdc: windows::Win32::Graphics::Gdi::HDC, line: &[u16], width: i32) -> Vec<&[u16]> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::SIZE;
+    use windows::Win32::Graphics::Gdi::GetTextExtentExPointW;
+
+    if line.is_empty() {
+        return vec![line];
+    }
+    let mut pieces = Vec::new();
+    let mut rest = line;
+    while !rest.is_empty() {
+        let mut fit = 0i32;
+        let mut size = SIZE::default();
+        let _ = GetTextExtentExPointW(hdc, PCWSTR(rest.as_ptr()), rest.len() as i32, width, Some(&mut fit), None, &mut size);
+        let fit = (fit.max(1) as usize).min(rest.len());
+        let mut cut = if fit < rest.len() {
+            rest[..fit].iter().rposition(|&c| c == b' ' as u16).map_or(fit, |i| i + 1)
+        } else {
+            fit
+        };
+        // Keep surrogate pairs together
+        if cut > 1 && cut < rest.len() && (0xD800..0xDC00).contains(&rest[cut - 1]) {
+            cut -= 1;
+        }
+        pieces.push(&rest[..cut]);
+        rest = &rest[cut..];
+    }
+    pieces
+}
+
+#[cfg(windows)]
+pub fn print_text(doc_name: &str, text: &str) -> Result<(), CutboardError> {
+    use windows::core::w;
+    use windows::Win32::Graphics::Gdi::*;
+
+    let job = PrintJob::start(doc_name)?;
+    let (left, top, right, bottom) = job.area();
+    unsafe {
+        let dpi = GetDeviceCaps(Some(job.hdc), LOGPIXELSY);
+        let font = CreateFontW(
+            -(FONT_POINTS * dpi / 72),
+            0,
+            0,
+            0,
+            FW_NORMAL.0 as i32,
+            0,
+            0,
+            0,
+            DEFAULT_CHARSET,
+            OUT_DEFAULT_PRECIS,
+            CLIP_DEFAULT_PRECIS,
+            DEFAULT_QUALITY,
+            (FIXED_PITCH.0 | FF_MODERN.0) as u32,
+            w!("Consolas"),
+        );
+        let previous = SelectObject(job.hdc, font.into());
+        let mut metrics = TEXTMETRICW::default();
+        let _ = GetTextMetricsW(job.hdc, &mut metrics);
+        let line_height = (metrics.tmHeight + metrics.tmExternalLeading).max(1);
+
+        let result = (|| {
+            job.start_page()?;
+            let mut y = top;
+            for line in text.lines() {
+                let wide: Vec<u16> = line.replace('\t', "    ").encode_utf16().collect();
+                for piece in wrap(job.hdc, &wide, right - left) {
+                    if y + line_height > bottom {
+                        job.end_page()?;
+                        job.start_page()?;
+                        y = top;
+                    }
+                    let _ = TextOutW(job.hdc, left, y, piece);
+                    y += line_height;
+                }
+            }
+            job.end_page()
+        })();
+        SelectObject(job.hdc, previous);
+        let _ = DeleteObject(font.into());
+        result?;
+    }
+    job.finish()
+}
+
+#[cfg(windows)]
+pub fn print_image(doc_name: &str, path: &std::path::Path) -> Result<(), CutboardError> {
+    use windows::Win32::Graphics::Gdi::*;
+
+    let img = image::open(path).map_err(|e| e.to_string())?.to_rgba8();
+    let (w, h) = (img.width() as i32, img.height() as i32);
+    // GDI wants BGRA
+    let mut bits = img.into_raw();
+    for px in bits.chunks_exact_mut(4) {
+        px.swap(0, 2);
+    }
+
+    let job = PrintJob::start(doc_name)?;
+    let (left, top, right, bottom) = job.area();
+    unsafe {
+        let natural = GetDeviceCaps(Some(job.hdc), LOGPIXELSX) as f64 / 96.0;
+        let scale = ((right - left) as f64 / w as f64).min((bottom - top) as f64 / h as f64).min(natural);
+        let (dw, dh) = ((w as f64 * scale) as i32, (h as f64 * scale) as i32);
+
+        let info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: w,
+                // Negative for rows stored top-down
+                biHeight: -h,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        job.start_page()?;
+        SetStretchBltMode(job.hdc, HALFTONE);
+        let drawn = StretchDIBits(
+            job.hdc,
+            left,
+            top,
+            dw,
+            dh,
+            0,
+            0,
+            w,
+            h,
+            Some(bits.as_ptr().cast()),
+            &info,
+            DIB_RGB_COLORS,
+            SRCCOPY,
+        );
+        if drawn == 0 {
+            return Err("The image could not be sent to the printer".into());
+        }
+        job.end_page()?;
+    }
+    job.finish()
+}
+
+#[cfg(not(windows))]
+pub fn print_text(_doc_name: &str, _text: &str) -> Result<(), CutboardError> {
+    Err(CutboardError::unsupported("Printing is only supported on Windows"))
+}
+
+#[cfg(not(windows))]
+pub fn print_image(_doc_name: &str, _path: &std::path::Path) -> Result<(), CutboardError> {
+    Err(CutboardError::unsupported("Printing is only supported on Windows"))
+}
@@ -0,0 +1,56 @@
+//! Builds the `WHERE ... LIMIT` part of `Database` queries from optional filters. Placeholders are
+//! numbered as filters are added, and a given combination of filters always yields the same SQL,
+//! so the text can key rusqlite's prepared statement cache.
+
+use rusqlite::ToSql;
+
+pub struct Query<'a> {
+    conditions: Vec<String>,
+    params: Vec<&'a dyn ToSql>,
+    tail: String,
+}
+
+impl<'a> Query<'a> {
+    pub fn new() -> Self {
+        Self { conditions: Vec::new(), params: Vec::new(), tail: String::new() }
+    }
+
+    fn bind(&mut self, value: &'a dyn ToSql) -> String {
+        self.params.push(value);
+        format!("?{}", self.params.len())
+    }
+
+    /// Adds a condition; every `{p}` in `template` becomes the placeholder bound to `value`.
+    pub fn filter(mut self, template: &str, value: &'a dyn ToSql) -> Self {
+        let placeholder = self.bind(value);
+        self.conditions.push(template.replace("{p}", &placeholder));
+        self
+    }
+
+    pub fn filter_if(self, apply: bool, template: &str, value: &'a dyn ToSql) -> Self {
+        if apply { self.filter(template, value) } else { self }
+    }
+
+    pub fn page(mut self, limit: &'a i64, offset: &'a i64) -> Self {
+        let limit = self.bind(limit);
+        let offset = self.bind(offset);
+        self.tail = format!(" LIMIT {} OFFSET {}", limit, offset);
+        self
+    }
+
+    /// `select` is everything before `WHERE`; `order` an optional `ORDER BY` clause.
+    pub fn sql(&self, select: &str, order: &str) -> String {
+        let mut sql = String::from(select);
+        if !self.conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&self.conditions.join(" AND "));
+        }
+        sql.push_str(order);
+        sql.push_str(&self.tail);
+        sql
+    }
+
+    pub fn params(&self) -> &[&'a dyn ToSql] {
+        &self.params
+    }
+}
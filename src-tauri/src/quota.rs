@@ -0,0 +1,72 @@
+//! Keeps history within `max_database_size_mb` by evicting the oldest non-favorite, unlocked
+//! entries. The size counts the pages the database uses plus the image files, since screenshots
+//! are usually most of the footprint. Run from the maintenance timer, so the cap can be exceeded
+//! briefly.
+
+use crate::database::Database;
+use serde::Serialize;
+use std::collections::HashSet;
+
+const CANDIDATE_BATCH: i64 = 200;
+
+/// Payload of the `quota-enforced` event.
+#[derive(Serialize, Clone)]
+pub struct QuotaReport {
+    pub removed: usize,
+    /// Estimated; image files shared with kept entries stay on disk.
+    pub freed_bytes: u64,
+    pub entry_ids: Vec<i64>,
+    pub limit_bytes: u64,
+}
+
+fn dir_size(dir: &std::path::Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().filter_map(|e| e.metadata().ok()).filter(|m| m.is_file()).map(|m| m.len()).sum())
+        .unwrap_or(0)
+}
+
+/// Returns `None` when the cap is off or not exceeded.
+pub fn enforce(db: &Database, max_mb: u32) -> Result<Option<QuotaReport>, String> {
+    if max_mb == 0 {
+        return Ok(None);
+    }
+    let limit_bytes = max_mb as u64 * 1024 * 1024;
+    let images_dir = db.images_dir();
+    let used = db.used_bytes().map_err(|e| e.to_string())?.max(0) as u64 + dir_size(&images_dir);
+    if used <= limit_bytes {
+        return Ok(None);
+    }
+    let excess = used - limit_bytes;
+
+    let mut ids = Vec::new();
+    let mut counted_images = HashSet::new();
+    let mut freed = 0u64;
+    let mut offset = 0;
+    while freed < excess {
+        let batch = db.oldest_evictable_entries(CANDIDATE_BATCH, offset).map_err(|e| e.to_string())?;
+        if batch.is_empty() {
+            break;
+        }
+        offset += batch.len() as i64;
+        for candidate in batch {
+            freed += candidate.bytes.max(0) as u64;
+            if let Some(image) = candidate.image_path {
+                if counted_images.insert(image.clone()) {
+                    freed += std::fs::metadata(images_dir.join(&image)).map(|m| m.len()).unwrap_or(0);
+                }
+            }
+            ids.push(candidate.id);
+            if freed >= excess {
+                break;
+            }
+        }
+    }
+    if ids.is_empty() {
+        return Ok(None);
+    }
+
+    for file in db.delete_entries(&ids).map_err(|e| e.to_string())? {
+        std::fs::remove_file(images_dir.join(&file)).ok();
+    }
+    Ok(Some(QuotaReport { removed: ids.len(), freed_bytes: freed, entry_ids: ids, limit_bytes }))
+}
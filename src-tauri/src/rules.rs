@@ -0,0 +1,182 @@
+use crate::database::RuleInfo;
+use fancy_regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// All set conditions must hold for a rule to fire; unset ones are ignored.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RuleConditions {
+    /// Source domain, also matching subdomains ("mycorp.com" matches "jira.mycorp.com").
+    pub domain: Option<String>,
+    /// App name or executable file name, case-insensitive.
+    pub app: Option<String>,
+    /// "text" or "image".
+    pub content_type: Option<String>,
+    pub text_pattern: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RuleActions {
+    pub tags: Vec<String>,
+    pub favorite: bool,
+    /// Delete the entry this many days after capture.
+    pub retention_days: Option<i64>,
+    pub transform: Option<Transform>,
+    /// Also put the transformed text back on the clipboard, not just into history.
+    pub apply_to_clipboard: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Transform {
+    Trim,
+    Uppercase,
+    Lowercase,
+    RegexReplace { pattern: String, replacement: String },
+    /// Strips the indentation all lines share.
+    Dedent,
+    StraightQuotes,
+    StripLineNumbers,
+    /// Strips shell and REPL prompts such as `$ ` and `>>> `.
+    StripPrompts,
+    /// All of the code clean-ups above.
+    CleanCode,
+    /// Converts the HTML flavor when there is one, otherwise the text as HTML source.
+    HtmlToMarkdown,
+    MarkdownToHtml,
+    /// Rewrites a table copied as text (CSV, semicolons, pipes, aligned columns) as tab separated
+    /// cells, so pasting into a spreadsheet fills one column per cell.
+    ToTsv,
+}
+
+/// What is known about a clip at capture time.
+pub struct CaptureContext<'a> {
+    pub app_name: &'a str,
+    pub exe_path: &'a str,
+    pub content_type: &'a str,
+    pub text: Option<&'a str>,
+    /// HTML flavor of `text`, if the clipboard had one.
+    pub html: Option<&'a str>,
+    pub source_url: Option<&'a str>,
+}
+
+/// Combined effect of every matching rule.
+#[derive(Default)]
+pub struct RuleOutcome {
+    pub tags: Vec<String>,
+    pub favorite: bool,
+    pub retention_days: Option<i64>,
+    /// Replacement text when a transform changed it.
+    pub text: Option<String>,
+    pub apply_to_clipboard: bool,
+}
+
+/// Checks that every pattern in the rule compiles, so bad rules are rejected when saved.
+pub fn validate(conditions: &RuleConditions, actions: &RuleActions) -> Result<(), String> {
+    if let Some(p) = &conditions.text_pattern {
+        Regex::new(p).map_err(|e| format!("Invalid text pattern: {}", e))?;
+    }
+    if let Some(Transform::RegexReplace { pattern, .. }) = &actions.transform {
+        Regex::new(pattern).map_err(|e| format!("Invalid transform pattern: {}", e))?;
+    }
+    if actions.retention_days.is_some_and(|d| d <= 0) {
+        return Err("Retention must be at least one day".into());
+    }
+    Ok(())
+}
+
+/// Runs enabled rules in order. Later rules see text already transformed by earlier ones.
+pub fn evaluate(rules: &[RuleInfo], ctx: &CaptureContext) -> RuleOutcome {
+    let mut outcome = RuleOutcome::default();
+    let mut text = ctx.text.map(str::to_string);
+
+    for rule in rules.iter().filter(|r| r.enabled) {
+        if !matches(&rule.conditions, ctx, text.as_deref()) {
+            continue;
+        }
+        let actions = &rule.actions;
+        for tag in &actions.tags {
+            if !outcome.tags.contains(tag) {
+                outcome.tags.push(tag.clone());
+            }
+        }
+        outcome.favorite |= actions.favorite;
+        if let Some(days) = actions.retention_days {
+            outcome.retention_days = Some(outcome.retention_days.map_or(days, |d| d.min(days)));
+        }
+        if let (Some(transform), Some(current)) = (&actions.transform, text.as_deref()) {
+            // Once an earlier rule rewrote the text, the HTML flavor no longer matches it
+            let html = ctx.html.filter(|_| text.as_deref() == ctx.text);
+            let transformed = apply_transform(transform, current, html);
+            if transformed != current {
+                text = Some(transformed);
+                outcome.apply_to_clipboard |= actions.apply_to_clipboard;
+            }
+        }
+    }
+
+    if text.as_deref() != ctx.text {
+        outcome.text = text;
+    }
+    outcome
+}
+
+fn matches(cond: &RuleConditions, ctx: &CaptureContext, text: Option<&str>) -> bool {
+    if let Some(want) = cond.content_type.as_deref().filter(|s| !s.is_empty()) {
+        if want != ctx.content_type {
+            return false;
+        }
+    }
+    if let Some(want) = cond.domain.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        let want = want.to_lowercase();
+        let domain = ctx.source_url.map(crate::database::extract_domain).unwrap_or_default().to_lowercase();
+        if domain != want && !domain.ends_with(&format!(".{}", want)) {
+            return false;
+        }
+    }
+    if let Some(want) = cond.app.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        let file_name = std::path::Path::new(ctx.exe_path)
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !want.eq_ignore_ascii_case(ctx.app_name) && !want.eq_ignore_ascii_case(&file_name) {
+            return false;
+        }
+    }
+    if let Some(pattern) = cond.text_pattern.as_deref().filter(|s| !s.is_empty()) {
+        let Some(text) = text else {
+            return false;
+        };
+        match Regex::new(pattern) {
+            Ok(re) => {
+                if !re.is_match(text).unwrap_or(false) {
+                    return false;
+                }
+            }
+            Err(_) => return false,
+        }
+    }
+    true
+}
+
+/// `html` is the HTML flavor of `text`, used by `HtmlToMarkdown` when given.
+pub fn apply_transform(transform: &Transform, text: &str, html: Option<&str>) -> String {
+    match transform {
+        Transform::Trim => text.trim().to_string(),
+        Transform::Uppercase => text.to_uppercase(),
+        Transform::Lowercase => text.to_lowercase(),
+        Transform::RegexReplace { pattern, replacement } => match Regex::new(pattern) {
+            Ok(re) => re.replace_all(text, replacement.as_str()).into_owned(),
+            Err(_) => text.to_string(),
+        },
+        Transform::Dedent => crate::code::dedent(text),
+        Transform::StraightQuotes => crate::code::straighten_quotes(text),
+        Transform::StripLineNumbers => crate::code::strip_line_numbers(text),
+        Transform::StripPrompts => crate::code::strip_prompts(text),
+        Transform::CleanCode => crate::code::clean(text),
+        Transform::HtmlToMarkdown => crate::markdown::from_html(html.unwrap_or(text)),
+        Transform::MarkdownToHtml => crate::markdown::to_html(text),
+        Transform::ToTsv => crate::table::to_tsv(text),
+    }
+}
@@ -0,0 +1,51 @@
+//! Images copied by screenshot tools. They are tagged with the "screenshot" kind, which gives them
+//! their own view and retention, and can be kept at full size when other images are scaled down.
+
+/// Executables of common screenshot tools. `screenshot_tools` in the config adds more.
+const TOOLS: &[&str] = &[
+    "snippingtool.exe",
+    "screenclippinghost.exe",
+    "screensketch.exe",
+    "sharex.exe",
+    "greenshot.exe",
+    "lightshot.exe",
+    "snagit32.exe",
+    "snagiteditor.exe",
+    "flameshot.exe",
+    "picpick.exe",
+];
+
+/// Whether any of the given executables (clipboard owner, foreground app) is a screenshot tool.
+/// `extra` is a comma or semicolon separated list of file names, with or without ".exe".
+pub fn is_screenshot_tool<'a>(exe_paths: impl IntoIterator<Item = &'a str>, extra: &str) -> bool {
+    exe_paths.into_iter().any(|exe| {
+        let path = std::path::Path::new(exe);
+        let file_name = path.file_name().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+        TOOLS.contains(&file_name.as_str())
+            || extra
+                .split([',', ';'])
+                .map(|s| s.trim().to_lowercase())
+                .any(|s| !s.is_empty() && (s == file_name || s == stem))
+    })
+}
+
+/// Scales a PNG down so its longer side is at most `max_side` pixels. Returns `None` when it is
+/// already small enough, `max_side` is 0 or the image cannot be decoded.
+pub fn downscale_png(png: &[u8], max_side: u32) -> Option<Vec<u8>> {
+    if max_side == 0 {
+        return None;
+    }
+    // The header is enough to tell whether the image needs decoding at all
+    let reader = image::ImageReader::with_format(std::io::Cursor::new(png), image::ImageFormat::Png);
+    let (width, height) = reader.into_dimensions().ok()?;
+    if width.max(height) <= max_side {
+        return None;
+    }
+    let img = image::load_from_memory_with_format(png, image::ImageFormat::Png).ok()?;
+    let _t = crate::metrics::timer("image.downscale");
+    let scaled = img.resize(max_side, max_side, image::imageops::FilterType::Lanczos3);
+    let mut buf = Vec::new();
+    scaled.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png).ok()?;
+    Some(buf)
+}
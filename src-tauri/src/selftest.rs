@@ -0,0 +1,167 @@
+//! Self-test behind `run_self_test`: checks the parts that otherwise fail silently and says what
+//! the user can do about each problem.
+
+use crate::config::AppConfig;
+use crate::database::Database;
+use serde::Serialize;
+
+/// Free space on the images drive below which captures may soon stop being saved.
+const LOW_SPACE_BYTES: u64 = 500 * 1024 * 1024;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Serialize)]
+pub struct SelfTestCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or error.
+    pub action: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SelfTestReport {
+    /// The worst status of any check.
+    pub status: CheckStatus,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+fn check(name: &'static str, status: CheckStatus, detail: impl Into<String>, action: Option<&str>) -> SelfTestCheck {
+    SelfTestCheck { name, status, detail: detail.into(), action: action.map(str::to_string) }
+}
+
+/// Runs every check. The first hotkey registration retries for a few seconds after startup, so
+/// a report taken right away may still show it as missing.
+pub fn run(db: &Database, config: &AppConfig) -> SelfTestReport {
+    let checks = vec![hotkey(config), clipboard_listener(config), database(db), images(db, config), language(config)];
+    let status = checks.iter().map(|c| c.status).max().unwrap_or(CheckStatus::Ok);
+    SelfTestReport { status, checks }
+}
+
+fn hotkey(config: &AppConfig) -> SelfTestCheck {
+    let status = crate::hotkey::status();
+    let shortcut = if config.shortcut.is_empty() { "Alt+Q" } else { config.shortcut.as_str() };
+    if !status.thread_running {
+        return check("hotkey", CheckStatus::Error, "The hotkey thread is not running", Some("Restart CutBoard"));
+    }
+    if status.hook_mode && !status.hook_installed {
+        return check(
+            "hotkey",
+            CheckStatus::Error,
+            "The keyboard hook could not be installed",
+            Some("Switch the hotkey mode to register"),
+        );
+    }
+    if !status.hook_mode && !status.registered {
+        return check(
+            "hotkey",
+            CheckStatus::Error,
+            format!("{} could not be registered, another app may be using it", shortcut),
+            Some("Pick another shortcut, or switch the hotkey mode to hook"),
+        );
+    }
+    if status.double_tap && !status.hook_installed {
+        return check(
+            "hotkey",
+            CheckStatus::Warning,
+            "The double-tap gesture needs the keyboard hook, which is not installed",
+            Some("Restart CutBoard"),
+        );
+    }
+    check("hotkey", CheckStatus::Ok, format!("{} is active", shortcut), None)
+}
+
+fn clipboard_listener(config: &AppConfig) -> SelfTestCheck {
+    let (active, paused) = crate::clipboard::listener_status();
+    if !active {
+        return check(
+            "clipboard_listener",
+            CheckStatus::Error,
+            "Clipboard changes are not being received",
+            Some("Restart CutBoard"),
+        );
+    }
+    if paused {
+        return check(
+            "clipboard_listener",
+            CheckStatus::Warning,
+            "Capture is paused while the session is locked or a secure desktop is shown",
+            None,
+        );
+    }
+    if !config.capture_text && !config.capture_images {
+        return check(
+            "clipboard_listener",
+            CheckStatus::Warning,
+            "Text and image capture are both turned off",
+            Some("Turn on text or image capture in the settings"),
+        );
+    }
+    check("clipboard_listener", CheckStatus::Ok, "Listening for clipboard changes", None)
+}
+
+fn database(db: &Database) -> SelfTestCheck {
+    match db.check_writable() {
+        Ok(()) => check("database", CheckStatus::Ok, "The database is writable", None),
+        Err(e) => check(
+            "database",
+            CheckStatus::Error,
+            format!("The database cannot be written: {}", e),
+            Some("Check that the data folder is not read-only and the drive is not full"),
+        ),
+    }
+}
+
+fn images(db: &Database, config: &AppConfig) -> SelfTestCheck {
+    let dir = db.images_dir();
+    let probe = dir.join(".selftest");
+    let written = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&probe, b"ok"));
+    let _ = std::fs::remove_file(&probe);
+    if let Err(e) = written {
+        return check(
+            "images_dir",
+            CheckStatus::Error,
+            format!("Images cannot be saved to {}: {}", dir.display(), e),
+            Some("Check that the data folder is not read-only"),
+        );
+    }
+    if let Some(free) = crate::disk::low_space(&dir, config.min_free_space_mb) {
+        return check(
+            "images_dir",
+            CheckStatus::Error,
+            format!("Only {} MB free on the images drive, new images are not being saved", free / (1024 * 1024)),
+            Some("Free up disk space or move the data folder"),
+        );
+    }
+    match crate::disk::free_space(&dir) {
+        Some(free) if free < LOW_SPACE_BYTES => check(
+            "images_dir",
+            CheckStatus::Warning,
+            format!("Only {} MB free on the images drive", free / (1024 * 1024)),
+            Some("Free up disk space or move the data folder"),
+        ),
+        Some(free) => check("images_dir", CheckStatus::Ok, format!("{} MB free", free / (1024 * 1024)), None),
+        None => check("images_dir", CheckStatus::Ok, "Writable", None),
+    }
+}
+
+fn language(config: &AppConfig) -> SelfTestCheck {
+    if crate::commands::find_language_dir().is_none() {
+        return check(
+            "language",
+            CheckStatus::Error,
+            "The language folder was not found",
+            Some("Reinstall CutBoard"),
+        );
+    }
+    match crate::commands::load_language_map(&config.language) {
+        Ok(_) => check("language", CheckStatus::Ok, format!("{} loaded", config.language), None),
+        Err(e) => check("language", CheckStatus::Warning, e, Some("Pick another language in the settings")),
+    }
+}
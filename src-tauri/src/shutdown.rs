@@ -0,0 +1,30 @@
+//! Orderly exit, run once the event loop ends after tray Quit or closing the window: records a
+//! copy still waiting to be captured, stops the background listeners and checkpoints the WAL.
+
+use crate::DbState;
+use tauri::Manager;
+
+pub fn run(app: &tauri::AppHandle) {
+    crate::clipboard::stop_monitor();
+    crate::hotkey::stop();
+    crate::expander::set_enabled(app, false);
+
+    if let Some(window) = app.get_webview_window("main") {
+        crate::geometry::save(app, &window);
+    }
+    if let Some(mini) = app.get_webview_window(crate::commands::MINI_WINDOW_LABEL) {
+        crate::commands::save_mini_window_size(app, &mini);
+    }
+
+    if let Some(state) = app.try_state::<DbState>() {
+        // Held until the process ends, so the timer threads cannot start another write
+        let db = match state.0.lock() {
+            Ok(db) => db,
+            Err(e) => e.into_inner(),
+        };
+        if let Err(e) = db.checkpoint() {
+            eprintln!("WAL checkpoint failed: {}", e);
+        }
+        std::mem::forget(db);
+    }
+}
@@ -0,0 +1,64 @@
+//! Optional sounds when an entry is captured, one per kind. Each setting holds a .wav path or a
+//! Windows sound alias such as "SystemAsterisk"; empty picks the default and "none" mutes that
+//! kind. `sound_volume` (0-100) sets CutBoard's own volume, and 0 turns sounds off. Quiet hours
+//! and do not disturb mute them too.
+
+use crate::config::AppConfig;
+
+#[derive(Clone, Copy)]
+pub enum CaptureSound {
+    Text,
+    Image,
+    Sensitive,
+}
+
+impl CaptureSound {
+    fn setting<'a>(&self, config: &'a AppConfig) -> &'a str {
+        match self {
+            CaptureSound::Text => &config.sound_text,
+            CaptureSound::Image => &config.sound_image,
+            CaptureSound::Sensitive => &config.sound_sensitive,
+        }
+    }
+
+    fn default_alias(&self) -> &'static str {
+        match self {
+            CaptureSound::Text => "SystemAsterisk",
+            CaptureSound::Image => "SystemNotification",
+            CaptureSound::Sensitive => "SystemExclamation",
+        }
+    }
+}
+
+/// Plays without waiting; a newer capture cuts off the previous sound.
+pub fn play(config: &AppConfig, sound: CaptureSound) {
+    if config.sound_volume == 0
+        || crate::dnd::is_active(config)
+        || crate::clipboard::in_quiet_hours(&config.quiet_hours, chrono::Local::now().time())
+    {
+        return;
+    }
+    let name = match sound.setting(config).trim() {
+        "" => sound.default_alias(),
+        "none" => return,
+        name => name,
+    };
+
+    #[cfg(windows)]
+    unsafe {
+        use windows::core::HSTRING;
+        use windows::Win32::Media::Audio::{waveOutSetVolume, PlaySoundW, SND_ALIAS, SND_ASYNC, SND_FILENAME, SND_NODEFAULT};
+
+        let is_file = name.contains(['\\', '/']) || name.to_lowercase().ends_with(".wav");
+        // Same level on both channels, in the low and high words
+        let level = config.sound_volume.min(100) * 0xFFFF / 100;
+        waveOutSetVolume(None, level | (level << 16));
+        let _ = PlaySoundW(
+            &HSTRING::from(name),
+            None,
+            SND_ASYNC | SND_NODEFAULT | if is_file { SND_FILENAME } else { SND_ALIAS },
+        );
+    }
+    #[cfg(not(windows))]
+    let _ = name;
+}
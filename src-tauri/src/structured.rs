@@ -0,0 +1,155 @@
+//! JSON, XML and YAML clips. They are recognized at capture and stored with that `detected_kind`,
+//! and `format_entry` pretty-prints or minifies them here rather than in the webview, which
+//! struggles with multi-megabyte payloads.
+
+use crate::error::CutboardError;
+
+/// Larger clips are not parsed at capture; they can still be formatted on demand.
+const MAX_DETECT_BYTES: usize = 2 * 1024 * 1024;
+
+/// "json", "xml" or "yaml" when the whole text parses as that format. Only objects and arrays
+/// count as JSON, and only mappings and sequences over several lines as YAML, since a bare word
+/// or number is valid in both.
+pub fn detect(text: &str) -> Option<&'static str> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() || trimmed.len() > MAX_DETECT_BYTES {
+        return None;
+    }
+    match trimmed.as_bytes()[0] {
+        b'{' | b'['
+            if serde_json::from_str::<serde_json::Value>(trimmed).is_ok_and(|v| v.is_object() || v.is_array()) =>
+        {
+            return Some("json");
+        }
+        b'<' if format_xml(trimmed, false).is_ok() => return Some("xml"),
+        _ => {}
+    }
+    let yaml_like = trimmed.lines().filter(|l| !l.trim().is_empty()).count() >= 2
+        && trimmed.lines().any(|l| l.trim_end().ends_with(':') || l.contains(": ") || l.trim_start().starts_with("- "));
+    if yaml_like && serde_yaml::from_str::<serde_yaml::Value>(trimmed).is_ok_and(|v| v.is_mapping() || v.is_sequence()) {
+        return Some("yaml");
+    }
+    None
+}
+
+/// `text` in `kind`'s format, pretty-printed or on as few lines as the format allows.
+pub fn format(text: &str, kind: &str, pretty: bool) -> Result<String, CutboardError> {
+    let _t = crate::metrics::timer("structured.format");
+    let text = text.trim();
+    match kind {
+        "json" => {
+            let value: serde_json::Value =
+                serde_json::from_str(text).map_err(|e| CutboardError::invalid_input(format!("Invalid JSON: {}", e)))?;
+            let out = if pretty { serde_json::to_string_pretty(&value) } else { serde_json::to_string(&value) };
+            out.map_err(|e| e.to_string().into())
+        }
+        "xml" => format_xml(text, pretty),
+        "yaml" => {
+            let value: serde_yaml::Value =
+                serde_yaml::from_str(text).map_err(|e| CutboardError::invalid_input(format!("Invalid YAML: {}", e)))?;
+            if pretty {
+                serde_yaml::to_string(&value).map(|s| s.trim_end().to_string()).map_err(|e| e.to_string().into())
+            } else {
+                // YAML's compact form is flow style, which JSON is a subset of
+                serde_json::to_string(&value).map_err(|e| e.to_string().into())
+            }
+        }
+        other => Err(CutboardError::invalid_input(format!("Unknown format: {}", other))),
+    }
+}
+
+/// Re-serializes the XML with two-space indentation, or without whitespace between tags.
+fn format_xml(text: &str, pretty: bool) -> Result<String, CutboardError> {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+    let mut writer = if pretty {
+        quick_xml::Writer::new_with_indent(Vec::new(), b' ', 2)
+    } else {
+        quick_xml::Writer::new(Vec::new())
+    };
+    let (mut has_element, mut depth) = (false, 0usize);
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| CutboardError::invalid_input(format!("Invalid XML at byte {}: {}", reader.error_position(), e)))?;
+        match event {
+            Event::Eof => break,
+            // A second root or text beside the root, as in an HTML snippet, is not a document
+            Event::Start(_) | Event::Empty(_) | Event::Text(_) | Event::CData(_) if depth == 0 && has_element => {
+                return Err(CutboardError::invalid_input("Invalid XML: content after the root element"));
+            }
+            Event::Text(_) | Event::CData(_) if depth == 0 => {
+                return Err(CutboardError::invalid_input("Invalid XML: text outside the root element"));
+            }
+            Event::Start(_) => {
+                has_element = true;
+                depth += 1;
+            }
+            Event::End(_) => depth = depth.saturating_sub(1),
+            Event::Empty(_) => has_element = true,
+            _ => {}
+        }
+        writer.write_event(event)?;
+    }
+    // The reader stops quietly at the end of the text, even inside an element
+    if !has_element || depth > 0 {
+        return Err(CutboardError::invalid_input("Invalid XML: unclosed or missing root element"));
+    }
+    String::from_utf8(writer.into_inner()).map_err(|e| e.to_string().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_each_format() {
+        assert_eq!(detect(" {\"a\": [1, 2]} "), Some("json"));
+        assert_eq!(detect("[1, 2]"), Some("json"));
+        assert_eq!(detect("<a><b x=\"1\"/></a>"), Some("xml"));
+        assert_eq!(detect("name: cutboard\ntags:\n  - clip\n  - board"), Some("yaml"));
+        assert_eq!(detect("- one\n- two"), Some("yaml"));
+    }
+
+    #[test]
+    fn plain_text_is_not_structured() {
+        assert_eq!(detect(""), None);
+        assert_eq!(detect("42"), None);
+        assert_eq!(detect("\"just a string\""), None);
+        assert_eq!(detect("key: value"), None);
+        assert_eq!(detect("Dear team,\nthanks for the notes"), None);
+        assert_eq!(detect("{not json"), None);
+        assert_eq!(detect("<a><b></a>"), None);
+        assert_eq!(detect("<a>"), None);
+        assert_eq!(detect("<br/> and then some"), None);
+        assert_eq!(detect("<b>bold</b><i>italic</i>"), None);
+    }
+
+    #[test]
+    fn formats_json() {
+        assert_eq!(format("{\"a\":[1,2]}", "json", true).unwrap(), "{\n  \"a\": [\n    1,\n    2\n  ]\n}");
+        assert_eq!(format("{ \"a\" : [ 1 , 2 ] }", "json", false).unwrap(), "{\"a\":[1,2]}");
+        assert!(format("{", "json", true).is_err());
+    }
+
+    #[test]
+    fn formats_xml() {
+        assert_eq!(format("<a><b>x</b><c/></a>", "xml", true).unwrap(), "<a>\n  <b>x</b>\n  <c/>\n</a>");
+        assert_eq!(format("<a>\n  <b>x</b>\n</a>", "xml", false).unwrap(), "<a><b>x</b></a>");
+        assert!(format("<a><b>", "xml", true).is_err());
+        assert!(format("<a/>trailing", "xml", true).is_err());
+    }
+
+    #[test]
+    fn formats_yaml() {
+        assert_eq!(format("a: [1, 2]", "yaml", true).unwrap(), "a:\n- 1\n- 2");
+        assert_eq!(format("a:\n  - 1\n  - 2\n", "yaml", false).unwrap(), "{\"a\":[1,2]}");
+    }
+
+    #[test]
+    fn rejects_unknown_formats() {
+        assert!(format("a,b", "csv", true).is_err());
+    }
+}
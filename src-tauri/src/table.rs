@@ -0,0 +1,284 @@
+//! Tables copied as text: tab, comma, semicolon or pipe separated, or columns lined up with
+//! spaces, and HTML tables. Used by the `to_tsv` transform, which rewrites them the way
+//! spreadsheets paste, and to store clips detected as tables so they can be copied back as CSV,
+//! Markdown or HTML.
+
+use fancy_regex::Regex;
+use std::sync::LazyLock;
+
+static HTML_ROW: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<tr\b[^>]*>(.*?)</tr\s*>").unwrap());
+static HTML_CELL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<t[hd]\b[^>]*>(.*?)</t[hd]\s*>").unwrap());
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+/// Larger clips are not checked at capture.
+const MAX_DETECT_BYTES: usize = 1024 * 1024;
+
+/// Separators tried in order; the first that splits every row into the same number of columns wins.
+const DELIMITERS: [char; 4] = ['\t', ';', ',', '|'];
+
+/// The rows and cells of a text table, trimmed, or `None` when the text does not look like one.
+pub fn parse_delimited(text: &str) -> Option<Vec<Vec<String>>> {
+    parse_text(text).map(|(rows, _)| rows)
+}
+
+/// Also tells whether the columns were only lined up with spaces.
+fn parse_text(text: &str) -> Option<(Vec<Vec<String>>, bool)> {
+    if text.trim().is_empty() {
+        return None;
+    }
+    for delimiter in DELIMITERS {
+        let rows = if delimiter == '|' { parse_pipes(text) } else { parse_quoted(text, delimiter) };
+        if is_consistent(&rows) {
+            return Some((rows, false));
+        }
+    }
+    let rows = parse_aligned(text);
+    is_consistent(&rows).then_some((rows, true))
+}
+
+/// The cells of a clip that is a table, checked at capture: the HTML flavor's first `<table>`
+/// when there is one, otherwise the text with at least two rows. Aligned columns need three, since
+/// two lines of prose can happen to line up.
+pub fn detect(text: &str, html: Option<&str>) -> Option<Vec<Vec<String>>> {
+    if text.len() > MAX_DETECT_BYTES {
+        return None;
+    }
+    if let Some(rows) = html.and_then(parse_html) {
+        return Some(rows);
+    }
+    let (rows, aligned) = parse_text(text)?;
+    (rows.len() >= if aligned { 3 } else { 2 }).then_some(rows)
+}
+
+/// The rows of the first `<table>` in an HTML fragment, with tags stripped from the cells.
+/// Merged cells are not spread out, so rows keep the cells they list.
+fn parse_html(html: &str) -> Option<Vec<Vec<String>>> {
+    let start = html.to_ascii_lowercase().find("<table")?;
+    let html = &html[start..];
+    let end = html.to_ascii_lowercase().find("</table").unwrap_or(html.len());
+    let rows: Vec<Vec<String>> = HTML_ROW
+        .captures_iter(&html[..end])
+        .filter_map(|caps| caps.ok()?.get(1))
+        .map(|row| {
+            HTML_CELL
+                .captures_iter(row.as_str())
+                .filter_map(|caps| caps.ok()?.get(1))
+                .map(|cell| html_text(cell.as_str()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|row| !row.is_empty())
+        .collect();
+    let width = rows.iter().map(Vec::len).max()?;
+    (rows.len() >= 2 || width >= 2).then_some(rows)
+}
+
+fn html_text(fragment: &str) -> String {
+    let text = HTML_TAG.replace_all(fragment, " ");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Tab separated text with trimmed cells and the same number of cells on every row, so a
+/// spreadsheet puts each cell in its own column. Text that is not a table is returned unchanged.
+pub fn to_tsv(text: &str) -> String {
+    match parse_delimited(text) {
+        Some(rows) => rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.replace(['\t', '\r', '\n'], " ")).collect::<Vec<_>>().join("\t"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => text.to_string(),
+    }
+}
+
+/// Comma separated, quoting cells that hold a comma, quote or line break.
+pub fn to_csv(rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    if cell.contains([',', '"', '\n', '\r']) {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A GitHub-style Markdown table with the first row as the header.
+pub fn to_markdown(rows: &[Vec<String>]) -> String {
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let line = |row: &[String]| {
+        let cells: Vec<String> = (0..width)
+            .map(|i| row.get(i).map_or(String::new(), |c| c.replace('|', "\\|").replace(['\r', '\n'], " ")))
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut lines = Vec::with_capacity(rows.len() + 1);
+    if let Some((header, body)) = rows.split_first() {
+        lines.push(line(header));
+        lines.push(format!("|{}", " --- |".repeat(width)));
+        lines.extend(body.iter().map(|row| line(row)));
+    }
+    lines.join("\n")
+}
+
+/// An HTML `<table>` with the first row as header cells.
+pub fn to_html(rows: &[Vec<String>]) -> String {
+    let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let mut html = String::from("<table>");
+    for (i, row) in rows.iter().enumerate() {
+        let tag = if i == 0 { "th" } else { "td" };
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<{tag}>{}</{tag}>", escape(cell)));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
+
+/// At least two columns, and every row has as many.
+fn is_consistent(rows: &[Vec<String>]) -> bool {
+    let Some(first) = rows.first() else {
+        return false;
+    };
+    first.len() > 1 && rows.iter().all(|row| row.len() == first.len())
+}
+
+/// CSV-style parsing: a cell in double quotes may hold the delimiter, line breaks and `""`.
+/// Blank lines are skipped.
+fn parse_quoted(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => cell.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if cell.trim().is_empty() => {
+                cell.clear();
+                in_quotes = true;
+            }
+            '\r' => {}
+            '\n' => {
+                row.push(cell.trim().to_string());
+                cell.clear();
+                push_row(&mut rows, std::mem::take(&mut row));
+            }
+            _ if c == delimiter => row.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    row.push(cell.trim().to_string());
+    push_row(&mut rows, row);
+    rows
+}
+
+fn push_row(rows: &mut Vec<Vec<String>>, row: Vec<String>) {
+    if row.iter().any(|cell| !cell.is_empty()) {
+        rows.push(row);
+    }
+}
+
+/// Markdown and ASCII tables: outer pipes are dropped, as are `|---|:---:|` and `+----+` rules.
+fn parse_pipes(text: &str) -> Vec<Vec<String>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.chars().all(|c| matches!(c, '-' | ':' | '|' | '+' | '=' | ' ')))
+        .map(|line| {
+            let inner = line.strip_prefix('|').unwrap_or(line);
+            let inner = inner.strip_suffix('|').unwrap_or(inner);
+            inner.split('|').map(|cell| cell.trim().to_string()).collect()
+        })
+        .collect()
+}
+
+/// Columns lined up with runs of two or more spaces, as in terminal output.
+fn parse_aligned(text: &str) -> Vec<Vec<String>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.split("  ").map(str::trim).filter(|cell| !cell.is_empty()).map(String::from).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(cells: &[&[&str]]) -> Vec<Vec<String>> {
+        cells.iter().map(|row| row.iter().map(|c| c.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn detects_delimited_text() {
+        assert_eq!(detect("a\tb\n1\t2", None), Some(rows(&[&["a", "b"], &["1", "2"]])));
+        assert_eq!(detect("name;age\nAda;36\n", None), Some(rows(&[&["name", "age"], &["Ada", "36"]])));
+        assert_eq!(
+            detect("name,note\n\"Lovelace, Ada\",\"said \"\"hi\"\"\nthen left\"", None),
+            Some(rows(&[&["name", "note"], &["Lovelace, Ada", "said \"hi\"\nthen left"]]))
+        );
+    }
+
+    #[test]
+    fn detects_markdown_tables() {
+        let text = "| a | b |\n|---|:---:|\n| 1 | 2 |";
+        assert_eq!(detect(text, None), Some(rows(&[&["a", "b"], &["1", "2"]])));
+    }
+
+    #[test]
+    fn aligned_columns_need_three_rows() {
+        assert_eq!(detect("NAME   SIZE\nfoo    1", None), None);
+        assert_eq!(
+            detect("NAME   SIZE\nfoo    1\nbar    22", None),
+            Some(rows(&[&["NAME", "SIZE"], &["foo", "1"], &["bar", "22"]]))
+        );
+    }
+
+    #[test]
+    fn prose_is_not_a_table() {
+        assert_eq!(detect("Hello, world", None), None);
+        assert_eq!(detect("First line, with a comma\nsecond line without", None), None);
+        assert_eq!(detect("", None), None);
+    }
+
+    #[test]
+    fn html_flavor_wins() {
+        let html = "<p>x</p><TABLE><tr><th>a</th><th>b &amp; c</th></tr><tr><td><b>1</b></td><td>2</td></tr></TABLE>";
+        assert_eq!(detect("a b & c 1 2", Some(html)), Some(rows(&[&["a", "b & c"], &["1", "2"]])));
+    }
+
+    #[test]
+    fn converts_to_other_formats() {
+        let table = rows(&[&["a", "b|c"], &["1, 2", "x\"y"]]);
+        assert_eq!(to_csv(&table), "a,b|c\n\"1, 2\",\"x\"\"y\"");
+        assert_eq!(to_markdown(&table), "| a | b\\|c |\n| --- | --- |\n| 1, 2 | x\"y |");
+        assert_eq!(to_html(&rows(&[&["<a>"], &["&"]])), "<table><tr><th>&lt;a&gt;</th></tr><tr><td>&amp;</td></tr></table>");
+        assert_eq!(to_tsv("a, b\n1 ,2"), "a\tb\n1\t2");
+        assert_eq!(to_tsv("just text"), "just text");
+    }
+}
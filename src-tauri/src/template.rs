@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+/// Expands the placeholders of a stored template.
+///
+/// Supported placeholders: `{date}`, `{time}`, `{clipboard}`, `{n:counter}` (per-name
+/// counter incremented on every render) and `{input:Name}` (value supplied by the caller).
+/// Anything else between braces is left untouched.
+pub fn render(
+    template: &str,
+    inputs: &HashMap<String, String>,
+    clipboard_text: Option<&str>,
+    next_counter: &mut dyn FnMut(&str) -> Result<i64, String>,
+) -> Result<String, String> {
+    let now = chrono::Local::now();
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let close = match after.find('}') {
+            Some(c) => c,
+            None => {
+                out.push_str(&rest[open..]);
+                return Ok(out);
+            }
+        };
+        let token = &after[..close];
+        // A literal brace, as in code or JSON, may come before a placeholder
+        if let Some(inner) = token.find('{') {
+            out.push('{');
+            out.push_str(&token[..inner]);
+            rest = &after[inner..];
+            continue;
+        }
+        match token {
+            "date" => out.push_str(&now.format("%Y-%m-%d").to_string()),
+            "time" => out.push_str(&now.format("%H:%M:%S").to_string()),
+            "clipboard" => out.push_str(clipboard_text.unwrap_or("")),
+            _ => {
+                if let Some(name) = token.strip_prefix("n:") {
+                    out.push_str(&next_counter(name.trim())?.to_string());
+                } else if let Some(name) = token.strip_prefix("input:") {
+                    let value = inputs
+                        .get(name.trim())
+                        .ok_or_else(|| format!("Missing input: {}", name.trim()))?;
+                    out.push_str(value);
+                } else {
+                    out.push('{');
+                    out.push_str(token);
+                    out.push('}');
+                }
+            }
+        }
+        rest = &after[close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Names of the `{input:Name}` placeholders, in order of first appearance.
+pub fn input_names(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(pos) = rest.find("{input:") {
+        let after = &rest[pos + 7..];
+        let end = match after.find('}') {
+            Some(e) => e,
+            None => break,
+        };
+        let name = after[..end].trim().to_string();
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+        rest = &after[end + 1..];
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_with(template: &str, inputs: &[(&str, &str)], clipboard: Option<&str>) -> Result<String, String> {
+        let inputs = inputs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let mut counters: HashMap<String, i64> = HashMap::new();
+        render(template, &inputs, clipboard, &mut |name| {
+            let value = counters.entry(name.to_string()).or_insert(0);
+            *value += 1;
+            Ok(*value)
+        })
+    }
+
+    #[test]
+    fn fills_inputs_clipboard_and_counters() {
+        let out = render_with(
+            "Hi {input: Name}, #{n:ticket} #{n:ticket} #{n:other}: {clipboard}",
+            &[("Name", "Ada")],
+            Some("copied"),
+        );
+        assert_eq!(out.unwrap(), "Hi Ada, #1 #2 #1: copied");
+        assert_eq!(render_with("[{clipboard}]", &[], None).unwrap(), "[]");
+    }
+
+    #[test]
+    fn date_and_time_are_formatted() {
+        let out = render_with("{date} {time}", &[], None).unwrap();
+        let (date, time) = out.split_once(' ').unwrap();
+        assert!(chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok(), "{}", date);
+        assert!(chrono::NaiveTime::parse_from_str(time, "%H:%M:%S").is_ok(), "{}", time);
+    }
+
+    #[test]
+    fn unknown_and_unclosed_braces_are_kept() {
+        assert_eq!(render_with("fn() { {other} }", &[], None).unwrap(), "fn() { {other} }");
+        assert_eq!(render_with("a {date", &[], None).unwrap(), "a {date");
+        assert_eq!(render_with("if (x) { {clipboard} }", &[], Some("y()")).unwrap(), "if (x) { y() }");
+        assert_eq!(
+            render_with("{\"to\": \"{input:Email}\"}", &[("Email", "a@b.c")], None).unwrap(),
+            "{\"to\": \"a@b.c\"}"
+        );
+        assert_eq!(render_with("{{ {{clipboard}", &[], Some("x")).unwrap(), "{{ {x");
+    }
+
+    #[test]
+    fn missing_input_is_an_error() {
+        assert_eq!(render_with("{input:Email}", &[], None), Err("Missing input: Email".to_string()));
+    }
+
+    #[test]
+    fn input_names_are_listed_once_in_order() {
+        assert_eq!(input_names("{input:B} {input: A } {input:B} {input:} {input:C"), ["B", "A"]);
+    }
+}
@@ -0,0 +1,72 @@
+//! Short titles for long text clips so the history list stays skimmable.
+
+const MIN_LENGTH: usize = 80;
+const MIN_LINES: usize = 3;
+const MAX_TITLE_CHARS: usize = 60;
+
+/// Returns a title for text long enough to need one: a markdown heading if there is one,
+/// otherwise the first meaningful line. A lone long URL is shortened to its domain and last path
+/// segment.
+pub fn generate(text: &str) -> Option<String> {
+    let trimmed = text.trim();
+    let line_count = trimmed.lines().filter(|l| !l.trim().is_empty()).count();
+    if trimmed.chars().count() < MIN_LENGTH && line_count < MIN_LINES {
+        return None;
+    }
+
+    if line_count == 1 && (trimmed.starts_with("http://") || trimmed.starts_with("https://")) {
+        return Some(truncate(&url_title(trimmed)));
+    }
+
+    let heading = trimmed.lines().map(str::trim).find_map(|l| {
+        let rest = l.trim_start_matches('#');
+        (rest.len() < l.len() && rest.starts_with(' ')).then(|| rest.trim())
+    });
+    if let Some(h) = heading.filter(|h| !h.is_empty()) {
+        return Some(truncate(h));
+    }
+
+    trimmed
+        .lines()
+        .map(clean_line)
+        .find(|l| l.chars().any(char::is_alphanumeric))
+        .map(|l| truncate(&l))
+}
+
+/// Strips comment, quote and list markers so code and quoted mail get a readable title.
+fn clean_line(line: &str) -> String {
+    let mut l = line.trim();
+    for prefix in ["//", "/*", "*", "#", "--", ">", "- ", "• "] {
+        if let Some(rest) = l.strip_prefix(prefix) {
+            l = rest.trim_start();
+        }
+    }
+    l.trim_end_matches("*/").trim().to_string()
+}
+
+fn url_title(url: &str) -> String {
+    let domain = crate::database::extract_domain(url);
+    let path = url
+        .split("://")
+        .nth(1)
+        .and_then(|rest| rest.split_once('/'))
+        .map(|(_, p)| p.split(['?', '#']).next().unwrap_or(""))
+        .unwrap_or("");
+    match path.trim_end_matches('/').rsplit('/').next().filter(|s| !s.is_empty()) {
+        Some(segment) => format!("{} › {}", domain, segment),
+        None => domain,
+    }
+}
+
+fn truncate(s: &str) -> String {
+    if s.chars().count() <= MAX_TITLE_CHARS {
+        return s.to_string();
+    }
+    let cut: String = s.chars().take(MAX_TITLE_CHARS).collect();
+    // Prefer breaking at a word boundary when one is reasonably close to the limit
+    let cut = match cut.rfind(' ') {
+        Some(pos) if pos > MAX_TITLE_CHARS / 2 => &cut[..pos],
+        _ => &cut[..],
+    };
+    format!("{}…", cut.trim_end())
+}
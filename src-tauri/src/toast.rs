@@ -0,0 +1,145 @@
+//! Capture notifications as Windows toasts, with Favorite, Delete and Paste plain buttons. Toasts go
+//! through the notification platform, so Focus Assist and the per-app notification settings apply.
+//! Older Windows, or an install without a registered app id, falls back to the tray balloon.
+
+/// Button captions, from the language file.
+#[derive(Clone)]
+pub struct ToastLabels {
+    pub favorite: String,
+    pub delete: String,
+    pub paste_plain: String,
+}
+
+/// The latest toast, kept alive so its button clicks still arrive, and the window that had focus
+/// when it was shown, which "Paste plain" pastes into. Each toast replaces the previous one.
+#[cfg(windows)]
+static CURRENT: std::sync::Mutex<Option<(windows::UI::Notifications::ToastNotification, isize)>> =
+    std::sync::Mutex::new(None);
+
+/// Shows the toast for a new entry. Returns false when toasts are unavailable, so the caller can
+/// show a balloon instead; a toast the user has turned off or Focus Assist holds back counts as
+/// shown.
+#[cfg(windows)]
+pub fn show(app: &tauri::AppHandle, entry_id: i64, content_type: &str, title: &str, body: &str, labels: &ToastLabels) -> bool {
+    use windows::core::{Interface, HSTRING, IInspectable};
+    use windows::Data::Xml::Dom::XmlDocument;
+    use windows::Foundation::TypedEventHandler;
+    use windows::UI::Notifications::{NotificationSetting, ToastActivatedEventArgs, ToastNotification, ToastNotificationManager};
+    use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+    let paste_action = if content_type == "text" {
+        format!(r#"<action content="{}" arguments="paste_plain:{}"/>"#, escape(&labels.paste_plain), entry_id)
+    } else {
+        String::new()
+    };
+    let xml = format!(
+        r#"<toast launch="open:{id}"><visual><binding template="ToastGeneric"><text>{}</text><text>{}</text></binding></visual><actions><action content="{}" arguments="favorite:{id}"/><action content="{}" arguments="delete:{id}"/>{}</actions><audio silent="true"/></toast>"#,
+        escape(title),
+        escape(body),
+        escape(&labels.favorite),
+        escape(&labels.delete),
+        paste_action,
+        id = entry_id,
+    );
+
+    let result = (|| -> windows::core::Result<bool> {
+        let notifier = ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(&app.config().identifier))?;
+        // Fails when the app id has no Start menu shortcut, e.g. in a portable copy
+        if notifier.Setting()? != NotificationSetting::Enabled {
+            return Ok(true);
+        }
+        let doc = XmlDocument::new()?;
+        doc.LoadXml(&HSTRING::from(xml))?;
+        let toast = ToastNotification::CreateToastNotification(&doc)?;
+        toast.SetTag(&HSTRING::from("capture"))?;
+        let app = app.clone();
+        toast.Activated(&TypedEventHandler::new(move |_, args: windows::core::Ref<IInspectable>| {
+            let arguments = args.ok()?.cast::<ToastActivatedEventArgs>()?.Arguments()?;
+            let app = app.clone();
+            // Off the notification thread, which must not block on the database
+            std::thread::spawn(move || handle_action(&app, &arguments.to_string()));
+            Ok(())
+        }))?;
+        let target = unsafe { GetForegroundWindow() }.0 as isize;
+        notifier.Show(&toast)?;
+        *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = Some((toast, target));
+        Ok(true)
+    })();
+    result.unwrap_or(false)
+}
+
+#[cfg(not(windows))]
+pub fn show(_app: &tauri::AppHandle, _entry_id: i64, _content_type: &str, _title: &str, _body: &str, _labels: &ToastLabels) -> bool {
+    false
+}
+
+#[cfg(windows)]
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Runs a toast button, or opens the main window for a click on the toast itself.
+#[cfg(windows)]
+fn handle_action(app: &tauri::AppHandle, arguments: &str) {
+    use tauri::{Emitter, Manager};
+
+    let Some((action, id)) = arguments.split_once(':').and_then(|(a, id)| Some((a, id.parse::<i64>().ok()?))) else {
+        return;
+    };
+    let result = match action {
+        "favorite" => (|| -> Result<(), crate::error::CutboardError> {
+            let state = app.state::<crate::DbState>();
+            let db = state.0.lock().map_err(|e| e.to_string())?;
+            if !db.get_entry_by_id(id)?.is_favorite {
+                db.toggle_entry_favorite(id)?;
+            }
+            Ok(())
+        })(),
+        "delete" => crate::commands::delete_entry(app.clone(), id),
+        "paste_plain" => paste_plain(app, id),
+        _ => {
+            if let Some(window) = app.get_webview_window("main") {
+                if !window.is_visible().unwrap_or(false) {
+                    crate::geometry::restore(app, &window);
+                }
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            return;
+        }
+    };
+    match result {
+        Ok(()) => {
+            let _ = app.emit("clipboard-changed", action);
+        }
+        Err(e) => eprintln!("Toast action {} failed: {}", action, e),
+    }
+}
+
+/// Puts the entry's text on the clipboard without formatting and pastes it into the window that
+/// had focus when the toast appeared.
+#[cfg(windows)]
+fn paste_plain(app: &tauri::AppHandle, id: i64) -> Result<(), crate::error::CutboardError> {
+    use tauri::Manager;
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{VK_CONTROL, VK_V};
+    use windows::Win32::UI::WindowsAndMessaging::SetForegroundWindow;
+
+    crate::lock::ensure_unlocked()?;
+    let text = {
+        let state = app.state::<crate::DbState>();
+        let db = state.0.lock().map_err(|e| e.to_string())?;
+        db.get_entry_by_id(id)?.text_content
+    }
+    .ok_or_else(|| crate::error::CutboardError::not_found("Entry has no text"))?;
+    crate::clipboard::write_text_to_clipboard(&text)?;
+
+    let target = CURRENT.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map_or(0, |(_, hwnd)| *hwnd);
+    if target != 0 {
+        // Clicking a toast button lets the app take the foreground back
+        let _ = unsafe { SetForegroundWindow(HWND(target as *mut _)) };
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    crate::expander::send_keys(&[(VK_CONTROL, false), (VK_V, false), (VK_V, true), (VK_CONTROL, true)]);
+    Ok(())
+}
@@ -1,68 +1,60 @@
 use base64::{engine::general_purpose::STANDARD, Engine};
-use std::collections::{HashMap, VecDeque};
+use crate::cache::LruCache;
 use std::sync::Mutex;
 
-const MAX_ICON_CACHE_SIZE: usize = 200;
+/// Budget until `set_icon_cache_budget` runs with the configured `icon_cache_mb`.
+const DEFAULT_ICON_CACHE_BYTES: usize = 8 * 1024 * 1024;
 
-struct LruIconCache {
-    map: HashMap<String, String>,
-    order: VecDeque<String>,
-}
-
-impl LruIconCache {
-    fn new() -> Self {
-        Self {
-            map: HashMap::new(),
-            order: VecDeque::new(),
-        }
-    }
+/// (exe stamp, base64 icon PNG) by exe path. Icons vary in size, so the cache is bounded by total
+/// bytes.
+static ICON_CACHE: std::sync::LazyLock<Mutex<LruCache<String, (String, String)>>> =
+    std::sync::LazyLock::new(|| Mutex::new(LruCache::new().max_weight(DEFAULT_ICON_CACHE_BYTES, |(_, icon)| icon.len())));
 
-    fn get(&mut self, key: &str) -> Option<&String> {
-        if self.map.contains_key(key) {
-            self.order.retain(|k| k != key);
-            self.order.push_back(key.to_string());
-            self.map.get(key)
-        } else {
-            None
-        }
-    }
+/// Modification time and size of an executable, which change when the app updates and may bring
+/// a new icon.
+pub fn exe_stamp(exe_path: &str) -> Option<String> {
+    let meta = std::fs::metadata(exe_path).ok()?;
+    let modified = meta.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs();
+    Some(format!("{}:{}", modified, meta.len()))
+}
 
-    fn insert(&mut self, key: String, value: String) {
-        if self.map.contains_key(&key) {
-            self.order.retain(|k| k != &key);
-            self.order.push_back(key.clone());
-            self.map.insert(key, value);
-            return;
-        }
-        if self.order.len() >= MAX_ICON_CACHE_SIZE {
-            if let Some(oldest) = self.order.pop_front() {
-                self.map.remove(&oldest);
-            }
-        }
-        self.order.push_back(key.clone());
-        self.map.insert(key, value);
-    }
+pub fn set_icon_cache_budget(budget: usize) {
+    ICON_CACHE.lock().unwrap_or_else(|e| e.into_inner()).set_max_weight(budget);
 }
 
-static ICON_CACHE: std::sync::LazyLock<Mutex<LruIconCache>> =
-    std::sync::LazyLock::new(|| Mutex::new(LruIconCache::new()));
+/// (entries, bytes, budget) of the icon cache.
+pub fn icon_cache_stats() -> (usize, usize, usize) {
+    let cache = ICON_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    (cache.len(), cache.weight(), cache.weight_budget().unwrap_or(0))
+}
 
 pub struct AppWindowInfo {
     pub name: String,
     pub exe_path: String,
     pub icon_base64: Option<String>,
+    /// Identifies the executable build the icon came from; see `exe_stamp`.
+    pub icon_stamp: Option<String>,
     pub is_self: bool,
+    /// Title of the foreground window; only filled in by `get_foreground_app`.
+    pub window_title: Option<String>,
+}
+
+/// Stands in for the source when nothing has focus, e.g. copies made by background tools or
+/// scheduled tasks.
+pub fn system_app() -> AppWindowInfo {
+    AppWindowInfo {
+        name: "Unknown/System".to_string(),
+        exe_path: crate::database::SYSTEM_APP_EXE.to_string(),
+        icon_base64: None,
+        icon_stamp: None,
+        is_self: false,
+        window_title: None,
+    }
 }
 
 #[cfg(windows)]
 pub fn get_foreground_app() -> Option<AppWindowInfo> {
-    use windows::core::PWSTR;
-    use windows::Win32::Foundation::CloseHandle;
-    use windows::Win32::System::Threading::{
-        GetCurrentProcessId, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_FORMAT,
-        PROCESS_QUERY_LIMITED_INFORMATION,
-    };
-    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowTextW};
 
     unsafe {
         let hwnd = GetForegroundWindow();
@@ -70,14 +62,119 @@ pub fn get_foreground_app() -> Option<AppWindowInfo> {
             return None;
         }
 
+        let mut info = app_info_for_window(hwnd)?;
+        let mut title = [0u16; 512];
+        let len = GetWindowTextW(hwnd, &mut title);
+        if len > 0 {
+            info.window_title = Some(String::from_utf16_lossy(&title[..len as usize]));
+        }
+        Some(info)
+    }
+}
+
+#[cfg(windows)]
+fn app_info_for_window(hwnd: windows::Win32::Foundation::HWND) -> Option<AppWindowInfo> {
+    use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    if pid == 0 {
+        return None;
+    }
+    app_info_for_pid(resolve_frame_host(hwnd, pid))
+}
+
+/// Store (UWP) apps are shown in a window owned by ApplicationFrameHost.exe; the app's own process
+/// owns a child window inside it. Returns that process, or `pid` for any other window.
+#[cfg(windows)]
+fn resolve_frame_host(hwnd: windows::Win32::Foundation::HWND, pid: u32) -> u32 {
+    use windows::core::BOOL;
+    use windows::Win32::Foundation::{HWND, LPARAM};
+    use windows::Win32::UI::WindowsAndMessaging::{EnumChildWindows, GetWindowThreadProcessId};
+
+    let is_frame_host = process_exe_path(pid)
+        .and_then(|path| {
+            std::path::Path::new(&path)
+                .file_name()
+                .map(|n| n.to_string_lossy().eq_ignore_ascii_case("ApplicationFrameHost.exe"))
+        })
+        .unwrap_or(false);
+    if !is_frame_host {
+        return pid;
+    }
+
+    struct Search {
+        host: u32,
+        found: u32,
+    }
+
+    unsafe extern "system" fn visit(child: HWND, lparam: LPARAM) -> BOOL {
+        let search = &mut *(lparam.0 as *mut Search);
         let mut pid = 0u32;
-        GetWindowThreadProcessId(hwnd, Some(&mut pid));
-        if pid == 0 {
-            return None;
+        GetWindowThreadProcessId(child, Some(&mut pid));
+        if pid != 0 && pid != search.host {
+            search.found = pid;
+            return BOOL(0);
         }
+        BOOL(1)
+    }
+
+    let mut search = Search { host: pid, found: 0 };
+    unsafe {
+        let _ = EnumChildWindows(Some(hwnd), Some(visit), LPARAM(&mut search as *mut Search as isize));
+    }
+    // While the app is suspended or still starting there is no child window; keep the host
+    if search.found != 0 { search.found } else { pid }
+}
+
+#[cfg(windows)]
+fn app_info_for_pid(pid: u32) -> Option<AppWindowInfo> {
+    use windows::Win32::System::Threading::GetCurrentProcessId;
+
+    let is_self = pid == unsafe { GetCurrentProcessId() };
+
+    let exe_path = process_exe_path(pid)?;
+    let package = packaged_app(pid, &exe_path);
+    let name = package
+        .as_ref()
+        .and_then(|p| p.display_name.clone())
+        .or_else(|| {
+            std::path::Path::new(&exe_path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+        })
+        .unwrap_or_default();
 
-        let is_self = pid == GetCurrentProcessId();
+    if name.is_empty() {
+        return None;
+    }
 
+    let logo = package.as_ref().and_then(|p| p.logo.as_deref());
+    let (icon_base64, icon_stamp) = match get_cached_icon(&exe_path, logo) {
+        Some((icon, stamp)) => (Some(icon), Some(stamp)),
+        None => (None, None),
+    };
+
+    Some(AppWindowInfo {
+        name,
+        exe_path,
+        icon_base64,
+        icon_stamp,
+        is_self,
+        window_title: None,
+    })
+}
+
+#[cfg(windows)]
+fn process_exe_path(pid: u32) -> Option<String> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_FORMAT,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    unsafe {
         let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
 
         let mut buf = [0u16; 1024];
@@ -91,26 +188,333 @@ pub fn get_foreground_app() -> Option<AppWindowInfo> {
         let _ = CloseHandle(process);
 
         result.ok()?;
+        Some(String::from_utf16_lossy(&buf[..size as usize]))
+    }
+}
 
-        let exe_path = String::from_utf16_lossy(&buf[..size as usize]);
-        let name = std::path::Path::new(&exe_path)
-            .file_stem()
-            .map(|s| s.to_string_lossy().to_string())
-            .unwrap_or_default();
+/// Name and logo a Store app declares in its manifest; its exe is often a generic host or has a
+/// terse name (olk.exe for the new Outlook).
+#[cfg(windows)]
+#[derive(Clone)]
+struct PackagedApp {
+    display_name: Option<String>,
+    logo: Option<std::path::PathBuf>,
+}
 
-        if name.is_empty() {
+/// Packaged apps by exe path. Updates install into a new versioned folder, so entries never go
+/// stale.
+#[cfg(windows)]
+static PACKAGE_CACHE: std::sync::LazyLock<Mutex<LruCache<String, Option<PackagedApp>>>> =
+    std::sync::LazyLock::new(|| Mutex::new(LruCache::new().max_entries(64)));
+
+#[cfg(windows)]
+fn packaged_app(pid: u32, exe_path: &str) -> Option<PackagedApp> {
+    let (aumid, full_name) = package_ids(pid)?;
+    if let Some(cached) = PACKAGE_CACHE.lock().ok()?.get(exe_path) {
+        return cached.clone();
+    }
+    let app = read_package_manifest(exe_path, &aumid, &full_name);
+    if let Ok(mut cache) = PACKAGE_CACHE.lock() {
+        cache.insert(exe_path.to_string(), app.clone());
+    }
+    app
+}
+
+/// (application user model id, package full name), or `None` for unpackaged processes.
+#[cfg(windows)]
+fn package_ids(pid: u32) -> Option<(String, String)> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, ERROR_SUCCESS};
+    use windows::Win32::Storage::Packaging::Appx::{GetApplicationUserModelId, GetPackageFullName};
+    use windows::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+    fn wide(buf: &[u16]) -> String {
+        let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+        String::from_utf16_lossy(&buf[..len])
+    }
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+
+        let mut aumid = [0u16; 256];
+        let mut aumid_len = aumid.len() as u32;
+        let aumid_result =
+            GetApplicationUserModelId(process, &mut aumid_len, Some(PWSTR(aumid.as_mut_ptr())));
+        let mut full_name = [0u16; 256];
+        let mut full_name_len = full_name.len() as u32;
+        let full_name_result =
+            GetPackageFullName(process, &mut full_name_len, Some(PWSTR(full_name.as_mut_ptr())));
+        let _ = CloseHandle(process);
+
+        if aumid_result != ERROR_SUCCESS || full_name_result != ERROR_SUCCESS {
             return None;
         }
+        Some((wide(&aumid), wide(&full_name)))
+    }
+}
 
-        let icon_base64 = get_cached_icon(&exe_path);
+/// Reads the `<Application>` entry matching `aumid` ("Family_publisherid!AppId") from the
+/// AppxManifest.xml at the package root above the exe.
+#[cfg(windows)]
+fn read_package_manifest(exe_path: &str, aumid: &str, full_name: &str) -> Option<PackagedApp> {
+    let (family, app_id) = aumid.split_once('!')?;
+    let package_name = family.split('_').next()?;
+    let root = std::path::Path::new(exe_path)
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("AppxManifest.xml").is_file())?;
+    let manifest = std::fs::read_to_string(root.join("AppxManifest.xml")).ok()?;
+
+    let id_attr = format!("Id=\"{}\"", app_id);
+    let start = manifest
+        .match_indices("<Application ")
+        .map(|(i, _)| i)
+        .find(|&i| manifest[i..].split('>').next().is_some_and(|tag| tag.contains(&id_attr)))?;
+    let application = &manifest[start..];
+    let application = &application[..application.find("</Application>").unwrap_or(application.len())];
+
+    Some(PackagedApp {
+        display_name: xml_attr(application, "DisplayName")
+            .and_then(|name| resolve_manifest_string(&name, full_name, package_name)),
+        logo: xml_attr(application, "Square44x44Logo")
+            .and_then(|logo| find_scaled_asset(&root.join(logo))),
+    })
+}
+
+/// First ` name="value"` in `xml`, with the common entities decoded.
+#[cfg(windows)]
+fn xml_attr(xml: &str, name: &str) -> Option<String> {
+    let needle = format!(" {}=\"", name);
+    let start = xml.find(&needle)? + needle.len();
+    let value = &xml[start..start + xml[start..].find('"')?];
+    Some(
+        value
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// Manifest strings are often `ms-resource:` references into the package's resources.pri.
+#[cfg(windows)]
+fn resolve_manifest_string(value: &str, full_name: &str, package_name: &str) -> Option<String> {
+    use windows::core::PCWSTR;
+    use windows::Win32::UI::Shell::SHLoadIndirectString;
+
+    let Some(resource) = value.strip_prefix("ms-resource:") else {
+        let value = value.trim();
+        return (!value.is_empty()).then(|| value.to_string());
+    };
+    let uri = if resource.starts_with("//") {
+        format!("ms-resource:{}", resource)
+    } else if resource.starts_with('/') {
+        format!("ms-resource://{}{}", package_name, resource)
+    } else {
+        format!("ms-resource://{}/Resources/{}", package_name, resource)
+    };
+    let source: Vec<u16> = format!("@{{{}?{}}}", full_name, uri)
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut buf = [0u16; 256];
+    unsafe { SHLoadIndirectString(PCWSTR(source.as_ptr()), &mut buf, None) }.ok()?;
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    let name = String::from_utf16_lossy(&buf[..len]).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
 
-        Some(AppWindowInfo {
-            name,
-            exe_path,
-            icon_base64,
-            is_self,
+/// Manifests name assets like `Assets\Logo.png`, while the package ships qualified variants such as
+/// `Logo.targetsize-32.png` or `Logo.scale-200.png`. Prefers the variant closest to icon size.
+#[cfg(windows)]
+fn find_scaled_asset(path: &std::path::Path) -> Option<std::path::PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let stem = path.file_stem()?.to_string_lossy().to_lowercase();
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    let prefix = format!("{}.", stem);
+    let suffix = format!(".{}", ext);
+    let mut variants: Vec<std::path::PathBuf> = std::fs::read_dir(path.parent()?)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+            name.starts_with(&prefix) && name.ends_with(&suffix)
         })
+        .collect();
+    variants.sort();
+    let rank = |p: &std::path::PathBuf| {
+        let name = p.to_string_lossy().to_lowercase();
+        if name.contains("targetsize-32.") || name.contains("targetsize-32_") {
+            0
+        } else if name.contains("scale-100") {
+            1
+        } else {
+            2
+        }
+    };
+    variants.into_iter().min_by_key(rank)
+}
+
+/// Loads a package logo as a 32px PNG, the size `extract_icon` produces for exes.
+#[cfg(windows)]
+fn load_logo_icon(path: &std::path::Path) -> Option<String> {
+    const ICON_SIZE: u32 = 32;
+    let mut img = image::open(path).ok()?.to_rgba8();
+    if img.width() != ICON_SIZE || img.height() != ICON_SIZE {
+        img = image::imageops::resize(&img, ICON_SIZE, ICON_SIZE, image::imageops::FilterType::Triangle);
     }
+    let mut buf = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+        .ok()?;
+    Some(STANDARD.encode(&buf))
+}
+
+/// Process that owns the clipboard (the last one to call EmptyClipboard). Clipboard utilities
+/// and background copy operations set it while some unrelated window is in the foreground.
+#[cfg(windows)]
+pub fn get_clipboard_owner_app() -> Option<AppWindowInfo> {
+    use windows::Win32::System::DataExchange::GetClipboardOwner;
+
+    unsafe {
+        let hwnd = GetClipboardOwner().ok()?;
+        if hwnd.0.is_null() {
+            return None;
+        }
+        app_info_for_window(hwnd)
+    }
+}
+
+/// Process whose window currently has the clipboard open, blocking everyone else.
+#[cfg(windows)]
+pub fn get_clipboard_holder_app() -> Option<AppWindowInfo> {
+    use windows::Win32::System::DataExchange::GetOpenClipboardWindow;
+
+    unsafe {
+        let hwnd = GetOpenClipboardWindow().ok()?;
+        if hwnd.0.is_null() {
+            return None;
+        }
+        app_info_for_window(hwnd)
+    }
+}
+
+/// Remote desktop clients and VM consoles: (lowercase exe name, display label).
+const REMOTE_HOSTS: &[(&str, &str)] = &[
+    ("mstsc.exe", "Remote Desktop"),
+    ("msrdc.exe", "Remote Desktop"),
+    ("vmconnect.exe", "Hyper-V"),
+    ("vmware.exe", "VMware"),
+    ("vmplayer.exe", "VMware"),
+    ("virtualboxvm.exe", "VirtualBox"),
+    ("wfica32.exe", "Citrix"),
+    ("cdviewer.exe", "Citrix"),
+];
+
+pub struct RemoteSession {
+    pub host_label: &'static str,
+    /// Machine or VM name taken from the client's window title, e.g. "build-server".
+    pub session: String,
+}
+
+/// Detects copies made inside a remote desktop or VM window, which would otherwise all be
+/// attributed to the client executable.
+pub fn detect_remote_session(info: &AppWindowInfo) -> Option<RemoteSession> {
+    let file_name = std::path::Path::new(&info.exe_path)
+        .file_name()?
+        .to_string_lossy()
+        .to_lowercase();
+    let &(_, host_label) = REMOTE_HOSTS.iter().find(|(exe, _)| *exe == file_name)?;
+
+    let title = info.window_title.as_deref().unwrap_or("").trim();
+    // "build-server - Remote Desktop Connection", "Win11 on HOST - Virtual Machine Connection",
+    // "Ubuntu [Running] - Oracle VM VirtualBox"
+    let session = title
+        .rsplit_once(" - ")
+        .map(|(head, _)| head)
+        .unwrap_or(title);
+    let session = match host_label {
+        "Hyper-V" => session.split(" on ").next().unwrap_or(session),
+        "VirtualBox" => session.split(" [").next().unwrap_or(session),
+        _ => session,
+    }
+    .trim();
+    if session.is_empty() {
+        return None;
+    }
+
+    Some(RemoteSession {
+        host_label,
+        session: session.to_string(),
+    })
+}
+
+/// True while input goes to a desktop other than the user's own, e.g. the UAC secure desktop or
+/// the Winlogon desktop during unlock. We cannot open those, which is itself the signal.
+#[cfg(windows)]
+pub fn is_secure_desktop_active() -> bool {
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::StationsAndDesktops::*;
+
+    unsafe {
+        let desktop = match OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_READOBJECTS) {
+            Ok(d) => d,
+            Err(_) => return true,
+        };
+        let mut name = [0u16; 64];
+        let read = GetUserObjectInformationW(
+            HANDLE(desktop.0),
+            UOI_NAME,
+            Some(name.as_mut_ptr() as *mut _),
+            (name.len() * 2) as u32,
+            None,
+        );
+        let _ = CloseDesktop(desktop);
+        if read.is_err() {
+            return false;
+        }
+        let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+        !String::from_utf16_lossy(&name[..len]).eq_ignore_ascii_case("Default")
+    }
+}
+
+#[cfg(not(windows))]
+pub fn is_secure_desktop_active() -> bool {
+    false
+}
+
+/// Executable file names of all running processes.
+#[cfg(windows)]
+pub fn running_process_names() -> Vec<String> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::*;
+
+    let mut names = Vec::new();
+    unsafe {
+        let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else {
+            return names;
+        };
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut ok = Process32FirstW(snapshot, &mut entry).is_ok();
+        while ok {
+            let len = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len());
+            names.push(String::from_utf16_lossy(&entry.szExeFile[..len]));
+            ok = Process32NextW(snapshot, &mut entry).is_ok();
+        }
+        let _ = CloseHandle(snapshot);
+    }
+    names
+}
+
+#[cfg(not(windows))]
+pub fn running_process_names() -> Vec<String> {
+    Vec::new()
 }
 
 #[cfg(not(windows))]
@@ -118,23 +522,40 @@ pub fn get_foreground_app() -> Option<AppWindowInfo> {
     None
 }
 
+#[cfg(not(windows))]
+pub fn get_clipboard_owner_app() -> Option<AppWindowInfo> {
+    None
+}
+
+#[cfg(not(windows))]
+pub fn get_clipboard_holder_app() -> Option<AppWindowInfo> {
+    None
+}
+
+/// Returns the icon and the exe stamp it was extracted at, re-extracting once the exe changes.
+/// Packaged apps use their manifest `logo`, falling back to the exe's own icon.
 #[cfg(windows)]
-fn get_cached_icon(exe_path: &str) -> Option<String> {
+fn get_cached_icon(exe_path: &str, logo: Option<&std::path::Path>) -> Option<(String, String)> {
+    let stamp = exe_stamp(exe_path)?;
     {
         let mut cache = ICON_CACHE.lock().ok()?;
-        if let Some(icon) = cache.get(exe_path) {
-            return Some(icon.clone());
+        if let Some((cached_stamp, icon)) = cache.get(exe_path) {
+            if *cached_stamp == stamp {
+                crate::metrics::increment("icon_cache.hit");
+                return Some((icon.clone(), stamp));
+            }
         }
     }
-
-    let icon = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| extract_icon(exe_path)))
-        .unwrap_or(None);
-    if let Some(ref icon_data) = icon {
-        if let Ok(mut cache) = ICON_CACHE.lock() {
-            cache.insert(exe_path.to_string(), icon_data.clone());
-        }
+    crate::metrics::increment("icon_cache.miss");
+
+    let icon = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        logo.and_then(load_logo_icon).or_else(|| extract_icon(exe_path))
+    }))
+    .unwrap_or(None)?;
+    if let Ok(mut cache) = ICON_CACHE.lock() {
+        cache.insert(exe_path.to_string(), (stamp.clone(), icon.clone()));
     }
-    icon
+    Some((icon, stamp))
 }
 
 #[cfg(windows)]
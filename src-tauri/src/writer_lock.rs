@@ -0,0 +1,49 @@
+//! Single-writer ownership of a data directory. When `data_path` is on a network drive or a
+//! synced folder, two machines or users could otherwise open the same `cutboard.db` and corrupt
+//! it; the first to open holds an OS file lock until it exits.
+
+use crate::error::CutboardError;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+const LOCK_FILE: &str = "cutboard.lock";
+/// Kept separate from the lock file because Windows blocks reads of a locked file.
+const OWNER_FILE: &str = "cutboard.owner";
+
+/// Holds the lock until dropped.
+pub struct WriterLock {
+    _file: File,
+}
+
+fn describe_self() -> String {
+    let user = std::env::var("USERNAME").or_else(|_| std::env::var("USER")).unwrap_or_default();
+    let host = std::env::var("COMPUTERNAME").or_else(|_| std::env::var("HOSTNAME")).unwrap_or_default();
+    format!("{}\\{} (pid {})", host, user, std::process::id())
+}
+
+/// Who currently holds `data_dir`, as recorded by the owner.
+pub fn owner(data_dir: &Path) -> String {
+    std::fs::read_to_string(data_dir.join(OWNER_FILE))
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Fails with `CutboardError::DataPathInUse` when another process holds the directory.
+pub fn acquire(data_dir: &Path) -> Result<WriterLock, CutboardError> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(data_dir.join(LOCK_FILE))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(std::fs::TryLockError::WouldBlock) => {
+            let owner = owner(data_dir);
+            let message = format!("{} is in use by {}", data_dir.display(), if owner.is_empty() { "another instance" } else { &owner });
+            return Err(CutboardError::DataPathInUse { owner, message });
+        }
+        Err(std::fs::TryLockError::Error(e)) => return Err(e.into()),
+    }
+    let _ = std::fs::write(data_dir.join(OWNER_FILE), describe_self());
+    Ok(WriterLock { _file: file })
+}
@@ -27,6 +27,27 @@ export interface ClipboardEntry {
   is_favorite: boolean;
   is_sensitive: boolean;
   html_content: string | null;
+  source_exe: string | null;
+  owner_exe: string | null;
+  remote_session: string | null;
+  title: string | null;
+  detected_kind: string | null;
+  file_size: number | null;
+  file_ext: string | null;
+  derived_from: number | null;
+  derived_kind: string | null;
+  sensitive_kind: string | null;
+  masked_preview: string | null;
+  is_locked: boolean;
+  links: EntryLink[] | null;
+}
+
+export interface EntryLink {
+  id: number;
+  kind: "auto" | "manual";
+  content_type: string;
+  title: string | null;
+  created_at: string;
 }
 
 type ThemeMode = "light" | "dark" | "system";
@@ -59,6 +80,12 @@ function App() {
   const [copyToast, setCopyToast] = useState<string | null>(null);
   const copyToastTimer = useRef<ReturnType<typeof setTimeout> | null>(null);
   const [crashInfo, setCrashInfo] = useState<{ file: string; log_dir: string } | null>(null);
+  const [dbBusy, setDbBusy] = useState<{ path: string; owner: string } | null>(null);
+  const [locked, setLocked] = useState(false);
+  const [pin, setPin] = useState("");
+  const [pinError, setPinError] = useState(false);
+  const [helloUnlock, setHelloUnlock] = useState(false);
+  const [lockEnabled, setLockEnabled] = useState(false);
 
   useEffect(() => {
     const title = t("app.window_title");
@@ -160,6 +187,41 @@ function App() {
     return () => { unlisten.then((fn) => fn()); };
   }, []);
 
+  // App lock: the backend refuses content commands while locked, this only renders the prompt
+  const loadLockState = useCallback(() => {
+    invoke<{ enabled: boolean; locked: boolean; windows_hello: boolean }>("get_lock_state")
+      .then((s) => {
+        setLocked(s.locked);
+        setLockEnabled(s.enabled);
+        setHelloUnlock(s.windows_hello);
+      })
+      .catch(() => {});
+  }, []);
+
+  useEffect(() => {
+    loadLockState();
+    const unlisten = listen<boolean>("app-lock-changed", (e) => {
+      setLocked(e.payload);
+      if (e.payload) loadLockState();
+      setPin("");
+      setPinError(false);
+      if (!e.payload) {
+        loadApps();
+        setRefreshKey((k) => k + 1);
+      }
+    });
+    return () => { unlisten.then((fn) => fn()); };
+  }, [loadApps, loadLockState]);
+
+  const handleUnlock = async () => {
+    try {
+      await invoke("unlock_app", { pin });
+    } catch {
+      setPinError(true);
+      setPin("");
+    }
+  };
+
   // Crash detection listener
   useEffect(() => {
     const unlisten = listen<{ file: string; log_dir: string }>("crash-detected", (e) => {
@@ -168,6 +230,53 @@ function App() {
     return () => { unlisten.then((fn) => fn()); };
   }, []);
 
+  // The data path is held by another machine or user
+  useEffect(() => {
+    const unlisten = listen<{ path: string; owner: string }>("db-busy", (e) => {
+      setDbBusy(e.payload);
+    });
+    return () => { unlisten.then((fn) => fn()); };
+  }, []);
+
+  // Dropping text onto the window stashes it as a manual entry, and dropped files are imported.
+  // The webview handles drops itself, so it sees file contents but not paths.
+  useEffect(() => {
+    const handleDragOver = (e: DragEvent) => {
+      const types = e.dataTransfer?.types;
+      if (types?.includes("Files") || types?.includes("text/plain")) {
+        e.preventDefault();
+        e.dataTransfer!.dropEffect = "copy";
+      }
+    };
+    const handleDrop = (e: DragEvent) => {
+      const dropped = Array.from(e.dataTransfer?.files ?? []);
+      if (dropped.length > 0) {
+        e.preventDefault();
+        Promise.all(
+          dropped.map(async (file) => ({ name: file.name, data: Array.from(new Uint8Array(await file.arrayBuffer())) })),
+        )
+          .then((files) => invoke("import_dropped_files", { files }))
+          .catch((err) => {
+            console.error("Failed to import dropped files:", err);
+          });
+        return;
+      }
+      const text = e.dataTransfer?.getData("text/plain");
+      if (!text || !text.trim()) return;
+      e.preventDefault();
+      invoke("add_manual_entry", { text }).catch((err) => {
+        console.error("Failed to add manual entry:", err);
+      });
+    };
+
+    document.addEventListener("dragover", handleDragOver);
+    document.addEventListener("drop", handleDrop);
+    return () => {
+      document.removeEventListener("dragover", handleDragOver);
+      document.removeEventListener("drop", handleDrop);
+    };
+  }, []);
+
   useEffect(() => {
     const handleKeyDown = (e: KeyboardEvent) => {
       if (e.key === "F5" || e.key === "F12") {
@@ -289,6 +398,55 @@ function App() {
         </div>
       )}
 
+      {locked && !lockEnabled && (
+        // Idle lock without a PIN: blur the history until the user comes back
+        <div
+          className="fixed inset-0 z-[90] flex items-center justify-center backdrop-blur-lg bg-gray-50/40 dark:bg-gray-900/40 cursor-pointer"
+          onClick={() => invoke("unlock_app", { pin: "" }).catch(() => {})}
+        >
+          <p className="text-sm text-gray-600 dark:text-gray-300">{t("lock.resume")}</p>
+        </div>
+      )}
+
+      {locked && lockEnabled && (
+        <div className="fixed inset-0 z-[90] flex items-center justify-center bg-gray-50 dark:bg-gray-900">
+          <form
+            className="flex flex-col items-center gap-3 w-64"
+            onSubmit={(e) => { e.preventDefault(); handleUnlock(); }}
+          >
+            <svg className="w-10 h-10 text-gray-400" fill="none" viewBox="0 0 24 24" stroke="currentColor">
+              <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M12 15v2m-6 4h12a2 2 0 002-2v-6a2 2 0 00-2-2H6a2 2 0 00-2 2v6a2 2 0 002 2zm10-10V7a4 4 0 00-8 0v4h8z" />
+            </svg>
+            <p className="text-sm text-gray-600 dark:text-gray-400">{t("lock.prompt")}</p>
+            <input
+              type="password"
+              autoFocus
+              value={pin}
+              onChange={(e) => { setPin(e.target.value); setPinError(false); }}
+              className={`w-full px-3 py-2 text-sm rounded-lg border bg-white dark:bg-gray-800 outline-none ${
+                pinError ? "border-red-400" : "border-gray-300 dark:border-gray-600 focus:border-blue-400"
+              }`}
+            />
+            {pinError && <p className="text-xs text-red-500">{t("lock.invalid")}</p>}
+            <button
+              type="submit"
+              className="w-full py-2 text-sm font-medium text-white bg-blue-500 hover:bg-blue-600 rounded-lg transition-colors"
+            >
+              {t("lock.unlock")}
+            </button>
+            {helloUnlock && (
+              <button
+                type="button"
+                className="w-full py-2 text-sm text-blue-600 hover:bg-blue-50 dark:hover:bg-blue-900/30 rounded-lg transition-colors"
+                onClick={() => invoke("unlock_with_windows_hello").catch(() => {})}
+              >
+                {t("lock.windows_hello")}
+              </button>
+            )}
+          </form>
+        </div>
+      )}
+
       {crashInfo && (
         <div className="fixed top-0 left-0 right-0 z-[80] bg-amber-50 dark:bg-amber-900/40 border-b border-amber-200 dark:border-amber-700 px-4 py-2.5 flex items-center gap-3">
           <svg className="w-5 h-5 text-amber-500 shrink-0" fill="none" viewBox="0 0 24 24" stroke="currentColor">
@@ -309,6 +467,26 @@ function App() {
           </button>
         </div>
       )}
+
+      {dbBusy && (
+        <div className="fixed top-0 left-0 right-0 z-[80] bg-amber-50 dark:bg-amber-900/40 border-b border-amber-200 dark:border-amber-700 px-4 py-2.5 flex items-center gap-3">
+          <svg className="w-5 h-5 text-amber-500 shrink-0" fill="none" viewBox="0 0 24 24" stroke="currentColor">
+            <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M12 9v2m0 4h.01m-6.938 4h13.856c1.54 0 2.502-1.667 1.732-3L13.732 4c-.77-1.333-2.694-1.333-3.464 0L3.34 16c-.77 1.333.192 3 1.732 3z" />
+          </svg>
+          <span className="text-sm text-amber-800 dark:text-amber-200 flex-1">
+            {t("db_busy.banner")}
+            <span className="ml-1 text-xs text-amber-600 dark:text-amber-400 opacity-80">
+              {dbBusy.path}{dbBusy.owner && ` (${dbBusy.owner})`}
+            </span>
+          </span>
+          <button
+            className="px-2.5 py-1 text-xs font-medium text-amber-700 dark:text-amber-300 bg-amber-200/60 dark:bg-amber-800/50 hover:bg-amber-200 dark:hover:bg-amber-800 rounded-md transition-colors"
+            onClick={() => setDbBusy(null)}
+          >
+            {t("crash.dismiss")}
+          </button>
+        </div>
+      )}
     </div>
   );
 }
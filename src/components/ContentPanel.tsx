@@ -230,8 +230,8 @@ export default function ContentPanel({ appId, appName, onEntryChange, favoritesM
     setExportProgress(0);
     setExportDone(null);
 
-    const unlisten = await listen<number>("export-progress", (event) => {
-      setExportProgress(event.payload);
+    const unlisten = await listen<{ percent: number; bytes_done: number; bytes_total: number }>("export-progress", (event) => {
+      setExportProgress(event.payload.percent);
     });
     exportUnlistenRef.current = unlisten;
 
@@ -419,6 +419,12 @@ export default function ContentPanel({ appId, appName, onEntryChange, favoritesM
                 />
               </div>
             </div>
+            <button
+              className="px-3 py-1 text-xs text-gray-600 hover:text-red-500 border border-gray-300 hover:border-red-300 rounded-md transition-colors"
+              onClick={() => invoke("cancel_export").catch(() => {})}
+            >
+              {t("export.cancel")}
+            </button>
           </div>
         )}
 
@@ -0,0 +1,46 @@
+import { useState, useEffect, useCallback } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type { ClipboardEntry } from "../App";
+
+const MINI_ENTRY_LIMIT = 20;
+
+export default function MiniStrip() {
+  const [entries, setEntries] = useState<ClipboardEntry[]>([]);
+
+  const load = useCallback(async () => {
+    try {
+      const result = await invoke<ClipboardEntry[]>("get_recent_entries", { limit: MINI_ENTRY_LIMIT });
+      setEntries(result);
+    } catch (e) {
+      console.error("Failed to load recent entries:", e);
+    }
+  }, []);
+
+  useEffect(() => {
+    load();
+    const unlisten = listen("clipboard-changed", () => load());
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [load]);
+
+  return (
+    <div className="mini-strip" data-tauri-drag-region>
+      {entries.map((entry) => (
+        <button
+          key={entry.id}
+          className="mini-item"
+          title={entry.is_sensitive ? undefined : entry.text_content ?? undefined}
+          onClick={() => invoke("copy_entry_to_clipboard", { id: entry.id })}
+        >
+          {entry.content_type === "image"
+            ? "[image]"
+            : entry.is_sensitive
+              ? (entry.masked_preview ?? "••••••").slice(0, 60)
+              : (entry.text_content ?? "").slice(0, 60)}
+        </button>
+      ))}
+    </div>
+  );
+}
@@ -47,6 +47,11 @@ const RETENTION_COUNT = [
   { value: "5000", label: "retention.5000" },
 ];
 
+const CODE_KEYS: Record<string, string> = {
+  Space: "Space", Backquote: "`", Minus: "-", Equal: "=", BracketLeft: "[", BracketRight: "]",
+  Backslash: "\\", Semicolon: ";", Quote: "'", Comma: ",", Period: ".", Slash: "/",
+};
+
 type RetentionTab = "none" | "days" | "count" | "midnight";
 
 function formatSize(bytes: number): string {
@@ -93,15 +98,17 @@ export default function Settings({ isOpen, onClose, onThemeChange }: Props) {
     const s = { ...stateRef.current, ...overrides };
     try {
       await invoke("save_settings", {
-        dataPath: s.dataPath,
-        autoClearMidnight: s.retentionPolicy === "midnight",
-        autoStart: s.autoStart,
-        closeToTray: s.closeToTray,
-        language: s.language,
-        shortcut: s.shortcut,
-        theme: s.theme,
-        showCopyToast: s.showCopyToast,
-        retentionPolicy: s.retentionPolicy,
+        settings: {
+          dataPath: s.dataPath,
+          autoClearMidnight: s.retentionPolicy === "midnight",
+          autoStart: s.autoStart,
+          closeToTray: s.closeToTray,
+          language: s.language,
+          shortcut: s.shortcut,
+          theme: s.theme,
+          showCopyToast: s.showCopyToast,
+          retentionPolicy: s.retentionPolicy,
+        },
       });
     } catch (e) {
       console.error("Failed to save settings:", e);
@@ -177,7 +184,9 @@ export default function Settings({ isOpen, onClose, onThemeChange }: Props) {
     if (e.altKey) parts.push("Alt");
     if (e.shiftKey) parts.push("Shift");
     if (e.metaKey) parts.push("Super");
-    const key = e.key.length === 1 ? e.key.toUpperCase() : e.key;
+    // Numpad and punctuation go by physical key, so Shift or the layout can't change the name
+    const key = e.code.startsWith("Numpad") ? e.code
+      : CODE_KEYS[e.code] ?? (e.key.length === 1 ? e.key.toUpperCase() : e.key);
     if (!["Control", "Alt", "Shift", "Meta"].includes(e.key)) {
       parts.push(key);
     }
@@ -58,7 +58,9 @@ const TextCard = memo(function TextCard({
   onToggleSensitive?: (id: number) => void;
 }) {
   const { t } = useTranslation();
-  const text = entry.text_content || "";
+  // Listings carry only a masked preview of sensitive text; the text itself is fetched on reveal
+  const [revealedText, setRevealedText] = useState<string | null>(null);
+  const text = entry.text_content || revealedText || "";
   const totalPages = Math.max(1, Math.ceil(text.length / TEXT_PAGE_SIZE));
   const needsPaging = totalPages > 1;
   const [page, setPage] = useState(1);
@@ -96,6 +98,24 @@ const TextCard = memo(function TextCard({
 
   const maskedText = entry.is_sensitive && !showSensitive;
 
+  const toggleShowSensitive = useCallback(() => {
+    if (showSensitive) {
+      setShowSensitive(false);
+      setRevealedText(null);
+      return;
+    }
+    if (entry.text_content !== null) {
+      setShowSensitive(true);
+      return;
+    }
+    invoke<string>("reveal_sensitive_entry", { id: entry.id })
+      .then((revealed) => {
+        setRevealedText(revealed);
+        setShowSensitive(true);
+      })
+      .catch(() => {});
+  }, [showSensitive, entry.id, entry.text_content]);
+
   const handleContextMenu = useCallback((e: React.MouseEvent) => {
     e.preventDefault();
     setCtxMenu({ x: e.clientX, y: e.clientY });
@@ -159,7 +179,7 @@ const TextCard = memo(function TextCard({
         {entry.is_sensitive && (
           <button
             className="mt-0.5 shrink-0 text-gray-400 hover:text-amber-500 transition-colors"
-            onClick={() => setShowSensitive(!showSensitive)}
+            onClick={toggleShowSensitive}
             title={showSensitive ? t("sensitive.hide") : t("sensitive.show")}
           >
             {showSensitive ? (
@@ -170,8 +190,11 @@ const TextCard = memo(function TextCard({
           </button>
         )}
         <div className="flex-1 min-w-0">
+          {entry.title && !maskedText && (
+            <div className="text-sm font-medium text-gray-800 dark:text-gray-200 truncate mb-1">{entry.title}</div>
+          )}
           {maskedText ? (
-            <div className="text-sm text-gray-400 italic select-none">••••••••••••••••</div>
+            <div className="text-sm text-gray-400 italic select-none break-all">{entry.masked_preview ?? "••••••••••••••••"}</div>
           ) : hasHtml ? (
             <div
               className="text-sm text-gray-700 dark:text-gray-300 leading-relaxed max-h-40 overflow-y-auto select-text cursor-text prose prose-sm dark:prose-invert max-w-none"
@@ -1,13 +1,15 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
+import { getCurrentWindow } from "@tauri-apps/api/window";
 import App from "./App";
+import MiniStrip from "./components/MiniStrip";
 import { I18nProvider } from "./i18n";
 import "./styles/index.css";
 
 ReactDOM.createRoot(document.getElementById("root")!).render(
   <React.StrictMode>
     <I18nProvider>
-      <App />
+      {getCurrentWindow().label === "mini" ? <MiniStrip /> : <App />}
     </I18nProvider>
   </React.StrictMode>
 );